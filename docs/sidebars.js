@@ -33,6 +33,7 @@ const sidebars = {
       items: [
         'cli-reference/overview',
         'cli-reference/serve',
+        'cli-reference/completions',
       ],
     },
   ],
@@ -0,0 +1,248 @@
+//! Stops idle machines whose services set `autostop`
+//!
+//! Proxies report how many connections they hold open to each machine
+//! through the `connections` endpoint, renewing the report before its
+//! [`CONNECTION_LEASE_SECS`] lease runs out. The connections of a proxy that
+//! stops reporting, e.g. because it exited, count as closed once its lease
+//! has expired. A started machine with a service
+//! whose `autostop.enabled` is set is stopped once it has had no open
+//! connections for `autostop.seconds` (five minutes by default), counted from
+//! its last connection closing or, if it never had one, from when the monitor
+//! first saw it started.
+
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use minifly_core::models::{MachineConfig, MachineConnections, MachineState, CONNECTION_LEASE_SECS};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How often machines are checked for idleness
+const MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+const DEFAULT_IDLE_PERIOD: Duration = Duration::from_secs(300);
+
+/// How long a machine may go without connections, if it's stopped when idle
+pub fn idle_period(config: &MachineConfig) -> Option<Duration> {
+    config.services.as_ref()?
+        .iter()
+        .filter_map(|service| service.autostop.as_ref())
+        .filter(|autostop| autostop.enabled == Some(true))
+        .map(|autostop| autostop.seconds.map_or(DEFAULT_IDLE_PERIOD, |seconds| Duration::from_secs(seconds.into())))
+        .min()
+}
+
+/// Connections proxies hold open to a machine, leased per proxy
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLeases {
+    /// Open connections of each proxy and when its report expires, by client ID
+    clients: HashMap<String, (u32, DateTime<Utc>)>,
+    /// When the machine was last left without open connections
+    idle_since: Option<DateTime<Utc>>,
+}
+
+impl ConnectionLeases {
+    /// Records the connections a proxy currently holds open, leasing them
+    /// for [`CONNECTION_LEASE_SECS`].
+    ///
+    /// Proxies only report no connections after they had some, so such a
+    /// report leaves the machine idle from `now`.
+    pub fn report(&mut self, client_id: &str, active: u32, now: DateTime<Utc>) {
+        self.expire(now);
+        if active == 0 {
+            self.clients.remove(client_id);
+        } else {
+            let expires = now + chrono::Duration::seconds(CONNECTION_LEASE_SECS as i64);
+            self.clients.insert(client_id.to_string(), (active, expires));
+        }
+        self.idle_since = match self.clients.is_empty() {
+            true => Some(now),
+            false => None,
+        };
+    }
+
+    /// Connections open at `now`, with when the machine was last left
+    /// without any
+    pub fn status(&mut self, now: DateTime<Utc>) -> MachineConnections {
+        self.expire(now);
+        MachineConnections {
+            active: self.clients.values().map(|(active, _)| active).sum(),
+            idle_since: self.idle_since,
+        }
+    }
+
+    /// Drops expired leases; a machine left without connections by one is
+    /// idle from when the last of them expired
+    fn expire(&mut self, now: DateTime<Utc>) {
+        let expired = self.clients.values()
+            .map(|(_, expires)| *expires)
+            .filter(|expires| *expires <= now)
+            .max();
+        self.clients.retain(|_, (_, expires)| *expires > now);
+        if let (Some(expired), true) = (expired, self.clients.is_empty()) {
+            self.idle_since = Some(expired);
+        }
+    }
+}
+
+/// Stops started machines that have been idle for their idle period
+#[derive(Default)]
+pub struct IdleMonitor {
+    /// When the monitor first saw each machine started
+    started: HashMap<String, DateTime<Utc>>,
+}
+
+impl IdleMonitor {
+    /// Checks for idle machines every [`MONITOR_INTERVAL`] for as long as the API runs
+    pub fn spawn(mut self, state: AppState) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MONITOR_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.check(&state).await;
+            }
+        });
+    }
+
+    pub async fn check(&mut self, state: &AppState) {
+        let machines: Vec<(String, Duration)> = state.machines.read().unwrap()
+            .values()
+            .filter(|machine| machine.state == MachineState::Started)
+            .filter_map(|machine| Some((machine.id.clone(), idle_period(&machine.config)?)))
+            .collect();
+        // Machines that stop start over as if never seen, and connections of
+        // destroyed machines are forgotten
+        self.started.retain(|id, _| machines.iter().any(|(machine_id, _)| machine_id == id));
+        {
+            let existing = state.machines.read().unwrap();
+            state.connections.write().unwrap().retain(|id, _| existing.contains_key(id));
+        }
+
+        let now = Utc::now();
+        for (machine_id, period) in machines {
+            let started = *self.started.entry(machine_id.clone()).or_insert(now);
+            let connections = state.connections.write().unwrap().get_mut(&machine_id)
+                .map(|leases| leases.status(now))
+                .unwrap_or_default();
+            if connections.active > 0 {
+                continue;
+            }
+            let idle_since = connections.idle_since.map_or(started, |since| since.max(started));
+            if (now - idle_since).to_std().unwrap_or_default() < period {
+                continue;
+            }
+
+            let app_name = match state.docker.get_container_id_by_machine(&machine_id).await {
+                Ok(Some(container_id)) => state.docker.inspect_container(&container_id).await.ok()
+                    .and_then(|info| info.config?.labels?.get("minifly.app_name").cloned()),
+                _ => None,
+            };
+            let Some(app_name) = app_name else {
+                debug!(machine_id = %machine_id, "Skipping idle machine whose container can't be inspected");
+                continue;
+            };
+
+            match crate::handlers::stop(state, &app_name, &machine_id, None, "autostop").await {
+                Ok(()) => info!(machine_id = %machine_id, app_name = %app_name, "Stopped idle machine"),
+                Err(e) => warn!(machine_id = %machine_id, "Failed to stop idle machine: {:?}", e.0),
+            }
+            self.started.remove(&machine_id);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use minifly_core::models::Machine;
+
+    #[tokio::test]
+    async fn test_idle_machine_is_stopped() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |request| {
+            let labels = serde_json::json!({ "minifly.machine_id": "d8e7f6", "minifly.app_name": "web" });
+            let body = if request.starts_with("POST") {
+                return (204, String::new());
+            } else if request.contains("/containers/json") {
+                serde_json::json!([{ "Id": "abc123", "Labels": labels }])
+            } else {
+                serde_json::json!({ "Id": "abc123", "Config": { "Labels": labels } })
+            };
+            (200, body.to_string())
+        });
+
+        let config: MachineConfig = serde_json::from_value(serde_json::json!({
+            "image": "web:latest",
+            "guest": { "cpu_kind": "shared", "cpus": 1, "memory_mb": 256 },
+            "services": [{
+                "protocol": "tcp",
+                "internal_port": 8080,
+                "ports": [{ "port": 80, "handlers": ["http"] }],
+                "autostop": { "enabled": true, "seconds": 60 },
+            }],
+        })).unwrap();
+        assert_eq!(idle_period(&config), Some(Duration::from_secs(60)));
+        state.machines.write().unwrap().insert("d8e7f6".to_string(), Machine {
+            id: "d8e7f6".to_string(),
+            name: "web-d8e7f6".to_string(),
+            app_name: "web".to_string(),
+            state: MachineState::Started,
+            region: "local".to_string(),
+            image_ref: crate::handlers::parse_image_ref(&config.image),
+            instance_id: state.generate_instance_id(),
+            private_ip: "fdaa::2".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            config,
+            events: Vec::new(),
+            cordoned: false,
+            checks: Vec::new(),
+            mapped_ports: Vec::new(),
+        });
+
+        let mut monitor = IdleMonitor::default();
+        monitor.check(&state).await;
+        assert_eq!(state.machines.read().unwrap()["d8e7f6"].state, MachineState::Started);
+
+        // An open connection keeps the machine up however long it has run
+        monitor.started.insert("d8e7f6".to_string(), Utc::now() - chrono::Duration::minutes(5));
+        let mut leases = ConnectionLeases::default();
+        leases.report("proxy-1", 1, Utc::now());
+        state.connections.write().unwrap().insert("d8e7f6".to_string(), leases);
+        monitor.check(&state).await;
+        assert_eq!(state.machines.read().unwrap()["d8e7f6"].state, MachineState::Started);
+
+        // A proxy that exited stops renewing its lease, so its connection
+        // counts as closed once the lease expires
+        let mut leases = ConnectionLeases::default();
+        let reported = Utc::now() - chrono::Duration::seconds(CONNECTION_LEASE_SECS as i64 + 61);
+        leases.report("proxy-1", 1, reported);
+        state.connections.write().unwrap().insert("d8e7f6".to_string(), leases);
+        monitor.check(&state).await;
+        let machine = state.machines.read().unwrap()["d8e7f6"].clone();
+        assert_eq!(machine.state, MachineState::Stopped);
+        assert_eq!(machine.events.last().unwrap().source, "autostop");
+        assert!(requests.lock().unwrap().iter().any(|r| r.contains("/stop")));
+    }
+
+    #[test]
+    fn test_connection_leases_sum_clients_and_expire() {
+        let now = Utc::now();
+        let mut leases = ConnectionLeases::default();
+        leases.report("gateway", 2, now);
+        leases.report("proxy", 1, now);
+        assert_eq!(leases.status(now), MachineConnections { active: 3, idle_since: None });
+
+        // Reports replace a client's count rather than adding to it
+        leases.report("gateway", 0, now);
+        assert_eq!(leases.status(now).active, 1);
+
+        let later = now + chrono::Duration::seconds(CONNECTION_LEASE_SECS as i64 + 1);
+        let expired = now + chrono::Duration::seconds(CONNECTION_LEASE_SECS as i64);
+        assert_eq!(leases.status(later), MachineConnections { active: 0, idle_since: Some(expired) });
+
+        leases.report("gateway", 0, later);
+        assert_eq!(leases.status(later).idle_since, Some(later));
+    }
+}
@@ -0,0 +1,355 @@
+//! Runs the checks in machines' `config.checks`
+//!
+//! Once a started machine's `grace_period` has passed, each of its checks is
+//! run every `interval` and the latest result is stored in `Machine.checks`,
+//! where `get_machine` and the platform health report pick it up. `http` and
+//! `tcp` checks connect to the port Docker published for the check's port,
+//! falling back to the container's own address; `script` checks run their
+//! command inside the container. Due checks run concurrently, so a check
+//! waiting out its timeout delays no other. Durations are in seconds.
+
+use crate::state::AppState;
+use bollard::models::ContainerInspectResponse;
+use chrono::Utc;
+use minifly_core::models::{CheckConfig, CheckStatus, HealthCheck, MachineState};
+use minifly_network::extract_container_ip;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// How often the checker looks for checks that are due
+const CHECKER_INTERVAL: Duration = Duration::from_secs(1);
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(15);
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest output kept per check result
+const MAX_OUTPUT: usize = 1024;
+
+/// The outcome of running a check once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub passing: bool,
+    pub output: String,
+}
+
+impl CheckResult {
+    fn new(passing: bool, output: impl Into<String>) -> Self {
+        let mut output = output.into();
+        if output.len() > MAX_OUTPUT {
+            let end = (0..=MAX_OUTPUT).rev().find(|&i| output.is_char_boundary(i)).unwrap_or(0);
+            output.truncate(end);
+        }
+        Self { passing, output }
+    }
+}
+
+/// Sends `method path` to `addr`, passing on `expected_status` or any 2xx without one
+pub async fn http_check(
+    client: &reqwest::Client,
+    addr: SocketAddr,
+    method: &str,
+    path: &str,
+    expected_status: Option<u16>,
+    timeout: Duration,
+) -> CheckResult {
+    let method = match reqwest::Method::from_bytes(method.as_bytes()) {
+        Ok(method) => method,
+        Err(_) => return CheckResult::new(false, format!("invalid method {}", method)),
+    };
+    let url = format!("http://{}{}", addr, path);
+    match client.request(method, &url).timeout(timeout).send().await {
+        Ok(response) => {
+            let status = response.status();
+            let passing = expected_status.map_or(status.is_success(), |expected| status.as_u16() == expected);
+            let body = response.text().await.unwrap_or_default();
+            CheckResult::new(passing, format!("{} {}", status, body.trim()).trim_end().to_string())
+        }
+        Err(e) => CheckResult::new(false, format!("{} failed: {}", url, e)),
+    }
+}
+
+/// Passes when a TCP connection to `addr` opens within `timeout`
+pub async fn tcp_check(addr: SocketAddr, timeout: Duration) -> CheckResult {
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => CheckResult::new(true, format!("connected to {}", addr)),
+        Ok(Err(e)) => CheckResult::new(false, format!("connecting to {} failed: {}", addr, e)),
+        Err(_) => CheckResult::new(false, format!("connecting to {} timed out", addr)),
+    }
+}
+
+async fn script_check(state: &AppState, container_id: &str, command: &[String], timeout: Duration) -> CheckResult {
+    match tokio::time::timeout(timeout, state.docker.exec(container_id, command)).await {
+        Ok(Ok((exit_code, output))) => CheckResult::new(exit_code == 0, format!("exit code {}: {}", exit_code, output.trim())),
+        Ok(Err(e)) => CheckResult::new(false, format!("running {:?} failed: {}", command, e)),
+        Err(_) => CheckResult::new(false, format!("{:?} timed out", command)),
+    }
+}
+
+/// Where the API reaches a container's `port`: the host port Docker
+/// published for it, or the port on the container's own address
+pub fn check_address(info: &ContainerInspectResponse, port: u16) -> Option<SocketAddr> {
+    let settings = info.network_settings.as_ref()?;
+    let published = settings.ports.as_ref()
+        .and_then(|ports| ports.get(&format!("{}/tcp", port)).cloned().flatten())
+        .and_then(|bindings| bindings.into_iter().find_map(|binding| binding.host_port?.parse::<u16>().ok()));
+    if let Some(host_port) = published {
+        return Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), host_port));
+    }
+
+    let networks = serde_json::to_value(settings.networks.as_ref()?).ok()?;
+    extract_container_ip(&networks).map(|ip| SocketAddr::new(ip, port))
+}
+
+async fn run_check(state: &AppState, client: &reqwest::Client, info: &ContainerInspectResponse, check: &HealthCheck) -> CheckResult {
+    let timeout = check.timeout.map_or(DEFAULT_TIMEOUT, Duration::from_secs);
+    let config = match check.config() {
+        Ok(config) => config,
+        Err(msg) => return CheckResult::new(false, msg),
+    };
+    match config {
+        CheckConfig::Http { port, method, path, expected_status } => match check_address(info, port) {
+            Some(addr) => http_check(client, addr, &method, &path, expected_status, timeout).await,
+            None => CheckResult::new(false, format!("port {} is not reachable", port)),
+        },
+        CheckConfig::Tcp { port } => match check_address(info, port) {
+            Some(addr) => tcp_check(addr, timeout).await,
+            None => CheckResult::new(false, format!("port {} is not reachable", port)),
+        },
+        CheckConfig::Script { command } => match &info.id {
+            Some(container_id) => script_check(state, container_id, &command, timeout).await,
+            None => CheckResult::new(false, "container has no ID"),
+        },
+    }
+}
+
+/// Runs the named checks of a container concurrently, returning their
+/// results in the same order
+async fn run_checks(
+    state: &AppState,
+    client: &reqwest::Client,
+    info: &ContainerInspectResponse,
+    checks: &HashMap<String, HealthCheck>,
+    names: &[String],
+) -> Vec<CheckResult> {
+    futures::future::join_all(names.iter().map(|name| run_check(state, client, info, &checks[name]))).await
+}
+
+/// Runs due checks of every started machine
+#[derive(Default)]
+pub struct Checker {
+    /// When the checker first saw each machine started, for grace periods
+    started: HashMap<String, Instant>,
+    last_run: HashMap<(String, String), Instant>,
+    /// Shared by every `http` check
+    client: reqwest::Client,
+}
+
+impl Checker {
+    /// Runs due checks every [`CHECKER_INTERVAL`] for as long as the API runs
+    pub fn spawn(mut self, state: AppState) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECKER_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.run_due(&state).await;
+            }
+        });
+    }
+
+    /// Runs every due check, all at once so a slow check holds up no other
+    pub async fn run_due(&mut self, state: &AppState) {
+        let machines: Vec<(String, HashMap<String, HealthCheck>)> = state.machines.read().unwrap()
+            .values()
+            .filter(|machine| machine.state == MachineState::Started)
+            .filter_map(|machine| Some((machine.id.clone(), machine.config.checks.clone().filter(|c| !c.is_empty())?)))
+            .collect();
+        // Machines that stop start their grace period over
+        self.started.retain(|id, _| machines.iter().any(|(machine_id, _)| machine_id == id));
+        self.last_run.retain(|(id, _), _| self.started.contains_key(id));
+
+        let now = Instant::now();
+        let mut runs = Vec::new();
+        for (machine_id, checks) in machines {
+            let started = *self.started.entry(machine_id.clone()).or_insert(now);
+            let mut due: Vec<String> = checks.iter()
+                .filter(|(_, check)| now.duration_since(started) >= Duration::from_secs(check.grace_period.unwrap_or(0)))
+                .filter(|(name, check)| {
+                    let interval = check.interval.map_or(DEFAULT_INTERVAL, Duration::from_secs);
+                    self.last_run.get(&(machine_id.clone(), name.to_string()))
+                        .is_none_or(|last| now.duration_since(*last) >= interval)
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            if due.is_empty() {
+                continue;
+            }
+            due.sort();
+            runs.push(run_machine_checks(state, &self.client, machine_id, checks, due));
+        }
+
+        for (machine_id, ran) in futures::future::join_all(runs).await {
+            for name in ran {
+                self.last_run.insert((machine_id.clone(), name), now);
+            }
+        }
+    }
+}
+
+/// Runs a machine's due checks and records their results, returning the
+/// machine ID and the checks that ran. None run while its container can't be
+/// inspected.
+async fn run_machine_checks(
+    state: &AppState,
+    client: &reqwest::Client,
+    machine_id: String,
+    checks: HashMap<String, HealthCheck>,
+    due: Vec<String>,
+) -> (String, Vec<String>) {
+    let info = match state.docker.get_container_id_by_machine(&machine_id).await {
+        Ok(Some(container_id)) => state.docker.inspect_container(&container_id).await,
+        Ok(None) => return (machine_id, Vec::new()),
+        Err(e) => Err(e),
+    };
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            debug!(machine_id = %machine_id, "Skipping checks of machine whose container can't be inspected: {}", e);
+            return (machine_id, Vec::new());
+        }
+    };
+
+    let results = run_checks(state, client, &info, &checks, &due).await;
+    for (name, result) in due.iter().zip(results) {
+        record(state, &machine_id, &checks, name, result);
+    }
+    (machine_id, due)
+}
+
+/// Stores a check result on the machine, dropping results of checks no
+/// longer in its config
+fn record(state: &AppState, machine_id: &str, checks: &HashMap<String, HealthCheck>, name: &str, result: CheckResult) {
+    let mut machines = state.machines.write().unwrap();
+    let Some(machine) = machines.get_mut(machine_id) else { return };
+
+    let status = if result.passing { "passing" } else { "critical" };
+    let previous = machine.checks.iter().find(|check| check.name == name).map(|check| check.status.clone());
+    if previous.as_deref() != Some(status) {
+        if result.passing {
+            info!(machine_id = %machine_id, check = %name, "Machine check is passing");
+        } else {
+            warn!(machine_id = %machine_id, check = %name, output = %result.output, "Machine check is failing");
+        }
+    }
+
+    machine.checks.retain(|check| check.name != name && checks.contains_key(&check.name));
+    machine.checks.push(CheckStatus {
+        name: name.to_string(),
+        status: status.to_string(),
+        output: result.output,
+        updated_at: Utc::now(),
+    });
+    machine.checks.sort_by(|a, b| a.name.cmp(&b.name));
+}
+
+/// Failing checks of started machines as `machine_id/check`, sorted
+pub fn failing_checks(state: &AppState) -> Vec<String> {
+    let mut failing: Vec<String> = state.machines.read().unwrap()
+        .values()
+        .filter(|machine| machine.state == MachineState::Started)
+        .flat_map(|machine| {
+            machine.checks.iter()
+                .filter(|check| !check.passing())
+                .map(move |check| format!("{}/{}", machine.id, check.name))
+        })
+        .collect();
+    failing.sort();
+    failing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_http_check_against_mock_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/healthz"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ready"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let addr = *server.address();
+        let timeout = Duration::from_secs(2);
+        let client = reqwest::Client::new();
+
+        let result = http_check(&client, addr, "GET", "/healthz", None, timeout).await;
+        assert!(result.passing, "{}", result.output);
+        assert_eq!(result.output, "200 OK ok");
+
+        assert!(!http_check(&client, addr, "GET", "/ready", None, timeout).await.passing);
+        // An expected status replaces the 2xx rule
+        assert!(http_check(&client, addr, "GET", "/ready", Some(503), timeout).await.passing);
+        assert!(!http_check(&client, addr, "GET", "/healthz", Some(204), timeout).await.passing);
+    }
+
+    #[tokio::test]
+    async fn test_checks_run_concurrently() {
+        let delay = Duration::from_millis(300);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(delay))
+            .mount(&server)
+            .await;
+        let info: ContainerInspectResponse = serde_json::from_value(serde_json::json!({
+            "NetworkSettings": {
+                "Ports": { "8080/tcp": [{ "HostIp": "0.0.0.0", "HostPort": server.address().port().to_string() }] },
+            },
+        })).unwrap();
+        let check: HealthCheck = serde_json::from_value(serde_json::json!({ "type": "http", "port": 8080, "path": "/slow" })).unwrap();
+        let checks: HashMap<String, HealthCheck> = ["a", "b", "c"].iter().map(|name| (name.to_string(), check.clone())).collect();
+        let names: Vec<String> = checks.keys().cloned().collect();
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+
+        let start = std::time::Instant::now();
+        let results = run_checks(&state, &reqwest::Client::new(), &info, &checks, &names).await;
+        assert!(results.iter().all(|result| result.passing), "{:?}", results);
+        assert!(start.elapsed() < delay * 3, "checks ran one after another: {:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_check() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(tcp_check(addr, Duration::from_secs(2)).await.passing);
+
+        drop(listener);
+        let result = tcp_check(addr, Duration::from_secs(2)).await;
+        assert!(!result.passing);
+        assert!(result.output.contains("failed"), "{}", result.output);
+    }
+
+    #[test]
+    fn test_check_address_prefers_published_port() {
+        let info: ContainerInspectResponse = serde_json::from_value(serde_json::json!({
+            "NetworkSettings": {
+                "Ports": { "8080/tcp": [{ "HostIp": "0.0.0.0", "HostPort": "32768" }] },
+                "Networks": { "minifly": { "IPAddress": "172.19.0.7" } },
+            },
+        })).unwrap();
+        assert_eq!(check_address(&info, 8080), Some("127.0.0.1:32768".parse().unwrap()));
+        assert_eq!(check_address(&info, 5432), Some("172.19.0.7:5432".parse().unwrap()));
+    }
+}
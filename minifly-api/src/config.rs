@@ -1,4 +1,6 @@
 use anyhow::Result;
+use minifly_litefs::process::RestartPolicy;
+use minifly_core::region::RegionLatency;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +12,107 @@ pub struct Config {
     pub internal_network_prefix: String,
     pub dns_port: u16,
     pub litefs_port: u16,
+    /// Tee container logs into rotating files under `data_dir/logs`
+    #[serde(default)]
+    pub persist_logs: bool,
+    /// Size at which a persisted log file is rotated
+    #[serde(default = "default_log_max_bytes")]
+    pub log_max_bytes: u64,
+    /// Services that must pass for `/v1/health/ready` to report ready
+    #[serde(default = "default_readiness_services")]
+    pub readiness_services: Vec<String>,
+    /// Free space in `data_dir` or the volume root below which the
+    /// filesystem check is degraded and fails readiness. Below a tenth of it
+    /// the check is unhealthy.
+    #[serde(default = "default_disk_low_space_bytes")]
+    pub disk_low_space_bytes: u64,
+    /// What to do when a machine's LiteFS process exits on its own
+    #[serde(default)]
+    pub litefs_restart_policy: RestartPolicy,
+    /// Region requests and machines are in unless they name another
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Simulated latency for traffic to machines in another region
+    #[serde(default)]
+    pub region_latency: RegionLatency,
+    /// Local S3-compatible storage injected into every machine's environment
+    #[serde(default)]
+    pub object_storage: Option<ObjectStorage>,
+    /// Publish service ports on host ports derived from the app, instead of
+    /// ports Docker picks, so an app keeps its ports across deploys
+    #[serde(default)]
+    pub stable_ports: bool,
+    /// Events kept per machine, oldest dropped first; `None` keeps them all
+    #[serde(default = "default_max_machine_events")]
+    pub max_machine_events: Option<usize>,
+}
+
+/// Credentials of the MinIO container `minifly serve --minio` manages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectStorage {
+    pub endpoint: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub bucket: String,
+}
+
+impl ObjectStorage {
+    /// Reads `MINIFLY_MINIO_*`, returning `None` unless credentials are set
+    fn from_env() -> Option<Self> {
+        let access_key = std::env::var("MINIFLY_MINIO_ACCESS_KEY").ok()?;
+        let secret_key = std::env::var("MINIFLY_MINIO_SECRET_KEY").ok()?;
+        Some(Self {
+            endpoint: std::env::var("MINIFLY_MINIO_ENDPOINT")
+                .unwrap_or_else(|_| "http://localhost:9000".to_string()),
+            access_key,
+            secret_key,
+            bucket: std::env::var("MINIFLY_MINIO_BUCKET")
+                .unwrap_or_else(|_| "minifly".to_string()),
+        })
+    }
+}
+
+/// Services the readiness probe knows how to check
+pub const READINESS_SERVICES: [&str; 5] = ["database", "docker", "litefs", "filesystem", "dns"];
+
+fn default_log_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_disk_low_space_bytes() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_max_machine_events() -> Option<usize> {
+    Some(100)
+}
+
+fn default_region() -> String {
+    crate::middleware::region::DEFAULT_REGION.to_string()
+}
+
+fn default_readiness_services() -> Vec<String> {
+    vec!["database".to_string(), "docker".to_string(), "filesystem".to_string()]
+}
+
+/// Parses a comma-separated list of readiness services, rejecting unknown names
+fn parse_readiness_services(value: &str) -> Result<Vec<String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|service| !service.is_empty())
+        .map(|service| {
+            if READINESS_SERVICES.contains(&service) {
+                Ok(service.to_string())
+            } else {
+                anyhow::bail!(
+                    "Unknown readiness service '{}'; expected one of {}",
+                    service,
+                    READINESS_SERVICES.join(", ")
+                )
+            }
+        })
+        .collect()
 }
 
 impl Config {
@@ -31,6 +134,37 @@ impl Config {
             litefs_port: std::env::var("MINIFLY_LITEFS_PORT")
                 .unwrap_or_else(|_| "20202".to_string())
                 .parse()?,
+            persist_logs: std::env::var("MINIFLY_PERSIST_LOGS")
+                .is_ok_and(|v| v == "1" || v == "true"),
+            log_max_bytes: match std::env::var("MINIFLY_LOG_MAX_BYTES") {
+                Ok(bytes) => bytes.parse()?,
+                Err(_) => default_log_max_bytes(),
+            },
+            readiness_services: match std::env::var("MINIFLY_READINESS_SERVICES") {
+                Ok(services) => parse_readiness_services(&services)?,
+                Err(_) => default_readiness_services(),
+            },
+            disk_low_space_bytes: match std::env::var("MINIFLY_DISK_LOW_SPACE_BYTES") {
+                Ok(bytes) => bytes.parse()?,
+                Err(_) => default_disk_low_space_bytes(),
+            },
+            litefs_restart_policy: match std::env::var("MINIFLY_LITEFS_RESTART") {
+                Ok(policy) => RestartPolicy::parse(&policy)?,
+                Err(_) => RestartPolicy::default(),
+            },
+            region: std::env::var("MINIFLY_REGION").unwrap_or_else(|_| default_region()),
+            region_latency: match std::env::var("MINIFLY_REGION_LATENCY") {
+                Ok(latency) => RegionLatency::parse(&latency)?,
+                Err(_) => RegionLatency::default(),
+            },
+            object_storage: ObjectStorage::from_env(),
+            stable_ports: std::env::var("MINIFLY_STABLE_PORTS")
+                .is_ok_and(|v| v == "1" || v == "true"),
+            // 0 keeps every event
+            max_machine_events: match std::env::var("MINIFLY_MAX_MACHINE_EVENTS") {
+                Ok(max) => Some(max.parse()?).filter(|&max: &usize| max > 0),
+                Err(_) => default_max_machine_events(),
+            },
         })
     }
 }
@@ -52,6 +186,7 @@ mod tests {
         env::remove_var("MINIFLY_NETWORK_PREFIX");
         env::remove_var("MINIFLY_DNS_PORT");
         env::remove_var("MINIFLY_LITEFS_PORT");
+        env::remove_var("MINIFLY_REGION");
         
         let config = Config::from_env().unwrap();
         
@@ -62,6 +197,8 @@ mod tests {
         assert_eq!(config.internal_network_prefix, "fdaa:0:");
         assert_eq!(config.dns_port, 5353);
         assert_eq!(config.litefs_port, 20202);
+        assert_eq!(config.readiness_services, vec!["database", "docker", "filesystem"]);
+        assert_eq!(config.region, "local");
     }
     
     #[test]
@@ -117,6 +254,13 @@ mod tests {
         env::remove_var("MINIFLY_DNS_PORT");
     }
     
+    #[test]
+    fn test_parse_readiness_services() {
+        assert_eq!(parse_readiness_services("database, dns").unwrap(), vec!["database", "dns"]);
+        assert!(parse_readiness_services("").unwrap().is_empty());
+        assert!(parse_readiness_services("database,redis").is_err());
+    }
+    
     #[test]
     #[serial]
     fn test_partial_config() {
@@ -6,32 +6,199 @@ use bollard::{
     service::{HostConfig, PortBinding, RestartPolicy, RestartPolicyNameEnum, Mount, MountTypeEnum},
 };
 use futures::StreamExt;
-use minifly_core::models::{MachineConfig, GuestConfig, MountConfig};
-use std::collections::HashMap;
+use crate::config::ObjectStorage;
+use minifly_core::models::{MachineConfig, GuestConfig, MountConfig, FileConfig, MappedPort};
+use minifly_core::volumes;
+use minifly_core::Error as CoreError;
+use minifly_litefs::manager::DatabaseMount;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
-use tracing::{debug, error, info};
+use std::sync::{Arc, RwLock};
+use tracing::{debug, error, info, warn};
 
+/// Label holding the JSON machine config a container was created from, so
+/// its machine can be rebuilt after an API restart. Labels are visible to
+/// anyone who can inspect the container, so the config's env values, which
+/// may be interpolated secrets, are left out; see [`config_label`].
+pub const CONFIG_LABEL: &str = "minifly.config";
+
+/// Label listing the names of the secrets injected into a container's
+/// environment, comma-separated
+pub const SECRETS_LABEL: &str = "minifly.secrets";
+
+/// Host ports stable ports are picked from
+const STABLE_PORTS: RangeInclusive<u16> = 20000..=29999;
+
+/// Stable ports tried for a service port before falling back to one Docker picks
+const STABLE_PORT_ATTEMPTS: u32 = 8;
+
+/// Name containers reach the host by, e.g. for the MinIO `serve --minio` runs
+const HOST_GATEWAY: &str = "host.docker.internal";
+
+/// Picks the host port to publish an app's service port on when stable ports
+/// are enabled: the first of a fixed sequence of ports derived from the app,
+/// port and protocol that `is_free` accepts. Machines of an app get the same
+/// ports each time they are created, in the order they are created; `None`
+/// means every candidate was taken and Docker should pick a port.
+pub fn stable_host_port(app_name: &str, internal_port: u16, protocol: &str, is_free: impl Fn(u16) -> bool) -> Option<u16> {
+    let range = u64::from(STABLE_PORTS.end() - STABLE_PORTS.start()) + 1;
+    (0..STABLE_PORT_ATTEMPTS)
+        .map(|attempt| {
+            // FNV-1a, which unlike std's hasher is stable across releases
+            let key = format!("{}/{}/{}/{}", app_name, internal_port, protocol, attempt);
+            let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+            STABLE_PORTS.start() + (hash % range) as u16
+        })
+        .find(|&port| is_free(port))
+}
+
+/// Whether nothing on this host is listening on a port
+fn host_port_is_free(port: u16, protocol: &str) -> bool {
+    if protocol == "udp" {
+        std::net::UdpSocket::bind(("0.0.0.0", port)).is_ok()
+    } else {
+        std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
+    }
+}
+
+/// The host ports a container's ports are published on
+pub fn mapped_ports(info: &bollard::models::ContainerInspectResponse) -> Vec<MappedPort> {
+    let Some(ports) = info.network_settings.as_ref().and_then(|settings| settings.ports.as_ref()) else {
+        return Vec::new();
+    };
+    let mut mapped: Vec<MappedPort> = ports.iter()
+        .filter_map(|(port, bindings)| {
+            let (internal_port, protocol) = port.split_once('/')?;
+            let host_port = bindings.as_ref()?.iter().find_map(|b| b.host_port.as_ref()?.parse().ok())?;
+            Some(MappedPort { internal_port: internal_port.parse().ok()?, protocol: protocol.to_string(), host_port })
+        })
+        .collect();
+    mapped.sort_by_key(|port| (port.internal_port, port.protocol.clone()));
+    mapped
+}
+
+/// The environment variables a container was created with, by name
+pub fn container_env(info: &bollard::models::ContainerInspectResponse) -> BTreeMap<String, String> {
+    info.config.as_ref()
+        .and_then(|config| config.env.as_ref())
+        .map(|env| env.iter()
+            .filter_map(|var| var.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// The [`CONFIG_LABEL`] value for a config: its JSON with every env value
+/// blanked, keeping only the variable names
+pub fn config_label(config: &MachineConfig) -> Option<String> {
+    let mut config = config.clone();
+    config.env = config.env.map(|env| env.into_keys().map(|key| (key, String::new())).collect());
+    serde_json::to_string(&config).ok()
+}
+
+/// Names of the secrets injected into a container, from its [`SECRETS_LABEL`];
+/// empty for containers created before the label existed
+pub fn container_secrets(info: &bollard::models::ContainerInspectResponse) -> Vec<String> {
+    info.config.as_ref()
+        .and_then(|config| config.labels.as_ref())
+        .and_then(|labels| labels.get(SECRETS_LABEL))
+        .map(|keys| keys.split(',').filter(|key| !key.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Error for Docker operations attempted while the daemon can't be reached
+#[derive(Debug, thiserror::Error)]
+#[error("Docker unavailable: {0}")]
+pub struct DockerUnavailable(pub String);
+
+impl From<DockerUnavailable> for CoreError {
+    fn from(err: DockerUnavailable) -> Self {
+        CoreError::DockerUnavailable(err.0)
+    }
+}
+
+/// Maps a failed Docker operation to an API error, reporting an unreachable
+/// daemon as [`CoreError::DockerUnavailable`]
+pub(crate) fn docker_error(context: &str, err: anyhow::Error) -> CoreError {
+    if let Some(unavailable) = err.downcast_ref::<DockerUnavailable>() {
+        return CoreError::DockerUnavailable(unavailable.0.clone());
+    }
+    let unreachable = err.chain().any(|cause| {
+        cause.downcast_ref::<std::io::Error>().is_some_and(|io| {
+            matches!(io.kind(), std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound)
+        })
+    });
+    if unreachable {
+        return CoreError::DockerUnavailable(format!("{}: {}", context, err));
+    }
+    CoreError::DockerError(format!("{}: {}", context, err))
+}
+
+/// Docker client that connects lazily
+///
+/// The API starts even when the Docker daemon is down, e.g. while Docker
+/// Desktop is still starting. Until a connection succeeds every operation
+/// fails with [`DockerUnavailable`], and each one retries the connection.
 #[derive(Clone)]
 pub struct DockerClient {
-    client: Docker,
+    docker_host: Option<String>,
+    client: Arc<RwLock<Option<Docker>>>,
+    object_storage: Option<ObjectStorage>,
+    stable_ports: bool,
 }
 
 impl DockerClient {
-    pub fn new(docker_host: Option<&str>) -> Result<Self> {
-        let client = if let Some(host) = docker_host {
-            Docker::connect_with_socket(host, 120, bollard::API_DEFAULT_VERSION)?
-        } else {
-            Docker::connect_with_local_defaults()?
+    pub fn new(docker_host: Option<&str>) -> Self {
+        let client = Self {
+            docker_host: docker_host.map(str::to_string),
+            client: Arc::new(RwLock::new(None)),
+            object_storage: None,
+            stable_ports: false,
         };
+        if let Err(e) = client.client() {
+            warn!("{}; machine operations will fail until it is reachable", e);
+        }
+        client
+    }
+    
+    /// Points every machine's S3/Tigris settings at local object storage
+    pub fn with_object_storage(mut self, object_storage: Option<ObjectStorage>) -> Self {
+        self.object_storage = object_storage;
+        self
+    }
+    
+    /// Publishes service ports on [`stable_host_port`]s rather than ports Docker picks
+    pub fn with_stable_ports(mut self, stable_ports: bool) -> Self {
+        self.stable_ports = stable_ports;
+        self
+    }
+    
+    /// Returns the connected client, connecting first if needed
+    fn client(&self) -> Result<Docker> {
+        if let Some(client) = self.client.read().unwrap().as_ref() {
+            return Ok(client.clone());
+        }
+        
+        let client = match &self.docker_host {
+            Some(host) => Docker::connect_with_socket(host, 120, bollard::API_DEFAULT_VERSION),
+            None => Docker::connect_with_local_defaults(),
+        }
+        .map_err(|e| DockerUnavailable(e.to_string()))?;
         
-        Ok(Self { client })
+        *self.client.write().unwrap() = Some(client.clone());
+        Ok(client)
     }
     
     pub async fn create_container(
         &self,
         machine_id: &str,
         app_name: &str,
+        region: &str,
         config: &MachineConfig,
+        database: Option<&DatabaseMount>,
     ) -> Result<String> {
         info!("Creating container for machine {}", machine_id);
         
@@ -39,7 +206,7 @@ impl DockerClient {
         self.pull_image(&config.image).await?;
         
         // Build container configuration
-        let container_config = self.build_container_config(machine_id, app_name, config).await?;
+        let container_config = self.build_container_config(machine_id, app_name, region, config, database).await?;
         
         // Create container
         let options = CreateContainerOptions {
@@ -47,7 +214,7 @@ impl DockerClient {
             ..Default::default()
         };
         
-        let response = self.client
+        let response = self.client()?
             .create_container(Some(options), container_config)
             .await
             .context("Failed to create container")?;
@@ -58,7 +225,7 @@ impl DockerClient {
     pub async fn start_container(&self, container_id: &str) -> Result<()> {
         info!("Starting container {}", container_id);
         
-        self.client
+        self.client()?
             .start_container(container_id, None::<StartContainerOptions<String>>)
             .await
             .context("Failed to start container")?;
@@ -73,7 +240,7 @@ impl DockerClient {
             t: timeout.unwrap_or(30),
         };
         
-        self.client
+        self.client()?
             .stop_container(container_id, Some(options))
             .await
             .context("Failed to stop container")?;
@@ -89,16 +256,15 @@ impl DockerClient {
             ..Default::default()
         };
         
-        self.client
-            .remove_container(container_id, Some(options))
-            .await
-            .context("Failed to remove container")?;
-        
-        Ok(())
+        match self.client()?.remove_container(container_id, Some(options)).await {
+            // Already gone, e.g. a machine that was never launched
+            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => Ok(()),
+            result => result.context("Failed to remove container"),
+        }
     }
     
     pub async fn inspect_container(&self, container_id: &str) -> Result<bollard::models::ContainerInspectResponse> {
-        self.client
+        self.client()?
             .inspect_container(container_id, None)
             .await
             .context("Failed to inspect container")
@@ -106,42 +272,23 @@ impl DockerClient {
     
     /// Get the assigned host ports for a container
     /// 
-    /// Since we use automatic port allocation (port 0), Docker assigns ephemeral ports.
-    /// This function retrieves the actual assigned ports after container creation.
+    /// Unless stable ports are enabled, Docker assigns ephemeral ports (port 0).
+    /// This function retrieves the actual assigned ports after the container starts.
     /// 
     /// # Arguments
     /// * `container_id` - The Docker container ID
     /// 
     /// # Returns
-    /// * `Ok(Vec<u16>)` - List of assigned host ports
+    /// * `Ok(Vec<MappedPort>)` - The host port each published container port is mapped to
     /// * `Err(...)` - Failed to inspect container
-    pub async fn get_container_ports(&self, container_id: &str) -> Result<Vec<u16>> {
+    pub async fn get_container_ports(&self, container_id: &str) -> Result<Vec<MappedPort>> {
         let container_info = self.inspect_container(container_id).await?;
-        let mut ports = Vec::new();
-        
-        if let Some(network_settings) = &container_info.network_settings {
-            if let Some(port_bindings) = &network_settings.ports {
-                for (_, bindings) in port_bindings {
-                    if let Some(bindings) = bindings {
-                        for binding in bindings {
-                            if let Some(host_port) = &binding.host_port {
-                                if let Ok(port) = host_port.parse::<u16>() {
-                                    ports.push(port);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        ports.sort();
-        Ok(ports)
+        Ok(mapped_ports(&container_info))
     }
     
     /// Get Docker daemon version information
     pub async fn version(&self) -> Result<bollard::system::Version> {
-        self.client
+        self.client()?
             .version()
             .await
             .context("Failed to get Docker version")
@@ -155,7 +302,7 @@ impl DockerClient {
             ..Default::default()
         };
         
-        self.client
+        self.client()?
             .list_containers(Some(options))
             .await
             .context("Failed to list containers")
@@ -168,19 +315,48 @@ impl DockerClient {
         follow: bool,
         tail: Option<String>,
         timestamps: bool,
+        (since, until): (i64, i64),
     ) -> Result<impl futures::Stream<Item = Result<bollard::container::LogOutput, bollard::errors::Error>>> {
         use bollard::container::LogsOptions;
         
+        // Docker treats 0 as "no bound" for both ends of the window
         let options = LogsOptions::<String> {
             follow,
             stdout: true,
             stderr: true,
+            since,
+            until,
             timestamps,
             tail: tail.unwrap_or_default(),
-            ..Default::default()
         };
         
-        Ok(self.client.logs(container_id, Some(options)))
+        Ok(self.client()?.logs(container_id, Some(options)))
+    }
+    
+    /// Runs `cmd` in a running container, returning its exit code and output
+    pub async fn exec(&self, container_id: &str, cmd: &[String]) -> Result<(i64, String)> {
+        use bollard::exec::{CreateExecOptions, StartExecResults};
+        
+        let client = self.client()?;
+        let exec = client
+            .create_exec(container_id, CreateExecOptions {
+                cmd: Some(cmd.to_vec()),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to create exec")?;
+        
+        let mut output = String::new();
+        if let StartExecResults::Attached { output: mut stream, .. } = client.start_exec(&exec.id, None).await? {
+            while let Some(chunk) = stream.next().await {
+                output.push_str(&chunk?.to_string());
+            }
+        }
+        
+        let exit_code = client.inspect_exec(&exec.id).await?.exit_code.unwrap_or(-1);
+        Ok((exit_code, output))
     }
     
     /// Get container ID by machine ID
@@ -209,7 +385,7 @@ impl DockerClient {
             ..Default::default()
         };
         
-        let mut stream = self.client.create_image(Some(options), None, None);
+        let mut stream = self.client()?.create_image(Some(options), None, None);
         
         while let Some(result) = stream.next().await {
             match result {
@@ -228,13 +404,18 @@ impl DockerClient {
         &self,
         machine_id: &str,
         app_name: &str,
+        region: &str,
         config: &MachineConfig,
+        database: Option<&DatabaseMount>,
     ) -> Result<ContainerConfig<String>> {
         let mut labels = HashMap::new();
         labels.insert("minifly.managed".to_string(), "true".to_string());
         labels.insert("minifly.machine_id".to_string(), machine_id.to_string());
         labels.insert("minifly.app_name".to_string(), app_name.to_string());
-        labels.insert("minifly.region".to_string(), "local".to_string());
+        labels.insert("minifly.region".to_string(), region.to_string());
+        if let Some(config) = config_label(config) {
+            labels.insert(CONFIG_LABEL.to_string(), config);
+        }
         
         let mut container_config = ContainerConfig::<String> {
             image: Some(config.image.clone()),
@@ -245,13 +426,18 @@ impl DockerClient {
         
         // Set environment variables with Fly.io translations
         let mut env_vars = config.env.clone().unwrap_or_default();
-        self.translate_fly_env_vars(&mut env_vars, app_name, machine_id);
+        self.translate_fly_env_vars(&mut env_vars, app_name, machine_id, region);
         
         // Load and inject secrets
-        if let Ok(secrets) = self.load_secrets(app_name).await {
-            for (key, value) in secrets {
-                env_vars.insert(key, value);
-            }
+        let secrets = self.load_secrets(app_name).await
+            .context(format!("Failed to load secrets of app {}", app_name))?;
+        for (key, value) in &secrets {
+            env_vars.insert(key.clone(), value.clone());
+        }
+        let mut secret_keys: Vec<&str> = secrets.keys().map(String::as_str).collect();
+        secret_keys.sort();
+        if let Some(labels) = container_config.labels.as_mut() {
+            labels.insert(SECRETS_LABEL.to_string(), secret_keys.join(","));
         }
         
         let env_vec: Vec<String> = env_vars.iter()
@@ -259,12 +445,16 @@ impl DockerClient {
             .collect();
         container_config.env = Some(env_vec);
         
-        // Set command
+        // Set command: exec replaces the image's CMD, while entrypoint and cmd
+        // replace its ENTRYPOINT and CMD independently. Configs combining exec
+        // with either are rejected by `InitConfig::validate`.
         if let Some(init) = &config.init {
             if let Some(exec) = &init.exec {
                 container_config.cmd = Some(exec.clone());
-            } else if let Some(entrypoint) = &init.entrypoint {
-                container_config.entrypoint = Some(entrypoint.clone());
+            } else {
+                if let Some(entrypoint) = &init.entrypoint {
+                    container_config.entrypoint = Some(entrypoint.clone());
+                }
                 if let Some(cmd) = &init.cmd {
                     container_config.cmd = Some(cmd.clone());
                 }
@@ -277,44 +467,60 @@ impl DockerClient {
         // Set resource limits
         self.set_resource_limits(&mut host_config, &config.guest);
         
-        // Set restart policy
-        if let Some(restart) = &config.restart {
-            host_config.restart_policy = Some(RestartPolicy {
-                name: Some(match restart.policy.as_str() {
-                    "always" => RestartPolicyNameEnum::ALWAYS,
-                    "on-failure" => RestartPolicyNameEnum::ON_FAILURE,
-                    "unless-stopped" => RestartPolicyNameEnum::UNLESS_STOPPED,
-                    _ => RestartPolicyNameEnum::NO,
-                }),
-                maximum_retry_count: restart.max_retries.map(|n| n as i64),
-            });
-        }
+        // Docker restarts immediately and never reports the retries, so the
+        // supervisor applies the machine's restart policy instead
+        host_config.restart_policy = Some(RestartPolicy {
+            name: Some(RestartPolicyNameEnum::NO),
+            maximum_retry_count: None,
+        });
+        
+        // Docker Desktop resolves the host gateway name itself, Linux needs it mapped
+        host_config.extra_hosts = Some(vec![format!("{}:host-gateway", HOST_GATEWAY)]);
         
         // Set port bindings with automatic port allocation for local development
         // This prevents port conflicts when running multiple apps or when ports are already in use
         if let Some(services) = &config.services {
             let mut port_bindings = HashMap::new();
+            let mut exposed_ports = HashMap::new();
             
             for service in services {
-                let internal_port = format!("{}/tcp", service.internal_port);
+                let protocol = if service.protocol == "udp" { "udp" } else { "tcp" };
+                let internal_port = format!("{}/{}", service.internal_port, protocol);
+                exposed_ports.insert(internal_port.clone(), HashMap::new());
                 
                 // For local development, use automatic port allocation (port 0)
                 // Docker will assign an available ephemeral port (typically 32768-65535)
                 // This avoids conflicts with other services like web servers on port 80/443
+                let stable_port = self.stable_ports
+                    .then(|| stable_host_port(app_name, service.internal_port, protocol, |port| host_port_is_free(port, protocol)))
+                    .flatten();
+                if self.stable_ports && stable_port.is_none() {
+                    warn!(machine_id = %machine_id, "No stable port free for {}; letting Docker pick one", internal_port);
+                }
                 let binding = PortBinding {
                     host_ip: Some("0.0.0.0".to_string()),
-                    host_port: Some("0".to_string()), // Let Docker assign an available port
+                    host_port: Some(stable_port.unwrap_or(0).to_string()),
                 };
                 
                 port_bindings.insert(internal_port, Some(vec![binding]));
             }
             
             host_config.port_bindings = Some(port_bindings);
+            container_config.exposed_ports = Some(exposed_ports);
         }
         
         // Set volume mounts
-        if let Some(mounts) = &config.mounts {
-            host_config.mounts = Some(self.map_fly_volumes(mounts, app_name)?);
+        let mut mounts = match &config.mounts {
+            Some(mounts) => self.map_fly_volumes(mounts, app_name, database)?,
+            None => Vec::new(),
+        };
+        
+        // Bind-mount machine files, written to the host first
+        if let Some(files) = &config.files {
+            mounts.extend(self.map_machine_files(files, &machine_files_path(app_name, machine_id), &secrets)?);
+        }
+        if !mounts.is_empty() {
+            host_config.mounts = Some(mounts);
         }
         
         container_config.host_config = Some(host_config);
@@ -342,11 +548,14 @@ impl DockerClient {
     }
     
     /// Translate Fly.io-specific environment variables to minifly equivalents
-    fn translate_fly_env_vars(&self, env: &mut HashMap<String, String>, app_name: &str, machine_id: &str) {
+    ///
+    /// `FLY_REGION` is the machine's region, so apps that route by region
+    /// see the one the machine was created in.
+    fn translate_fly_env_vars(&self, env: &mut HashMap<String, String>, app_name: &str, machine_id: &str, region: &str) {
         // Core Fly.io environment variables
         env.insert("FLY_APP_NAME".to_string(), app_name.to_string());
         env.insert("FLY_MACHINE_ID".to_string(), machine_id.to_string());
-        env.insert("FLY_REGION".to_string(), "local".to_string());
+        env.insert("FLY_REGION".to_string(), region.to_string());
         env.insert("FLY_PUBLIC_IP".to_string(), "127.0.0.1".to_string());
         
         // Generate a consistent private IP based on machine ID
@@ -362,11 +571,24 @@ impl DockerClient {
         env.insert("FLY_CONSUL_URL".to_string(), "http://localhost:8500".to_string());
         env.insert("PRIMARY_REGION".to_string(), "local".to_string());
         
-        // If using Tigris/S3, point to local MinIO (if configured)
-        if env.contains_key("TIGRIS_ENDPOINT") || env.contains_key("AWS_ENDPOINT_URL") {
-            env.insert("TIGRIS_ENDPOINT".to_string(), "http://localhost:9000".to_string());
-            env.insert("AWS_ENDPOINT_URL".to_string(), "http://localhost:9000".to_string());
-            env.insert("AWS_ENDPOINT_URL_S3".to_string(), "http://localhost:9000".to_string());
+        // If using Tigris/S3, point to local MinIO (if configured). MinIO
+        // publishes its port on the host, which containers reach through
+        // the host gateway rather than their own localhost.
+        if let Some(storage) = &self.object_storage {
+            // serve manages MinIO, so every machine gets working credentials
+            let endpoint = container_endpoint(&storage.endpoint);
+            for key in ["TIGRIS_ENDPOINT", "AWS_ENDPOINT_URL", "AWS_ENDPOINT_URL_S3"] {
+                env.insert(key.to_string(), endpoint.clone());
+            }
+            env.insert("AWS_ACCESS_KEY_ID".to_string(), storage.access_key.clone());
+            env.insert("AWS_SECRET_ACCESS_KEY".to_string(), storage.secret_key.clone());
+            env.entry("BUCKET_NAME".to_string()).or_insert_with(|| storage.bucket.clone());
+            env.entry("AWS_REGION".to_string()).or_insert_with(|| "auto".to_string());
+        } else if env.contains_key("TIGRIS_ENDPOINT") || env.contains_key("AWS_ENDPOINT_URL") {
+            let endpoint = format!("http://{}:9000", HOST_GATEWAY);
+            env.insert("TIGRIS_ENDPOINT".to_string(), endpoint.clone());
+            env.insert("AWS_ENDPOINT_URL".to_string(), endpoint.clone());
+            env.insert("AWS_ENDPOINT_URL_S3".to_string(), endpoint);
         }
         
         // Add helpful development overrides
@@ -377,99 +599,58 @@ impl DockerClient {
     
     /// Load secrets from .fly.secrets files for the specified application.
     /// 
-    /// This function implements a hierarchical secrets loading system:
-    /// 1. First loads app-specific secrets from `.fly.secrets.<app_name>`
-    /// 2. Then loads default secrets from `.fly.secrets`
-    /// 3. App-specific secrets take precedence over default secrets
+    /// Files are layered in the order of [`minifly_core::secrets::secrets_files`]:
+    /// `.fly.secrets.<app>.<env>`, `.fly.secrets.<env>`, `.fly.secrets.<app>`,
+    /// then `.fly.secrets`. A key is taken from the first file defining it.
     async fn load_secrets(&self, app_name: &str) -> Result<HashMap<String, String>> {
         use std::path::Path;
         use tokio::fs;
         
+        let environment = minifly_core::secrets::current_environment();
         let mut secrets = HashMap::new();
         
-        // Try app-specific secrets file first
-        let app_secrets_file = format!(".fly.secrets.{}", app_name);
-        if Path::new(&app_secrets_file).exists() {
-            let contents = fs::read_to_string(&app_secrets_file).await
-                .context(format!("Failed to read {}", app_secrets_file))?;
-            self.parse_secrets(&contents, &mut secrets)?;
-        }
-        
-        // Then load default secrets file
-        let default_secrets_file = ".fly.secrets";
-        if Path::new(default_secrets_file).exists() {
-            let contents = fs::read_to_string(default_secrets_file).await
-                .context("Failed to read .fly.secrets")?;
-            // Don't overwrite app-specific secrets
-            let mut default_secrets = HashMap::new();
-            self.parse_secrets(&contents, &mut default_secrets)?;
-            for (k, v) in default_secrets {
-                secrets.entry(k).or_insert(v);
-            }
-        }
-        
-        Ok(secrets)
-    }
-
-    /// Parse secrets from file contents in KEY=VALUE format
-    fn parse_secrets(&self, contents: &str, secrets: &mut HashMap<String, String>) -> Result<()> {
-        use anyhow::bail;
-        
-        for (line_num, line) in contents.lines().enumerate() {
-            let line = line.trim();
-            
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
+        for file in minifly_core::secrets::secrets_files(app_name, environment.as_deref()) {
+            if !Path::new(&file).exists() {
                 continue;
             }
-            
-            // Parse KEY=VALUE
-            if let Some(pos) = line.find('=') {
-                let key = line[..pos].trim();
-                let value = line[pos + 1..].trim();
-                
-                if key.is_empty() {
-                    bail!("Empty key at line {}", line_num + 1);
-                }
-                
-                // Remove quotes if present
-                let value = if (value.starts_with('"') && value.ends_with('"')) ||
-                            (value.starts_with('\'') && value.ends_with('\'')) {
-                    &value[1..value.len() - 1]
-                } else {
-                    value
-                };
-                
-                secrets.insert(key.to_string(), value.to_string());
-            } else {
-                bail!("Invalid format at line {} - expected KEY=VALUE", line_num + 1);
+            let contents = fs::read_to_string(&file).await
+                .context(format!("Failed to read {}", file))?;
+            let contents = minifly_core::secrets::decode_secrets_file(&contents)?;
+            // Don't overwrite secrets from more specific files
+            let mut file_secrets = HashMap::new();
+            minifly_core::secrets::parse_secrets(&contents, &mut file_secrets)?;
+            for (k, v) in file_secrets {
+                secrets.entry(k).or_insert(v);
             }
         }
         
-        Ok(())
+        Ok(secrets)
     }
 
     /// Map Fly volumes to local directories
-    fn map_fly_volumes(&self, mounts: &[MountConfig], app_name: &str) -> Result<Vec<Mount>> {
+    /// 
+    /// A LiteFS machine's database mount binds the directory LiteFS assigned
+    /// it instead of its own volume, so replicas share the primary's databases.
+    fn map_fly_volumes(
+        &self,
+        mounts: &[MountConfig],
+        app_name: &str,
+        database: Option<&DatabaseMount>,
+    ) -> Result<Vec<Mount>> {
         mounts.iter().map(|mount| {
-            // Use absolute path based on data directory (reuse existing minifly-data structure)
-            let base_path = if let Ok(data_dir) = std::env::var("MINIFLY_DATA_DIR") {
-                PathBuf::from(data_dir)
-            } else {
-                // Default to /tmp for volumes if no data dir specified
-                PathBuf::from("/tmp")
+            let is_database = volumes::is_database_mount(&mount.path);
+            let (local_path, read_only) = match database {
+                Some(database) if is_database => (database.dir.clone(), database.read_only),
+                _ => (volume_host_path(app_name, &mount.volume), false),
             };
             
-            // Keep the existing minifly-data structure for compatibility
-            let local_path = base_path.join("minifly-data").join(app_name).join("volumes").join(&mount.volume);
-            
             // Ensure directory exists
             std::fs::create_dir_all(&local_path)
                 .context(format!("Failed to create volume directory: {:?}", local_path))?;
             
             // Create database file if it's a SQLite database path
-            if mount.path == "/litefs" || mount.path.contains("data") {
-                let db_file = local_path.join("app.db");
+            if is_database && !read_only {
+                let db_file = local_path.join(volumes::DATABASE_FILE);
                 if !db_file.exists() {
                     std::fs::File::create(&db_file)
                         .context(format!("Failed to create database file: {:?}", db_file))?;
@@ -481,12 +662,190 @@ impl DockerClient {
                 target: Some(mount.path.clone()),
                 source: Some(local_path.to_string_lossy().to_string()),
                 typ: Some(MountTypeEnum::BIND),
-                read_only: Some(false),
+                read_only: Some(read_only),
                 consistency: Some("consistent".to_string()),
                 ..Default::default()
             })
         }).collect()
     }
+    
+    /// Write machine files under `files_dir` and bind-mount each at its guest path
+    /// 
+    /// Contents come from `raw_value` or from the app secret `secret_name`
+    /// names, both base64-encoded as on Fly.io. The directory is private to
+    /// the server's user, files holding secrets are readable by it alone,
+    /// and the directory is rewritten each time so removed files disappear.
+    fn map_machine_files(
+        &self,
+        files: &[FileConfig],
+        files_dir: &std::path::Path,
+        secrets: &HashMap<String, String>,
+    ) -> Result<Vec<Mount>> {
+        if files_dir.exists() {
+            std::fs::remove_dir_all(files_dir)
+                .context(format!("Failed to clear machine files: {:?}", files_dir))?;
+        }
+        if !files.is_empty() {
+            create_private_dir(files_dir)
+                .context(format!("Failed to create directory: {:?}", files_dir))?;
+        }
+        
+        files.iter().map(|file| {
+            let relative = file.guest_path.trim_start_matches('/');
+            if relative.is_empty() || relative.split('/').any(|part| part == "..") {
+                anyhow::bail!("Invalid guest path for machine file: {}", file.guest_path);
+            }
+            
+            let (contents, secret) = match (&file.raw_value, &file.secret_name) {
+                (Some(_), Some(_)) => {
+                    anyhow::bail!("Machine file {} sets both raw_value and secret_name", file.guest_path)
+                }
+                (Some(raw_value), None) => {
+                    let contents = decode_file_value(raw_value)
+                        .context(format!("raw_value of machine file {} is not valid base64", file.guest_path))?;
+                    (contents, false)
+                }
+                (None, Some(secret_name)) => {
+                    let value = secrets.get(secret_name).ok_or_else(|| {
+                        anyhow::anyhow!("Secret {} for file {} is not set", secret_name, file.guest_path)
+                    })?;
+                    let contents = decode_file_value(value)
+                        .context(format!("Secret {} for file {} is not valid base64", secret_name, file.guest_path))?;
+                    (contents, true)
+                }
+                (None, None) => anyhow::bail!("Machine file {} has neither raw_value nor secret_name", file.guest_path),
+            };
+            
+            let host_path = files_dir.join(relative);
+            if let Some(parent) = host_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .context(format!("Failed to create directory: {:?}", parent))?;
+            }
+            let written = if secret {
+                write_private(&host_path, &contents)
+            } else {
+                std::fs::write(&host_path, contents)
+            };
+            written.context(format!("Failed to write machine file: {:?}", host_path))?;
+            
+            Ok(Mount {
+                target: Some(file.guest_path.clone()),
+                source: Some(host_path.to_string_lossy().to_string()),
+                typ: Some(MountTypeEnum::BIND),
+                read_only: Some(false),
+                ..Default::default()
+            })
+        }).collect()
+    }
+}
+
+/// Returns the host directory holding the files written into a machine.
+pub fn machine_files_path(app_name: &str, machine_id: &str) -> PathBuf {
+    volumes::default_data_dir().join("minifly-data").join(app_name).join("files").join(machine_id)
+}
+
+/// Contents of a base64-encoded machine file value
+fn decode_file_value(value: &str) -> std::result::Result<Vec<u8>, base64::DecodeError> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    STANDARD.decode(value.trim())
+}
+
+/// Creates a directory only its owner can enter
+fn create_private_dir(path: &std::path::Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(path)
+}
+
+/// Writes a file only its owner can read
+fn write_private(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
+
+/// Rewrites a host-local endpoint such as `http://localhost:9000` to reach
+/// the host from inside a container
+fn container_endpoint(endpoint: &str) -> String {
+    ["localhost", "127.0.0.1"].iter()
+        .find_map(|host| {
+            let (scheme, rest) = endpoint.split_once("://")?;
+            let rest = rest.strip_prefix(host)?;
+            (rest.is_empty() || rest.starts_with([':', '/']))
+                .then(|| format!("{}://{}{}", scheme, HOST_GATEWAY, rest))
+        })
+        .unwrap_or_else(|| endpoint.to_string())
+}
+
+/// Returns the host directory backing one of an app's volumes, by volume ID.
+pub fn volume_host_path(app_name: &str, volume_id: &str) -> PathBuf {
+    volumes::mounted_volume_path(app_name, volume_id)
+}
+
+/// Serves a fake Docker API on `socket` for tests, answering each request
+/// with the status and JSON body `respond` returns for its request line.
+/// Returns the request lines seen so far.
+#[cfg(all(test, unix))]
+pub(crate) fn fake_docker(
+    socket: &std::path::Path,
+    respond: impl Fn(&str) -> (u16, String) + Send + Sync + 'static,
+) -> Arc<std::sync::Mutex<Vec<String>>> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _ = std::fs::remove_file(socket);
+    let listener = tokio::net::UnixListener::bind(socket).unwrap();
+    let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = Arc::clone(&requests);
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let seen = Arc::clone(&seen);
+            let respond = Arc::clone(&respond);
+            tokio::spawn(async move {
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    let read = stream.read(&mut chunk).await?;
+                    if read == 0 {
+                        return std::io::Result::Ok(());
+                    }
+                    buffer.extend_from_slice(&chunk[..read]);
+                    while let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&buffer[..end]).into_owned();
+                        buffer.drain(..end + 4);
+                        let request_line = head.lines().next().unwrap_or_default().to_string();
+                        let (status, body) = respond(&request_line);
+                        seen.lock().unwrap().push(request_line);
+                        let response = if body.is_empty() {
+                            format!("HTTP/1.1 {} No Content\r\n\r\n", status)
+                        } else {
+                            format!(
+                                "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                                status,
+                                body.len(),
+                                body
+                            )
+                        };
+                        stream.write_all(response.as_bytes()).await?;
+                    }
+                }
+            });
+        }
+    });
+    requests
 }
 
 #[cfg(test)]
@@ -496,10 +855,10 @@ mod tests {
     
     #[test]
     fn test_translate_fly_env_vars() {
-        let client = DockerClient { client: Docker::connect_with_local_defaults().unwrap() };
+        let client = DockerClient::new(None);
         let mut env = HashMap::new();
         
-        client.translate_fly_env_vars(&mut env, "test-app", "d123456789");
+        client.translate_fly_env_vars(&mut env, "test-app", "d123456789", "local");
         
         assert_eq!(env.get("FLY_APP_NAME").unwrap(), "test-app");
         assert_eq!(env.get("FLY_MACHINE_ID").unwrap(), "d123456789");
@@ -511,33 +870,168 @@ mod tests {
         assert_eq!(env.get("NODE_ENV").unwrap(), "development");
     }
     
+    #[test]
+    fn test_translate_fly_env_vars_uses_machine_region() {
+        let client = DockerClient::new(None);
+        let mut env = HashMap::new();
+        
+        client.translate_fly_env_vars(&mut env, "test-app", "d123456789", "ord");
+        
+        assert_eq!(env.get("FLY_REGION").unwrap(), "ord");
+        // The primary region stays the local one
+        assert_eq!(env.get("PRIMARY_REGION").unwrap(), "local");
+    }
+    
     #[test]
     fn test_translate_fly_env_vars_with_tigris() {
-        let client = DockerClient { client: Docker::connect_with_local_defaults().unwrap() };
+        let client = DockerClient::new(None);
         let mut env = HashMap::new();
         env.insert("TIGRIS_ENDPOINT".to_string(), "https://fly.storage.tigris.dev".to_string());
         
-        client.translate_fly_env_vars(&mut env, "test-app", "d123456789");
+        client.translate_fly_env_vars(&mut env, "test-app", "d123456789", "local");
+        
+        // localhost would be the machine's own container
+        assert_eq!(env.get("TIGRIS_ENDPOINT").unwrap(), "http://host.docker.internal:9000");
+        assert_eq!(env.get("AWS_ENDPOINT_URL").unwrap(), "http://host.docker.internal:9000");
+        assert_eq!(env.get("AWS_ENDPOINT_URL_S3").unwrap(), "http://host.docker.internal:9000");
+    }
+    
+    #[test]
+    fn test_translate_fly_env_vars_with_managed_minio() {
+        let client = DockerClient::new(None).with_object_storage(Some(ObjectStorage {
+            endpoint: "http://localhost:9000".to_string(),
+            access_key: "minifly".to_string(),
+            secret_key: "minifly-secret".to_string(),
+            bucket: "uploads".to_string(),
+        }));
+        let mut env = HashMap::new();
+        env.insert("BUCKET_NAME".to_string(), "avatars".to_string());
+        
+        client.translate_fly_env_vars(&mut env, "test-app", "d123456789", "local");
+        
+        assert_eq!(env.get("AWS_ENDPOINT_URL_S3").unwrap(), "http://host.docker.internal:9000");
+        assert_eq!(env.get("AWS_ACCESS_KEY_ID").unwrap(), "minifly");
+        assert_eq!(env.get("AWS_SECRET_ACCESS_KEY").unwrap(), "minifly-secret");
+        assert_eq!(env.get("AWS_REGION").unwrap(), "auto");
+        // A bucket the app configures itself is kept
+        assert_eq!(env.get("BUCKET_NAME").unwrap(), "avatars");
         
-        assert_eq!(env.get("TIGRIS_ENDPOINT").unwrap(), "http://localhost:9000");
-        assert_eq!(env.get("AWS_ENDPOINT_URL").unwrap(), "http://localhost:9000");
-        assert_eq!(env.get("AWS_ENDPOINT_URL_S3").unwrap(), "http://localhost:9000");
+        assert_eq!(container_endpoint("http://127.0.0.1:9100/"), "http://host.docker.internal:9100/");
+        assert_eq!(container_endpoint("https://minio.example.com"), "https://minio.example.com");
+        assert_eq!(container_endpoint("http://localhostess:9000"), "http://localhostess:9000");
     }
     
     #[test]
     fn test_translate_fly_env_vars_preserves_existing_node_env() {
-        let client = DockerClient { client: Docker::connect_with_local_defaults().unwrap() };
+        let client = DockerClient::new(None);
         let mut env = HashMap::new();
         env.insert("NODE_ENV".to_string(), "production".to_string());
         
-        client.translate_fly_env_vars(&mut env, "test-app", "d123456789");
+        client.translate_fly_env_vars(&mut env, "test-app", "d123456789", "local");
         
         assert_eq!(env.get("NODE_ENV").unwrap(), "production");
     }
     
+    #[test]
+    fn test_machine_files_are_mounted_at_guest_paths() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        
+        let client = DockerClient::new(None);
+        let dir = tempfile::tempdir().unwrap();
+        let files_dir = dir.path().join("files");
+        let secrets = HashMap::from([("TLS_KEY".to_string(), STANDARD.encode("-----BEGIN KEY-----"))]);
+        let files = vec![
+            FileConfig {
+                guest_path: "/etc/app/config.toml".to_string(),
+                raw_value: Some(STANDARD.encode("port = 8080\n")),
+                secret_name: None,
+            },
+            FileConfig {
+                guest_path: "/run/secrets/tls.key".to_string(),
+                raw_value: None,
+                secret_name: Some("TLS_KEY".to_string()),
+            },
+        ];
+        
+        let mounts = client.map_machine_files(&files, &files_dir, &secrets).unwrap();
+        let contents: HashMap<_, _> = mounts.iter()
+            .map(|mount| (
+                mount.target.clone().unwrap(),
+                std::fs::read_to_string(mount.source.as_ref().unwrap()).unwrap(),
+            ))
+            .collect();
+        assert_eq!(contents["/etc/app/config.toml"], "port = 8080\n");
+        assert_eq!(contents["/run/secrets/tls.key"], "-----BEGIN KEY-----");
+        
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&files_dir), 0o700);
+            assert_eq!(mode(&files_dir.join("run/secrets/tls.key")), 0o600);
+        }
+        
+        // Values are base64 as on Fly.io, never written as given
+        let plain_text = vec![FileConfig {
+            guest_path: "/etc/motd".to_string(),
+            raw_value: Some("plain text!".to_string()),
+            secret_name: None,
+        }];
+        assert!(client.map_machine_files(&plain_text, &files_dir, &secrets).is_err());
+        let plain_secret = HashMap::from([("TLS_KEY".to_string(), "-----BEGIN KEY-----".to_string())]);
+        assert!(client.map_machine_files(&files[1..], &files_dir, &plain_secret).is_err());
+        
+        let missing_secret = vec![FileConfig {
+            guest_path: "/etc/key".to_string(),
+            raw_value: None,
+            secret_name: Some("MISSING".to_string()),
+        }];
+        assert!(client.map_machine_files(&missing_secret, &files_dir, &secrets).is_err());
+    }
+
+    #[test]
+    fn test_database_mount_binds_litefs_directory() {
+        let client = DockerClient::new(None);
+        let dir = tempfile::tempdir().unwrap();
+        let mounts = vec![MountConfig { volume: "vol_replica".to_string(), path: "/litefs".to_string() }];
+
+        let primary = DatabaseMount { dir: dir.path().join("vol_primary"), read_only: false };
+        let mapped = client.map_fly_volumes(&mounts, "test-app", Some(&primary)).unwrap();
+        assert_eq!(mapped[0].source.as_deref(), Some(primary.dir.to_str().unwrap()));
+        assert_eq!(mapped[0].read_only, Some(false));
+        assert!(primary.dir.join(volumes::DATABASE_FILE).exists());
+
+        let copy = DatabaseMount { dir: dir.path().join("copy"), read_only: true };
+        let mapped = client.map_fly_volumes(&mounts, "test-app", Some(&copy)).unwrap();
+        assert_eq!(mapped[0].read_only, Some(true));
+        assert!(!copy.dir.join(volumes::DATABASE_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn test_init_overrides_map_to_container_command() {
+        let client = DockerClient::new(None);
+        let config_with_init = |init: serde_json::Value| -> MachineConfig {
+            serde_json::from_value(serde_json::json!({
+                "image": "nginx:alpine",
+                "guest": { "cpu_kind": "shared", "cpus": 1, "memory_mb": 256 },
+                "init": init,
+            })).unwrap()
+        };
+        
+        let exec_only = config_with_init(serde_json::json!({ "exec": ["/app/server", "--port", "8080"] }));
+        let container = client.build_container_config("m1", "test-app", "local", &exec_only, None).await.unwrap();
+        assert_eq!(container.cmd.unwrap(), ["/app/server", "--port", "8080"]);
+        assert!(container.entrypoint.is_none(), "exec keeps the image's entrypoint");
+        
+        let composed = config_with_init(serde_json::json!({ "entrypoint": ["/docker-entrypoint.sh"], "cmd": ["nginx", "-g", "daemon off;"] }));
+        let container = client.build_container_config("m1", "test-app", "local", &composed, None).await.unwrap();
+        assert_eq!(container.entrypoint.unwrap(), ["/docker-entrypoint.sh"]);
+        assert_eq!(container.cmd.unwrap(), ["nginx", "-g", "daemon off;"]);
+    }
+    
     #[tokio::test]
     async fn test_build_container_config_uses_automatic_port_allocation() {
-        let client = DockerClient { client: Docker::connect_with_local_defaults().unwrap() };
+        let client = DockerClient::new(None);
         
         let config = MachineConfig {
             image: "nginx:alpine".to_string(),
@@ -574,6 +1068,7 @@ mod tests {
             checks: None,
             restart: None,
             auto_destroy: None,
+            kill_timeout: None,
             dns: None,
             processes: None,
             files: None,
@@ -582,7 +1077,7 @@ mod tests {
             containers: None,
         };
         
-        let container_config = client.build_container_config("test-machine", "test-app", &config).await.unwrap();
+        let container_config = client.build_container_config("test-machine", "test-app", "local", &config, None).await.unwrap();
         
         // Check that host config has port bindings
         let host_config = container_config.host_config.unwrap();
@@ -600,4 +1095,32 @@ mod tests {
         assert_eq!(binding.host_ip.as_ref().unwrap(), "0.0.0.0");
         assert_eq!(binding.host_port.as_ref().unwrap(), "0");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_stable_host_port_is_deterministic_and_skips_taken_ports() {
+        let first = stable_host_port("web", 8080, "tcp", |_| true).unwrap();
+        assert!(STABLE_PORTS.contains(&first));
+        assert_eq!(stable_host_port("web", 8080, "tcp", |_| true), Some(first));
+        assert_ne!(stable_host_port("api", 8080, "tcp", |_| true), Some(first));
+        
+        let next = stable_host_port("web", 8080, "tcp", |port| port != first).unwrap();
+        assert_ne!(next, first, "a taken port falls back to the next candidate");
+        assert_eq!(stable_host_port("web", 8080, "tcp", |_| false), None);
+    }
+    
+    #[test]
+    fn test_mapped_ports_from_inspect() {
+        let info: bollard::models::ContainerInspectResponse = serde_json::from_value(serde_json::json!({
+            "NetworkSettings": { "Ports": {
+                "8080/tcp": [{ "HostIp": "0.0.0.0", "HostPort": "23456" }],
+                "53/udp": [{ "HostIp": "0.0.0.0", "HostPort": "21000" }],
+                "9000/tcp": null
+            } }
+        })).unwrap();
+        
+        let ports = mapped_ports(&info);
+        assert_eq!(ports.len(), 2, "unpublished ports are left out");
+        assert_eq!((ports[0].internal_port, ports[0].protocol.as_str(), ports[0].host_port), (53, "udp", 21000));
+        assert_eq!((ports[1].internal_port, ports[1].protocol.as_str(), ports[1].host_port), (8080, "tcp", 23456));
+    }
+}
@@ -3,8 +3,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use minifly_core::Error as CoreError;
-use serde_json::json;
+use minifly_core::{Error as CoreError, ErrorResponse};
 
 pub struct ApiError(pub CoreError);
 
@@ -25,13 +24,18 @@ impl IntoResponse for ApiError {
         let (status, error_message) = match self.0 {
             CoreError::MachineNotFound(ref id) => (StatusCode::NOT_FOUND, format!("Machine not found: {}", id)),
             CoreError::AppNotFound(ref name) => (StatusCode::NOT_FOUND, format!("App not found: {}", name)),
+            CoreError::VolumeNotFound(ref id) => (StatusCode::NOT_FOUND, format!("Volume not found: {}", id)),
             CoreError::NotFound => (StatusCode::NOT_FOUND, "Resource not found".to_string()),
             CoreError::InvalidConfiguration(ref msg) => (StatusCode::BAD_REQUEST, format!("Invalid configuration: {}", msg)),
+            CoreError::InvalidMachineConfig(ref msg) => (StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid machine config: {}", msg)),
             CoreError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             CoreError::AuthenticationFailed => (StatusCode::UNAUTHORIZED, "Authentication failed".to_string()),
             CoreError::LeaseConflict => (StatusCode::CONFLICT, "Lease conflict".to_string()),
             CoreError::InvalidLeaseNonce => (StatusCode::BAD_REQUEST, "Invalid lease nonce".to_string()),
+            CoreError::LeaseExpired => (StatusCode::CONFLICT, "Lease has expired".to_string()),
+            CoreError::VersionConflict(ref msg) => (StatusCode::CONFLICT, format!("Version conflict: {}", msg)),
             CoreError::DockerError(ref msg) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Docker error: {}", msg)),
+            CoreError::DockerUnavailable(ref msg) => (StatusCode::SERVICE_UNAVAILABLE, format!("Docker unavailable: {}", msg)),
             CoreError::DatabaseError(ref msg) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", msg)),
             CoreError::NetworkError(ref msg) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Network error: {}", msg)),
             CoreError::Internal(ref msg) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Internal error: {}", msg)),
@@ -39,12 +43,46 @@ impl IntoResponse for ApiError {
             CoreError::Anyhow(ref err) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Internal error: {}", err)),
         };
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+        let body = Json(ErrorResponse {
+            error: error_message,
+            status: self.0.code().to_string(),
+        });
 
         (status, body).into_response()
     }
 }
 
-pub type Result<T> = std::result::Result<T, ApiError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, ApiError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    async fn error_body(err: CoreError) -> (StatusCode, serde_json::Value) {
+        let response = ApiError(err).into_response();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_machine_not_found_body() {
+        let (status, body) = error_body(CoreError::MachineNotFound("abc123".to_string())).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, serde_json::json!({
+            "error": "Machine not found: abc123",
+            "status": "machine_not_found",
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_bad_request_body() {
+        let (status, body) = error_body(CoreError::BadRequest("size must grow".to_string())).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["status"], "bad_request");
+        assert_eq!(body["error"], "size must grow");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,76 @@
+//! In-process bus of machine lifecycle events.
+//!
+//! Handlers publish a [`MachineEvent`] whenever a machine is created,
+//! started, stopped or destroyed. Subscribers such as the `/v1/events`
+//! stream each get their own receiver; events published while nobody is
+//! listening are dropped.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before slow ones start missing events
+const CAPACITY: usize = 256;
+
+/// What happened to a machine
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MachineEventKind {
+    Created,
+    Started,
+    Stopped,
+    Destroyed,
+}
+
+/// A machine lifecycle change
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MachineEvent {
+    #[serde(rename = "type")]
+    pub kind: MachineEventKind,
+    pub app_name: String,
+    pub machine_id: String,
+    pub region: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MachineEvent {
+    pub fn new(kind: MachineEventKind, app_name: &str, machine_id: &str, region: &str) -> Self {
+        Self {
+            kind,
+            app_name: app_name.to_string(),
+            machine_id: machine_id.to_string(),
+            region: region.to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Broadcasts machine events to every subscriber
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<MachineEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes an event to all current subscribers
+    pub fn publish(&self, event: MachineEvent) {
+        // Sending only fails when there are no subscribers, which is fine
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns a receiver for events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<MachineEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -10,9 +10,10 @@ use axum::{
 };
 use serde_json::{json, Value};
 use tracing::{info, warn};
+use crate::handlers::machines;
 use crate::state::AppState;
+use minifly_core::models::MachineState;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
 
 /// Global shutdown flag shared across the application
 static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
@@ -20,43 +21,35 @@ static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
 /// Initiate graceful shutdown of the API server
 /// 
 /// This endpoint allows external systems (like the CLI) to request a graceful shutdown
-/// of the API server. The shutdown process will:
-/// 1. Stop accepting new requests
-/// 2. Complete existing requests
-/// 3. Clean up resources
-/// 4. Exit the process
+/// of the API server. The response is returned immediately, then in the background:
+/// 1. Tracked machines that are running are stopped
+/// 2. LiteFS processes are stopped
+/// 3. The server stops accepting new connections and drains existing ones
 /// 
 /// # Security Note
 /// This endpoint should be restricted in production environments
 /// 
 /// # Examples
 /// ```bash
-/// curl -X POST http://localhost:4280/admin/shutdown
+/// curl -X POST http://localhost:4280/v1/admin/shutdown
 /// ```
 pub async fn shutdown(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
 ) -> Result<Json<Value>, StatusCode> {
     info!("Graceful shutdown requested via admin endpoint");
     
     // Set the shutdown flag
     SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
     
-    // In a real implementation, you would:
-    // 1. Stop accepting new connections
-    // 2. Drain existing connections
-    // 3. Clean up resources
-    // 4. Signal the main process to exit
-    
-    // For now, we'll just acknowledge the request
-    // The actual shutdown would be handled by the main server process
-    tokio::spawn(async {
-        // Give time for the response to be sent
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    tokio::spawn(async move {
+        stop_tracked_machines(&state).await;
+        
+        if let Err(e) = state.litefs.stop_all().await {
+            warn!("Failed to stop LiteFS processes during shutdown: {}", e);
+        }
         
         warn!("Initiating graceful shutdown...");
-        // In practice, you would send a signal to the main process here
-        // For this implementation, we'll use std::process::exit
-        std::process::exit(0);
+        state.request_shutdown();
     });
     
     Ok(Json(json!({
@@ -66,6 +59,38 @@ pub async fn shutdown(
     })))
 }
 
+/// Stop the containers of all running machines tracked by the server
+async fn stop_tracked_machines(state: &AppState) {
+    let running: Vec<String> = state.machines.read().unwrap()
+        .values()
+        .filter(|m| m.state == MachineState::Started)
+        .map(|m| m.id.clone())
+        .collect();
+    
+    for machine_id in running {
+        let container_id = match state.docker.get_container_id_by_machine(&machine_id).await {
+            Ok(Some(id)) => id,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(machine_id = %machine_id, "Failed to look up container during shutdown: {}", e);
+                continue;
+            }
+        };
+        
+        // Like every other stop, give the machine its configured kill timeout
+        let kill_timeout = machines::kill_timeout(state, &machine_id).ok().flatten();
+        match state.docker.stop_container(&container_id, kill_timeout).await {
+            Ok(()) => {
+                if let Some(machine) = state.machines.write().unwrap().get_mut(&machine_id) {
+                    machine.state = MachineState::Stopped;
+                }
+                info!(machine_id = %machine_id, "Stopped machine during shutdown");
+            }
+            Err(e) => warn!(machine_id = %machine_id, "Failed to stop machine during shutdown: {}", e),
+        }
+    }
+}
+
 /// Get system status and runtime information
 /// 
 /// This endpoint provides detailed information about the current state of the API server,
@@ -77,11 +102,13 @@ pub async fn shutdown(
 /// - Configuration summary
 /// - Resource usage metrics
 /// - Shutdown status
+/// - DNS registrations by app
 pub async fn system_status(
     State(state): State<AppState>,
 ) -> Result<Json<Value>, StatusCode> {
     let uptime_seconds = state.start_time.elapsed().as_secs();
     let shutdown_requested = SHUTDOWN_REQUESTED.load(Ordering::Relaxed);
+    let dns_registrations = state.dns_resolver.list_registrations().await;
     
     Ok(Json(json!({
         "status": "ok",
@@ -97,6 +124,9 @@ pub async fn system_status(
             "data_directory": state.config.data_dir,
             "log_level": "info" // This would come from actual config
         },
+        "dns": {
+            "registrations": dns_registrations
+        },
         "timestamp": chrono::Utc::now().to_rfc3339()
     })))
 }
@@ -141,4 +171,62 @@ mod tests {
         assert_eq!(format_duration(3665), "1h 1m 5s");
         assert_eq!(format_duration(7200), "2h 0m 0s");
     }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_stops_machines_with_kill_timeout() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |request| {
+            match request.contains("/containers/json") {
+                true => (200, r#"[{"Id":"c1"}]"#.to_string()),
+                false => (204, String::new()),
+            }
+        });
+        let mut machine = crate::state::machine_for_tests("d1", "web");
+        machine.config.kill_timeout = Some(7);
+        state.machines.write().unwrap().insert("d1".to_string(), machine);
+
+        stop_tracked_machines(&state).await;
+
+        let requests = requests.lock().unwrap();
+        assert!(requests.iter().any(|request| request.contains("/containers/c1/stop?t=7")), "{:?}", requests);
+        assert_eq!(state.machines.read().unwrap()["d1"].state, MachineState::Stopped);
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_stops_server() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::{TcpListener, TcpStream};
+        
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new()
+            .nest("/v1", crate::handlers::routes())
+            .with_state(state.clone());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move { state.shutdown_signal().await })
+                .await
+        });
+        
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(
+            b"POST /v1/admin/shutdown HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        ).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("server did not shut down")
+            .unwrap()
+            .unwrap();
+        
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
 }
\ No newline at end of file
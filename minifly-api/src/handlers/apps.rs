@@ -1,11 +1,20 @@
 use axum::{
-    extract::{Path, State},
-    Json,
+    extract::{Path, Query, State},
+    Extension, Json,
 };
 use chrono::Utc;
-use minifly_core::models::{App, AppStatus, CreateAppRequest, AppResponse, Organization};
-use minifly_core::{SuccessResponse, Error as CoreError};
+use minifly_core::models::{
+    App, AppDnsResponse, AppMachineStatus, AppStatus, CreateAppRequest, AppResponse, DeleteAppResponse, Machine,
+    MachineState, Organization, ResolvedMachine,
+};
+use minifly_core::Error as CoreError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::{info, warn};
 use uuid::Uuid;
+use crate::docker::volume_host_path;
+use crate::handlers::machines::destroy_machine;
+use crate::middleware::region::RequestRegion;
 use crate::state::AppState;
 use crate::error::Result;
 
@@ -35,49 +44,348 @@ pub async fn create_app(
         },
         status: "pending".to_string(),
         created_at: app.created_at.to_rfc3339(),
+        machine_count: 0,
+        machine_status: AppMachineStatus::Pending,
     };
     
     Ok(Json(response))
 }
 
+/// Query parameters for listing apps
+#[derive(Debug, Deserialize)]
+pub struct ListAppsQuery {
+    /// Only list apps whose machines are in this aggregate status
+    pub status: Option<AppMachineStatus>,
+}
+
+/// Lists apps by name with their machine counts and aggregate machine status
+pub async fn list_apps(
+    State(state): State<AppState>,
+    Query(params): Query<ListAppsQuery>,
+) -> Result<Json<Vec<AppResponse>>> {
+    let apps = state.apps.read().unwrap();
+    let machines = state.machines.read().unwrap();
+    
+    let mut result: Vec<AppResponse> = apps.values()
+        .map(|app| app_response(app, &machines))
+        .filter(|app| params.status.is_none_or(|status| app.machine_status == status))
+        .collect();
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    Ok(Json(result))
+}
+
 pub async fn get_app(
     State(state): State<AppState>,
     Path(app_name): Path<String>,
 ) -> Result<Json<AppResponse>> {
     let apps = state.apps.read().unwrap();
     
+    let machines = state.machines.read().unwrap();
+    
     match apps.get(&app_name) {
-        Some(app) => {
-            let response = AppResponse {
-                id: app.id.to_string(),
-                name: app.name.clone(),
-                organization: Organization {
-                    id: Uuid::new_v4().to_string(),
-                    slug: app.organization_id.clone(),
-                    name: "Default Organization".to_string(),
-                },
-                status: match app.status {
-                    AppStatus::Pending => "pending",
-                    AppStatus::Deployed => "deployed",
-                    AppStatus::Suspended => "suspended",
-                }.to_string(),
-                created_at: app.created_at.to_rfc3339(),
-            };
-            
-            Ok(Json(response))
-        }
+        Some(app) => Ok(Json(app_response(app, &machines))),
         None => Err(CoreError::AppNotFound(app_name).into()),
     }
 }
 
+/// Builds the API view of an app, summarising the machines it owns
+fn app_response(app: &App, machines: &HashMap<String, Machine>) -> AppResponse {
+    let states: Vec<_> = machines.values()
+        .filter(|m| m.app_name == app.name)
+        .map(|m| &m.state)
+        .collect();
+    let machine_status = AppMachineStatus::from_states(states.iter().copied());
+    
+    AppResponse {
+        id: app.id.to_string(),
+        name: app.name.clone(),
+        organization: Organization {
+            id: Uuid::new_v4().to_string(),
+            slug: app.organization_id.clone(),
+            name: "Default Organization".to_string(),
+        },
+        status: match app.status {
+            AppStatus::Pending => "pending",
+            AppStatus::Deployed => "deployed",
+            AppStatus::Suspended => "suspended",
+        }.to_string(),
+        created_at: app.created_at.to_rfc3339(),
+        machine_count: states.iter()
+            .filter(|state| !matches!(state, MachineState::Destroying | MachineState::Destroyed))
+            .count(),
+        machine_status,
+    }
+}
+
+/// Resolves `<app>.internal` through the internal DNS resolver.
+///
+/// Stopped machines are unregistered and cordoned machines filtered out, so
+/// this lists the machines that should receive new traffic. Each comes with
+/// the simulated latency from the request's region to the machine's and the
+/// host ports its services are published on.
+pub async fn resolve_app(
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+    request_region: Option<Extension<RequestRegion>>,
+) -> Result<Json<AppDnsResponse>> {
+    let hostname = format!("{}.internal", app_name);
+    let ips = state.dns_resolver.resolve(&hostname).await
+        .map_err(|e| CoreError::Internal(format!("Failed to resolve {}: {}", hostname, e)))?;
+    let from = request_region.map_or_else(|| state.config.region.clone(), |Extension(region)| region.0);
+    let resolved = state.dns_resolver.resolve_machines(&app_name).await;
+    let machines = {
+        let machines = state.machines.read().unwrap();
+        resolved.into_iter()
+            .map(|(id, ip)| {
+                let machine = machines.get(&id);
+                let latency = machine
+                    .map(|machine| state.config.region_latency.delay(&from, &machine.region))
+                    .unwrap_or_default();
+                let mapped_ports = machine.map(|machine| machine.mapped_ports.clone()).unwrap_or_default();
+                ResolvedMachine { id, ip, latency_ms: latency.as_millis() as u64, mapped_ports }
+            })
+            .collect()
+    };
+    
+    Ok(Json(AppDnsResponse { hostname, ips, machines }))
+}
+
+/// Query parameters for deleting an app
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteAppQuery {
+    /// Keep the app's volumes and their data for a later app of the same name
+    #[serde(default)]
+    pub keep_volumes: bool,
+}
+
+/// Deletes an app along with everything it owns.
+///
+/// Machines are destroyed first: containers stopped and removed, LiteFS
+/// stopped and DNS records unregistered. Volumes and their data are then
+/// deleted unless `keep_volumes` is set. The app record goes last, so a
+/// failure part way through leaves an app that can be deleted again.
 pub async fn delete_app(
     State(state): State<AppState>,
     Path(app_name): Path<String>,
-) -> Result<Json<SuccessResponse>> {
-    let mut apps = state.apps.write().unwrap();
+    Query(params): Query<DeleteAppQuery>,
+) -> Result<Json<DeleteAppResponse>> {
+    if !state.apps.read().unwrap().contains_key(&app_name) {
+        return Err(CoreError::AppNotFound(app_name).into());
+    }
     
-    match apps.remove(&app_name) {
-        Some(_) => Ok(Json(SuccessResponse { ok: true })),
-        None => Err(CoreError::AppNotFound(app_name).into()),
+    let mut machines: Vec<String> = state.machines.read().unwrap().values()
+        .filter(|m| m.app_name == app_name)
+        .map(|m| m.id.clone())
+        .collect();
+    machines.sort();
+    for machine_id in &machines {
+        destroy_machine(&state, &app_name, machine_id, true).await?;
     }
-}
\ No newline at end of file
+    
+    let mut deleted_volumes = Vec::new();
+    let mut kept_volumes = Vec::new();
+    if params.keep_volumes {
+        if let Some(volumes) = state.volumes.read().unwrap().get(&app_name) {
+            kept_volumes.extend(volumes.keys().cloned());
+        }
+    } else {
+        let volumes = state.volumes.write().unwrap().remove(&app_name).unwrap_or_default();
+        for volume_id in volumes.into_keys() {
+            let path = volume_host_path(&app_name, &volume_id);
+            match std::fs::remove_dir_all(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!(volume_id = %volume_id, path = %path.display(), error = %e, "Failed to delete volume data"),
+            }
+            deleted_volumes.push(volume_id);
+        }
+    }
+    deleted_volumes.sort();
+    kept_volumes.sort();
+    
+    state.releases.write().unwrap().remove(&app_name);
+    state.apps.write().unwrap().remove(&app_name);
+    info!(
+        app_name = %app_name,
+        machines = machines.len(),
+        deleted_volumes = deleted_volumes.len(),
+        kept_volumes = kept_volumes.len(),
+        "Deleted app"
+    );
+    
+    Ok(Json(DeleteAppResponse {
+        ok: true,
+        machines,
+        deleted_volumes,
+        kept_volumes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minifly_core::models::MappedPort;
+
+    fn machine(app_name: &str, name: &str, state: MachineState) -> Machine {
+        let mut machine = crate::state::machine_for_tests(name, app_name);
+        machine.name = name.to_string();
+        machine.state = state;
+        machine
+    }
+
+    fn app(name: &str) -> App {
+        App {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            organization_id: "personal".to_string(),
+            status: AppStatus::Deployed,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_apps_reports_aggregate_machine_status() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        for name in ["web", "worker", "empty"] {
+            state.apps.write().unwrap().insert(name.to_string(), app(name));
+        }
+        for (name, machine_state) in [
+            ("web-1", MachineState::Started),
+            ("web-2", MachineState::Stopped),
+            ("web-3", MachineState::Destroyed),
+            ("worker-1", MachineState::Started),
+        ] {
+            let app_name = name.split('-').next().unwrap();
+            state.machines.write().unwrap().insert(name.to_string(), machine(app_name, name, machine_state));
+        }
+        // Machines count for the app that owns them, whatever they are named
+        state.machines.write().unwrap().insert("api".to_string(), machine("worker", "api", MachineState::Stopped));
+        state.machines.write().unwrap().insert("web-admin-1".to_string(), machine("web-admin", "web-admin-1", MachineState::Started));
+
+        let Ok(Json(apps)) = list_apps(State(state.clone()), Query(ListAppsQuery { status: None })).await else {
+            panic!("list_apps failed");
+        };
+        let summary: Vec<_> = apps.iter()
+            .map(|app| (app.name.as_str(), app.machine_count, app.machine_status))
+            .collect();
+        assert_eq!(summary, vec![
+            ("empty", 0, AppMachineStatus::Pending),
+            ("web", 2, AppMachineStatus::Partial),
+            ("worker", 2, AppMachineStatus::Partial),
+        ]);
+
+        let query = ListAppsQuery { status: Some(AppMachineStatus::Partial) };
+        let Ok(Json(apps)) = list_apps(State(state), Query(query)).await else {
+            panic!("list_apps failed");
+        };
+        assert_eq!(apps.iter().map(|app| app.name.as_str()).collect::<Vec<_>>(), vec!["web", "worker"]);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_app_reports_latency_from_request_region() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(data_dir.path()).await;
+        state.config.region_latency = minifly_core::region::RegionLatency::parse("40,syd=150").unwrap();
+        for (name, region, ip) in [("web-1", "local", "10.0.0.2"), ("web-2", "syd", "10.0.0.3")] {
+            let mut machine = machine("web", name, MachineState::Started);
+            machine.region = region.to_string();
+            if name == "web-1" {
+                machine.mapped_ports = vec![MappedPort { internal_port: 8080, protocol: "tcp".to_string(), host_port: 32768 }];
+            }
+            state.machines.write().unwrap().insert(name.to_string(), machine);
+            state.dns_resolver.register_machine("web", name, ip.parse().unwrap()).await.unwrap();
+        }
+
+        let resolve = |region: Option<&str>| {
+            let region = region.map(|region| Extension(RequestRegion(region.to_string())));
+            resolve_app(State(state.clone()), Path("web".to_string()), region)
+        };
+        let latency = |response: AppDnsResponse| -> HashMap<String, u64> {
+            response.machines.into_iter().map(|machine| (machine.id, machine.latency_ms)).collect()
+        };
+        let Ok(Json(local)) = resolve(None).await else { panic!("resolve_app failed") };
+        // Proxies dial the host ports machines publish rather than their bridge IPs
+        let published = local.machines.iter().find(|machine| machine.id == "web-1").unwrap();
+        assert_eq!(published.host_port(8080, "tcp"), Some(32768));
+        assert_eq!(latency(local), HashMap::from([("web-1".to_string(), 0), ("web-2".to_string(), 190)]));
+        let Ok(Json(syd)) = resolve(Some("syd")).await else { panic!("resolve_app failed") };
+        assert_eq!(latency(syd), HashMap::from([("web-1".to_string(), 190), ("web-2".to_string(), 0)]));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_delete_app_removes_containers_and_volumes() {
+        let data_dir = tempfile::tempdir().unwrap();
+        std::env::set_var("MINIFLY_DATA_DIR", data_dir.path());
+        let state = AppState::for_tests(data_dir.path()).await;
+        // Just enough of the Docker API to stop and remove containers
+        let docker_requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |_| (204, String::new()));
+
+        for name in ["web", "web-api", "worker"] {
+            state.apps.write().unwrap().insert(name.to_string(), app(name));
+        }
+        for (app_name, name, machine_state) in [
+            ("web", "web-1", MachineState::Started),
+            ("web", "web-2", MachineState::Stopped),
+            // Named after another app, but owned by web
+            ("web", "worker-7", MachineState::Started),
+            // Named like a web machine, but owned by web-api
+            ("web-api", "web-api-1", MachineState::Started),
+            ("worker", "worker-1", MachineState::Started),
+        ] {
+            state.machines.write().unwrap().insert(name.to_string(), machine(app_name, name, machine_state));
+        }
+        let volume: minifly_core::models::Volume = serde_json::from_value(serde_json::json!({
+            "id": "vol_web",
+            "name": "data",
+            "state": "created",
+            "size_gb": 1,
+            "region": "local",
+            "zone": "local",
+            "encrypted": false,
+            "attached_machine_id": null,
+            "attached_alloc_id": null,
+            "created_at": Utc::now(),
+        }))
+        .unwrap();
+        state.volumes.write().unwrap()
+            .insert("web".to_string(), HashMap::from([(volume.id.clone(), volume)]));
+        let volume_data = volume_host_path("web", "vol_web");
+        std::fs::create_dir_all(&volume_data).unwrap();
+
+        let Ok(Json(summary)) = delete_app(
+            State(state.clone()),
+            Path("web".to_string()),
+            Query(DeleteAppQuery::default()),
+        ).await else {
+            panic!("delete_app failed");
+        };
+
+        assert_eq!(summary.machines, vec!["web-1", "web-2", "worker-7"]);
+        assert_eq!(summary.deleted_volumes, vec!["vol_web"]);
+        assert!(summary.kept_volumes.is_empty());
+
+        // Both machines' containers were stopped and removed, stopped ones included
+        let requests = docker_requests.lock().unwrap().clone();
+        let called = |method: &str, path: String| {
+            requests.iter().any(|r| r.starts_with(method) && r.contains(&path))
+        };
+        for container in ["minifly-web-web-1", "minifly-web-web-2", "minifly-web-worker-7"] {
+            assert!(called("POST", format!("/containers/{}/stop", container)), "{:?}", requests);
+            assert!(called("DELETE", format!("/containers/{}?", container)), "{:?}", requests);
+        }
+        assert!(!requests.iter().any(|r| r.contains("web-api") || r.contains("worker-1")), "{:?}", requests);
+
+        assert!(!state.apps.read().unwrap().contains_key("web"));
+        let mut remaining: Vec<_> = state.machines.read().unwrap().keys().cloned().collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["web-api-1", "worker-1"]);
+        assert!(!state.volumes.read().unwrap().contains_key("web"));
+        assert!(!volume_data.exists());
+        std::env::remove_var("MINIFLY_DATA_DIR");
+    }
+}
@@ -0,0 +1,58 @@
+//! Machine lifecycle event stream
+//!
+//! Streams [`MachineEvent`]s from the event bus as Server-Sent Events so
+//! tools can react to machine state changes without polling.
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{stream, Stream};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+use crate::events::MachineEvent;
+use crate::state::AppState;
+
+/// Query parameters for the event stream
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Only stream events for this app
+    pub app: Option<String>,
+}
+
+/// Streams machine lifecycle events as they happen
+pub async fn stream_events(
+    State(state): State<AppState>,
+    Query(params): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.events.subscribe();
+
+    let events = stream::unfold((receiver, params.app), |(mut receiver, app)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if app.as_ref().is_some_and(|app| *app != event.app_name) => continue,
+                Ok(event) => return Some((Ok(to_sse(&event)), (receiver, app))),
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(missed, "Event stream subscriber lagged; events were dropped");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(10))
+            .text("heartbeat"),
+    )
+}
+
+fn to_sse(event: &MachineEvent) -> Event {
+    Event::default()
+        .event("machine")
+        .json_data(event)
+        .unwrap_or_else(|_| Event::default().data(r#"{"error": "Failed to serialize event"}"#))
+}
@@ -2,7 +2,7 @@ use axum::{extract::State, Json, http::StatusCode};
 use serde_json::json;
 use crate::error::Result;
 use crate::state::AppState;
-use crate::health::{health_handler, liveness_handler, readiness_handler, HealthResponse};
+use crate::health::{health_handler, liveness_handler, readiness_handler, HealthResponse, ReadinessResponse};
 
 /// Simple health check endpoint for backwards compatibility
 pub async fn health_check() -> Result<Json<serde_json::Value>> {
@@ -25,6 +25,6 @@ pub async fn liveness() -> StatusCode {
 }
 
 /// Kubernetes/Docker readiness probe endpoint  
-pub async fn readiness(state: State<AppState>) -> StatusCode {
+pub async fn readiness(state: State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
     readiness_handler(state).await
 }
\ No newline at end of file
@@ -0,0 +1,137 @@
+//! LiteFS role inspection and failover handlers
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use minifly_core::models::{MachineConfig, MachineState};
+use minifly_litefs::manager::{LiteFSMachineStatus, LiteFSRole};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::error::Result;
+use crate::state::AppState;
+
+/// Environment variable telling a machine's app whether it is the LiteFS primary
+const PRIMARY_ENV: &str = "FLY_LITEFS_PRIMARY";
+
+/// LiteFS roles of an app's machines
+#[derive(Debug, Serialize)]
+pub struct LiteFSStatusResponse {
+    pub app_name: String,
+    /// The current primary, if any machine holds the role
+    pub primary: Option<String>,
+    pub machines: Vec<LiteFSMachineStatus>,
+}
+
+/// Body of a promotion request
+#[derive(Debug, Deserialize)]
+pub struct PromoteRequest {
+    pub machine_id: String,
+}
+
+pub async fn get_status(
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+) -> Result<Json<LiteFSStatusResponse>> {
+    Ok(Json(status(&state, app_name).await))
+}
+
+/// Forces a machine to become the app's LiteFS primary, demoting the others
+pub async fn promote(
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+    Json(req): Json<PromoteRequest>,
+) -> Result<Json<LiteFSStatusResponse>> {
+    state.litefs.promote(&app_name, &req.machine_id).await?;
+    info!(app_name = %app_name, machine_id = %req.machine_id, "Promoted machine to LiteFS primary");
+    update_roles(&state, &app_name).await;
+    
+    Ok(Json(status(&state, app_name).await))
+}
+
+/// Sets a machine config's `FLY_LITEFS_PRIMARY` to the machine's LiteFS role
+pub(crate) fn set_primary_env(config: &mut MachineConfig, is_primary: bool) {
+    config.env.get_or_insert_with(Default::default)
+        .insert(PRIMARY_ENV.to_string(), is_primary.to_string());
+}
+
+/// Records each of the app's LiteFS machines' role in its env, then recreates
+/// the running containers, so each sees its new role and binds the database
+/// directory that goes with it
+async fn update_roles(state: &AppState, app_name: &str) {
+    let litefs_machines = state.litefs.app_status(app_name).await;
+    let running: Vec<_> = {
+        let mut machines = state.machines.write().unwrap();
+        for status in &litefs_machines {
+            if let Some(machine) = machines.get_mut(&status.machine_id) {
+                set_primary_env(&mut machine.config, status.role == LiteFSRole::Primary);
+            }
+        }
+        
+        litefs_machines.iter()
+            .filter_map(|status| machines.get(&status.machine_id))
+            .filter(|machine| machine.state == MachineState::Started)
+            .map(|machine| (machine.id.clone(), machine.region.clone(), machine.config.clone()))
+            .collect()
+    };
+    
+    for (machine_id, region, config) in running {
+        if let Err(e) = crate::handlers::releases::recreate_container(state, app_name, &machine_id, &region, &config).await {
+            warn!(machine_id = %machine_id, "Failed to apply LiteFS role: {}", e.0);
+        }
+    }
+}
+
+async fn status(state: &AppState, app_name: String) -> LiteFSStatusResponse {
+    let machines = state.litefs.app_status(&app_name).await;
+    let primary = machines.iter()
+        .find(|machine| machine.role == LiteFSRole::Primary)
+        .map(|machine| machine.machine_id.clone());
+    
+    LiteFSStatusResponse {
+        app_name,
+        primary,
+        machines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minifly_core::models::Machine;
+    use std::collections::HashMap;
+
+    fn machine(id: &str, primary: bool) -> Machine {
+        let mut machine = crate::state::machine_for_tests(id, "web");
+        machine.state = MachineState::Stopped;
+        machine.config.env = Some(HashMap::from([(PRIMARY_ENV.to_string(), primary.to_string())]));
+        machine
+    }
+
+    fn primary_env(state: &AppState, machine_id: &str) -> String {
+        let machines = state.machines.read().unwrap();
+        machines[machine_id].config.env.as_ref().unwrap()[PRIMARY_ENV].clone()
+    }
+
+    #[tokio::test]
+    async fn test_promote_updates_every_machines_env() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        for (id, primary) in [("m1", true), ("m2", false), ("m3", false)] {
+            state.machines.write().unwrap().insert(id.to_string(), machine(id, primary));
+            state.litefs.start_for_machine_with_config(id, primary, Some("web"), None).await.unwrap();
+        }
+
+        let request = PromoteRequest { machine_id: "m2".to_string() };
+        let Ok(Json(status)) = promote(State(state.clone()), Path("web".to_string()), Json(request)).await else {
+            panic!("promote failed");
+        };
+
+        assert_eq!(status.primary.as_deref(), Some("m2"));
+        let primaries = status.machines.iter().filter(|machine| machine.role == LiteFSRole::Primary).count();
+        assert_eq!(primaries, 1);
+        assert_eq!(primary_env(&state, "m1"), "false");
+        assert_eq!(primary_env(&state, "m2"), "true");
+        assert_eq!(primary_env(&state, "m3"), "false");
+    }
+}
@@ -6,16 +6,24 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     response::sse::{Event, KeepAlive, Sse},
 };
+use futures::stream::{self, BoxStream};
 use futures::{Stream, StreamExt};
+use minifly_core::models::{latest, AppLogsSummary, LogEntry, MachineLogsSummary, MachineState};
+use minifly_core::Error as CoreError;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::time::Duration;
 use tracing::{error, info, warn};
+use crate::docker::docker_error;
+use crate::error::ApiError;
+use crate::log_store::LogStore;
 use crate::state::AppState;
 
+/// Recent lines of each machine the app log summary counts
+const SUMMARY_SAMPLE_LINES: &str = "1000";
+
 /// Query parameters for log streaming
 #[derive(Debug, Deserialize)]
 pub struct LogsQuery {
@@ -32,27 +40,56 @@ pub struct LogsQuery {
     /// Include log levels
     #[serde(default)]
     pub include_levels: bool,
+    /// Only show logs after this time (RFC3339, Unix seconds, or relative like `10m`)
+    pub since: Option<String>,
+    /// Only show logs before this time, in the same formats as `since`
+    pub until: Option<String>,
 }
 
-/// Log entry structure for streaming
-#[derive(Debug, Serialize)]
-pub struct LogEntry {
-    /// Timestamp of the log entry
-    pub timestamp: String,
-    /// Log level (info, warn, error, debug)
-    pub level: String,
-    /// Region where the log originated
-    pub region: String,
-    /// Machine ID that generated the log
-    pub machine_id: String,
-    /// App name
-    pub app_name: String,
-    /// Raw log message
-    pub message: String,
-    /// Stream type (stdout, stderr)
-    pub stream: String,
-    /// Correlation ID for request tracking
-    pub correlation_id: Option<String>,
+impl LogsQuery {
+    /// Resolves `since`/`until` to Unix timestamps, using 0 for an open end
+    fn window(&self) -> crate::error::Result<(i64, i64)> {
+        let now = chrono::Utc::now();
+        let resolve = |value: &Option<String>| match value {
+            Some(value) => parse_log_time(value, now).map_err(|e| {
+                warn!(value = %value, error = %e, "Invalid log time window");
+                ApiError::from(CoreError::BadRequest(e))
+            }),
+            None => Ok(0),
+        };
+
+        Ok((resolve(&self.since)?, resolve(&self.until)?))
+    }
+}
+
+/// Parses a log time bound into a Unix timestamp.
+/// 
+/// Accepts RFC3339 (`2024-06-22T10:00:00Z`), plain Unix seconds, or a
+/// duration before `now` such as `30s`, `10m`, `2h` or `1d`.
+fn parse_log_time(value: &str, now: chrono::DateTime<chrono::Utc>) -> Result<i64, String> {
+    let value = value.trim();
+
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp());
+    }
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| format!("invalid time '{}'", value))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit in '{}' (use s, m, h or d)", value)),
+    };
+
+    amount.checked_mul(unit_seconds)
+        .and_then(|seconds| now.timestamp().checked_sub(seconds))
+        .ok_or_else(|| format!("time '{}' is too far in the past", value))
 }
 
 /// Stream logs from a machine
@@ -66,6 +103,8 @@ pub struct LogEntry {
 /// - timestamps: bool - Include timestamps
 /// - region: String - Filter by region
 /// - include_levels: bool - Parse and include log levels
+/// - since: String - Only logs after this time (e.g., "10m", "2024-06-22T10:00:00Z")
+/// - until: String - Only logs before this time
 /// 
 /// # Examples
 /// ```bash
@@ -77,12 +116,15 @@ pub struct LogEntry {
 /// 
 /// # Get logs with timestamps and levels
 /// curl "http://localhost:4280/v1/apps/my-app/machines/abc123/logs?timestamps=true&include_levels=true"
+/// 
+/// # Logs from the last 5 minutes
+/// curl "http://localhost:4280/v1/apps/my-app/machines/abc123/logs?since=5m"
 /// ```
 pub async fn stream_machine_logs(
     Path((app_name, machine_id)): Path<(String, String)>,
     Query(params): Query<LogsQuery>,
     State(state): State<AppState>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+) -> crate::error::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
     info!(
         app.name = %app_name,
         machine.id = %machine_id,
@@ -92,15 +134,25 @@ pub async fn stream_machine_logs(
         "Starting log stream request"
     );
 
+    let window = params.window()?;
+
     // Get the container ID for this machine
     let container_id = match state.docker.get_container_id_by_machine(&machine_id).await {
         Ok(Some(id)) => id,
         Ok(None) => {
+            // The container is gone; fall back to persisted logs if there are any
+            let store = log_store(&state);
+            if store.stored_bytes(&app_name, &machine_id) > 0 {
+                info!(machine.id = %machine_id, "Serving persisted logs for removed machine");
+                let entries = stored_log_entries(&store, &app_name, &machine_id, params.tail.as_deref(), window);
+                return Ok(log_events_sse(stream::iter(entries.into_iter().map(Ok)).boxed()));
+            }
+            
             warn!(
                 machine.id = %machine_id,
                 "No container found for machine"
             );
-            return Err(StatusCode::NOT_FOUND);
+            return Err(CoreError::MachineNotFound(machine_id).into());
         }
         Err(e) => {
             error!(
@@ -108,7 +160,7 @@ pub async fn stream_machine_logs(
                 error = %e,
                 "Failed to get container for machine"
             );
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(docker_error(&format!("Failed to look up container for machine {}", machine_id), e).into());
         }
     };
 
@@ -118,6 +170,7 @@ pub async fn stream_machine_logs(
         params.follow,
         params.tail,
         params.timestamps,
+        window,
     ).await {
         Ok(stream) => stream,
         Err(e) => {
@@ -127,47 +180,206 @@ pub async fn stream_machine_logs(
                 error = %e,
                 "Failed to start log stream"
             );
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(docker_error(&format!("Failed to start log stream for machine {}", machine_id), e).into());
         }
     };
 
-    let region_filter = params.region.clone();
-    let include_levels = params.include_levels;
-    let app_name_clone = app_name.clone();
-    let machine_id_clone = machine_id.clone();
-
-    // Transform Docker log stream into SSE events
-    let event_stream = log_stream.map(move |log_result| {
-        match log_result {
-            Ok(log_output) => {
-                let log_entry = process_log_output(
-                    log_output,
-                    &app_name_clone,
-                    &machine_id_clone,
-                    &region_filter,
-                    include_levels,
-                );
-                
-                match serde_json::to_string(&log_entry) {
-                    Ok(json) => Ok(Event::default().data(json)),
-                    Err(e) => {
-                        error!(error = %e, "Failed to serialize log entry");
-                        Ok(Event::default().data(format!(r#"{{"error": "Failed to serialize log: {}"}}"#, e)))
-                    }
-                }
+    let region = state.machines.read().unwrap()
+        .get(&machine_id)
+        .map(|m| m.region.clone())
+        .unwrap_or_else(|| "local".to_string());
+
+    let entries = merge_log_streams(
+        &app_name,
+        vec![(machine_id, region, log_stream.boxed())],
+        params.include_levels,
+    );
+
+    Ok(log_events_sse(entries.boxed()))
+}
+
+/// IDs and regions of the app's machines that haven't been destroyed,
+/// optionally only those in `region`
+fn app_machines(state: &AppState, app_name: &str, region: Option<&str>) -> Vec<(String, String)> {
+    let mut machines: Vec<(String, String)> = state.machines.read().unwrap()
+        .values()
+        .filter(|m| m.app_name == app_name)
+        .filter(|m| m.state != MachineState::Destroyed)
+        .filter(|m| region.is_none_or(|r| r == m.region))
+        .map(|m| (m.id.clone(), m.region.clone()))
+        .collect();
+    machines.sort();
+    machines
+}
+
+/// Stream interleaved logs from every machine of an app
+/// 
+/// # Endpoint
+/// GET /v1/apps/{app_name}/logs
+/// 
+/// Takes the same query parameters as the machine endpoint. Each entry carries
+/// the `machine_id` and `region` of the machine that produced it, and with
+/// `follow=true` new lines from all machines are streamed as they arrive.
+pub async fn stream_app_logs(
+    Path(app_name): Path<String>,
+    Query(params): Query<LogsQuery>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let machines = app_machines(&state, &app_name, params.region.as_deref());
+
+    info!(app.name = %app_name, machines = machines.len(), follow = params.follow, "Starting app log stream");
+
+    let window = params.window()?;
+
+    let mut streams = Vec::new();
+    for (machine_id, region) in machines {
+        let container_id = match state.docker.get_container_id_by_machine(&machine_id).await {
+            Ok(Some(id)) => id,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(machine.id = %machine_id, error = %e, "Failed to get container for machine");
+                continue;
             }
+        };
+
+        match state.docker.stream_logs(&container_id, params.follow, params.tail.clone(), params.timestamps, window).await {
+            Ok(log_stream) => streams.push((machine_id, region, log_stream.boxed())),
+            Err(e) => warn!(machine.id = %machine_id, error = %e, "Failed to start log stream"),
+        }
+    }
+
+    if streams.is_empty() {
+        return Err(CoreError::MachineNotFound(format!("no machine of app {} has a container", app_name)).into());
+    }
+
+    Ok(log_events_sse(merge_log_streams(&app_name, streams, params.include_levels)))
+}
+
+/// Log store for this server's data directory
+pub(crate) fn log_store(state: &AppState) -> LogStore {
+    LogStore::new(&state.config.data_dir, state.config.log_max_bytes)
+}
+
+/// Tees a container's log stream into the machine's persisted log file.
+/// 
+/// Does nothing unless `MINIFLY_PERSIST_LOGS` is enabled. The task ends when
+/// the container stops and Docker closes the stream. Pass `tail = "all"` for a
+/// new container and `"0"` when restarting one whose earlier output is
+/// already stored.
+pub(crate) fn spawn_log_sink(state: &AppState, app_name: &str, machine_id: &str, container_id: &str, tail: &str) {
+    if !state.config.persist_logs {
+        return;
+    }
+
+    let state = state.clone();
+    let (app_name, machine_id, container_id, tail) =
+        (app_name.to_string(), machine_id.to_string(), container_id.to_string(), tail.to_string());
+    tokio::spawn(async move {
+        let log_stream = match state.docker.stream_logs(&container_id, true, Some(tail), true, (0, 0)).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(machine.id = %machine_id, error = %e, "Failed to start log sink");
+                return;
+            }
+        };
+
+        let region = state.machines.read().unwrap()
+            .get(&machine_id)
+            .map(|m| m.region.clone())
+            .unwrap_or_else(|| "local".to_string());
+        let store = log_store(&state);
+        let mut entries = merge_log_streams(&app_name, vec![(machine_id.clone(), region, log_stream.boxed())], true);
+
+        while let Some(Ok(entry)) = entries.next().await {
+            let line = serde_json::to_string(&entry).unwrap_or_default();
+            if let Err(e) = store.append(&app_name, &machine_id, &line) {
+                warn!(machine.id = %machine_id, error = %e, "Failed to persist log line");
+                break;
+            }
+        }
+    });
+}
+
+/// Reads persisted log entries for a machine within a `(since, until)` window,
+/// keeping the last `tail` lines
+fn stored_log_entries(
+    store: &LogStore,
+    app_name: &str,
+    machine_id: &str,
+    tail: Option<&str>,
+    (since, until): (i64, i64),
+) -> Vec<LogEntry> {
+    let lines = match store.read_lines(app_name, machine_id) {
+        Ok(lines) => lines,
+        Err(e) => {
+            warn!(machine.id = %machine_id, error = %e, "Failed to read persisted logs");
+            return Vec::new();
+        }
+    };
+
+    let mut entries: Vec<LogEntry> = lines.iter()
+        .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+        .filter(|entry| {
+            let Ok(time) = chrono::DateTime::parse_from_rfc3339(&entry.timestamp) else {
+                return true;
+            };
+            (since == 0 || time.timestamp() >= since) && (until == 0 || time.timestamp() <= until)
+        })
+        .collect();
+
+    if let Some(tail) = tail.and_then(|t| t.parse::<usize>().ok()) {
+        entries.drain(..entries.len().saturating_sub(tail));
+    }
+
+    entries
+}
+
+type DockerLogStream = BoxStream<'static, Result<bollard::container::LogOutput, bollard::errors::Error>>;
+
+/// Merges per-machine Docker log streams into one stream of log entries.
+/// 
+/// Entries are yielded as soon as any machine produces them, so lines from
+/// different machines interleave in arrival order.
+fn merge_log_streams(
+    app_name: &str,
+    streams: Vec<(String, String, DockerLogStream)>,
+    include_levels: bool,
+) -> impl Stream<Item = Result<LogEntry, String>> + Send + 'static {
+    let tagged = streams.into_iter().map(|(machine_id, region, log_stream)| {
+        let app_name = app_name.to_string();
+        log_stream.map(move |log_result| {
+            log_result
+                .map(|log_output| process_log_output(log_output, &app_name, &machine_id, &region, include_levels))
+                .map_err(|e| format!("Log stream error for machine {}: {}", machine_id, e))
+        })
+    });
+
+    stream::select_all(tagged)
+}
+
+/// Wraps log entries as SSE events with a heartbeat
+fn log_events_sse(
+    entries: impl Stream<Item = Result<LogEntry, String>> + Send + 'static,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let event_stream = entries.map(|entry| match entry {
+        Ok(log_entry) => match serde_json::to_string(&log_entry) {
+            Ok(json) => Ok(Event::default().data(json)),
             Err(e) => {
-                error!(error = %e, "Docker log stream error");
-                Ok(Event::default().data(format!(r#"{{"error": "Log stream error: {}"}}"#, e)))
+                error!(error = %e, "Failed to serialize log entry");
+                Ok(Event::default().data(format!(r#"{{"error": "Failed to serialize log: {}"}}"#, e)))
             }
+        },
+        Err(e) => {
+            error!(error = %e, "Docker log stream error");
+            Ok(Event::default().data(format!(r#"{{"error": "{}"}}"#, e)))
         }
     });
 
-    Ok(Sse::new(event_stream).keep_alive(
+    Sse::new(event_stream).keep_alive(
         KeepAlive::new()
             .interval(Duration::from_secs(10))
             .text("heartbeat"),
-    ))
+    )
 }
 
 /// Process Docker log output into structured log entry
@@ -175,7 +387,7 @@ fn process_log_output(
     log_output: bollard::container::LogOutput,
     app_name: &str,
     machine_id: &str,
-    _region_filter: &Option<String>,
+    region: &str,
     include_levels: bool,
 ) -> LogEntry {
     use bollard::container::LogOutput;
@@ -200,7 +412,7 @@ fn process_log_output(
     
     // Parse log level if requested
     let level = if include_levels {
-        extract_log_level(&message)
+        log_level(&message)
     } else {
         "info".to_string()
     };
@@ -211,7 +423,7 @@ fn process_log_output(
     LogEntry {
         timestamp,
         level,
-        region: "local".to_string(), // In real Fly.io this would be the actual region
+        region: region.to_string(),
         machine_id: machine_id.to_string(),
         app_name: app_name.to_string(),
         message,
@@ -220,6 +432,31 @@ fn process_log_output(
     }
 }
 
+/// Level of a log line: the `level`, `severity` or `lvl` field of a JSON
+/// line, where pino/bunyan numeric levels of 50 and 40 are errors and
+/// warnings, or else a guess from the text
+fn log_level(message: &str) -> String {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_str::<serde_json::Value>(message) else {
+        return extract_log_level(message);
+    };
+    let level = ["level", "severity", "lvl"].iter().find_map(|key| fields.get(*key));
+    match level {
+        Some(serde_json::Value::String(level)) => match level.to_lowercase().as_str() {
+            "error" | "err" | "fatal" | "critical" | "crit" | "panic" => "error".to_string(),
+            "warn" | "warning" => "warn".to_string(),
+            level => level.to_string(),
+        },
+        Some(serde_json::Value::Number(level)) => match level.as_u64().unwrap_or(0) {
+            50.. => "error".to_string(),
+            40..=49 => "warn".to_string(),
+            20..=29 => "debug".to_string(),
+            0..=19 => "trace".to_string(),
+            _ => "info".to_string(),
+        },
+        _ => extract_log_level(message),
+    }
+}
+
 /// Extract log level from message content
 fn extract_log_level(message: &str) -> String {
     let lower_msg = message.to_lowercase();
@@ -248,7 +485,7 @@ fn extract_log_level(message: &str) -> String {
 pub async fn get_logs_summary(
     Path((app_name, machine_id)): Path<(String, String)>,
     State(state): State<AppState>,
-) -> Result<axum::Json<LogsSummary>, StatusCode> {
+) -> crate::error::Result<axum::Json<LogsSummary>> {
     info!(
         app.name = %app_name,
         machine.id = %machine_id,
@@ -258,13 +495,13 @@ pub async fn get_logs_summary(
     // Get container ID for this machine
     let container_id = match state.docker.get_container_id_by_machine(&machine_id).await {
         Ok(Some(id)) => id,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok(None) => return Err(CoreError::MachineNotFound(machine_id).into()),
+        Err(e) => return Err(docker_error(&format!("Failed to look up container for machine {}", machine_id), e).into()),
     };
 
     // Get a small sample of recent logs to analyze
     let mut recent_logs = Vec::new();
-    if let Ok(log_stream) = state.docker.stream_logs(&container_id, false, Some("10".to_string()), true).await {
+    if let Ok(log_stream) = state.docker.stream_logs(&container_id, false, Some("10".to_string()), true, (0, 0)).await {
         let mut stream = log_stream.take(10);
         while let Some(log_result) = stream.next().await {
             if let Ok(log_output) = log_result {
@@ -289,11 +526,70 @@ pub async fn get_logs_summary(
         }),
         last_activity: chrono::Utc::now().to_rfc3339(),
         region: "local".to_string(),
+        stored_log_bytes: log_store(&state).stored_bytes(&app_name, &machine_id),
     };
 
     Ok(axum::Json(summary))
 }
 
+/// Get log health across every machine of an app
+/// 
+/// # Endpoint
+/// GET /v1/apps/{app_name}/logs/summary
+/// 
+/// # Returns
+/// Line, error and warning counts over the last [`SUMMARY_SAMPLE_LINES`]
+/// lines of each machine, and when each machine last logged. Levels come from
+/// the `level` field of JSON lines and are guessed from the text otherwise.
+pub async fn get_app_logs_summary(
+    Path(app_name): Path<String>,
+    State(state): State<AppState>,
+) -> crate::error::Result<axum::Json<AppLogsSummary>> {
+    if !state.apps.read().unwrap().contains_key(&app_name) {
+        return Err(CoreError::AppNotFound(app_name).into());
+    }
+    let machines = app_machines(&state, &app_name, None);
+
+    let mut summaries = Vec::new();
+    for (machine_id, region) in machines {
+        let container_id = match state.docker.get_container_id_by_machine(&machine_id).await {
+            Ok(Some(id)) => id,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(machine.id = %machine_id, error = %e, "Failed to get container for machine");
+                continue;
+            }
+        };
+        let log_stream = match state.docker.stream_logs(&container_id, false, Some(SUMMARY_SAMPLE_LINES.to_string()), true, (0, 0)).await {
+            Ok(log_stream) => log_stream,
+            Err(e) => {
+                warn!(machine.id = %machine_id, error = %e, "Failed to read logs for summary");
+                continue;
+            }
+        };
+        let entries: Vec<LogEntry> = merge_log_streams(&app_name, vec![(machine_id.clone(), region.clone(), log_stream.boxed())], true)
+            .filter_map(|entry| async move { entry.ok() })
+            .collect()
+            .await;
+        summaries.push(summarize_machine_logs(&machine_id, &region, &entries));
+    }
+    summaries.sort_by(|a, b| a.machine_id.cmp(&b.machine_id));
+
+    Ok(axum::Json(AppLogsSummary::new(&app_name, summaries)))
+}
+
+/// Counts a machine's log lines by level
+fn summarize_machine_logs(machine_id: &str, region: &str, entries: &[LogEntry]) -> MachineLogsSummary {
+    MachineLogsSummary {
+        machine_id: machine_id.to_string(),
+        region: region.to_string(),
+        line_count: entries.len(),
+        error_count: entries.iter().filter(|entry| entry.level == "error").count(),
+        warn_count: entries.iter().filter(|entry| entry.level == "warn").count(),
+        last_activity: latest(entries.iter().map(|entry| entry.timestamp.as_str())),
+    }
+}
+
 /// Log summary information
 #[derive(Debug, Serialize)]
 pub struct LogsSummary {
@@ -304,11 +600,14 @@ pub struct LogsSummary {
     pub has_errors: bool,
     pub last_activity: String,
     pub region: String,
+    /// Size of the machine's persisted logs on disk, including rotated files
+    pub stored_log_bytes: u64,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::http::StatusCode;
 
     #[test]
     fn test_extract_log_level() {
@@ -319,6 +618,199 @@ mod tests {
         assert_eq!(extract_log_level("Normal message"), "info");
     }
 
+    #[tokio::test]
+    async fn test_app_machines_are_selected_by_ownership() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        // `web-admin` machines are named like `web` ones but belong to another app
+        for (id, name, app_name, machine_state, region) in [
+            ("m1", "web-1", "web", MachineState::Started, "sjc"),
+            ("m2", "custom", "web", MachineState::Stopped, "ams"),
+            ("m3", "web-admin-1", "web-admin", MachineState::Started, "sjc"),
+            ("m4", "web-2", "web", MachineState::Destroyed, "sjc"),
+        ] {
+            let mut machine = crate::state::machine_for_tests(id, app_name);
+            machine.name = name.to_string();
+            machine.state = machine_state;
+            machine.region = region.to_string();
+            state.machines.write().unwrap().insert(id.to_string(), machine);
+        }
+
+        let ids = |region| app_machines(&state, "web", region).into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids(None), vec!["m1", "m2"]);
+        assert_eq!(ids(Some("ams")), vec!["m2"]);
+    }
+
+    async fn error_body(response: axum::response::Response) -> (StatusCode, serde_json::Value) {
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_log_errors_use_error_envelope() {
+        use axum::response::IntoResponse;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+
+        // The test Docker socket isn't a daemon, so the container lookup fails
+        let response = get_logs_summary(Path(("web".to_string(), "abc123".to_string())), State(state))
+            .await
+            .into_response();
+        let (status, body) = error_body(response).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "docker_unavailable");
+        assert!(body["error"].as_str().unwrap().contains("abc123"));
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let response = get_app_logs_summary(Path("missing".to_string()), State(state.clone()))
+            .await
+            .into_response();
+        let (status, body) = error_body(response).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["status"], "app_not_found");
+
+        let query = LogsQuery {
+            follow: false,
+            tail: None,
+            timestamps: false,
+            region: None,
+            include_levels: false,
+            since: Some("999999999999999d".to_string()),
+            until: None,
+        };
+        let response = stream_app_logs(Path("web".to_string()), Query(query), State(state))
+            .await
+            .into_response();
+        let (status, body) = error_body(response).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["status"], "bad_request");
+        assert!(body["error"].as_str().unwrap().contains("999999999999999d"));
+    }
+
+    fn mock_stream(lines: &[&'static str]) -> DockerLogStream {
+        let outputs: Vec<_> = lines.iter()
+            .map(|line| Ok(bollard::container::LogOutput::StdOut { message: line.as_bytes().to_vec().into() }))
+            .collect();
+        stream::iter(outputs).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_merge_log_streams_interleaves_and_tags_machines() {
+        let entries: Vec<LogEntry> = merge_log_streams(
+            "web-app",
+            vec![
+                ("machine-a".to_string(), "sjc".to_string(), mock_stream(&["a1\n", "a2\n", "a3\n"])),
+                ("machine-b".to_string(), "ams".to_string(), mock_stream(&["b1\n", "b2\n"])),
+            ],
+            false,
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+        let lines: Vec<(&str, &str, &str)> = entries.iter()
+            .map(|e| (e.machine_id.as_str(), e.region.as_str(), e.message.as_str()))
+            .collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(entries.iter().all(|e| e.app_name == "web-app"));
+
+        // Each machine's lines keep their order and carry that machine's tags
+        let a: Vec<_> = lines.iter().filter(|l| l.0 == "machine-a").collect();
+        let b: Vec<_> = lines.iter().filter(|l| l.0 == "machine-b").collect();
+        assert_eq!(a.iter().map(|l| l.2).collect::<Vec<_>>(), vec!["a1", "a2", "a3"]);
+        assert_eq!(b.iter().map(|l| l.2).collect::<Vec<_>>(), vec!["b1", "b2"]);
+        assert!(a.iter().all(|l| l.1 == "sjc") && b.iter().all(|l| l.1 == "ams"));
+
+        // Lines from the two machines are interleaved rather than concatenated
+        let first_b = lines.iter().position(|l| l.0 == "machine-b").unwrap();
+        let last_a = lines.iter().rposition(|l| l.0 == "machine-a").unwrap();
+        assert!(first_b < last_a);
+    }
+
+    #[test]
+    fn test_stored_log_entries_respect_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LogStore::new(dir.path(), 1024 * 1024);
+
+        for i in 0..5 {
+            let entry = process_log_output(
+                bollard::container::LogOutput::StdOut { message: format!("line {}\n", i).into_bytes().into() },
+                "web-app", "machine-a", "sjc", true,
+            );
+            store.append("web-app", "machine-a", &serde_json::to_string(&entry).unwrap()).unwrap();
+        }
+
+        let all = stored_log_entries(&store, "web-app", "machine-a", None, (0, 0));
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[0].region, "sjc");
+
+        let last_two = stored_log_entries(&store, "web-app", "machine-a", Some("2"), (0, 0));
+        assert_eq!(last_two.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(), vec!["line 3", "line 4"]);
+
+        assert!(stored_log_entries(&store, "web-app", "missing", None, (0, 0)).is_empty());
+
+        // Entries are stamped with the current time, so a window in the past excludes them
+        let past = chrono::Utc::now().timestamp() - 3600;
+        assert!(stored_log_entries(&store, "web-app", "machine-a", None, (0, past)).is_empty());
+        assert_eq!(stored_log_entries(&store, "web-app", "machine-a", None, (past, 0)).len(), 5);
+    }
+
+    #[test]
+    fn test_app_summary_counts_levels_of_json_and_plain_lines() {
+        let entries = |machine_id: &str, lines: &[&str]| -> Vec<LogEntry> {
+            lines.iter().enumerate()
+                .map(|(i, line)| {
+                    let message = format!("2024-06-22T10:30:0{}.000000000Z {}\n", i, line);
+                    process_log_output(
+                        bollard::container::LogOutput::StdOut { message: message.into_bytes().into() },
+                        "web-app", machine_id, "sjc", true,
+                    )
+                })
+                .collect()
+        };
+
+        let a = entries("machine-a", &[
+            r#"{"level":"error","msg":"db down"}"#,
+            r#"{"level":50,"msg":"pino error"}"#,
+            r#"{"severity":"WARNING","msg":"slow query"}"#,
+            r#"{"level":"info","msg":"error budget ok"}"#,
+            "Server listening on port 8080",
+        ]);
+        let b = entries("machine-b", &["panic: failed to bind", r#"{"level":40}"#]);
+
+        let summary = AppLogsSummary::new("web-app", vec![
+            summarize_machine_logs("machine-a", "sjc", &a),
+            summarize_machine_logs("machine-b", "sjc", &b),
+        ]);
+        assert_eq!((summary.machines[0].error_count, summary.machines[0].warn_count), (2, 1));
+        assert_eq!((summary.machines[1].error_count, summary.machines[1].warn_count), (1, 1));
+        assert_eq!((summary.line_count, summary.error_count, summary.warn_count), (7, 3, 2));
+        assert_eq!(summary.machines[0].last_activity.as_deref(), Some("2024-06-22T10:30:04.000000000Z"));
+        assert_eq!(summary.last_activity.as_deref(), Some("2024-06-22T10:30:04.000000000Z"));
+    }
+
+    #[test]
+    fn test_parse_log_time() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-06-22T12:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let ts = now.timestamp();
+
+        assert_eq!(parse_log_time("30s", now), Ok(ts - 30));
+        assert_eq!(parse_log_time("10m", now), Ok(ts - 600));
+        assert_eq!(parse_log_time("2h", now), Ok(ts - 7200));
+        assert_eq!(parse_log_time("1d", now), Ok(ts - 86400));
+        assert_eq!(parse_log_time("2024-06-22T11:00:00Z", now), Ok(ts - 3600));
+        assert_eq!(parse_log_time("2024-06-22T13:00:00+02:00", now), Ok(ts - 3600));
+        assert_eq!(parse_log_time("1719057600", now), Ok(1719057600));
+
+        assert!(parse_log_time("10y", now).is_err());
+        assert!(parse_log_time("soon", now).is_err());
+        assert!(parse_log_time("999999999999999d", now).is_err());
+    }
+
     #[test]
     fn test_log_entry_serialization() {
         let entry = LogEntry {
@@ -1,63 +1,110 @@
 use axum::{
     extract::{Path, Query, State},
-    http::HeaderMap,
-    Json,
+    http::{header, HeaderMap},
+    Extension, Json,
 };
 use chrono::Utc;
 use minifly_core::models::{
     Machine, MachineState, MachineEvent, ImageRef,
     CreateMachineRequest, UpdateMachineRequest, StopMachineRequest,
     StartMachineResponse, StopMachineResponse, WaitMachineQuery,
-    CreateLeaseRequest, LeaseResponse, Lease,
+    MachineActionRequest, MachineActionResponse, MachineActionResult, MachineSelection,
+    CreateLeaseRequest, RenewLeaseRequest, LeaseResponse, Lease, MachineConnections, UpdateConnectionsRequest,
+    MachineEnvResponse,
 };
+use minifly_core::secrets::looks_secret;
 use minifly_core::{SuccessResponse, Error as CoreError};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tracing::{info, instrument};
+use crate::docker::{container_env, container_secrets, docker_error};
 use crate::state::AppState;
 use crate::error::{ApiError, Result};
-use crate::middleware::region::{log_machine_operation, get_machine_region};
+use crate::events::MachineEventKind;
+use crate::handlers::pagination::{paginate, page_headers};
+use crate::handlers::volumes;
+use crate::middleware::region::{log_machine_operation, get_machine_region, MachineRegion, RequestRegion};
 use minifly_network::extract_container_ip;
 
+/// Seconds a lease lasts when the request doesn't give a `ttl`
+const DEFAULT_LEASE_TTL: u32 = 300;
+
 pub async fn list_machines(
     State(state): State<AppState>,
     Path(app_name): Path<String>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<Machine>>> {
+) -> Result<(HeaderMap, Json<Vec<Machine>>)> {
     let machines = state.machines.read().unwrap();
     
-    let mut result: Vec<Machine> = machines.values()
-        .filter(|m| m.name.starts_with(&format!("{}-", app_name)))
+    let result: Vec<Machine> = machines.values()
+        .filter(|m| m.app_name == app_name)
         .cloned()
         .collect();
     
-    // Apply filters
+    let metadata = state.machine_metadata.read().unwrap();
+    let result = filter_machines(result, &params, &metadata);
+    let (page, next_offset) = paginate(result, &params)?;
+    
+    Ok((page_headers(next_offset), Json(page)))
+}
+
+/// Applies the list query filters and sorts machines oldest first so that
+/// pages are stable between requests.
+///
+/// Filters combine with AND semantics. Supported filters are `region`,
+/// `include_deleted`, `state` (comma separated) and `metadata.<key>=<value>`.
+fn filter_machines(
+    mut result: Vec<Machine>,
+    params: &HashMap<String, String>,
+    metadata: &HashMap<String, HashMap<String, String>>,
+) -> Vec<Machine> {
     if let Some(region) = params.get("region") {
         result.retain(|m| &m.region == region);
     }
     
+    if let Some(states) = params.get("state") {
+        let states: Vec<&str> = states.split(',').map(str::trim).collect();
+        result.retain(|m| states.contains(&m.state.as_str()));
+    }
+    
+    for (param, expected) in params {
+        if let Some(key) = param.strip_prefix("metadata.") {
+            result.retain(|m| {
+                metadata
+                    .get(&m.id)
+                    .and_then(|values| values.get(key))
+                    .is_some_and(|value| value == expected)
+            });
+        }
+    }
+    
     if let Some(include_deleted) = params.get("include_deleted") {
         if include_deleted != "true" {
             result.retain(|m| m.state != MachineState::Destroyed);
         }
     }
     
-    Ok(Json(result))
+    result.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    result
 }
 
 #[instrument(skip(state), fields(app_name = %app_name, region = tracing::field::Empty))]
 pub async fn create_machine(
     State(state): State<AppState>,
     Path(app_name): Path<String>,
-    Json(req): Json<CreateMachineRequest>,
-) -> Result<Json<Machine>> {
+    request_region: Option<Extension<RequestRegion>>,
+    Json(mut req): Json<CreateMachineRequest>,
+) -> Result<(Extension<MachineRegion>, Json<Machine>)> {
+    req.config.validate()?;
+    
     let machine_id = state.generate_machine_id();
     let instance_id = state.generate_instance_id();
     let machine_index = state.machines.read().unwrap().len() as u32;
     let private_ip = state.generate_private_ip(&app_name, machine_index);
     
-    // Get region from request or use default
-    let region = get_machine_region(req.region.as_deref());
+    // Use the region from the body, else the one the request was routed to
+    let request_region = request_region.map_or_else(|| state.config.region.clone(), |Extension(region)| region.0);
+    let region = get_machine_region(req.region.as_deref(), &request_region);
     tracing::Span::current().record("region", &region);
     
     info!(
@@ -68,37 +115,22 @@ pub async fn create_machine(
         "Creating machine"
     );
     
-    // Parse image reference
-    let image_parts: Vec<&str> = req.config.image.split('/').collect();
-    let (registry, repository, tag_digest) = match image_parts.len() {
-        1 => ("registry-1.docker.io", "library", image_parts[0]),
-        2 => ("registry-1.docker.io", image_parts[0], image_parts[1]),
-        _ => (image_parts[0], image_parts[1], image_parts[2]),
-    };
-    
-    let (tag, digest) = if let Some((t, d)) = tag_digest.split_once('@') {
-        (t.to_string(), Some(d.to_string()))
-    } else if let Some((_repo, t)) = tag_digest.split_once(':') {
-        (t.to_string(), None)
-    } else {
-        ("latest".to_string(), None)
-    };
+    // Mounts use the app's registered volumes, which are created if missing
+    if let Some(mounts) = req.config.mounts.as_mut() {
+        volumes::resolve_mounts(&state, &app_name, &region, mounts);
+    }
     
-    let machine = Machine {
+    let mut machine = Machine {
         id: machine_id.clone(),
         name: req.name.unwrap_or_else(|| format!("{}-{}", app_name, machine_id)),
+        app_name: app_name.clone(),
         state: if req.skip_launch.unwrap_or(false) {
             MachineState::Created
         } else {
             MachineState::Starting
         },
         region: region.clone(),
-        image_ref: ImageRef {
-            registry: registry.to_string(),
-            repository: repository.to_string(),
-            tag,
-            digest,
-        },
+        image_ref: parse_image_ref(&req.config.image),
         instance_id: instance_id.clone(),
         private_ip,
         created_at: Utc::now(),
@@ -109,7 +141,11 @@ pub async fn create_machine(
             status: "created".to_string(),
             source: "user".to_string(),
             timestamp: Utc::now().timestamp_millis() as u64,
+            request: None,
         }],
+        cordoned: false,
+        checks: Vec::new(),
+        mapped_ports: Vec::new(),
     };
     
     // Create container
@@ -125,12 +161,19 @@ pub async fn create_machine(
                 .map(|v| v == "true")
                 .unwrap_or(true);
             
-            if let Err(e) = state.litefs.start_for_machine_with_config(&machine_id, is_primary, Some(&app_name)).await {
+            let database_dir = req.config.mounts.iter().flatten()
+                .find(|mount| minifly_core::volumes::is_database_mount(&mount.path))
+                .map(|mount| crate::docker::volume_host_path(&app_name, &mount.volume));
+            if let Err(e) = state.litefs.start_for_machine_with_config(&machine_id, is_primary, Some(&app_name), database_dir.as_deref()).await {
                 return Err(CoreError::LiteFSError(format!("Failed to start LiteFS: {}", e)).into());
             }
+            // The app may already have a primary, in which case this machine is a replica
+            let is_primary = state.litefs.is_primary(&machine_id).unwrap_or(is_primary);
+            crate::handlers::litefs::set_primary_env(&mut machine.config, is_primary);
         }
         
-        match state.docker.create_container(&machine_id, &app_name, &req.config).await {
+        let database = state.litefs.database_mount(&machine_id);
+        match state.docker.create_container(&machine_id, &app_name, &region, &machine.config, database.as_ref()).await {
             Ok(container_id) => {
                 // Start container
                 if let Err(e) = state.docker.start_container(&container_id).await {
@@ -138,14 +181,26 @@ pub async fn create_machine(
                     if has_volumes {
                         let _ = state.litefs.stop_for_machine(&machine_id).await;
                     }
-                    return Err(CoreError::DockerError(format!("Failed to start container: {}", e)).into());
+                    return Err(docker_error("Failed to start container", e).into());
                 }
                 
+                crate::handlers::logs::spawn_log_sink(&state, &app_name, &machine_id, &container_id, "all");
+                
+                machine.state = MachineState::Started;
+                machine.push_event(MachineEvent {
+                    event_type: "start".to_string(),
+                    status: "started".to_string(),
+                    source: "flyd".to_string(),
+                    timestamp: Utc::now().timestamp_millis() as u64,
+                    request: None,
+                }, state.config.max_machine_events);
+                
                 // Wait a moment for container to get IP
                 tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
                 
                 // Get container IP and register with DNS
                 if let Ok(container_info) = state.docker.inspect_container(&container_id).await {
+                    machine.mapped_ports = crate::docker::mapped_ports(&container_info);
                     if let Some(network_settings) = container_info.network_settings {
                         if let Some(networks) = network_settings.networks {
                             let networks_value = serde_json::to_value(&networks).unwrap_or_default();
@@ -164,22 +219,29 @@ pub async fn create_machine(
                 if has_volumes {
                     let _ = state.litefs.stop_for_machine(&machine_id).await;
                 }
-                return Err(CoreError::DockerError(format!("Failed to create container: {}", e)).into());
+                return Err(docker_error("Failed to create container", e).into());
             }
         }
     }
     
     // Store machine
     state.machines.write().unwrap().insert(machine_id.clone(), machine.clone());
+    if let Some(mounts) = &req.config.mounts {
+        volumes::attach_volumes(&state, &app_name, &machine_id, mounts);
+    }
     
     // Handle lease if requested
     if let Some(ttl) = req.lease_ttl {
-        let lease = create_machine_lease(&state, &machine_id, ttl, None);
+        let lease = create_machine_lease(&state, &machine_id, ttl, None, None);
         state.leases.write().unwrap().insert(machine_id.clone(), lease);
     }
     
     // Log successful creation
     log_machine_operation("create", &machine_id, &app_name, &region);
+    publish_event(&state, MachineEventKind::Created, &app_name, &machine_id, &region);
+    if machine.state == MachineState::Started {
+        publish_event(&state, MachineEventKind::Started, &app_name, &machine_id, &region);
+    }
     
     info!(
         machine_id = %machine_id,
@@ -189,82 +251,216 @@ pub async fn create_machine(
         "Machine created successfully"
     );
     
-    Ok(Json(machine))
+    Ok((Extension(MachineRegion(region)), Json(machine)))
+}
+
+/// Parses an image reference like `registry/repo/name:tag@digest`
+pub(crate) fn parse_image_ref(image: &str) -> ImageRef {
+    let image_parts: Vec<&str> = image.split('/').collect();
+    let (registry, repository, tag_digest) = match image_parts.len() {
+        1 => ("registry-1.docker.io", "library", image_parts[0]),
+        2 => ("registry-1.docker.io", image_parts[0], image_parts[1]),
+        _ => (image_parts[0], image_parts[1], image_parts[2]),
+    };
+    
+    let (tag, digest) = if let Some((t, d)) = tag_digest.split_once('@') {
+        (t.to_string(), Some(d.to_string()))
+    } else if let Some((_repo, t)) = tag_digest.split_once(':') {
+        (t.to_string(), None)
+    } else {
+        ("latest".to_string(), None)
+    };
+    
+    ImageRef {
+        registry: registry.to_string(),
+        repository: repository.to_string(),
+        tag,
+        digest,
+    }
 }
 
 pub async fn get_machine(
     State(state): State<AppState>,
     Path((_app_name, machine_id)): Path<(String, String)>,
-) -> Result<Json<Machine>> {
+) -> Result<(Extension<MachineRegion>, HeaderMap, Json<Machine>)> {
     let machines = state.machines.read().unwrap();
     
     match machines.get(&machine_id) {
-        Some(machine) => Ok(Json(machine.clone())),
+        Some(machine) => Ok((
+            Extension(MachineRegion(machine.region.clone())),
+            version_headers(machine),
+            Json(machine.clone()),
+        )),
         None => Err(CoreError::MachineNotFound(machine_id).into()),
     }
 }
 
-pub async fn update_machine(
+/// Lists a machine's events newest first, paginated with `limit` and `offset`.
+///
+/// Only the latest `max_machine_events` events are kept per machine.
+pub async fn list_events(
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<(HeaderMap, Json<Vec<MachineEvent>>)> {
+    let events: Vec<MachineEvent> = {
+        let machines = state.machines.read().unwrap();
+        let machine = machines
+            .get(&machine_id)
+            .ok_or_else(|| CoreError::MachineNotFound(machine_id.clone()))?;
+        machine.events.iter().rev().cloned().collect()
+    };
+    let (page, next_offset) = paginate(events, &params)?;
+
+    Ok((page_headers(next_offset), Json(page)))
+}
+
+/// Returns the environment of a machine's container, including the `FLY_*`
+/// variables and secrets injected when it was created.
+///
+/// Values of the injected secrets, and of other variables that look like
+/// secrets, are redacted unless `reveal` is set.
+pub async fn get_env(
     State(state): State<AppState>,
     Path((_app_name, machine_id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<MachineEnvResponse>> {
+    ensure_machine_exists(&state, &machine_id)?;
+    let reveal = params.get("reveal").map(|v| v == "true").unwrap_or(false);
+    
+    let container_id = state.docker.get_container_id_by_machine(&machine_id).await
+        .map_err(|e| docker_error("Failed to look up container", e))?
+        .ok_or_else(|| CoreError::BadRequest(format!("Machine {} has no container", machine_id)))?;
+    let info = state.docker.inspect_container(&container_id).await
+        .map_err(|e| docker_error("Failed to inspect container", e))?;
+    
+    let secrets = container_secrets(&info);
+    Ok(Json(redact_env(container_env(&info), secrets, reveal)))
+}
+
+/// Redacts the values of `secrets` and of other variables that look like
+/// secrets, unless `reveal` is set
+fn redact_env(mut env: BTreeMap<String, String>, secrets: Vec<String>, reveal: bool) -> MachineEnvResponse {
+    let mut redacted = Vec::new();
+    if !reveal {
+        let is_secret = |key: &str| looks_secret(key) || secrets.iter().any(|secret| secret == key);
+        for (key, value) in env.iter_mut().filter(|(key, _)| is_secret(key)) {
+            *value = "<redacted>".to_string();
+            redacted.push(key.clone());
+        }
+    }
+    MachineEnvResponse { env, redacted, secrets }
+}
+
+/// Updates a machine's config and gives it a new `instance_id`.
+///
+/// The `instance_id` is the machine's version: when the request names the
+/// version it expects, in an `If-Match` header or as `current_version`, the
+/// update is refused with 409 unless that is still the machine's version, so
+/// concurrent updates can't silently overwrite each other.
+pub async fn update_machine(
+    State(state): State<AppState>,
+    Path((app_name, machine_id)): Path<(String, String)>,
     headers: HeaderMap,
-    Json(req): Json<UpdateMachineRequest>,
-) -> Result<Json<Machine>> {
+    Json(mut req): Json<UpdateMachineRequest>,
+) -> Result<(HeaderMap, Json<Machine>)> {
+    req.config.validate()?;
+    
     // Check lease if provided
     if let Some(nonce) = headers.get("fly-machine-lease-nonce") {
-        let leases = state.leases.read().unwrap();
-        if let Some(lease) = leases.get(&machine_id) {
-            if lease.nonce != nonce.to_str().unwrap_or("") {
-                return Err(CoreError::InvalidLeaseNonce.into());
-            }
-        }
+        check_lease(&mut state.leases.write().unwrap(), &machine_id, nonce.to_str().unwrap_or(""))?;
     }
     
+    let expected_version = headers.get(header::IF_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().trim_start_matches("W/").trim_matches('"').to_string())
+        .or(req.current_version);
+    
     let mut machines = state.machines.write().unwrap();
     
     match machines.get_mut(&machine_id) {
         Some(machine) => {
+            if let Some(expected) = expected_version.filter(|expected| expected != "*") {
+                if expected != machine.instance_id {
+                    return Err(CoreError::VersionConflict(format!(
+                        "machine {} is at version {}, not {}",
+                        machine_id, machine.instance_id, expected
+                    )).into());
+                }
+            }
+            
+            // Mounts use the app's registered volumes, like on create. Only
+            // once the update is accepted, so a refused one registers none
+            if let Some(mounts) = req.config.mounts.as_mut() {
+                volumes::resolve_mounts(&state, &app_name, &machine.region, mounts);
+            }
+            
+            machine.image_ref = parse_image_ref(&req.config.image);
             machine.config = req.config;
+            machine.instance_id = state.generate_instance_id();
             machine.updated_at = Utc::now();
             
-            Ok(Json(machine.clone()))
+            Ok((version_headers(machine), Json(machine.clone())))
         }
         None => Err(CoreError::MachineNotFound(machine_id).into()),
     }
 }
 
+/// An `ETag` carrying the machine's version, for `If-Match` on updates
+fn version_headers(machine: &Machine) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(etag) = format!("\"{}\"", machine.instance_id).parse() {
+        headers.insert(header::ETAG, etag);
+    }
+    headers
+}
+
 pub async fn delete_machine(
     State(state): State<AppState>,
     Path((app_name, machine_id)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<SuccessResponse>> {
     let force = params.get("force").map(|v| v == "true").unwrap_or(false);
+    destroy_machine(&state, &app_name, &machine_id, force).await?;
     
+    Ok(Json(SuccessResponse { ok: true }))
+}
+
+/// Removes a machine: its container if it is running (or always when
+/// `force` is set), its LiteFS process, lease, metadata and DNS records.
+pub(crate) async fn destroy_machine(state: &AppState, app_name: &str, machine_id: &str, force: bool) -> Result<()> {
     // Check if machine exists and needs container operations
-    let needs_stop = {
+    let (needs_stop, region, kill_timeout, owner) = {
         let machines = state.machines.read().unwrap();
-        match machines.get(&machine_id) {
-            Some(machine) => machine.state == MachineState::Started || force,
-            None => return Err(CoreError::MachineNotFound(machine_id.clone()).into()),
+        match machines.get(machine_id) {
+            Some(machine) => (
+                machine.state == MachineState::Started || force,
+                machine.region.clone(),
+                machine.config.kill_timeout.map(i64::from),
+                machine.app_name.clone(),
+            ),
+            None => return Err(CoreError::MachineNotFound(machine_id.to_string()).into()),
         }
     };
+    // The container is named after the app that owns the machine
+    let app_name = if owner.is_empty() { app_name } else { owner.as_str() };
     
     // Stop and remove container if needed
     if needs_stop {
         let container_name = format!("minifly-{}-{}", app_name, machine_id);
-        if let Err(e) = state.docker.stop_container(&container_name, Some(30)).await {
+        if let Err(e) = state.docker.stop_container(&container_name, kill_timeout).await {
             if !force {
-                return Err(CoreError::DockerError(format!("Failed to stop container: {}", e)).into());
+                return Err(docker_error("Failed to stop container", e).into());
             }
         }
         
         if let Err(e) = state.docker.remove_container(&container_name).await {
-            return Err(CoreError::DockerError(format!("Failed to remove container: {}", e)).into());
+            return Err(docker_error("Failed to remove container", e).into());
         }
         
         // Stop LiteFS if running
-        if state.litefs.is_running(&machine_id).await {
-            if let Err(e) = state.litefs.stop_for_machine(&machine_id).await {
+        if state.litefs.is_running(machine_id).await {
+            if let Err(e) = state.litefs.stop_for_machine(machine_id).await {
                 if !force {
                     return Err(CoreError::LiteFSError(format!("Failed to stop LiteFS: {}", e)).into());
                 }
@@ -275,18 +471,24 @@ pub async fn delete_machine(
     // Update state and remove machine
     {
         let mut machines = state.machines.write().unwrap();
-        if let Some(machine) = machines.get_mut(&machine_id) {
+        if let Some(machine) = machines.get_mut(machine_id) {
             machine.state = MachineState::Destroyed;
         }
-        machines.remove(&machine_id);
+        machines.remove(machine_id);
     }
+    state.machine_metadata.write().unwrap().remove(machine_id);
+    state.leases.write().unwrap().remove(machine_id);
+    volumes::detach_volumes(state, machine_id);
+    state.dns_resolver.uncordon_machine(machine_id).await;
     
     // Unregister from DNS
-    if let Err(e) = state.dns_resolver.unregister_machine(&app_name, &machine_id).await {
+    if let Err(e) = state.dns_resolver.unregister_machine(app_name, machine_id).await {
         tracing::warn!("Failed to unregister machine from DNS: {}", e);
     }
     
-    Ok(Json(SuccessResponse { ok: true }))
+    publish_event(state, MachineEventKind::Destroyed, app_name, machine_id, &region);
+    
+    Ok(())
 }
 
 #[instrument(skip(state), fields(app_name = %app_name, machine_id = %machine_id, region = tracing::field::Empty))]
@@ -320,12 +522,17 @@ pub async fn start_machine(
     // Start container
     let container_name = format!("minifly-{}-{}", app_name, machine_id);
     if let Err(e) = state.docker.start_container(&container_name).await {
-        return Err(CoreError::DockerError(format!("Failed to start container: {}", e)).into());
+        return Err(docker_error("Failed to start container", e).into());
     }
     
+    crate::handlers::logs::spawn_log_sink(&state, &app_name, &machine_id, &container_name, "0");
+    
     // Re-register with DNS after starting
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    let mut mapped_ports = Vec::new();
     if let Ok(container_info) = state.docker.inspect_container(&container_name).await {
+        // Ephemeral host ports change every time the container starts
+        mapped_ports = crate::docker::mapped_ports(&container_info);
         if let Some(network_settings) = container_info.network_settings {
             if let Some(networks) = network_settings.networks {
                 let networks_value = serde_json::to_value(&networks).unwrap_or_default();
@@ -340,22 +547,26 @@ pub async fn start_machine(
     }
     
     // Update machine state
+    reset_connections(&state, &machine_id);
     {
         let mut machines = state.machines.write().unwrap();
         if let Some(machine) = machines.get_mut(&machine_id) {
             machine.state = MachineState::Started;
+            machine.mapped_ports = mapped_ports;
             machine.updated_at = Utc::now();
-            machine.events.push(MachineEvent {
+            machine.push_event(MachineEvent {
                 event_type: "start".to_string(),
                 status: "started".to_string(),
                 source: "user".to_string(),
                 timestamp: Utc::now().timestamp_millis() as u64,
-            });
+                request: None,
+            }, state.config.max_machine_events);
         }
     }
     
     // Log successful start
     log_machine_operation("start", &machine_id, &app_name, &region);
+    publish_event(&state, MachineEventKind::Started, &app_name, &machine_id, &region);
     
     info!(
         machine_id = %machine_id,
@@ -371,69 +582,208 @@ pub async fn start_machine(
     }))
 }
 
+/// Records the host ports a machine's freshly started container is published on
+pub(crate) async fn record_mapped_ports(state: &AppState, machine_id: &str, container_id: &str) {
+    match state.docker.get_container_ports(container_id).await {
+        Ok(mapped_ports) => {
+            if let Some(machine) = state.machines.write().unwrap().get_mut(machine_id) {
+                machine.mapped_ports = mapped_ports;
+            }
+        }
+        Err(e) => tracing::warn!(machine_id = %machine_id, "Failed to read container ports: {}", e),
+    }
+}
+
 pub async fn stop_machine(
     State(state): State<AppState>,
     Path((app_name, machine_id)): Path<(String, String)>,
     req: Option<Json<StopMachineRequest>>,
 ) -> Result<Json<StopMachineResponse>> {
-    // Check if machine exists
-    {
-        let machines = state.machines.read().unwrap();
-        if !machines.contains_key(&machine_id) {
-            return Err(CoreError::MachineNotFound(machine_id.clone()).into());
-        }
-    }
-    
+    // A timeout in the request overrides the machine's kill_timeout
     let timeout = req.as_ref()
         .and_then(|Json(r)| r.timeout.as_ref())
-        .and_then(|t| t.parse::<i64>().ok())
-        .unwrap_or(30);
+        .and_then(|t| t.parse::<i64>().ok());
+    
+    stop(&state, &app_name, &machine_id, timeout, "user").await?;
+    Ok(Json(StopMachineResponse { ok: true }))
+}
+
+/// Stops a machine's container, giving it `timeout` seconds (or its
+/// `kill_timeout`) to exit, and records the stop as coming from `source`
+pub(crate) async fn stop(
+    state: &AppState,
+    app_name: &str,
+    machine_id: &str,
+    timeout: Option<i64>,
+    source: &str,
+) -> Result<()> {
+    let timeout = match timeout {
+        Some(timeout) => Some(timeout),
+        None => kill_timeout(state, machine_id)?,
+    };
     
     // Stop container
     let container_name = format!("minifly-{}-{}", app_name, machine_id);
-    if let Err(e) = state.docker.stop_container(&container_name, Some(timeout)).await {
-        return Err(CoreError::DockerError(format!("Failed to stop container: {}", e)).into());
+    if let Err(e) = state.docker.stop_container(&container_name, timeout).await {
+        return Err(docker_error("Failed to stop container", e).into());
     }
     
     // Update machine state
-    {
+    reset_connections(state, machine_id);
+    let region = {
         let mut machines = state.machines.write().unwrap();
-        if let Some(machine) = machines.get_mut(&machine_id) {
+        machines.get_mut(machine_id).map(|machine| {
             machine.state = MachineState::Stopped;
             machine.updated_at = Utc::now();
-            machine.events.push(MachineEvent {
+            machine.push_event(MachineEvent {
                 event_type: "stop".to_string(),
                 status: "stopped".to_string(),
-                source: "user".to_string(),
+                source: source.to_string(),
                 timestamp: Utc::now().timestamp_millis() as u64,
-            });
-        }
-    }
+                request: None,
+            }, state.config.max_machine_events);
+            machine.region.clone()
+        })
+    };
     
     // Unregister from DNS when stopped
-    if let Err(e) = state.dns_resolver.unregister_machine(&app_name, &machine_id).await {
+    if let Err(e) = state.dns_resolver.unregister_machine(app_name, machine_id).await {
         tracing::warn!("Failed to unregister machine from DNS: {}", e);
     }
     
-    Ok(Json(StopMachineResponse { ok: true }))
+    if let Some(region) = region {
+        publish_event(state, MachineEventKind::Stopped, app_name, machine_id, &region);
+    }
+    auto_destroy(state, app_name, machine_id).await;
+    Ok(())
 }
 
-pub async fn suspend_machine(
+/// Recreates a machine's container from its current config, so changes to
+/// its config or the app's secrets take effect without a new image.
+pub async fn restart_machine(
     State(state): State<AppState>,
     Path((app_name, machine_id)): Path<(String, String)>,
 ) -> Result<Json<SuccessResponse>> {
-    // Check if machine exists
-    {
+    let (region, config) = {
         let machines = state.machines.read().unwrap();
-        if !machines.contains_key(&machine_id) {
-            return Err(CoreError::MachineNotFound(machine_id.clone()).into());
+        let machine = machines
+            .get(&machine_id)
+            .ok_or_else(|| CoreError::MachineNotFound(machine_id.clone()))?;
+        (machine.region.clone(), machine.config.clone())
+    };
+    
+    info!(machine_id = %machine_id, app_name = %app_name, "Restarting machine");
+    crate::handlers::releases::recreate_container(&state, &app_name, &machine_id, &region, &config).await?;
+    
+    let container_name = format!("minifly-{}-{}", app_name, machine_id);
+    crate::handlers::logs::spawn_log_sink(&state, &app_name, &machine_id, &container_name, "0");
+    
+    {
+        let mut machines = state.machines.write().unwrap();
+        if let Some(machine) = machines.get_mut(&machine_id) {
+            machine.state = MachineState::Started;
+            machine.updated_at = Utc::now();
+            machine.push_event(MachineEvent {
+                event_type: "restart".to_string(),
+                status: "started".to_string(),
+                source: "user".to_string(),
+                timestamp: Utc::now().timestamp_millis() as u64,
+                request: None,
+            }, state.config.max_machine_events);
+        }
+    }
+    
+    log_machine_operation("restart", &machine_id, &app_name, &region);
+    publish_event(&state, MachineEventKind::Started, &app_name, &machine_id, &region);
+    
+    Ok(Json(SuccessResponse { ok: true }))
+}
+
+/// Starts or stops several of an app's machines at once.
+///
+/// With `"all"` the action applies to the app's machines it would change:
+/// `stop` to started machines and `start` to created and stopped ones. The
+/// machines are handled concurrently and each one's outcome is reported on
+/// its own, so a failure doesn't hide the rest.
+pub async fn machine_actions(
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+    Json(req): Json<MachineActionRequest>,
+) -> Result<Json<MachineActionResponse>> {
+    let stopping = match req.action.as_str() {
+        "stop" => true,
+        "start" => false,
+        other => return Err(CoreError::BadRequest(format!("Unknown action {:?}; use start or stop", other)).into()),
+    };
+    
+    let machine_ids = match req.machine_ids {
+        MachineSelection::Ids(ids) => ids,
+        MachineSelection::All => {
+            let machines = state.machines.read().unwrap();
+            let mut ids: Vec<String> = machines.values()
+                .filter(|m| m.app_name == app_name)
+                .filter(|m| match stopping {
+                    true => m.state == MachineState::Started,
+                    false => matches!(m.state, MachineState::Created | MachineState::Stopped),
+                })
+                .map(|m| m.id.clone())
+                .collect();
+            ids.sort();
+            ids
+        }
+    };
+    
+    let results = futures::future::join_all(machine_ids.into_iter().map(|machine_id| {
+        let (state, app_name) = (state.clone(), app_name.clone());
+        async move {
+            // Machines belong to the app that created them, whatever they are named
+            let in_app = state.machines.read().unwrap()
+                .get(&machine_id)
+                .is_some_and(|m| m.app_name == app_name);
+            let result = if !in_app {
+                Err(CoreError::MachineNotFound(machine_id.clone()).into())
+            } else if stopping {
+                stop(&state, &app_name, &machine_id, None, "user").await
+            } else {
+                start_machine(State(state.clone()), Path((app_name, machine_id.clone()))).await.map(|_| ())
+            };
+            MachineActionResult {
+                machine_id,
+                ok: result.is_ok(),
+                error: result.err().map(|e| e.0.to_string()),
+            }
         }
+    })).await;
+    
+    Ok(Json(MachineActionResponse { results }))
+}
+
+/// Destroys a stopped machine whose config sets `auto_destroy`, as Fly.io
+/// does for ephemeral machines
+pub(crate) async fn auto_destroy(state: &AppState, app_name: &str, machine_id: &str) {
+    let enabled = state.machines.read().unwrap().get(machine_id).is_some_and(|machine| {
+        machine.state == MachineState::Stopped && machine.config.auto_destroy == Some(true)
+    });
+    if !enabled {
+        return;
     }
     
+    match destroy_machine(state, app_name, machine_id, true).await {
+        Ok(()) => info!(machine_id = %machine_id, app_name = %app_name, "Auto-destroyed stopped machine"),
+        Err(e) => tracing::warn!(machine_id = %machine_id, "Failed to auto-destroy machine: {:?}", e.0),
+    }
+}
+
+pub async fn suspend_machine(
+    State(state): State<AppState>,
+    Path((app_name, machine_id)): Path<(String, String)>,
+) -> Result<Json<SuccessResponse>> {
+    let kill_timeout = kill_timeout(&state, &machine_id)?;
+    
     // Note: Docker doesn't support true suspend, so we'll just stop the container
     let container_name = format!("minifly-{}-{}", app_name, machine_id);
-    if let Err(e) = state.docker.stop_container(&container_name, Some(30)).await {
-        return Err(CoreError::DockerError(format!("Failed to suspend container: {}", e)).into());
+    if let Err(e) = state.docker.stop_container(&container_name, kill_timeout).await {
+        return Err(docker_error("Failed to suspend container", e).into());
     }
     
     // Update machine state
@@ -448,6 +798,51 @@ pub async fn suspend_machine(
     Ok(Json(SuccessResponse { ok: true }))
 }
 
+/// Cordons a machine so it stops receiving traffic while it keeps running.
+///
+/// The machine is dropped from `<app>.internal` resolution and from proxy
+/// target selection until it is uncordoned.
+pub async fn cordon_machine(
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+) -> Result<Json<SuccessResponse>> {
+    set_cordoned(&state, &machine_id, true)?;
+    state.dns_resolver.cordon_machine(&machine_id).await;
+
+    Ok(Json(SuccessResponse { ok: true }))
+}
+
+/// Uncordons a machine so it receives traffic again.
+pub async fn uncordon_machine(
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+) -> Result<Json<SuccessResponse>> {
+    set_cordoned(&state, &machine_id, false)?;
+    state.dns_resolver.uncordon_machine(&machine_id).await;
+
+    Ok(Json(SuccessResponse { ok: true }))
+}
+
+fn set_cordoned(state: &AppState, machine_id: &str, cordoned: bool) -> std::result::Result<(), CoreError> {
+    let mut machines = state.machines.write().unwrap();
+    let machine = machines
+        .get_mut(machine_id)
+        .ok_or_else(|| CoreError::MachineNotFound(machine_id.to_string()))?;
+
+    machine.cordoned = cordoned;
+    machine.updated_at = Utc::now();
+    machine.push_event(MachineEvent {
+        event_type: if cordoned { "cordon" } else { "uncordon" }.to_string(),
+        status: machine.state.as_str().to_string(),
+        source: "user".to_string(),
+        timestamp: Utc::now().timestamp_millis() as u64,
+        request: None,
+    }, state.config.max_machine_events);
+
+    info!(machine_id = %machine_id, cordoned = cordoned, "Updated machine cordon state");
+    Ok(())
+}
+
 pub async fn wait_machine(
     State(state): State<AppState>,
     Path((_app_name, machine_id)): Path<(String, String)>,
@@ -482,12 +877,12 @@ pub async fn create_lease(
     
     let mut leases = state.leases.write().unwrap();
     
-    // Check if lease already exists
-    if leases.contains_key(&machine_id) {
+    // Check if lease already exists; an expired one is replaced
+    if leases.get(&machine_id).is_some_and(|lease| !lease.is_expired()) {
         return Err(CoreError::LeaseConflict.into());
     }
     
-    let lease = create_machine_lease(&state, &machine_id, req.ttl.unwrap_or(300), req.description);
+    let lease = create_machine_lease(&state, &machine_id, req.ttl.unwrap_or(DEFAULT_LEASE_TTL), req.description, req.owner);
     leases.insert(machine_id.clone(), lease.clone());
     
     Ok(Json(LeaseResponse {
@@ -503,11 +898,11 @@ pub async fn get_lease(
     let leases = state.leases.read().unwrap();
     
     match leases.get(&machine_id) {
-        Some(lease) => Ok(Json(LeaseResponse {
+        Some(lease) if !lease.is_expired() => Ok(Json(LeaseResponse {
             status: "success".to_string(),
             data: lease.clone(),
         })),
-        None => Err(CoreError::NotFound.into()),
+        _ => Err(CoreError::NotFound.into()),
     }
 }
 
@@ -522,45 +917,166 @@ pub async fn release_lease(
     
     let mut leases = state.leases.write().unwrap();
     
-    match leases.get(&machine_id) {
-        Some(lease) => {
-            if lease.nonce != nonce {
-                return Err(CoreError::InvalidLeaseNonce.into());
-            }
-            
-            leases.remove(&machine_id);
-            Ok(Json(SuccessResponse { ok: true }))
-        }
-        None => Err(CoreError::NotFound.into()),
+    if !check_lease(&mut leases, &machine_id, nonce)? {
+        return Err(CoreError::NotFound.into());
     }
+    leases.remove(&machine_id);
+    Ok(Json(SuccessResponse { ok: true }))
+}
+
+/// Extends the lease whose nonce is given to `ttl` seconds from now, so
+/// long-running operations can keep it without releasing it
+pub async fn renew_lease(
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    req: Option<Json<RenewLeaseRequest>>,
+) -> Result<Json<LeaseResponse>> {
+    let nonce = headers.get("fly-machine-lease-nonce")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| ApiError::from(CoreError::BadRequest("Missing lease nonce".to_string())))?;
+    let ttl = req.and_then(|Json(r)| r.ttl).unwrap_or(DEFAULT_LEASE_TTL);
+    
+    let mut leases = state.leases.write().unwrap();
+    
+    if !check_lease(&mut leases, &machine_id, nonce)? {
+        return Err(CoreError::NotFound.into());
+    }
+    let lease = leases.get_mut(&machine_id).expect("checked lease exists");
+    lease.expires_at = Utc::now().timestamp() + ttl as i64;
+    
+    Ok(Json(LeaseResponse {
+        status: "success".to_string(),
+        data: lease.clone(),
+    }))
+}
+
+/// Checks `nonce` against the machine's lease, returning whether it has one.
+/// An expired lease is removed and rejected, so a stale nonce is never
+/// accepted.
+fn check_lease(leases: &mut HashMap<String, Lease>, machine_id: &str, nonce: &str) -> Result<bool> {
+    let Some(lease) = leases.get(machine_id) else {
+        return Ok(false);
+    };
+    if lease.is_expired() {
+        leases.remove(machine_id);
+        return Err(CoreError::LeaseExpired.into());
+    }
+    if lease.nonce != nonce {
+        return Err(CoreError::InvalidLeaseNonce.into());
+    }
+    Ok(true)
 }
 
 pub async fn get_metadata(
-    State(_state): State<AppState>,
-    Path((_app_name, _machine_id)): Path<(String, String)>,
-) -> Result<Json<Value>> {
-    // TODO: Implement metadata storage
-    Ok(Json(json!({})))
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+) -> Result<Json<HashMap<String, String>>> {
+    ensure_machine_exists(&state, &machine_id)?;
+    
+    let metadata = state.machine_metadata.read().unwrap();
+    Ok(Json(metadata.get(&machine_id).cloned().unwrap_or_default()))
 }
 
+/// Sets a metadata key on a machine.
+///
+/// Accepts either `{"value": "..."}` as Fly does or a bare JSON value.
 pub async fn set_metadata(
-    State(_state): State<AppState>,
-    Path((_app_name, _machine_id, _key)): Path<(String, String, String)>,
-    Json(_value): Json<Value>,
+    State(state): State<AppState>,
+    Path((_app_name, machine_id, key)): Path<(String, String, String)>,
+    Json(body): Json<Value>,
 ) -> Result<Json<SuccessResponse>> {
-    // TODO: Implement metadata storage
+    ensure_machine_exists(&state, &machine_id)?;
+    
+    let value = match body.get("value").unwrap_or(&body) {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    
+    state.machine_metadata.write().unwrap()
+        .entry(machine_id)
+        .or_default()
+        .insert(key, value);
+    
     Ok(Json(SuccessResponse { ok: true }))
 }
 
 pub async fn delete_metadata(
-    State(_state): State<AppState>,
-    Path((_app_name, _machine_id, _key)): Path<(String, String, String)>,
+    State(state): State<AppState>,
+    Path((_app_name, machine_id, key)): Path<(String, String, String)>,
 ) -> Result<Json<SuccessResponse>> {
-    // TODO: Implement metadata storage
+    ensure_machine_exists(&state, &machine_id)?;
+    
+    if let Some(values) = state.machine_metadata.write().unwrap().get_mut(&machine_id) {
+        values.remove(&key);
+    }
+    
     Ok(Json(SuccessResponse { ok: true }))
 }
 
-fn create_machine_lease(_state: &AppState, _machine_id: &str, ttl: u32, description: Option<String>) -> Lease {
+/// Connections proxies currently hold open to a machine
+pub async fn get_connections(
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+) -> Result<Json<MachineConnections>> {
+    ensure_machine_exists(&state, &machine_id)?;
+    
+    let mut connections = state.connections.write().unwrap();
+    Ok(Json(connections.get_mut(&machine_id).map(|leases| leases.status(Utc::now())).unwrap_or_default()))
+}
+
+/// Records how many connections a proxy holds open to a machine.
+///
+/// The idle monitor in [`crate::autostop`] stops machines whose services set
+/// `autostop` once they have had no open connections for long enough.
+pub async fn update_connections(
+    State(state): State<AppState>,
+    Path((_app_name, machine_id)): Path<(String, String)>,
+    Json(req): Json<UpdateConnectionsRequest>,
+) -> Result<Json<MachineConnections>> {
+    ensure_machine_exists(&state, &machine_id)?;
+    
+    let now = Utc::now();
+    let mut connections = state.connections.write().unwrap();
+    let leases = connections.entry(machine_id).or_default();
+    leases.report(&req.client_id, req.active, now);
+    Ok(Json(leases.status(now)))
+}
+
+/// Forgets the connections reported to a machine that stopped or started,
+/// which proxies report again if they still hold any
+fn reset_connections(state: &AppState, machine_id: &str) {
+    state.connections.write().unwrap().remove(machine_id);
+}
+
+/// Publishes a lifecycle event on the state's event bus
+fn publish_event(state: &AppState, kind: MachineEventKind, app_name: &str, machine_id: &str, region: &str) {
+    state.events.publish(crate::events::MachineEvent::new(kind, app_name, machine_id, region));
+}
+
+/// The machine's `kill_timeout`; without one Docker's stop default applies
+pub(crate) fn kill_timeout(state: &AppState, machine_id: &str) -> Result<Option<i64>> {
+    match state.machines.read().unwrap().get(machine_id) {
+        Some(machine) => Ok(machine.config.kill_timeout.map(i64::from)),
+        None => Err(CoreError::MachineNotFound(machine_id.to_string()).into()),
+    }
+}
+
+fn ensure_machine_exists(state: &AppState, machine_id: &str) -> Result<()> {
+    if state.machines.read().unwrap().contains_key(machine_id) {
+        Ok(())
+    } else {
+        Err(CoreError::MachineNotFound(machine_id.to_string()).into())
+    }
+}
+
+fn create_machine_lease(
+    _state: &AppState,
+    _machine_id: &str,
+    ttl: u32,
+    description: Option<String>,
+    owner: Option<String>,
+) -> Lease {
     use rand::Rng;
     use uuid::Uuid;
     let mut rng = rand::thread_rng();
@@ -571,8 +1087,608 @@ fn create_machine_lease(_state: &AppState, _machine_id: &str, ttl: u32, descript
     Lease {
         nonce,
         expires_at: Utc::now().timestamp() + ttl as i64,
-        owner: "minifly@local".to_string(),
+        owner: owner.filter(|owner| !owner.is_empty()).unwrap_or_else(|| "minifly@local".to_string()),
         description: description.unwrap_or_default(),
         version: format!("01{}", Uuid::new_v4().simple()),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minifly_core::models::{GuestConfig, ImageRef, MachineConfig, MountConfig};
+
+    fn test_machine(id: &str, state: MachineState) -> Machine {
+        Machine {
+            id: id.to_string(),
+            name: format!("test-app-{}", id),
+            app_name: "test-app".to_string(),
+            state,
+            region: "local".to_string(),
+            image_ref: ImageRef {
+                registry: "registry-1.docker.io".to_string(),
+                repository: "library".to_string(),
+                tag: "nginx:latest".to_string(),
+                digest: None,
+            },
+            instance_id: "01TEST".to_string(),
+            private_ip: "fdaa:0:1:a7b:0::2".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            config: MachineConfig {
+                image: "nginx:latest".to_string(),
+                guest: GuestConfig {
+                    cpu_kind: "shared".to_string(),
+                    cpus: 1,
+                    memory_mb: 256,
+                    gpu_kind: None,
+                    gpus: None,
+                    kernel_args: None,
+                },
+                env: None,
+                services: None,
+                checks: None,
+                restart: None,
+                auto_destroy: None,
+                kill_timeout: None,
+                dns: None,
+                processes: None,
+                files: None,
+                init: None,
+                mounts: None,
+                containers: None,
+            },
+            events: vec![],
+            cordoned: false,
+            checks: Vec::new(),
+            mapped_ports: Vec::new(),
+        }
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_list_machines_pagination() {
+        let machines: Vec<Machine> = (1..=5)
+            .map(|i| test_machine(&format!("m{}", i), MachineState::Started))
+            .collect();
+        let machines = filter_machines(machines, &HashMap::new(), &HashMap::new());
+
+        let (page, next) = paginate(machines.clone(), &query(&[("limit", "2")])).unwrap();
+        assert_eq!(page.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m1", "m2"]);
+        assert_eq!(next, Some(2));
+
+        let (page, next) = paginate(machines.clone(), &query(&[("limit", "2"), ("offset", "2")])).unwrap();
+        assert_eq!(page.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m3", "m4"]);
+        assert_eq!(next, Some(4));
+
+        let (page, next) = paginate(machines, &query(&[("limit", "2"), ("offset", "4")])).unwrap();
+        assert_eq!(page.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m5"]);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_redact_env() {
+        let env: BTreeMap<String, String> = [
+            ("FLY_APP_NAME", "web"),
+            ("FLY_MACHINE_ID", "d123456789"),
+            ("DATABASE_URL", "postgres://localhost/app"),
+            ("STRIPE_SECRET_KEY", "sk_test_123"),
+            ("auth_token", "abc"),
+        ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        // Secrets are redacted whatever they are named
+        let env: BTreeMap<String, String> = env.into_iter()
+            .chain([("GREETING".to_string(), "hunter2".to_string())])
+            .collect();
+        let secrets = vec!["GREETING".to_string()];
+
+        let response = redact_env(env.clone(), secrets.clone(), false);
+        assert_eq!(response.redacted, ["DATABASE_URL", "GREETING", "STRIPE_SECRET_KEY", "auth_token"]);
+        assert_eq!(response.env["GREETING"], "<redacted>");
+        assert_eq!(response.secrets, secrets);
+        assert_eq!(response.env["FLY_APP_NAME"], "web");
+        assert_eq!(response.env["FLY_MACHINE_ID"], "d123456789");
+        assert_eq!(response.env["DATABASE_URL"], "<redacted>");
+        assert_eq!(response.env["auth_token"], "<redacted>");
+
+        let response = redact_env(env.clone(), secrets, true);
+        assert!(response.redacted.is_empty());
+        assert_eq!(response.env, env);
+    }
+
+    #[test]
+    fn test_list_machines_state_filter() {
+        let machines = vec![
+            test_machine("m1", MachineState::Started),
+            test_machine("m2", MachineState::Stopped),
+            test_machine("m3", MachineState::Started),
+        ];
+
+        let result = filter_machines(machines.clone(), &query(&[("state", "started")]), &HashMap::new());
+        assert_eq!(result.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m1", "m3"]);
+
+        let result = filter_machines(machines, &query(&[("state", "started,stopped")]), &HashMap::new());
+        assert_eq!(result.len(), 3);
+    }
+
+    #[test]
+    fn test_list_machines_metadata_filter() {
+        let machines = vec![
+            test_machine("m1", MachineState::Started),
+            test_machine("m2", MachineState::Started),
+            test_machine("m3", MachineState::Stopped),
+        ];
+        let metadata: HashMap<String, HashMap<String, String>> = [
+            ("m1", "worker"),
+            ("m2", "web"),
+            ("m3", "worker"),
+        ]
+        .iter()
+        .map(|(id, role)| (id.to_string(), HashMap::from([("role".to_string(), role.to_string())])))
+        .collect();
+
+        let result = filter_machines(machines.clone(), &query(&[("metadata.role", "worker")]), &metadata);
+        assert_eq!(result.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m1", "m3"]);
+
+        let result = filter_machines(
+            machines,
+            &query(&[("metadata.role", "worker"), ("state", "started")]),
+            &metadata,
+        );
+        assert_eq!(result.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m1"]);
+    }
+
+    #[tokio::test]
+    async fn test_create_publishes_event() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let mut events = state.events.subscribe();
+
+        let request = CreateMachineRequest {
+            name: None,
+            region: Some("ord".to_string()),
+            config: test_machine("unused", MachineState::Created).config,
+            skip_launch: Some(true),
+            skip_service_registration: None,
+            lease_ttl: None,
+        };
+        let Ok((_, Json(machine))) = create_machine(State(state), Path("web".to_string()), None, Json(request)).await else {
+            panic!("create_machine failed");
+        };
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, MachineEventKind::Created);
+        assert_eq!(event.app_name, "web");
+        assert_eq!(event.machine_id, machine.id);
+        assert_eq!(event.region, "ord");
+        // Skipping launch leaves the machine created but not started
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_init_overrides_are_rejected() {
+        use axum::response::IntoResponse;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let mut config = test_machine("unused", MachineState::Created).config;
+        config.init = Some(minifly_core::models::InitConfig {
+            exec: Some(vec!["/app/server".to_string()]),
+            entrypoint: None,
+            cmd: Some(vec!["serve".to_string()]),
+        });
+
+        let request = CreateMachineRequest {
+            name: None,
+            region: None,
+            config,
+            skip_launch: None,
+            skip_service_registration: None,
+            lease_ttl: None,
+        };
+        let Err(err) = create_machine(State(state.clone()), Path("web".to_string()), None, Json(request)).await else {
+            panic!("create_machine accepted exec with cmd");
+        };
+        assert_eq!(err.into_response().status(), axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(state.machines.read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_without_docker_is_unavailable() {
+        use axum::response::IntoResponse;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(data_dir.path()).await;
+        let missing_socket = data_dir.path().join("missing.sock");
+        state.docker = crate::docker::DockerClient::new(Some(missing_socket.to_str().unwrap()));
+
+        let request = CreateMachineRequest {
+            name: None,
+            region: None,
+            config: test_machine("unused", MachineState::Created).config,
+            skip_launch: None,
+            skip_service_registration: None,
+            lease_ttl: None,
+        };
+        let Err(err) = create_machine(State(state.clone()), Path("web".to_string()), None, Json(request)).await else {
+            panic!("create_machine succeeded without Docker");
+        };
+        assert!(matches!(err.0, CoreError::DockerUnavailable(_)));
+        assert_eq!(err.into_response().status(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert!(state.machines.read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_defaults_to_request_region() {
+        use tower::ServiceExt;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let app = axum::Router::new()
+            .nest("/v1", crate::handlers::routes())
+            .layer(axum::middleware::from_fn_with_state(
+                state.config.region.clone(),
+                crate::middleware::region::region_middleware,
+            ))
+            .with_state(state);
+
+        let body = json!({ "config": test_machine("unused", MachineState::Created).config, "skip_launch": true });
+        let create = |region_header: Option<&str>| {
+            let mut request = axum::http::Request::post("/v1/apps/web/machines")
+                .header("content-type", "application/json");
+            if let Some(region) = region_header {
+                request = request.header("fly-region", region);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::from(body.to_string())).unwrap())
+        };
+
+        let response = create(Some("ams")).await.unwrap();
+        assert_eq!(response.headers()["x-minifly-region"], "ams");
+        let body = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        let machine: Machine = serde_json::from_slice(&body).unwrap();
+        assert_eq!(machine.region, "ams");
+
+        // Without the header the configured local region is used
+        let response = create(None).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        let machine: Machine = serde_json::from_slice(&body).unwrap();
+        assert_eq!(machine.region, "local");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_uses_kill_timeout() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |_| (204, String::new()));
+
+        let mut machine = test_machine("d1", MachineState::Started);
+        machine.config.kill_timeout = Some(7);
+        state.machines.write().unwrap().insert("d1".to_string(), machine);
+
+        let stop = |timeout: Option<&str>| {
+            let req = timeout.map(|timeout| Json(StopMachineRequest { signal: None, timeout: Some(timeout.to_string()) }));
+            stop_machine(State(state.clone()), Path(("test-app".to_string(), "d1".to_string())), req)
+        };
+        assert!(stop(None).await.is_ok());
+        // A timeout in the request still wins
+        assert!(stop(Some("2")).await.is_ok());
+
+        let stops: Vec<_> = requests.lock().unwrap().iter()
+            .filter(|request| request.contains("/stop"))
+            .cloned()
+            .collect();
+        assert_eq!(stops.len(), 2);
+        assert!(stops[0].contains("/containers/minifly-test-app-d1/stop?t=7"), "{}", stops[0]);
+        assert!(stops[1].contains("stop?t=2"), "{}", stops[1]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_started_machine_records_host_ports() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |request| {
+            match request.starts_with("GET /containers/minifly-test-app-d1/json") {
+                true => (200, json!({
+                    "NetworkSettings": { "Ports": { "8080/tcp": [{ "HostIp": "0.0.0.0", "HostPort": "32771" }] } }
+                }).to_string()),
+                false => (204, String::new()),
+            }
+        });
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Stopped));
+
+        let start = start_machine(State(state.clone()), Path(("test-app".to_string(), "d1".to_string()))).await;
+        assert!(start.is_ok());
+
+        let machines = state.machines.read().unwrap();
+        let ports = &machines["d1"].mapped_ports;
+        assert_eq!(ports.len(), 1);
+        assert_eq!((ports[0].internal_port, ports[0].protocol.as_str(), ports[0].host_port), (8080, "tcp", 32771));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stopping_auto_destroy_machine_removes_it() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |_| (204, String::new()));
+        let mut events = state.events.subscribe();
+
+        let mut machine = test_machine("d1", MachineState::Started);
+        machine.config.auto_destroy = Some(true);
+        state.machines.write().unwrap().insert("d1".to_string(), machine);
+        state.dns_resolver.register_machine("test-app", "d1", "172.19.0.7".parse().unwrap()).await.unwrap();
+
+        let stop = stop_machine(State(state.clone()), Path(("test-app".to_string(), "d1".to_string())), None).await;
+        assert!(stop.is_ok());
+
+        assert!(state.machines.read().unwrap().is_empty());
+        assert!(!matches!(state.dns_resolver.resolve("test-app.internal").await, Ok(ips) if !ips.is_empty()));
+        assert!(requests.lock().unwrap().iter().any(|r| r.starts_with("DELETE /containers/minifly-test-app-d1")));
+        assert_eq!(events.try_recv().unwrap().kind, MachineEventKind::Stopped);
+        assert_eq!(events.try_recv().unwrap().kind, MachineEventKind::Destroyed);
+    }
+
+    #[tokio::test]
+    async fn test_connection_counter() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Started));
+
+        let update = |client_id: &str, active: u32| {
+            let path = Path(("test-app".to_string(), "d1".to_string()));
+            update_connections(State(state.clone()), path, Json(UpdateConnectionsRequest { client_id: client_id.to_string(), active }))
+        };
+        assert!(update("gateway", 2).await.is_ok());
+        assert!(update("proxy", 1).await.is_ok());
+        let Ok(Json(connections)) = update("gateway", 1).await else { panic!("report failed") };
+        assert_eq!(connections, MachineConnections { active: 2, idle_since: None });
+
+        assert!(update("gateway", 0).await.is_ok());
+        let Ok(Json(connections)) = update("proxy", 0).await else { panic!("report failed") };
+        assert_eq!(connections.active, 0);
+        assert!(connections.idle_since.is_some());
+
+        // Stopping or starting a machine forgets its connections
+        assert!(update("proxy", 1).await.is_ok());
+        reset_connections(&state, "d1");
+        let path = Path(("test-app".to_string(), "d1".to_string()));
+        let Ok(Json(connections)) = get_connections(State(state.clone()), path).await else { panic!("lookup failed") };
+        assert_eq!(connections.active, 0);
+
+        let path = Path(("test-app".to_string(), "missing".to_string()));
+        let request = UpdateConnectionsRequest { client_id: "proxy".to_string(), active: 1 };
+        assert!(update_connections(State(state.clone()), path, Json(request)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_are_capped_and_listed_newest_first() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(data_dir.path()).await;
+        state.config.max_machine_events = Some(3);
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Started));
+
+        for cordoned in [true, false, true, false, true] {
+            set_cordoned(&state, "d1", cordoned).unwrap();
+        }
+        assert_eq!(state.machines.read().unwrap()["d1"].events.len(), 3);
+
+        let path = || Path(("test-app".to_string(), "d1".to_string()));
+        let Ok((headers, Json(events))) = list_events(State(state.clone()), path(), Query(query(&[("limit", "2")]))).await else {
+            panic!("listing events failed");
+        };
+        let types: Vec<_> = events.iter().map(|event| event.event_type.as_str()).collect();
+        assert_eq!(types, ["cordon", "uncordon"]);
+        assert_eq!(headers[crate::handlers::pagination::NEXT_OFFSET_HEADER], "2");
+
+        let Ok((_, Json(events))) = list_events(State(state.clone()), path(), Query(query(&[("offset", "2")]))).await else {
+            panic!("listing events failed");
+        };
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "cordon");
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_is_treated_as_absent() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Started));
+        let path = || Path(("test-app".to_string(), "d1".to_string()));
+        let lease = |ttl: u32| create_lease(State(state.clone()), path(), Json(CreateLeaseRequest { description: None, owner: None, ttl: Some(ttl) }));
+        let nonce_header = |nonce: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("fly-machine-lease-nonce", nonce.parse().unwrap());
+            headers
+        };
+
+        let Ok(Json(first)) = lease(1).await else { panic!("first lease was refused") };
+        assert!(lease(1).await.is_err());
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        assert!(get_lease(State(state.clone()), path()).await.is_err());
+        // The stale nonce no longer releases anything
+        let release = release_lease(State(state.clone()), path(), nonce_header(&first.data.nonce)).await;
+        assert!(matches!(release, Err(ApiError(CoreError::LeaseExpired))));
+
+        // A new lease can be taken once the old one has expired
+        let Ok(Json(second)) = lease(1).await else { panic!("lease after expiry was refused") };
+        assert_ne!(second.data.nonce, first.data.nonce);
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let update = update_machine(
+            State(state.clone()),
+            path(),
+            nonce_header(&second.data.nonce),
+            Json(serde_json::from_value(serde_json::json!({ "config": test_machine("d1", MachineState::Started).config })).unwrap()),
+        ).await;
+        assert!(matches!(update, Err(ApiError(CoreError::LeaseExpired))));
+        assert!(state.leases.read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_renewing_lease_extends_expiry() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Started));
+        let path = || Path(("test-app".to_string(), "d1".to_string()));
+        let renew = |nonce: &str, ttl: u32| {
+            let mut headers = HeaderMap::new();
+            headers.insert("fly-machine-lease-nonce", nonce.parse().unwrap());
+            renew_lease(State(state.clone()), path(), headers, Some(Json(RenewLeaseRequest { ttl: Some(ttl) })))
+        };
+
+        let create = create_lease(State(state.clone()), path(), Json(CreateLeaseRequest { description: None, owner: None, ttl: Some(1) })).await;
+        let Ok(Json(lease)) = create else { panic!("lease was refused") };
+
+        let Ok(Json(renewed)) = renew(&lease.data.nonce, 600).await else { panic!("renewal was refused") };
+        assert_eq!(renewed.data.nonce, lease.data.nonce);
+        assert!(renewed.data.expires_at >= lease.data.expires_at + 599);
+        assert!(matches!(renew("wrong", 600).await, Err(ApiError(CoreError::InvalidLeaseNonce))));
+
+        // The renewed lease outlives its original one-second TTL
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        assert!(get_lease(State(state.clone()), path()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_lease_owner_is_echoed_back() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Started));
+        state.machines.write().unwrap().insert("d2".to_string(), test_machine("d2", MachineState::Started));
+        let path = |id: &str| Path(("test-app".to_string(), id.to_string()));
+
+        let req = CreateLeaseRequest { description: None, owner: Some("deploy-worker-2".to_string()), ttl: None };
+        assert!(create_lease(State(state.clone()), path("d1"), Json(req)).await.is_ok());
+        let Ok(Json(lease)) = get_lease(State(state.clone()), path("d1")).await else { panic!("lease not found") };
+        assert_eq!(lease.data.owner, "deploy-worker-2");
+
+        let req = CreateLeaseRequest { description: None, owner: None, ttl: None };
+        assert!(create_lease(State(state.clone()), path("d2"), Json(req)).await.is_ok());
+        let Ok(Json(lease)) = get_lease(State(state.clone()), path("d2")).await else { panic!("lease not found") };
+        assert_eq!(lease.data.owner, "minifly@local");
+    }
+
+    #[tokio::test]
+    async fn test_stale_version_update_is_rejected() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        state.machines.write().unwrap().insert("d1".to_string(), test_machine("d1", MachineState::Started));
+        let update = |if_match: &str, image: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_MATCH, if_match.parse().unwrap());
+            let mut config = test_machine("d1", MachineState::Started).config;
+            config.image = image.to_string();
+            config.mounts = Some(vec![MountConfig { volume: image.replace(':', "-"), path: "/data".to_string() }]);
+            let req = serde_json::from_value(serde_json::json!({ "config": config })).unwrap();
+            update_machine(State(state.clone()), Path(("test-app".to_string(), "d1".to_string())), headers, Json(req))
+        };
+
+        let Ok((headers, Json(updated))) = update("\"01TEST\"", "nginx:1.27").await else {
+            panic!("update at the current version was refused");
+        };
+        assert_ne!(updated.instance_id, "01TEST");
+        assert_eq!(headers[header::ETAG], format!("\"{}\"", updated.instance_id));
+
+        // A client still holding the old version loses instead of overwriting
+        let stale = update("\"01TEST\"", "nginx:1.25").await;
+        assert!(matches!(stale, Err(ApiError(CoreError::VersionConflict(_)))));
+        assert_eq!(state.machines.read().unwrap()["d1"].config.image, "nginx:1.27");
+        let volume_names = || {
+            let mut names: Vec<_> = state.volumes.read().unwrap()["test-app"].values().map(|v| v.name.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(volume_names(), ["nginx-1.27"]);
+
+        assert!(update(&updated.instance_id, "nginx:1.28").await.is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_all_reports_each_machine() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |request| {
+            match request.contains("minifly-test-app-d2/stop") {
+                true => (500, r#"{"message":"container is stuck"}"#.to_string()),
+                false => (204, String::new()),
+            }
+        });
+        for (id, machine_state) in [("d1", MachineState::Started), ("d2", MachineState::Started), ("d3", MachineState::Stopped)] {
+            state.machines.write().unwrap().insert(id.to_string(), test_machine(id, machine_state));
+        }
+        // Ownership decides membership, not the name: e1 only looks like a test-app machine
+        let mut other = test_machine("e1", MachineState::Started);
+        other.name = "test-app-staging-e1".to_string();
+        other.app_name = "test-app-staging".to_string();
+        state.machines.write().unwrap().insert("e1".to_string(), other);
+        let mut renamed = test_machine("d4", MachineState::Started);
+        renamed.name = "worker".to_string();
+        state.machines.write().unwrap().insert("d4".to_string(), renamed);
+
+        let req = MachineActionRequest { action: "stop".to_string(), machine_ids: MachineSelection::All };
+        let Ok(Json(resp)) = machine_actions(State(state.clone()), Path("test-app".to_string()), Json(req)).await else {
+            panic!("bulk stop was refused");
+        };
+
+        let outcomes: Vec<_> = resp.results.iter().map(|r| (r.machine_id.as_str(), r.ok)).collect();
+        assert_eq!(outcomes, [("d1", true), ("d2", false), ("d4", true)]);
+        assert!(resp.results[1].error.as_deref().unwrap().contains("Failed to stop container"));
+
+        // Explicit IDs are checked the same way
+        let req = MachineActionRequest {
+            action: "start".to_string(),
+            machine_ids: MachineSelection::Ids(vec!["e1".to_string()]),
+        };
+        let Ok(Json(resp)) = machine_actions(State(state.clone()), Path("test-app".to_string()), Json(req)).await else {
+            panic!("bulk start was refused");
+        };
+        assert!(!resp.results[0].ok);
+
+        let machines = state.machines.read().unwrap();
+        assert_eq!(machines["d1"].state, MachineState::Stopped);
+        assert_eq!(machines["d2"].state, MachineState::Started);
+        assert_eq!(machines["d4"].state, MachineState::Stopped);
+        assert_eq!(machines["e1"].state, MachineState::Started);
+        assert!(!requests.lock().unwrap().iter().any(|r| r.contains("minifly-test-app-d3")));
+    }
+
+    #[tokio::test]
+    async fn test_mounts_use_registered_volumes() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let req = serde_json::from_value(json!({ "name": "data", "region": "local" })).unwrap();
+        let Ok(Json(data)) = volumes::create_volume(State(state.clone()), Path("web".to_string()), Json(req)).await else {
+            panic!("volume creation was refused");
+        };
+
+        // One mount names the volume by ID, the other a volume that doesn't exist yet
+        let mut config = test_machine("unused", MachineState::Created).config;
+        config.mounts = Some(vec![
+            MountConfig { volume: data.id.clone(), path: "/data".to_string() },
+            MountConfig { volume: "uploads".to_string(), path: "/uploads".to_string() },
+        ]);
+        let req = serde_json::from_value(json!({ "config": config, "skip_launch": true })).unwrap();
+        let Ok((_, Json(machine))) = create_machine(State(state.clone()), Path("web".to_string()), None, Json(req)).await else {
+            panic!("machine creation was refused");
+        };
+
+        let mounts = machine.config.mounts.unwrap();
+        assert_eq!(mounts[0].volume, data.id);
+        {
+            let volumes = state.volumes.read().unwrap();
+            assert_eq!(volumes["web"][&mounts[1].volume].name, "uploads");
+            let mut attached: Vec<_> = volumes["web"].values()
+                .map(|volume| (volume.name.as_str(), volume.attached_machine_id.as_deref()))
+                .collect();
+            attached.sort();
+            assert_eq!(attached, [("data", Some(machine.id.as_str())), ("uploads", Some(machine.id.as_str()))]);
+        }
+
+        assert!(destroy_machine(&state, "web", &machine.id, false).await.is_ok());
+        assert!(state.volumes.read().unwrap()["web"].values().all(|volume| volume.attached_machine_id.is_none()));
+    }
+}
@@ -6,17 +6,27 @@ use crate::state::AppState;
 
 mod admin;
 mod apps;
+mod events;
+mod litefs;
 mod logs;
 mod machines;
+mod pagination;
+mod releases;
 mod volumes;
 mod health;
 
+pub(crate) use logs::spawn_log_sink;
+pub(crate) use machines::{auto_destroy, parse_image_ref, record_mapped_ports, stop};
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         // Administrative endpoints
         .route("/admin/shutdown", post(admin::shutdown))
         .route("/admin/status", get(admin::system_status))
         
+        // Event stream
+        .route("/events", get(events::stream_events))
+        
         // Health endpoints
         .route("/health", get(health::health_check))
         .route("/health/comprehensive", get(health::comprehensive_health))
@@ -24,25 +34,41 @@ pub fn routes() -> Router<AppState> {
         .route("/health/ready", get(health::readiness))
         
         // Apps endpoints
+        .route("/apps", get(apps::list_apps))
         .route("/apps", post(apps::create_app))
         .route("/apps/:app_name", get(apps::get_app))
         .route("/apps/:app_name", delete(apps::delete_app))
+        .route("/apps/:app_name/dns", get(apps::resolve_app))
+        
+        // Release endpoints
+        .route("/apps/:app_name/releases", get(releases::list_releases))
+        .route("/apps/:app_name/releases", post(releases::create_release))
+        .route("/apps/:app_name/releases/:version/rollback", post(releases::rollback_release))
         
         // Machines endpoints
         .route("/apps/:app_name/machines", get(machines::list_machines))
         .route("/apps/:app_name/machines", post(machines::create_machine))
+        .route("/apps/:app_name/machines/actions", post(machines::machine_actions))
         .route("/apps/:app_name/machines/:machine_id", get(machines::get_machine))
         .route("/apps/:app_name/machines/:machine_id", post(machines::update_machine))
         .route("/apps/:app_name/machines/:machine_id", delete(machines::delete_machine))
         .route("/apps/:app_name/machines/:machine_id/start", post(machines::start_machine))
         .route("/apps/:app_name/machines/:machine_id/stop", post(machines::stop_machine))
+        .route("/apps/:app_name/machines/:machine_id/restart", post(machines::restart_machine))
         .route("/apps/:app_name/machines/:machine_id/suspend", post(machines::suspend_machine))
+        .route("/apps/:app_name/machines/:machine_id/cordon", post(machines::cordon_machine))
+        .route("/apps/:app_name/machines/:machine_id/uncordon", post(machines::uncordon_machine))
         .route("/apps/:app_name/machines/:machine_id/wait", get(machines::wait_machine))
+        .route("/apps/:app_name/machines/:machine_id/events", get(machines::list_events))
+        .route("/apps/:app_name/machines/:machine_id/env", get(machines::get_env))
+        .route("/apps/:app_name/machines/:machine_id/connections", get(machines::get_connections))
+        .route("/apps/:app_name/machines/:machine_id/connections", post(machines::update_connections))
         
         // Lease endpoints
         .route("/apps/:app_name/machines/:machine_id/lease", post(machines::create_lease))
         .route("/apps/:app_name/machines/:machine_id/lease", get(machines::get_lease))
         .route("/apps/:app_name/machines/:machine_id/lease", delete(machines::release_lease))
+        .route("/apps/:app_name/machines/:machine_id/lease/renew", post(machines::renew_lease))
         
         // Metadata endpoints
         .route("/apps/:app_name/machines/:machine_id/metadata", get(machines::get_metadata))
@@ -50,12 +76,19 @@ pub fn routes() -> Router<AppState> {
         .route("/apps/:app_name/machines/:machine_id/metadata/:key", delete(machines::delete_metadata))
         
         // Log endpoints
+        .route("/apps/:app_name/logs", get(logs::stream_app_logs))
+        .route("/apps/:app_name/logs/summary", get(logs::get_app_logs_summary))
         .route("/apps/:app_name/machines/:machine_id/logs", get(logs::stream_machine_logs))
         .route("/apps/:app_name/machines/:machine_id/logs/summary", get(logs::get_logs_summary))
         
+        // LiteFS endpoints
+        .route("/apps/:app_name/litefs/status", get(litefs::get_status))
+        .route("/apps/:app_name/litefs/promote", post(litefs::promote))
+        
         // Volume endpoints
         .route("/apps/:app_name/volumes", get(volumes::list_volumes))
         .route("/apps/:app_name/volumes", post(volumes::create_volume))
         .route("/apps/:app_name/volumes/:volume_id", get(volumes::get_volume))
         .route("/apps/:app_name/volumes/:volume_id", delete(volumes::delete_volume))
+        .route("/apps/:app_name/volumes/:volume_id/extend", post(volumes::extend_volume))
 }
\ No newline at end of file
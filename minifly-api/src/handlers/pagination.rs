@@ -0,0 +1,79 @@
+use axum::http::{HeaderMap, HeaderValue};
+use minifly_core::Error as CoreError;
+use std::collections::HashMap;
+
+/// Header carrying the offset of the next page when more results are available
+pub const NEXT_OFFSET_HEADER: &str = "x-minifly-next-offset";
+
+/// Slices `items` according to the `limit` and `offset` query parameters.
+///
+/// Without a `limit` every item from `offset` onwards is returned, so list
+/// endpoints keep returning everything when no parameters are given.
+///
+/// # Returns
+/// The page of items and the offset of the next page, if there is one
+pub fn paginate<T>(
+    items: Vec<T>,
+    params: &HashMap<String, String>,
+) -> Result<(Vec<T>, Option<usize>), CoreError> {
+    let offset = parse_param(params, "offset")?.unwrap_or(0);
+    let limit = parse_param(params, "limit")?;
+
+    if limit == Some(0) {
+        return Err(CoreError::BadRequest("limit must be greater than 0".to_string()));
+    }
+
+    let total = items.len();
+    let page: Vec<T> = items
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    let next = offset + page.len();
+    let next_offset = if limit.is_some() && next < total { Some(next) } else { None };
+
+    Ok((page, next_offset))
+}
+
+/// Builds the response headers advertising the next page offset.
+pub fn page_headers(next_offset: Option<usize>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(next) = next_offset {
+        headers.insert(NEXT_OFFSET_HEADER, HeaderValue::from(next));
+    }
+    headers
+}
+
+fn parse_param(params: &HashMap<String, String>, name: &str) -> Result<Option<usize>, CoreError> {
+    params
+        .get(name)
+        .map(|value| {
+            value.parse::<usize>().map_err(|_| {
+                CoreError::BadRequest(format!("{} must be a non-negative integer, got '{}'", name, value))
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_no_params_returns_everything() {
+        let (page, next) = paginate(vec![1, 2, 3], &HashMap::new()).unwrap();
+        assert_eq!(page, vec![1, 2, 3]);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_rejects_invalid_limit() {
+        assert!(paginate(vec![1], &query(&[("limit", "0")])).is_err());
+        assert!(paginate(vec![1], &query(&[("limit", "abc")])).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::Utc;
+use minifly_core::models::{
+    CreateReleaseRequest, MachineConfig, MachineState, Release, RollbackMachineResult, RollbackResponse,
+    DEFAULT_PROCESS_GROUP, PROCESS_GROUP_METADATA_KEY,
+};
+use std::collections::BTreeMap;
+use minifly_core::Error as CoreError;
+use tracing::{info, warn};
+use crate::docker::docker_error;
+use crate::state::AppState;
+use crate::error::Result;
+use crate::handlers::machines::parse_image_ref;
+
+/// Lists an app's releases, newest first
+pub async fn list_releases(
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+) -> Result<Json<Vec<Release>>> {
+    let releases = state.releases.read().unwrap();
+
+    let mut result = releases.get(&app_name).cloned().unwrap_or_default();
+    result.reverse();
+
+    Ok(Json(result))
+}
+
+/// Records a deploy as a release of the app.
+///
+/// The release stores the config each process group's machines run, and
+/// the machines are tagged with their group in the `fly_process_group`
+/// metadata key so rollbacks know which config to apply. A deploy that
+/// changed no config returns the latest release instead of recording one.
+pub async fn create_release(
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+    Json(req): Json<CreateReleaseRequest>,
+) -> Result<Json<Release>> {
+    let mut configs = BTreeMap::new();
+    {
+        let machines = state.machines.read().unwrap();
+        let mut metadata = state.machine_metadata.write().unwrap();
+        for (group, machine_ids) in &req.process_groups {
+            for machine_id in machine_ids {
+                let machine = machines.get(machine_id)
+                    .filter(|m| m.app_name == app_name)
+                    .ok_or_else(|| CoreError::MachineNotFound(machine_id.clone()))?;
+                configs.entry(group.clone()).or_insert_with(|| machine.config.clone());
+                metadata.entry(machine_id.clone()).or_default()
+                    .insert(PROCESS_GROUP_METADATA_KEY.to_string(), group.clone());
+            }
+        }
+    }
+    if configs.is_empty() {
+        return Err(CoreError::BadRequest("a release needs at least one deployed machine".to_string()).into());
+    }
+
+    let mut releases = state.releases.write().unwrap();
+    let app_releases = releases.entry(app_name.clone()).or_default();
+    let release = match push_release(app_releases, configs, req.description, false) {
+        Some(release) => {
+            info!(app_name = %app_name, version = release.version, image = %release.image, "Recorded release");
+            release
+        }
+        None => app_releases.last().cloned().expect("unchanged releases have a latest release"),
+    };
+
+    Ok(Json(release))
+}
+
+/// Rolls an app back to the configs of a previous release.
+///
+/// Each of the app's machines gets the release's config for its process
+/// group. Running machines have their containers recreated so the old image
+/// takes effect. Machines that fail to roll back keep their config and are
+/// reported with the error; the rollback is recorded as a new release only
+/// if every machine was rolled back.
+pub async fn rollback_release(
+    State(state): State<AppState>,
+    Path((app_name, version)): Path<(String, u32)>,
+) -> Result<Json<RollbackResponse>> {
+    let configs = {
+        let releases = state.releases.read().unwrap();
+        release_configs(releases.get(&app_name).map(Vec::as_slice).unwrap_or_default(), version)?
+    };
+
+    info!(app_name = %app_name, version = version, "Rolling back app");
+
+    let targets = rollback_targets(&state, &app_name, &configs);
+
+    let mut results = Vec::new();
+    for (machine_id, group, region, running) in targets {
+        let config = &configs[&group];
+        let rolled_back = if running {
+            recreate_container(&state, &app_name, &machine_id, &region, config).await
+        } else {
+            Ok(())
+        };
+
+        let error = match rolled_back {
+            Ok(()) => {
+                if let Some(machine) = state.machines.write().unwrap().get_mut(&machine_id) {
+                    machine.image_ref = parse_image_ref(&config.image);
+                    machine.config = config.clone();
+                    machine.updated_at = Utc::now();
+                }
+                None
+            }
+            Err(e) => {
+                warn!(machine_id = %machine_id, "Failed to roll back machine: {}", e.0);
+                Some(e.0.to_string())
+            }
+        };
+        results.push(RollbackMachineResult { machine_id, process_group: group, error });
+    }
+
+    let release = if results.iter().all(|result| result.error.is_none()) {
+        let mut releases = state.releases.write().unwrap();
+        let app_releases = releases.entry(app_name).or_default();
+        Some(push_release(app_releases, configs, format!("Rollback to v{}", version), true)
+            .expect("forced releases are always recorded"))
+    } else {
+        None
+    };
+
+    Ok(Json(RollbackResponse { release, machines: results }))
+}
+
+/// The machines a rollback applies to, with their process group, region and
+/// whether they are running.
+///
+/// Machines without a group metadata key belong to the default group.
+/// One-shot machines and machines of groups the release doesn't have are
+/// left alone.
+fn rollback_targets(
+    state: &AppState,
+    app_name: &str,
+    configs: &BTreeMap<String, MachineConfig>,
+) -> Vec<(String, String, String, bool)> {
+    let machines = state.machines.read().unwrap();
+    let metadata = state.machine_metadata.read().unwrap();
+    let mut targets: Vec<_> = machines.values()
+        .filter(|m| m.app_name == app_name)
+        .filter(|m| m.state != MachineState::Destroyed)
+        .filter(|m| m.config.auto_destroy != Some(true))
+        .filter_map(|m| {
+            let group = metadata.get(&m.id)
+                .and_then(|values| values.get(PROCESS_GROUP_METADATA_KEY))
+                .map(String::as_str)
+                .unwrap_or(DEFAULT_PROCESS_GROUP);
+            configs.contains_key(group)
+                .then(|| (m.id.clone(), group.to_string(), m.region.clone(), m.state == MachineState::Started))
+        })
+        .collect();
+    targets.sort();
+    targets
+}
+
+/// Appends a release, skipping configs identical to the latest one unless `force` is set
+fn push_release(
+    releases: &mut Vec<Release>,
+    configs: BTreeMap<String, MachineConfig>,
+    description: String,
+    force: bool,
+) -> Option<Release> {
+    if let Some(latest) = releases.last() {
+        let unchanged = serde_json::to_value(&latest.configs).ok() == serde_json::to_value(&configs).ok();
+        if unchanged && !force {
+            return None;
+        }
+    }
+
+    let image = configs.get(DEFAULT_PROCESS_GROUP)
+        .or_else(|| configs.values().next())
+        .map(|config| config.image.clone())
+        .unwrap_or_default();
+    let release = Release {
+        version: releases.last().map(|r| r.version + 1).unwrap_or(1),
+        image,
+        configs,
+        description,
+        created_at: Utc::now(),
+    };
+    releases.push(release.clone());
+
+    Some(release)
+}
+
+fn release_configs(releases: &[Release], version: u32) -> std::result::Result<BTreeMap<String, MachineConfig>, CoreError> {
+    releases
+        .iter()
+        .find(|r| r.version == version)
+        .map(|r| r.configs.clone())
+        .ok_or(CoreError::NotFound)
+}
+
+pub(crate) async fn recreate_container(
+    state: &AppState,
+    app_name: &str,
+    machine_id: &str,
+    region: &str,
+    config: &MachineConfig,
+) -> Result<()> {
+    let container_name = format!("minifly-{}-{}", app_name, machine_id);
+
+    let kill_timeout = crate::handlers::machines::kill_timeout(state, machine_id).ok().flatten();
+    if let Err(e) = state.docker.stop_container(&container_name, kill_timeout).await {
+        warn!(machine_id = %machine_id, "Failed to stop container to recreate it: {}", e);
+    }
+    if let Err(e) = state.docker.remove_container(&container_name).await {
+        warn!(machine_id = %machine_id, "Failed to remove container to recreate it: {}", e);
+    }
+
+    let database = state.litefs.database_mount(machine_id);
+    let container_id = state.docker.create_container(machine_id, app_name, region, config, database.as_ref()).await
+        .map_err(|e| docker_error("Failed to create container", e))?;
+    state.docker.start_container(&container_id).await
+        .map_err(|e| docker_error("Failed to start container", e))?;
+    crate::handlers::machines::record_mapped_ports(state, machine_id, &container_id).await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minifly_core::models::Machine;
+
+    fn machine(id: &str, image: &str) -> Machine {
+        Machine {
+            id: id.to_string(),
+            name: format!("web-{}", id),
+            app_name: "web".to_string(),
+            state: MachineState::Stopped,
+            region: "local".to_string(),
+            image_ref: parse_image_ref(image),
+            instance_id: "01TEST".to_string(),
+            private_ip: "fdaa:0:1:a7b:0::2".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            config: MachineConfig::for_image(image),
+            events: vec![],
+            cordoned: false,
+            checks: vec![],
+            mapped_ports: vec![],
+        }
+    }
+
+    fn set_image(state: &AppState, machine_id: &str, image: &str) {
+        state.machines.write().unwrap().get_mut(machine_id).unwrap().config.image = image.to_string();
+    }
+
+    async fn deploy(state: &AppState, groups: &[(&str, &str)]) -> Release {
+        let mut process_groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (group, machine_id) in groups {
+            process_groups.entry(group.to_string()).or_default().push(machine_id.to_string());
+        }
+        let request = CreateReleaseRequest { description: "Deploy".to_string(), process_groups };
+        let Ok(Json(release)) = create_release(State(state.clone()), Path("web".to_string()), Json(request)).await else {
+            panic!("create_release failed");
+        };
+        release
+    }
+
+    #[tokio::test]
+    async fn test_deploys_record_one_release_with_a_config_per_process_group() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let mut one_shot = machine("run1", "web:task");
+        one_shot.config.auto_destroy = Some(true);
+        for machine in [machine("m1", "web:v1"), machine("m2", "worker:v1"), one_shot] {
+            state.machines.write().unwrap().insert(machine.id.clone(), machine);
+        }
+
+        let v1 = deploy(&state, &[("app", "m1"), ("worker", "m2")]).await;
+        assert_eq!(v1.version, 1);
+        assert_eq!(v1.image, "web:v1");
+        assert_eq!(v1.configs["worker"].image, "worker:v1");
+
+        set_image(&state, "m1", "web:v2");
+        set_image(&state, "m2", "worker:v2");
+        assert_eq!(deploy(&state, &[("app", "m1"), ("worker", "m2")]).await.version, 2);
+        // Deploying the same configs again records nothing
+        assert_eq!(deploy(&state, &[("app", "m1"), ("worker", "m2")]).await.version, 2);
+
+        let Ok(Json(response)) = rollback_release(State(state.clone()), Path(("web".to_string(), 1))).await else {
+            panic!("rollback_release failed");
+        };
+        let rolled_back: Vec<_> = response.machines.iter()
+            .map(|result| (result.machine_id.as_str(), result.process_group.as_str(), result.error.is_none()))
+            .collect();
+        assert_eq!(rolled_back, vec![("m1", "app", true), ("m2", "worker", true)]);
+        assert_eq!(response.release.unwrap().version, 3);
+
+        let machines = state.machines.read().unwrap();
+        assert_eq!(machines["m1"].config.image, "web:v1");
+        assert_eq!(machines["m2"].config.image, "worker:v1");
+        assert_eq!(machines["run1"].config.image, "web:task");
+    }
+
+    #[test]
+    fn test_unknown_release_versions_are_not_found() {
+        let mut releases = Vec::new();
+        let configs = BTreeMap::from([("app".to_string(), MachineConfig::for_image("my-app:v1"))]);
+        push_release(&mut releases, configs.clone(), "deploy".to_string(), false).unwrap();
+        assert!(push_release(&mut releases, configs, "deploy".to_string(), false).is_none());
+
+        assert_eq!(release_configs(&releases, 1).unwrap()["app"].image, "my-app:v1");
+        assert!(release_configs(&releases, 9).is_err());
+    }
+}
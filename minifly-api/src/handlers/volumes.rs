@@ -1,56 +1,388 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::HeaderMap,
     Json,
 };
-use minifly_core::models::{Volume, VolumeState, CreateVolumeRequest};
+use minifly_core::models::{
+    Volume, VolumeState, CreateVolumeRequest, ExtendVolumeRequest, ExtendVolumeResponse, MountConfig,
+};
 use minifly_core::{SuccessResponse, Error as CoreError};
 use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path as FsPath;
+use tracing::info;
+use crate::docker::volume_host_path;
+use crate::handlers::pagination::{paginate, page_headers};
 use crate::state::AppState;
 use crate::error::Result;
 
 pub async fn list_volumes(
-    State(_state): State<AppState>,
-    Path(_app_name): Path<String>,
-) -> Result<Json<Vec<Volume>>> {
-    // TODO: Implement volume storage and filtering by app
-    Ok(Json(Vec::<Volume>::new()))
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<(HeaderMap, Json<Vec<Volume>>)> {
+    let volumes = state.volumes.read().unwrap();
+
+    let mut app_volumes: Vec<Volume> = volumes
+        .get(&app_name)
+        .map(|v| v.values().cloned().collect())
+        .unwrap_or_default();
+    app_volumes.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+
+    let (page, next_offset) = paginate(app_volumes, &params)?;
+
+    Ok((page_headers(next_offset), Json(page)))
 }
 
 pub async fn create_volume(
-    State(_state): State<AppState>,
-    Path(_app_name): Path<String>,
+    State(state): State<AppState>,
+    Path(app_name): Path<String>,
     Json(req): Json<CreateVolumeRequest>,
 ) -> Result<Json<Volume>> {
-    let volume = Volume {
+    if let Some(snapshot_id) = &req.snapshot_id {
+        return Err(CoreError::BadRequest(format!(
+            "Cannot restore snapshot {}: local volumes have no snapshots; fork with source_volume_id instead",
+            snapshot_id
+        )).into());
+    }
+
+    let mut volume = new_volume(req.name, req.region, req.encrypted.unwrap_or(true));
+    volume.set_size(req.size_gb.unwrap_or(1));
+
+    if let Some(source_id) = &req.source_volume_id {
+        let (source_app, source) = find_volume(&state, source_id)
+            .ok_or_else(|| CoreError::VolumeNotFound(source_id.clone()))?;
+
+        if source_app != app_name && !req.allow_cross_app_fork.unwrap_or(false) {
+            return Err(CoreError::BadRequest(format!(
+                "Volume {} belongs to app {}; set allow_cross_app_fork to fork across apps",
+                source_id, source_app
+            )).into());
+        }
+
+        if volume.size_gb < source.size_gb {
+            volume.set_size(source.size_gb);
+        }
+
+        let source_path = volume_host_path(&source_app, &source.id);
+        let dest_path = volume_host_path(&app_name, &volume.id);
+        tokio::task::spawn_blocking(move || copy_volume_data(&source_path, &dest_path))
+            .await
+            .map_err(|e| CoreError::Internal(format!("Failed to fork volume {}: {}", source_id, e)))?
+            .map_err(|e| CoreError::Internal(format!("Failed to fork volume {}: {}", source_id, e)))?;
+
+        info!(volume_id = %volume.id, source_volume_id = %source_id, "Forked volume");
+    }
+
+    // Volumes are bind mounts on the host, so only metadata is tracked here
+    state.volumes.write().unwrap()
+        .entry(app_name)
+        .or_default()
+        .insert(volume.id.clone(), volume.clone());
+
+    Ok(Json(volume))
+}
+
+pub async fn get_volume(
+    State(state): State<AppState>,
+    Path((app_name, volume_id)): Path<(String, String)>,
+) -> Result<Json<Volume>> {
+    let volumes = state.volumes.read().unwrap();
+
+    match volumes.get(&app_name).and_then(|v| v.get(&volume_id)) {
+        Some(volume) => Ok(Json(volume.clone())),
+        None => Err(CoreError::VolumeNotFound(volume_id).into()),
+    }
+}
+
+/// Extends a volume to a larger size.
+///
+/// Local volumes are bind mounts with no block device behind them, so this
+/// only updates the stored size and block counts.
+pub async fn extend_volume(
+    State(state): State<AppState>,
+    Path((app_name, volume_id)): Path<(String, String)>,
+    Json(req): Json<ExtendVolumeRequest>,
+) -> Result<Json<ExtendVolumeResponse>> {
+    let mut volumes = state.volumes.write().unwrap();
+
+    let volume = volumes
+        .get_mut(&app_name)
+        .and_then(|v| v.get_mut(&volume_id))
+        .ok_or_else(|| CoreError::VolumeNotFound(volume_id.clone()))?;
+
+    extend_volume_size(volume, req.size_gb)?;
+
+    info!(volume_id = %volume_id, size_gb = req.size_gb, "Extended volume");
+
+    Ok(Json(ExtendVolumeResponse {
+        volume: volume.clone(),
+        needs_restart: false,
+    }))
+}
+
+pub async fn delete_volume(
+    State(state): State<AppState>,
+    Path((app_name, volume_id)): Path<(String, String)>,
+) -> Result<Json<SuccessResponse>> {
+    let mut volumes = state.volumes.write().unwrap();
+
+    match volumes.get_mut(&app_name).and_then(|v| v.remove(&volume_id)) {
+        Some(_) => Ok(Json(SuccessResponse { ok: true })),
+        None => Err(CoreError::VolumeNotFound(volume_id).into()),
+    }
+}
+
+/// A new 1GB volume record
+fn new_volume(name: String, region: String, encrypted: bool) -> Volume {
+    let mut volume = Volume {
         id: format!("vol_{}", uuid::Uuid::new_v4().simple()),
-        name: req.name,
+        name,
         state: VolumeState::Created,
-        size_gb: req.size_gb.unwrap_or(1),
-        region: req.region,
+        size_gb: 0,
+        block_size: 0,
+        blocks: 0,
+        blocks_free: 0,
+        blocks_avail: 0,
+        region,
         zone: "a".to_string(),
-        encrypted: req.encrypted.unwrap_or(true),
+        encrypted,
         attached_machine_id: None,
         attached_alloc_id: None,
         created_at: Utc::now(),
     };
-    
-    // TODO: Implement actual volume creation with Docker volumes
-    
-    Ok(Json(volume))
+    volume.set_size(1);
+    volume
 }
 
-pub async fn get_volume(
-    State(_state): State<AppState>,
-    Path((_app_name, _volume_id)): Path<(String, String)>,
-) -> Result<Json<Volume>> {
-    // TODO: Implement volume storage
-    Err(CoreError::NotFound.into())
+/// Points each of a new machine's mounts at one of the app's registered
+/// volumes, so the machine uses the same host directory the volumes API
+/// reports for it.
+///
+/// A mount may name its volume by ID or by name. Names are replaced by the
+/// volume's ID, which the host directory is keyed by, and a name no volume
+/// has yet is registered as a new 1GB volume.
+pub(crate) fn resolve_mounts(state: &AppState, app_name: &str, region: &str, mounts: &mut [MountConfig]) {
+    let mut volumes = state.volumes.write().unwrap();
+    let app_volumes = volumes.entry(app_name.to_string()).or_default();
+
+    for mount in mounts {
+        let existing = app_volumes.get(&mount.volume).or_else(|| {
+            app_volumes.values()
+                .filter(|volume| volume.name == mount.volume)
+                .min_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)))
+        });
+        match existing {
+            Some(volume) => mount.volume = volume.id.clone(),
+            None => {
+                let volume = new_volume(mount.volume.clone(), region.to_string(), true);
+                info!(volume_id = %volume.id, volume = %volume.name, app_name = %app_name, "Registered volume for mount");
+                mount.volume = volume.id.clone();
+                app_volumes.insert(volume.id.clone(), volume);
+            }
+        }
+    }
 }
 
-pub async fn delete_volume(
-    State(_state): State<AppState>,
-    Path((_app_name, _volume_id)): Path<(String, String)>,
-) -> Result<Json<SuccessResponse>> {
-    // TODO: Implement volume deletion
-    Ok(Json(SuccessResponse { ok: true }))
-}
\ No newline at end of file
+/// Marks the app's volumes that `mounts` use as attached to `machine_id`,
+/// unless another machine already has them
+pub(crate) fn attach_volumes(state: &AppState, app_name: &str, machine_id: &str, mounts: &[MountConfig]) {
+    let mut volumes = state.volumes.write().unwrap();
+    let Some(app_volumes) = volumes.get_mut(app_name) else { return };
+
+    for mount in mounts {
+        if let Some(volume) = app_volumes.get_mut(&mount.volume).filter(|volume| volume.attached_machine_id.is_none()) {
+            volume.attached_machine_id = Some(machine_id.to_string());
+        }
+    }
+}
+
+/// Detaches the volumes of a destroyed machine
+pub(crate) fn detach_volumes(state: &AppState, machine_id: &str) {
+    for volume in state.volumes.write().unwrap().values_mut().flat_map(|app_volumes| app_volumes.values_mut()) {
+        if volume.attached_machine_id.as_deref() == Some(machine_id) {
+            volume.attached_machine_id = None;
+        }
+    }
+}
+
+/// Looks up a volume by ID across all apps, returning its owning app.
+fn find_volume(state: &AppState, volume_id: &str) -> Option<(String, Volume)> {
+    let volumes = state.volumes.read().unwrap();
+
+    volumes.iter().find_map(|(app_name, app_volumes)| {
+        app_volumes
+            .get(volume_id)
+            .map(|volume| (app_name.clone(), volume.clone()))
+    })
+}
+
+/// Recursively copies a volume's host directory into another.
+///
+/// A missing source directory is treated as an empty volume. Copying a
+/// directory onto itself would truncate every file, so it is refused.
+/// Symlinks are recreated rather than followed, so a link to a host file
+/// outside the volume doesn't pull that file into the copy.
+fn copy_volume_data(source: &FsPath, dest: &FsPath) -> std::io::Result<()> {
+    if source == dest {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is both the source and the destination", source.display()),
+        ));
+    }
+    std::fs::create_dir_all(dest)?;
+
+    if !source.exists() {
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+        } else if file_type.is_dir() {
+            copy_volume_data(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates and applies a new size to a volume.
+///
+/// Volumes can only grow; requests for the same or a smaller size are rejected.
+fn extend_volume_size(volume: &mut Volume, size_gb: u32) -> std::result::Result<(), CoreError> {
+    if size_gb <= volume.size_gb {
+        return Err(CoreError::BadRequest(format!(
+            "Volume {} is {}GB; new size must be larger (got {}GB)",
+            volume.id, volume.size_gb, size_gb
+        )));
+    }
+
+    volume.set_size(size_gb);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_volume(size_gb: u32) -> Volume {
+        let mut volume = Volume {
+            id: "vol_test".to_string(),
+            name: "data".to_string(),
+            state: VolumeState::Created,
+            size_gb: 0,
+            block_size: 0,
+            blocks: 0,
+            blocks_free: 0,
+            blocks_avail: 0,
+            region: "local".to_string(),
+            zone: "a".to_string(),
+            encrypted: true,
+            attached_machine_id: None,
+            attached_alloc_id: None,
+            created_at: Utc::now(),
+        };
+        volume.set_size(size_gb);
+        volume
+    }
+
+    #[test]
+    fn test_extend_volume_rejects_shrinking() {
+        let mut volume = test_volume(3);
+
+        assert!(matches!(extend_volume_size(&mut volume, 1), Err(CoreError::BadRequest(_))));
+        assert!(matches!(extend_volume_size(&mut volume, 3), Err(CoreError::BadRequest(_))));
+        assert_eq!(volume.size_gb, 3);
+    }
+
+    #[test]
+    fn test_extend_volume_grows_blocks() {
+        let mut volume = test_volume(1);
+
+        extend_volume_size(&mut volume, 5).unwrap();
+
+        assert_eq!(volume.size_gb, 5);
+        assert_eq!(volume.blocks, Volume::blocks_for_size(5));
+        assert_eq!(volume.blocks_free, volume.blocks);
+    }
+
+    #[test]
+    fn test_copy_volume_data_forks_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let fork = dir.path().join("fork");
+
+        std::fs::create_dir_all(source.join("nested")).unwrap();
+        std::fs::write(source.join("app.db"), b"production-like data").unwrap();
+        std::fs::write(source.join("nested").join("notes.txt"), b"hello").unwrap();
+
+        copy_volume_data(&source, &fork).unwrap();
+
+        assert_eq!(std::fs::read(fork.join("app.db")).unwrap(), b"production-like data");
+        assert_eq!(std::fs::read(fork.join("nested").join("notes.txt")).unwrap(), b"hello");
+        assert!(copy_volume_data(&source, &source).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_volume_data_keeps_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let fork = dir.path().join("fork");
+        let outside = dir.path().join("host-secret");
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(&outside, b"host only").unwrap();
+        std::os::unix::fs::symlink(&outside, source.join("link")).unwrap();
+
+        copy_volume_data(&source, &fork).unwrap();
+
+        let link = fork.join("link");
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_link(&link).unwrap(), outside);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_fork_named_like_its_source_keeps_source_data() {
+        let data_dir = tempfile::tempdir().unwrap();
+        std::env::set_var("MINIFLY_DATA_DIR", data_dir.path());
+        let state = AppState::for_tests(data_dir.path()).await;
+        let create = |body: serde_json::Value| create_volume(
+            State(state.clone()),
+            Path("web".to_string()),
+            Json(serde_json::from_value(body).unwrap()),
+        );
+
+        let Ok(Json(source)) = create(serde_json::json!({ "name": "data", "region": "local" })).await else {
+            panic!("volume creation failed");
+        };
+        let source_path = volume_host_path("web", &source.id);
+        std::fs::create_dir_all(&source_path).unwrap();
+        std::fs::write(source_path.join("app.db"), b"production-like data").unwrap();
+
+        let Ok(Json(fork)) = create(serde_json::json!({
+            "name": "data",
+            "region": "local",
+            "source_volume_id": source.id,
+        })).await else {
+            panic!("fork failed");
+        };
+
+        assert_ne!(volume_host_path("web", &fork.id), source_path);
+        assert_eq!(std::fs::read(source_path.join("app.db")).unwrap(), b"production-like data");
+        assert_eq!(std::fs::read(volume_host_path("web", &fork.id).join("app.db")).unwrap(), b"production-like data");
+
+        let snapshot = create(serde_json::json!({ "name": "data", "region": "local", "snapshot_id": "vs_1" })).await;
+        assert!(snapshot.is_err());
+        std::env::remove_var("MINIFLY_DATA_DIR");
+    }
+}
@@ -2,11 +2,21 @@
 
 use axum::{extract::State, response::Json, http::StatusCode};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tracing::{info, warn, error};
 use crate::state::AppState;
 use anyhow::Result;
+use minifly_network::InternalDnsResolver;
 use sqlx::Row;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+/// App name the DNS probe registers its throwaway machine under
+const DNS_PROBE_APP: &str = "minifly-health-probe";
+
+/// How long the DNS probe may take before the resolver is considered wedged
+const DNS_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// Overall health status
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,7 +77,19 @@ impl HealthChecker {
         services.insert("litefs".to_string(), self.check_litefs_health(state).await);
         
         // Check file system access
-        services.insert("filesystem".to_string(), self.check_filesystem_health().await);
+        services.insert("filesystem".to_string(), self.check_filesystem_health(state).await);
+        
+        // Check internal DNS resolution
+        services.insert("dns".to_string(), check_dns_health(probe_dns(&state.dns_resolver)).await);
+        
+        // Check the machines' own checks
+        services.insert("machine_checks".to_string(), check_machine_checks_health(state));
+        
+        for (name, service) in &services {
+            if let Some(ms) = service.response_time_ms {
+                state.metrics.observe_health_check(name, std::time::Duration::from_millis(ms));
+            }
+        }
         
         // Determine overall status
         let overall_status = self.determine_overall_status(&services);
@@ -151,9 +173,24 @@ impl HealthChecker {
     }
 
     /// Check LiteFS health
-    async fn check_litefs_health(&self, _state: &AppState) -> ServiceHealth {
+    async fn check_litefs_health(&self, state: &AppState) -> ServiceHealth {
         let start = std::time::Instant::now();
         
+        // A LiteFS process that died leaves its machine without a data layer
+        let crashed = state.litefs.crashed();
+        if !crashed.is_empty() {
+            let mut machine_ids: Vec<String> = crashed.into_iter().map(|exit| exit.machine_id).collect();
+            machine_ids.sort();
+            error!(machines = ?machine_ids, "LiteFS processes have exited");
+            return ServiceHealth {
+                status: HealthStatus::Unhealthy,
+                message: format!("LiteFS process exited for machines: {}", machine_ids.join(", ")),
+                last_checked: chrono::Utc::now().to_rfc3339(),
+                response_time_ms: Some(start.elapsed().as_millis() as u64),
+                details: HashMap::from([("crashed_machines".to_string(), serde_json::json!(machine_ids))]),
+            };
+        }
+        
         // Check if LiteFS mount points are accessible
         match self.test_litefs_mounts().await {
             Ok(mount_info) => {
@@ -178,15 +215,27 @@ impl HealthChecker {
         }
     }
 
-    /// Check filesystem health
-    async fn check_filesystem_health(&self) -> ServiceHealth {
+    /// Check filesystem health, including free space in the data directory
+    async fn check_filesystem_health(&self, state: &AppState) -> ServiceHealth {
         let start = std::time::Instant::now();
         
-        match self.test_filesystem_access().await {
+        match self.test_filesystem_access(state).await {
             Ok(fs_info) => {
+                let bytes_free = fs_info.get("bytes_free").and_then(serde_json::Value::as_u64);
+                let status = bytes_free
+                    .map(|free| disk_space_status(free, state.config.disk_low_space_bytes))
+                    .unwrap_or(HealthStatus::Healthy);
+                let message = match bytes_free {
+                    Some(free) if !matches!(status, HealthStatus::Healthy) => {
+                        warn!(bytes_free = free, "Data directory is low on disk space");
+                        format!("Data directory is low on disk space: {} bytes free", free)
+                    }
+                    _ => "Filesystem access normal".to_string(),
+                };
+                
                 ServiceHealth {
-                    status: HealthStatus::Healthy,
-                    message: "Filesystem access normal".to_string(),
+                    status,
+                    message,
                     last_checked: chrono::Utc::now().to_rfc3339(),
                     response_time_ms: Some(start.elapsed().as_millis() as u64),
                     details: fs_info,
@@ -258,20 +307,29 @@ impl HealthChecker {
         Ok(details)
     }
 
-    /// Test filesystem access
-    async fn test_filesystem_access(&self) -> Result<HashMap<String, serde_json::Value>> {
+    /// Test filesystem access and measure free space in the data directory
+    async fn test_filesystem_access(&self, state: &AppState) -> Result<HashMap<String, serde_json::Value>> {
         let mut details = HashMap::new();
         
         // Check current directory access
         let current_dir = std::env::current_dir()?;
         details.insert("current_dir".to_string(), 
                       serde_json::Value::String(current_dir.display().to_string()));
+        details.insert("current_dir_accessible".to_string(),
+                      serde_json::Value::Bool(std::fs::metadata(&current_dir).is_ok()));
         
-        // Check available disk space (simplified)
-        if let Ok(metadata) = std::fs::metadata(&current_dir) {
-            details.insert("current_dir_accessible".to_string(), serde_json::Value::Bool(true));
-        } else {
-            details.insert("current_dir_accessible".to_string(), serde_json::Value::Bool(false));
+        // Check available disk space where the database and the volumes
+        // live, reporting whichever disk has less free
+        let data_dir = std::path::PathBuf::from(&state.config.data_dir);
+        let volume_root = minifly_core::volumes::default_data_dir().join("minifly-data");
+        details.insert("data_dir".to_string(), serde_json::Value::String(data_dir.display().to_string()));
+        details.insert("volume_root".to_string(), serde_json::Value::String(volume_root.display().to_string()));
+        let lowest = [data_dir.as_path(), volume_root.as_path()].into_iter()
+            .filter_map(disk_space)
+            .min_by_key(|(bytes_free, _)| *bytes_free);
+        if let Some((bytes_free, bytes_total)) = lowest {
+            details.insert("bytes_free".to_string(), serde_json::Value::Number(bytes_free.into()));
+            details.insert("bytes_total".to_string(), serde_json::Value::Number(bytes_total.into()));
         }
         
         Ok(details)
@@ -322,6 +380,123 @@ impl HealthChecker {
     }
 }
 
+/// Maps free disk space to a health status: degraded below the low-space
+/// threshold, unhealthy below a tenth of it
+fn disk_space_status(bytes_free: u64, low_space_bytes: u64) -> HealthStatus {
+    if bytes_free < low_space_bytes / 10 {
+        HealthStatus::Unhealthy
+    } else if bytes_free < low_space_bytes {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    }
+}
+
+/// Fails when the measured free space is below the low-space threshold
+fn check_free_space(details: &HashMap<String, serde_json::Value>, low_space_bytes: u64) -> Result<()> {
+    match details.get("bytes_free").and_then(serde_json::Value::as_u64) {
+        Some(free) if free < low_space_bytes => {
+            anyhow::bail!("{} bytes free, below the low-space threshold of {} bytes", free, low_space_bytes)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Free and total bytes on the filesystem holding `path`, measured at its
+/// nearest existing ancestor since the data directory may not exist yet
+#[cfg(unix)]
+fn disk_space(path: &std::path::Path) -> Option<(u64, u64)> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let stats = nix::sys::statvfs::statvfs(existing).ok()?;
+    let fragment = stats.fragment_size() as u64;
+    Some((stats.blocks_available() as u64 * fragment, stats.blocks() as u64 * fragment))
+}
+
+#[cfg(not(unix))]
+fn disk_space(_path: &std::path::Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Reports DNS health from a resolver probe, treating a hung probe as failed.
+///
+/// [`probe_dns`] bounds its own lookup so it can still clean up after a
+/// timeout; the longer limit here only catches a cleanup that hangs too.
+async fn check_dns_health(probe: impl Future<Output = Result<()>>) -> ServiceHealth {
+    let start = std::time::Instant::now();
+    let limit = DNS_PROBE_TIMEOUT * 2;
+    let result = match tokio::time::timeout(limit, probe).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("resolver did not respond within {:?}", limit)),
+    };
+    
+    let (status, message) = match result {
+        Ok(()) => (HealthStatus::Healthy, "DNS resolver responding".to_string()),
+        Err(e) => {
+            warn!(error = %e, "DNS health check failed");
+            (HealthStatus::Degraded, format!("DNS resolution failed: {}", e))
+        }
+    };
+    
+    ServiceHealth {
+        status,
+        message,
+        last_checked: chrono::Utc::now().to_rfc3339(),
+        response_time_ms: Some(start.elapsed().as_millis() as u64),
+        details: HashMap::new(),
+    }
+}
+
+/// Registers a throwaway machine, checks it resolves, then removes it again
+async fn probe_dns(resolver: &InternalDnsResolver) -> Result<()> {
+    probe_dns_with(resolver, |hostname| async move { resolver.resolve(&hostname).await }).await
+}
+
+/// [`probe_dns`] with the lookup of the probe hostname done by `resolve`.
+///
+/// The probe machine is unregistered whether the lookup finishes or times
+/// out, so a wedged resolver doesn't collect a registration per poll.
+async fn probe_dns_with<F, Fut>(resolver: &InternalDnsResolver, resolve: F) -> Result<()>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<Vec<IpAddr>>>,
+{
+    let machine_id = format!("probe-{}", uuid::Uuid::new_v4().simple());
+    // TEST-NET-1 address, never routable
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    
+    let lookup = async {
+        resolver.register_machine(DNS_PROBE_APP, &machine_id, ip).await?;
+        resolve(format!("{}.vm.{}.internal", machine_id, DNS_PROBE_APP)).await
+    };
+    let resolved = tokio::time::timeout(DNS_PROBE_TIMEOUT, lookup).await;
+    resolver.unregister_machine(DNS_PROBE_APP, &machine_id).await?;
+    
+    let ips = resolved
+        .map_err(|_| anyhow::anyhow!("resolver did not respond within {:?}", DNS_PROBE_TIMEOUT))??;
+    if ips != [ip] {
+        anyhow::bail!("probe resolved to {:?}, expected {}", ips, ip);
+    }
+    Ok(())
+}
+
+/// Degraded while any started machine has a failing check
+fn check_machine_checks_health(state: &AppState) -> ServiceHealth {
+    let failing = crate::checks::failing_checks(state);
+    let (status, message) = if failing.is_empty() {
+        (HealthStatus::Healthy, "All machine checks passing".to_string())
+    } else {
+        warn!(checks = ?failing, "Machine checks are failing");
+        (HealthStatus::Degraded, format!("Failing machine checks: {}", failing.join(", ")))
+    };
+    ServiceHealth {
+        status,
+        message,
+        last_checked: chrono::Utc::now().to_rfc3339(),
+        response_time_ms: None,
+        details: HashMap::from([("failing_checks".to_string(), serde_json::json!(failing))]),
+    }
+}
+
 /// Docker information for health checks
 #[derive(Debug)]
 struct DockerInfo {
@@ -356,17 +531,129 @@ pub async fn liveness_handler() -> StatusCode {
     StatusCode::OK
 }
 
-/// Readiness probe endpoint
-pub async fn readiness_handler(State(state): State<AppState>) -> StatusCode {
+/// Readiness probe result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    /// Services that must pass, from `Config::readiness_services`
+    pub required: Vec<String>,
+    /// Error message of each required service that failed
+    pub failed: BTreeMap<String, String>,
+}
+
+/// Checks the services the config requires for readiness
+pub async fn check_readiness(state: &AppState) -> ReadinessResponse {
     let health_checker = HealthChecker::new();
+    let required = state.config.readiness_services.clone();
+    let mut failed = BTreeMap::new();
     
-    // Quick readiness check focusing on critical services
-    let db_ready = health_checker.test_database_connection(&state).await.is_ok();
-    let docker_ready = health_checker.test_docker_connection(&state).await.is_ok();
+    for service in &required {
+        let result = match service.as_str() {
+            "database" => health_checker.test_database_connection(state).await,
+            "docker" => health_checker.test_docker_connection(state).await.map(|_| ()),
+            "litefs" => health_checker.test_litefs_mounts().await.map(|_| ()),
+            "filesystem" => match health_checker.test_filesystem_access(state).await {
+                Ok(details) => check_free_space(&details, state.config.disk_low_space_bytes),
+                Err(e) => Err(e),
+            },
+            "dns" => probe_dns(&state.dns_resolver).await,
+            "machine_checks" => match crate::checks::failing_checks(state) {
+                failing if failing.is_empty() => Ok(()),
+                failing => Err(anyhow::anyhow!("failing checks: {}", failing.join(", "))),
+            },
+            other => Err(anyhow::anyhow!("unknown service '{}'", other)),
+        };
+        if let Err(e) = result {
+            failed.insert(service.clone(), e.to_string());
+        }
+    }
     
-    if db_ready && docker_ready {
-        StatusCode::OK
+    ReadinessResponse {
+        ready: failed.is_empty(),
+        required,
+        failed,
+    }
+}
+
+/// Readiness probe endpoint
+pub async fn readiness_handler(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let readiness = check_readiness(&state).await;
+    
+    if readiness.ready {
+        (StatusCode::OK, Json(readiness))
     } else {
-        StatusCode::SERVICE_UNAVAILABLE
+        warn!(failed = ?readiness.failed, "Readiness check failed");
+        (StatusCode::SERVICE_UNAVAILABLE, Json(readiness))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dns_check_reports_resolver_health() {
+        let resolver = InternalDnsResolver::new();
+        let health = check_dns_health(probe_dns(&resolver)).await;
+        assert!(matches!(health.status, HealthStatus::Healthy));
+        assert!(health.response_time_ms.is_some());
+        // The probe cleans up after itself
+        assert!(resolver.list_registrations().await.is_empty());
+
+        let health = check_dns_health(async { anyhow::bail!("resolver wedged") }).await;
+        assert!(matches!(health.status, HealthStatus::Degraded));
+        assert!(health.message.contains("resolver wedged"));
+    }
+
+    #[tokio::test]
+    async fn test_hung_dns_probe_removes_its_registration() {
+        let resolver = InternalDnsResolver::new();
+        let probe = probe_dns_with(&resolver, |_| std::future::pending());
+        let health = check_dns_health(probe).await;
+
+        assert!(matches!(health.status, HealthStatus::Degraded));
+        assert!(health.message.contains("did not respond"));
+        assert!(resolver.list_registrations().await.is_empty());
+    }
+
+    #[test]
+    fn test_disk_space_status_thresholds() {
+        let gib = 1024 * 1024 * 1024;
+        assert!(matches!(disk_space_status(50 * gib, gib), HealthStatus::Healthy));
+        assert!(matches!(disk_space_status(gib, gib), HealthStatus::Healthy));
+        assert!(matches!(disk_space_status(gib - 1, gib), HealthStatus::Degraded));
+        assert!(matches!(disk_space_status(gib / 10, gib), HealthStatus::Degraded));
+        assert!(matches!(disk_space_status(gib / 10 - 1, gib), HealthStatus::Unhealthy));
+        assert!(matches!(disk_space_status(0, gib), HealthStatus::Unhealthy));
+    }
+
+    #[tokio::test]
+    async fn test_readiness_with_optional_docker() {
+        // The test state's Docker socket is a plain file, so Docker never connects
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(data_dir.path()).await;
+
+        let readiness = check_readiness(&state).await;
+        assert!(!readiness.ready);
+        assert_eq!(readiness.failed.keys().collect::<Vec<_>>(), vec!["docker"]);
+
+        state.config.readiness_services = vec!["database".to_string(), "dns".to_string()];
+        let readiness = check_readiness(&state).await;
+        assert!(readiness.ready, "unexpected failures: {:?}", readiness.failed);
+        assert_eq!(readiness.required, vec!["database", "dns"]);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_fails_below_low_space_threshold() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(data_dir.path()).await;
+        state.config.readiness_services = vec!["filesystem".to_string()];
+
+        state.config.disk_low_space_bytes = 0;
+        assert!(check_readiness(&state).await.ready);
+
+        state.config.disk_low_space_bytes = u64::MAX;
+        let readiness = check_readiness(&state).await;
+        assert!(!readiness.ready);
+        assert!(readiness.failed["filesystem"].contains("below the low-space threshold"));
+    }
+}
@@ -0,0 +1,67 @@
+//! Removes expired machine leases
+//!
+//! Handlers already treat an expired lease as absent: a new lease can be
+//! taken over it and its nonce is rejected. The sweep drops expired leases
+//! that nobody touches again, so `state.leases` only holds live ones.
+
+use crate::state::AppState;
+use std::time::Duration;
+use tracing::debug;
+
+/// How often expired leases are removed
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Removes expired leases every [`SWEEP_INTERVAL`] for as long as the API runs
+pub fn spawn_sweeper(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweep(&state);
+        }
+    });
+}
+
+/// Removes expired leases, returning how many there were
+pub fn sweep(state: &AppState) -> usize {
+    let mut leases = state.leases.write().unwrap();
+    let before = leases.len();
+    leases.retain(|machine_id, lease| {
+        let expired = lease.is_expired();
+        if expired {
+            debug!(machine_id = %machine_id, "Removing expired lease");
+        }
+        !expired
+    });
+    before - leases.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use minifly_core::models::Lease;
+
+    #[tokio::test]
+    async fn test_sweep_removes_only_expired_leases() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let lease = |expires_at: i64| Lease {
+            nonce: "abc".to_string(),
+            expires_at,
+            owner: "minifly@local".to_string(),
+            description: String::new(),
+            version: "1".to_string(),
+        };
+        {
+            let mut leases = state.leases.write().unwrap();
+            leases.insert("expired".to_string(), lease(Utc::now().timestamp() - 1));
+            leases.insert("live".to_string(), lease(Utc::now().timestamp() + 60));
+        }
+
+        assert_eq!(sweep(&state), 1);
+        let leases = state.leases.read().unwrap();
+        assert!(leases.contains_key("live"));
+        assert!(!leases.contains_key("expired"));
+    }
+}
@@ -1,10 +1,18 @@
+pub mod autostop;
+pub mod checks;
 pub mod config;
 pub mod docker;
 pub mod error;
+pub mod events;
 pub mod handlers;
 pub mod health;
+pub mod leases;
+pub mod log_store;
+pub mod metrics;
 pub mod middleware;
+pub mod reconcile;
 pub mod state;
+pub mod supervisor;
 
 use axum::Router;
 use tower_http::trace::TraceLayer;
@@ -15,8 +23,13 @@ pub use config::Config;
 /// Create the main application router
 pub fn create_app(state: AppState) -> Router {
     Router::new()
+        .route("/metrics", axum::routing::get(metrics::metrics_handler))
         .nest("/v1", handlers::routes())
-        .layer(axum::middleware::from_fn(middleware::region::region_middleware))
+        .layer(axum::middleware::from_fn_with_state(
+            state.config.region.clone(),
+            middleware::region::region_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), metrics::track_requests))
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
\ No newline at end of file
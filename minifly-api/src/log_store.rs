@@ -0,0 +1,130 @@
+//! On-disk log storage so machine logs outlive their containers.
+//!
+//! Each machine gets `<data_dir>/logs/<app>/<machine>.log`. When the file
+//! would grow past the size limit it is rotated to `<machine>.log.1`, older
+//! rotations shift up, and anything past the last kept rotation is deleted.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of rotated files kept next to the active log
+const ROTATED_FILES: usize = 3;
+
+/// Rotating per-machine log files under the data directory
+#[derive(Debug, Clone)]
+pub struct LogStore {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl LogStore {
+    /// Creates a store under `<data_dir>/logs` rotating files at `max_bytes`
+    pub fn new(data_dir: impl AsRef<Path>, max_bytes: u64) -> Self {
+        Self {
+            dir: data_dir.as_ref().join("logs"),
+            max_bytes,
+        }
+    }
+
+    /// Path of a machine's active log file
+    pub fn path(&self, app_name: &str, machine_id: &str) -> PathBuf {
+        self.dir.join(app_name).join(format!("{}.log", machine_id))
+    }
+
+    fn rotated_path(&self, app_name: &str, machine_id: &str, index: usize) -> PathBuf {
+        self.dir.join(app_name).join(format!("{}.log.{}", machine_id, index))
+    }
+
+    /// Appends a line to a machine's log, rotating first if it would exceed the limit
+    pub fn append(&self, app_name: &str, machine_id: &str, line: &str) -> io::Result<()> {
+        let path = self.path(app_name, machine_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let current = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if current > 0 && current + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate(app_name, machine_id)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", line)
+    }
+
+    fn rotate(&self, app_name: &str, machine_id: &str) -> io::Result<()> {
+        let oldest = self.rotated_path(app_name, machine_id, ROTATED_FILES);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+
+        for index in (1..ROTATED_FILES).rev() {
+            let from = self.rotated_path(app_name, machine_id, index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(app_name, machine_id, index + 1))?;
+            }
+        }
+
+        fs::rename(self.path(app_name, machine_id), self.rotated_path(app_name, machine_id, 1))
+    }
+
+    /// All files holding a machine's logs, oldest first
+    fn files(&self, app_name: &str, machine_id: &str) -> Vec<PathBuf> {
+        (1..=ROTATED_FILES)
+            .rev()
+            .map(|index| self.rotated_path(app_name, machine_id, index))
+            .chain(std::iter::once(self.path(app_name, machine_id)))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    /// Reads every stored line for a machine, oldest first
+    pub fn read_lines(&self, app_name: &str, machine_id: &str) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        for path in self.files(app_name, machine_id) {
+            lines.extend(fs::read_to_string(path)?.lines().map(str::to_string));
+        }
+        Ok(lines)
+    }
+
+    /// Total size of a machine's stored logs across rotations
+    pub fn stored_bytes(&self, app_name: &str, machine_id: &str) -> u64 {
+        self.files(app_name, machine_id)
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_at_size_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LogStore::new(dir.path(), 20);
+
+        // Each line is 10 bytes with its newline, so two fit per file
+        for i in 0..5 {
+            store.append("web", "m1", &format!("line-{:04}", i)).unwrap();
+        }
+
+        let path = store.path("web", "m1");
+        assert_eq!(path, dir.path().join("logs/web/m1.log"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line-0004\n");
+        assert_eq!(fs::read_to_string(path.with_extension("log.1")).unwrap(), "line-0002\nline-0003\n");
+        assert_eq!(store.read_lines("web", "m1").unwrap().len(), 5);
+        assert_eq!(store.stored_bytes("web", "m1"), 50);
+
+        // Only the newest rotations are kept
+        for i in 5..12 {
+            store.append("web", "m1", &format!("line-{:04}", i)).unwrap();
+        }
+        let lines = store.read_lines("web", "m1").unwrap();
+        assert_eq!(lines.first().unwrap(), "line-0004");
+        assert_eq!(lines.last().unwrap(), "line-0011");
+        assert!(!path.with_extension("log.4").exists());
+    }
+}
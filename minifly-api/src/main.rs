@@ -5,13 +5,21 @@ use tower_http::trace::TraceLayer;
 use tracing::info;
 use minifly_logging::{LoggingConfig, LogFormat};
 
+mod autostop;
+mod checks;
 mod config;
 mod docker;
 mod error;
+mod events;
 mod handlers;
 mod health;
+mod leases;
+mod log_store;
+mod metrics;
 mod middleware;
+mod reconcile;
 mod state;
+mod supervisor;
 
 // use middleware::region; // Used via middleware::region in the layer
 
@@ -37,13 +45,30 @@ async fn main() -> Result<()> {
     
     // Initialize application state
     let state = AppState::new(config.clone()).await?;
+    state.spawn_litefs_monitor();
+    
+    // Adopt containers left running by an earlier API process
+    match reconcile::reconcile_containers(&state).await {
+        Ok(adopted) if !adopted.is_empty() => info!(machines = adopted.len(), "Reconciled existing containers"),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Skipping container reconciliation: {}", e),
+    }
+    supervisor::Supervisor::default().spawn(state.clone());
+    checks::Checker::default().spawn(state.clone());
+    autostop::IdleMonitor::default().spawn(state.clone());
+    leases::spawn_sweeper(state.clone());
     
     // Build our application with routes
     let app = Router::new()
+        .route("/metrics", axum::routing::get(metrics::metrics_handler))
         .nest("/v1", handlers::routes())
-        .layer(axum::middleware::from_fn(middleware::region::region_middleware))
+        .layer(axum::middleware::from_fn_with_state(
+            state.config.region.clone(),
+            middleware::region::region_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), metrics::track_requests))
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state.clone());
 
     // Bind to address
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
@@ -54,7 +79,12 @@ async fn main() -> Result<()> {
     );
     
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { state.shutdown_signal().await })
+        .await?;
+    
+    info!("API server stopped");
+    minifly_logging::shutdown_logging();
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,227 @@
+//! Prometheus metrics for the API server, served at `GET /metrics`.
+//!
+//! Request counts and health-check durations are recorded as they happen;
+//! machine, app and DNS gauges are refreshed from [`AppState`] on each scrape.
+
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use minifly_core::models::MachineState;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::time::Duration;
+
+/// Machine states reported even when no machine is in them
+const MACHINE_STATES: [MachineState; 9] = [
+    MachineState::Created,
+    MachineState::Starting,
+    MachineState::Started,
+    MachineState::Stopping,
+    MachineState::Stopped,
+    MachineState::Destroying,
+    MachineState::Destroyed,
+    MachineState::Suspending,
+    MachineState::Suspended,
+];
+
+/// Services checked by the health checker
+const HEALTH_CHECKS: [&str; 5] = ["database", "docker", "litefs", "filesystem", "dns"];
+
+/// Registry holding every metric the API server exposes
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    health_check_duration: HistogramVec,
+    machines: IntGaugeVec,
+    apps: IntGauge,
+    dns_registrations: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("minifly_http_requests_total", "API requests handled, by method and status"),
+            &["method", "status"],
+        )
+        .expect("valid metric");
+        let health_check_duration = HistogramVec::new(
+            HistogramOpts::new("minifly_health_check_duration_seconds", "Duration of each health check"),
+            &["check"],
+        )
+        .expect("valid metric");
+        let machines = IntGaugeVec::new(
+            Opts::new("minifly_machines", "Machines tracked by the API, by state"),
+            &["state"],
+        )
+        .expect("valid metric");
+        let apps = IntGauge::new("minifly_apps", "Apps tracked by the API").expect("valid metric");
+        let dns_registrations = IntGauge::new(
+            "minifly_dns_registrations",
+            "Machine addresses registered with the internal DNS resolver",
+        )
+        .expect("valid metric");
+
+        registry.register(Box::new(http_requests.clone())).expect("unique metric");
+        registry.register(Box::new(health_check_duration.clone())).expect("unique metric");
+        registry.register(Box::new(machines.clone())).expect("unique metric");
+        registry.register(Box::new(apps.clone())).expect("unique metric");
+        registry.register(Box::new(dns_registrations.clone())).expect("unique metric");
+
+        for check in HEALTH_CHECKS {
+            health_check_duration.with_label_values(&[check]);
+        }
+
+        Self {
+            registry,
+            http_requests,
+            health_check_duration,
+            machines,
+            apps,
+            dns_registrations,
+        }
+    }
+
+    /// Counts a handled API request
+    pub fn record_request(&self, method: &str, status: StatusCode) {
+        self.http_requests
+            .with_label_values(&[method, status.as_str()])
+            .inc();
+    }
+
+    /// Records how long a health check took
+    pub fn observe_health_check(&self, check: &str, duration: Duration) {
+        self.health_check_duration
+            .with_label_values(&[check])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Refreshes the gauges from the current state
+    async fn refresh(&self, state: &AppState) {
+        let machine_states: Vec<MachineState> = state
+            .machines
+            .read()
+            .unwrap()
+            .values()
+            .map(|machine| machine.state.clone())
+            .collect();
+        let apps = state.apps.read().unwrap().len();
+        let dns_registrations: usize = state
+            .dns_resolver
+            .list_registrations()
+            .await
+            .values()
+            .map(Vec::len)
+            .sum();
+
+        self.set_gauges(&machine_states, apps, dns_registrations);
+    }
+
+    fn set_gauges(&self, machine_states: &[MachineState], apps: usize, dns_registrations: usize) {
+        for machine_state in &MACHINE_STATES {
+            let count = machine_states.iter().filter(|s| *s == machine_state).count();
+            self.machines
+                .with_label_values(&[machine_state.as_str()])
+                .set(count as i64);
+        }
+        self.apps.set(apps as i64);
+        self.dns_registrations.set(dns_registrations as i64);
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding cannot fail");
+        String::from_utf8(buffer).expect("exposition format is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serves the current metrics in the Prometheus text format
+pub async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    state.metrics.refresh(&state).await;
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        state.metrics.encode(),
+    )
+}
+
+/// Middleware counting every request by method and response status
+pub async fn track_requests(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let response = next.run(request).await;
+    state.metrics.record_request(method.as_str(), response.status());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn request(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_exposition_format() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new()
+            .route("/metrics", get(metrics_handler))
+            .nest("/v1", crate::handlers::routes())
+            .layer(axum::middleware::from_fn_with_state(state.clone(), track_requests))
+            .with_state(state);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        request(addr, "/v1/apps/missing").await;
+        let response = request(addr, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("content-type: text/plain; version=0.0.4"));
+
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        for (name, kind) in [
+            ("minifly_machines", "gauge"),
+            ("minifly_apps", "gauge"),
+            ("minifly_dns_registrations", "gauge"),
+            ("minifly_http_requests_total", "counter"),
+            ("minifly_health_check_duration_seconds", "histogram"),
+        ] {
+            assert!(body.contains(&format!("# TYPE {} {}", name, kind)), "missing {}", name);
+        }
+        assert!(body.contains("minifly_machines{state=\"started\"} 0"));
+        assert!(body.contains("minifly_apps 0"));
+        assert!(body.contains("minifly_http_requests_total{method=\"GET\",status=\"404\"} 1"));
+
+        // Every sample line is `name{labels} value`
+        for line in body.lines().filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (_, value) = line.rsplit_once(' ').unwrap();
+            assert!(value.parse::<f64>().is_ok(), "bad sample: {}", line);
+        }
+    }
+}
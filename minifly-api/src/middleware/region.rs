@@ -1,11 +1,13 @@
 /// Region context middleware for adding region information to responses and logs
 /// 
 /// This middleware:
+/// - Takes the request's region from `Fly-Prefer-Region` or `Fly-Region`,
+///   like Fly's proxy, falling back to the configured local region
 /// - Adds region information to all API responses via headers
 /// - Injects region context into the logging framework
 /// - Tracks requests with correlation IDs for better debugging
 use axum::{
-    extract::Request,
+    extract::{Request, State},
     http::{HeaderMap, HeaderValue},
     middleware::Next,
     response::Response,
@@ -17,24 +19,59 @@ use minifly_logging::fields;
 /// Header name for region information
 pub const REGION_HEADER: &str = "x-minifly-region";
 
+/// Request headers naming the region a request is for, in order of preference
+const INCOMING_REGION_HEADERS: [&str; 2] = ["fly-prefer-region", "fly-region"];
+
+/// Longest region name accepted from a request header
+const MAX_REGION_LEN: usize = 32;
+
 /// Header name for correlation ID
 pub const CORRELATION_ID_HEADER: &str = "x-minifly-correlation-id";
 
+/// Incoming headers a correlation ID is taken from, in order of preference.
+/// Responses echo it as both `x-minifly-correlation-id` and `x-correlation-id`.
+const INCOMING_CORRELATION_HEADERS: [&str; 3] = [CORRELATION_ID_HEADER, "x-correlation-id", "fly-request-id"];
+
+/// Longest client-supplied correlation ID that is accepted
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// Correlation ID of the current request, available to handlers as
+/// `Extension<CorrelationId>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelationId(pub String);
+
 /// Default region for local development
 pub const DEFAULT_REGION: &str = "local";
 
+/// Region the current request is for, available to handlers as
+/// `Extension<RequestRegion>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestRegion(pub String);
+
+/// Region of the machine a response is about
+///
+/// Handlers put this in the response extensions so the middleware reports
+/// the machine's region instead of [`DEFAULT_REGION`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineRegion(pub String);
+
 /// Middleware to add region context to requests and responses
 /// 
 /// This function:
-/// 1. Generates a unique correlation ID for each request
-/// 2. Adds region information to response headers
-/// 3. Injects structured logging with region and correlation context
-/// 4. Tracks request duration and outcomes
+/// 1. Reuses the caller's correlation ID or generates one, and stores it in
+///    the request extensions
+/// 2. Resolves the request's region from its region headers or
+///    `local_region`, and stores it in the request extensions as
+///    [`RequestRegion`]
+/// 3. Adds region information to response headers, using the machine's
+///    region when the handler reports one via [`MachineRegion`]
+/// 4. Injects structured logging with region and correlation context
+/// 5. Tracks request duration and outcomes
 #[instrument(
     name = "region_middleware",
     skip_all,
     fields(
-        region = %DEFAULT_REGION,
+        region = tracing::field::Empty,
         correlation_id = tracing::field::Empty,
         request_id = tracing::field::Empty,
         http.method = %request.method(),
@@ -44,10 +81,17 @@ pub const DEFAULT_REGION: &str = "local";
         duration_ms = tracing::field::Empty,
     )
 )]
-pub async fn region_middleware(request: Request, next: Next) -> Response {
-    let correlation_id = minifly_logging::new_correlation_id();
+pub async fn region_middleware(
+    State(local_region): State<String>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let correlation_id = incoming_correlation_id(request.headers())
+        .unwrap_or_else(minifly_logging::new_correlation_id);
+    request.extensions_mut().insert(CorrelationId(correlation_id.clone()));
     let request_id = minifly_logging::new_request_id();
-    let region = DEFAULT_REGION.to_string();
+    let region = incoming_region(request.headers()).unwrap_or(local_region);
+    request.extensions_mut().insert(RequestRegion(region.clone()));
     
     // Record structured fields in span
     Span::current().record(fields::CORRELATION_ID, &correlation_id);
@@ -73,7 +117,13 @@ pub async fn region_middleware(request: Request, next: Next) -> Response {
     
     let duration = start_time.elapsed();
     
+    let region = response
+        .extensions()
+        .get::<MachineRegion>()
+        .map_or(region, |machine_region| machine_region.0.clone());
+    
     // Record final span fields
+    Span::current().record(fields::REGION, &region);
     Span::current().record(fields::HTTP_STATUS, response.status().as_u16());
     Span::current().record(fields::DURATION_MS, duration.as_millis());
     
@@ -90,6 +140,34 @@ pub async fn region_middleware(request: Request, next: Next) -> Response {
     response
 }
 
+/// Returns the caller-supplied correlation ID, if any header carries a usable one
+fn incoming_correlation_id(headers: &HeaderMap) -> Option<String> {
+    INCOMING_CORRELATION_HEADERS
+        .iter()
+        .filter_map(|name| headers.get(*name)?.to_str().ok())
+        .map(str::trim)
+        .find(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_CORRELATION_ID_LEN
+                && id.chars().all(|c| c.is_ascii_graphic())
+        })
+        .map(str::to_string)
+}
+
+/// Returns the region a region header asks for, if any carries a usable one
+fn incoming_region(headers: &HeaderMap) -> Option<String> {
+    INCOMING_REGION_HEADERS
+        .iter()
+        .filter_map(|name| headers.get(*name)?.to_str().ok())
+        .map(str::trim)
+        .find(|region| {
+            !region.is_empty()
+                && region.len() <= MAX_REGION_LEN
+                && region.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        .map(str::to_ascii_lowercase)
+}
+
 /// Add region and correlation headers to the response
 /// 
 /// # Arguments
@@ -102,19 +180,21 @@ fn add_region_headers(headers: &mut HeaderMap, region: &str, correlation_id: &st
     }
     
     if let Ok(correlation_value) = HeaderValue::from_str(correlation_id) {
-        headers.insert(CORRELATION_ID_HEADER, correlation_value);
+        headers.insert(CORRELATION_ID_HEADER, correlation_value.clone());
+        headers.insert("x-correlation-id", correlation_value);
     }
 }
 
 /// Extract region from machine information for logging context
 /// 
 /// # Arguments
-/// * `machine_region` - Optional machine region, defaults to local
+/// * `machine_region` - Optional machine region
+/// * `request_region` - Region of the request, used when the machine has none
 /// 
 /// # Returns
 /// * Region string for logging and response headers
-pub fn get_machine_region(machine_region: Option<&str>) -> String {
-    machine_region.unwrap_or(DEFAULT_REGION).to_string()
+pub fn get_machine_region(machine_region: Option<&str>, request_region: &str) -> String {
+    machine_region.unwrap_or(request_region).to_string()
 }
 
 /// Log machine operation with region context
@@ -148,4 +228,80 @@ macro_rules! api_log {
             $($arg)*
         )
     };
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|Extension(id): Extension<CorrelationId>| async move { id.0 }))
+            .route("/region", get(|Extension(region): Extension<RequestRegion>| async move { region.0 }))
+            .layer(axum::middleware::from_fn_with_state(DEFAULT_REGION.to_string(), region_middleware))
+    }
+
+    async fn send(header: Option<(&str, &str)>) -> (Response, String) {
+        let mut request = Request::builder().uri("/");
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let id = response.headers()["x-correlation-id"].to_str().unwrap().to_string();
+        (response, id)
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_is_echoed() {
+        for header in ["x-correlation-id", "fly-request-id", CORRELATION_ID_HEADER] {
+            let (response, id) = send(Some((header, "deploy-42"))).await;
+            assert_eq!(id, "deploy-42");
+            assert_eq!(response.headers()[CORRELATION_ID_HEADER], "deploy-42");
+
+            // Handlers see the same ID through the request extensions
+            let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+            assert_eq!(&body[..], b"deploy-42");
+        }
+
+        // Missing or unusable IDs are replaced with a generated one
+        let (_, generated) = send(None).await;
+        assert!(Uuid::parse_str(&generated).is_ok());
+        let (_, replaced) = send(Some(("x-correlation-id", &"x".repeat(200)))).await;
+        assert!(Uuid::parse_str(&replaced).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_region_header_follows_machine_region() {
+        let app = Router::new()
+            .route("/", get(|| async { "default" }))
+            .route("/machine", get(|| async { (Extension(MachineRegion("ord".to_string())), "machine") }))
+            .layer(axum::middleware::from_fn_with_state(DEFAULT_REGION.to_string(), region_middleware));
+
+        let response = app.clone().oneshot(Request::builder().uri("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[REGION_HEADER], DEFAULT_REGION);
+
+        let response = app.oneshot(Request::builder().uri("/machine").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[REGION_HEADER], "ord");
+    }
+
+    #[tokio::test]
+    async fn test_region_headers_set_request_region() {
+        for (headers, expected) in [
+            (vec![], DEFAULT_REGION),
+            (vec![("fly-region", "AMS")], "ams"),
+            (vec![("fly-prefer-region", "iad"), ("fly-region", "ams")], "iad"),
+            (vec![("fly-prefer-region", "not a region"), ("fly-region", "ams")], "ams"),
+        ] {
+            let mut request = Request::builder().uri("/region");
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+            let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.headers()[REGION_HEADER], expected);
+
+            let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+            assert_eq!(&body[..], expected.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Adopts containers left behind by an earlier API process
+//!
+//! Machines only live in memory, so restarting `minifly-api` forgets them
+//! while their containers keep running. On startup every container labeled
+//! `minifly.managed=true` whose machine isn't known is turned back into a
+//! [`Machine`] from its labels and inspected config, and running ones are
+//! registered with internal DNS again.
+
+use crate::docker::{container_env, container_secrets, CONFIG_LABEL};
+use crate::state::AppState;
+use anyhow::Result;
+use bollard::models::ContainerInspectResponse;
+use chrono::{DateTime, Utc};
+use minifly_core::models::{App, AppStatus, Machine, MachineConfig, MachineEvent, MachineState};
+use minifly_network::extract_container_ip;
+use std::collections::HashMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Rebuilds machines for minifly containers the API doesn't know about,
+/// returning the IDs of the machines adopted
+pub async fn reconcile_containers(state: &AppState) -> Result<Vec<String>> {
+    let mut filters = HashMap::new();
+    filters.insert("label".to_string(), vec!["minifly.managed=true".to_string()]);
+    let containers = state.docker.list_containers(Some(filters)).await?;
+
+    let mut adopted = Vec::new();
+    for container in containers {
+        let Some(container_id) = container.id else { continue };
+        let labels = container.labels.unwrap_or_default();
+        let (Some(machine_id), Some(app_name)) = (labels.get("minifly.machine_id"), labels.get("minifly.app_name")) else {
+            warn!(container_id = %container_id, "Skipping minifly container without machine labels");
+            continue;
+        };
+        if state.machines.read().unwrap().contains_key(machine_id) {
+            continue;
+        }
+
+        let info = match state.docker.inspect_container(&container_id).await {
+            Ok(info) => info,
+            Err(e) => {
+                warn!(machine_id = %machine_id, "Failed to inspect container {}: {}", container_id, e);
+                continue;
+            }
+        };
+
+        let machine = machine_from_container(state, machine_id, app_name, &labels, &info);
+        adopt_app(state, app_name);
+
+        if machine.state == MachineState::Started {
+            let ip = info.network_settings
+                .and_then(|settings| settings.networks)
+                .and_then(|networks| extract_container_ip(&serde_json::to_value(networks).ok()?));
+            if let Some(ip) = ip {
+                if let Err(e) = state.dns_resolver.register_machine(app_name, machine_id, ip).await {
+                    warn!(machine_id = %machine_id, "Failed to register machine with DNS: {}", e);
+                }
+            }
+        }
+
+        info!(
+            machine_id = %machine_id,
+            app_name = %app_name,
+            region = %machine.region,
+            state = ?machine.state,
+            "Adopted existing container"
+        );
+        state.machines.write().unwrap().insert(machine_id.clone(), machine);
+        adopted.push(machine_id.clone());
+    }
+
+    Ok(adopted)
+}
+
+/// Rebuilds a machine record from its container
+fn machine_from_container(
+    state: &AppState,
+    machine_id: &str,
+    app_name: &str,
+    labels: &HashMap<String, String>,
+    info: &ContainerInspectResponse,
+) -> Machine {
+    let running = info.state.as_ref().and_then(|s| s.running).unwrap_or(false);
+    let image = info.config.as_ref().and_then(|c| c.image.clone()).unwrap_or_default();
+    // Containers created before the config label existed only have their image
+    let mut config: MachineConfig = labels.get(CONFIG_LABEL)
+        .and_then(|config| serde_json::from_str(config).ok())
+        .unwrap_or_else(|| image_only_config(image));
+    // The label only names the env variables; their values come from the
+    // container, leaving out injected secrets
+    if let Some(env) = config.env.as_mut() {
+        let container_env = container_env(info);
+        let secrets = container_secrets(info);
+        env.retain(|key, _| !secrets.contains(key));
+        for (key, value) in env.iter_mut() {
+            if let Some(actual) = container_env.get(key) {
+                value.clone_from(actual);
+            }
+        }
+    }
+    let created_at = info.created.as_deref()
+        .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+        .map_or_else(Utc::now, |created| created.with_timezone(&Utc));
+    let machine_index = state.machines.read().unwrap().len() as u32;
+    let state_after = if running { MachineState::Started } else { MachineState::Stopped };
+
+    Machine {
+        id: machine_id.to_string(),
+        name: format!("{}-{}", app_name, machine_id),
+        app_name: app_name.to_string(),
+        region: labels.get("minifly.region").cloned().unwrap_or_else(|| state.config.region.clone()),
+        image_ref: crate::handlers::parse_image_ref(&config.image),
+        instance_id: state.generate_instance_id(),
+        private_ip: state.generate_private_ip(app_name, machine_index),
+        created_at,
+        updated_at: Utc::now(),
+        config,
+        events: vec![MachineEvent {
+            event_type: "reconcile".to_string(),
+            status: state_after.as_str().to_string(),
+            source: "minifly".to_string(),
+            timestamp: Utc::now().timestamp_millis() as u64,
+            request: None,
+        }],
+        state: state_after,
+        cordoned: false,
+        checks: Vec::new(),
+        mapped_ports: crate::docker::mapped_ports(info),
+    }
+}
+
+fn image_only_config(image: String) -> MachineConfig {
+    MachineConfig {
+        restart: None,
+        ..MachineConfig::for_image(image)
+    }
+}
+
+/// Recreates the record of an app that only survives in its containers
+fn adopt_app(state: &AppState, app_name: &str) {
+    state.apps.write().unwrap().entry(app_name.to_string()).or_insert_with(|| App {
+        id: Uuid::new_v4(),
+        name: app_name.to_string(),
+        organization_id: "personal".to_string(),
+        status: AppStatus::Deployed,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_labeled_container_becomes_machine() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+
+        let mut config = image_only_config("nginx:alpine".to_string());
+        config.env = Some(HashMap::from([
+            ("DATABASE_URL".to_string(), "postgres://app:hunter2@db/app".to_string()),
+            ("API_KEY".to_string(), "declared".to_string()),
+        ]));
+        let config = crate::docker::config_label(&config).unwrap();
+        assert!(!config.contains("hunter2"));
+        let labels = serde_json::json!({
+            "minifly.managed": "true",
+            "minifly.machine_id": "d8e7f6",
+            "minifly.app_name": "web",
+            "minifly.region": "ord",
+            "minifly.config": config,
+            "minifly.secrets": "API_KEY",
+        });
+        crate::docker::fake_docker(&data_dir.path().join("docker.sock"), move |request| {
+            let body = if request.contains("/containers/json") {
+                serde_json::json!([{ "Id": "abc123", "Labels": labels }])
+            } else {
+                serde_json::json!({
+                    "Id": "abc123",
+                    "Created": "2025-01-02T03:04:05Z",
+                    "State": { "Running": true },
+                    "Config": {
+                        "Image": "nginx:alpine",
+                        "Labels": labels,
+                        "Env": ["DATABASE_URL=postgres://app:hunter2@db/app", "API_KEY=s3cret", "FLY_REGION=ord"],
+                    },
+                    "NetworkSettings": { "Networks": { "minifly": { "IPAddress": "172.19.0.7" } } },
+                })
+            };
+            (200, body.to_string())
+        });
+
+        assert_eq!(reconcile_containers(&state).await.unwrap(), vec!["d8e7f6"]);
+
+        let machine = state.machines.read().unwrap()["d8e7f6"].clone();
+        assert_eq!(machine.name, "web-d8e7f6");
+        assert_eq!(machine.state, MachineState::Started);
+        assert_eq!(machine.region, "ord");
+        assert_eq!(machine.config.image, "nginx:alpine");
+        // Env values are read back from the container, without injected secrets
+        assert_eq!(machine.config.env, Some(HashMap::from([
+            ("DATABASE_URL".to_string(), "postgres://app:hunter2@db/app".to_string()),
+        ])));
+        assert_eq!(machine.created_at.to_rfc3339(), "2025-01-02T03:04:05+00:00");
+        assert!(state.apps.read().unwrap().contains_key("web"));
+        assert_eq!(
+            state.dns_resolver.resolve("web.internal").await.unwrap(),
+            vec!["172.19.0.7".parse::<std::net::IpAddr>().unwrap()]
+        );
+
+        // Machines the API already knows are left alone
+        assert!(reconcile_containers(&state).await.unwrap().is_empty());
+    }
+}
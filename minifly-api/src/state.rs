@@ -1,14 +1,25 @@
 use crate::config::Config;
 use crate::docker::DockerClient;
+use crate::events::EventBus;
+use crate::metrics::Metrics;
 use anyhow::Result;
-use minifly_core::models::{App, Machine, Lease};
+use minifly_core::models::{App, Machine, MachineEvent, Lease, Release, Volume};
+use minifly_litefs::config::ReplicationConfig;
 use minifly_litefs::manager::LiteFSManager;
 use minifly_network::InternalDnsResolver;
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+
+/// How often LiteFS processes are checked for unexpected exits
+const LITEFS_MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often lagging LiteFS replicas copy the primary's databases
+const LITEFS_REPLICATION_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Clone)]
 pub struct AppState {
@@ -19,8 +30,22 @@ pub struct AppState {
     pub dns_resolver: Arc<InternalDnsResolver>,
     pub leases: Arc<RwLock<HashMap<String, Lease>>>,
     pub machines: Arc<RwLock<HashMap<String, Machine>>>,
+    /// Machine metadata keyed by machine ID
+    pub machine_metadata: Arc<RwLock<HashMap<String, HashMap<String, String>>>>,
+    /// Connections proxies report holding open, keyed by machine ID
+    pub connections: Arc<RwLock<HashMap<String, crate::autostop::ConnectionLeases>>>,
     pub apps: Arc<RwLock<HashMap<String, App>>>,
+    /// Release history keyed by app name, oldest first
+    pub releases: Arc<RwLock<HashMap<String, Vec<Release>>>>,
+    /// Volumes keyed by app name, then volume ID
+    pub volumes: Arc<RwLock<HashMap<String, HashMap<String, Volume>>>>,
     pub start_time: Instant,
+    /// Notified when a graceful shutdown has been requested
+    pub shutdown: Arc<Notify>,
+    /// Machine lifecycle events, streamed at `/v1/events`
+    pub events: EventBus,
+    /// Prometheus metrics served at `/metrics`
+    pub metrics: Arc<Metrics>,
 }
 
 impl AppState {
@@ -34,12 +59,18 @@ impl AppState {
         // Run migrations
         sqlx::migrate!("./migrations").run(&db).await?;
         
-        // Initialize Docker client
-        let docker = DockerClient::new(config.docker_host.as_deref())?;
+        // Initialize Docker client; it connects lazily so the API starts without Docker
+        let docker = DockerClient::new(config.docker_host.as_deref())
+            .with_object_storage(config.object_storage.clone())
+            .with_stable_ports(config.stable_ports);
         
         // Initialize LiteFS manager
         let litefs_base_dir = PathBuf::from(&config.data_dir).join("litefs");
-        let litefs = Arc::new(LiteFSManager::new(litefs_base_dir).await?);
+        let litefs = Arc::new(
+            LiteFSManager::new(litefs_base_dir).await?
+                .with_restart_policy(config.litefs_restart_policy)
+                .with_replication(ReplicationConfig::from_env()?),
+        );
         
         // Initialize DNS resolver
         let dns_resolver = Arc::new(InternalDnsResolver::new());
@@ -52,11 +83,66 @@ impl AppState {
             dns_resolver,
             leases: Arc::new(RwLock::new(HashMap::new())),
             machines: Arc::new(RwLock::new(HashMap::new())),
+            machine_metadata: Arc::new(RwLock::new(HashMap::new())),
+            connections: Arc::new(RwLock::new(HashMap::new())),
             apps: Arc::new(RwLock::new(HashMap::new())),
+            releases: Arc::new(RwLock::new(HashMap::new())),
+            volumes: Arc::new(RwLock::new(HashMap::new())),
             start_time: Instant::now(),
+            shutdown: Arc::new(Notify::new()),
+            events: EventBus::new(),
+            metrics: Arc::new(Metrics::new()),
         })
     }
     
+    /// Watches machines' LiteFS processes, applying the restart policy and
+    /// recording an event on the machine whenever one exits.
+    pub fn spawn_litefs_monitor(&self) {
+        self.litefs.clone().spawn_monitor(LITEFS_MONITOR_INTERVAL);
+        self.litefs.clone().spawn_replication(LITEFS_REPLICATION_INTERVAL);
+        
+        let mut exits = self.litefs.subscribe_exits();
+        let machines = self.machines.clone();
+        let max_events = self.config.max_machine_events;
+        tokio::spawn(async move {
+            loop {
+                match exits.recv().await {
+                    Ok(exit) => {
+                        if let Some(machine) = machines.write().unwrap().get_mut(&exit.machine_id) {
+                            machine.push_event(MachineEvent {
+                                event_type: "litefs_exit".to_string(),
+                                status: if exit.restarted { "restarted" } else { "unhealthy" }.to_string(),
+                                source: "litefs".to_string(),
+                                timestamp: exit.timestamp.timestamp_millis() as u64,
+                                request: None,
+                            }, max_events);
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+    
+    /// Requests a graceful shutdown of the API server.
+    pub fn request_shutdown(&self) {
+        self.shutdown.notify_one();
+    }
+    
+    /// Resolves once a shutdown has been requested, either through
+    /// [`AppState::request_shutdown`] or Ctrl+C.
+    pub async fn shutdown_signal(&self) {
+        tokio::select! {
+            _ = self.shutdown.notified() => {
+                tracing::info!("Shutdown requested, draining connections");
+            }
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Received Ctrl+C, draining connections");
+            }
+        }
+    }
+    
     pub fn generate_machine_id(&self) -> String {
         // Generate a 15-character hex ID similar to Fly.io
         use rand::Rng;
@@ -94,4 +180,66 @@ impl AppState {
         
         format!("{}{}:a7b:{}::2", self.config.internal_network_prefix, app_hash, machine_index)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State backed by an in-memory database and a placeholder Docker socket
+    /// under `data_dir`, for tests that never reach Docker.
+    pub(crate) async fn for_tests(data_dir: &std::path::Path) -> Self {
+        let docker_socket = data_dir.join("docker.sock");
+        std::fs::File::create(&docker_socket).unwrap();
+        let config = Config {
+            port: 0,
+            database_url: "sqlite::memory:".to_string(),
+            docker_host: Some(docker_socket.to_string_lossy().to_string()),
+            data_dir: data_dir.to_string_lossy().to_string(),
+            internal_network_prefix: "fdaa:0:".to_string(),
+            dns_port: 0,
+            litefs_port: 0,
+            persist_logs: false,
+            log_max_bytes: 10 * 1024 * 1024,
+            readiness_services: vec!["database".to_string(), "docker".to_string()],
+            disk_low_space_bytes: 1024 * 1024 * 1024,
+            litefs_restart_policy: minifly_litefs::process::RestartPolicy::Never,
+            region: "local".to_string(),
+            region_latency: Default::default(),
+            object_storage: None,
+            stable_ports: false,
+            max_machine_events: Some(100),
+        };
+        Self::new(config).await.unwrap()
+    }
+}
+
+/// A started machine `id` of app `app_name` running `nginx:alpine`, without
+/// services
+#[cfg(test)]
+pub(crate) fn machine_for_tests(id: &str, app_name: &str) -> Machine {
+    use chrono::{TimeZone, Utc};
+    use minifly_core::models::{ImageRef, MachineConfig, MachineState};
+
+    let created_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    Machine {
+        id: id.to_string(),
+        name: format!("{}-{}", app_name, id),
+        app_name: app_name.to_string(),
+        state: MachineState::Started,
+        region: "local".to_string(),
+        image_ref: ImageRef {
+            registry: "docker.io".to_string(),
+            repository: "nginx".to_string(),
+            tag: "alpine".to_string(),
+            digest: None,
+        },
+        instance_id: "01TEST".to_string(),
+        private_ip: "fdaa::2".to_string(),
+        created_at,
+        updated_at: created_at,
+        config: MachineConfig::for_image("nginx:alpine"),
+        events: Vec::new(),
+        cordoned: false,
+        checks: Vec::new(),
+        mapped_ports: Vec::new(),
+    }
+}
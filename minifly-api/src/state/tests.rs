@@ -76,6 +76,7 @@ mod tests {
         let machine = Machine {
             id: format!("d{}", Uuid::new_v4().to_string().replace("-", "")[..15]),
             name: "test-machine".to_string(),
+            app_name: "test".to_string(),
             state: MachineState::Created,
             region: "local".to_string(),
             config: MachineConfig {
@@ -0,0 +1,363 @@
+//! Applies machines' restart policies when their containers exit
+//!
+//! Containers are created without a Docker restart policy: Docker retries
+//! immediately and never tells the API how often it did. Instead the
+//! supervisor polls the containers of started machines and, when one has
+//! exited, restarts it after an exponential backoff if the machine's
+//! `restart.policy` allows it. Every exit and restart is recorded in
+//! `Machine.events` with the exit code and restart count, and a machine that
+//! exhausts `restart.max_retries` (or whose policy doesn't restart it) is
+//! marked stopped.
+
+use crate::events::MachineEventKind;
+use crate::state::AppState;
+use chrono::Utc;
+use minifly_core::models::{MachineConfig, MachineEvent, MachineState};
+use minifly_network::extract_container_ip;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// How often the containers of started machines are checked
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Delay before the first restart, doubled for every further one
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A restarted container that stays up this long has its restart count reset
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// When a machine's container is restarted after it exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    No,
+    Always,
+    OnFailure,
+    /// Like `Always`; a machine stopped through the API stays stopped either way
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    /// The policy in `config.restart`; machines without one are never restarted
+    pub fn of(config: &MachineConfig) -> Self {
+        match config.restart.as_ref().map(|restart| restart.policy.as_str()) {
+            Some("always") => Self::Always,
+            Some("on-failure") => Self::OnFailure,
+            Some("unless-stopped") => Self::UnlessStopped,
+            _ => Self::No,
+        }
+    }
+
+    pub fn restarts(self, exit_code: i64) -> bool {
+        match self {
+            Self::No => false,
+            Self::Always | Self::UnlessStopped => true,
+            Self::OnFailure => exit_code != 0,
+        }
+    }
+}
+
+/// Delay before restart number `restarts + 1`: `base * 2^restarts`, capped at `max`
+pub fn backoff(restarts: u32, base: Duration, max: Duration) -> Duration {
+    base.checked_mul(2u32.saturating_pow(restarts)).map_or(max, |delay| delay.min(max))
+}
+
+/// Restarts of one machine since its container was last stable
+#[derive(Debug)]
+struct Restarts {
+    count: u32,
+    /// When the exited container is due to be started again
+    due: Option<Instant>,
+    last_restart: Option<Instant>,
+}
+
+/// A started machine as seen by one check
+struct Watched {
+    id: String,
+    region: String,
+    policy: RestartPolicy,
+    max_retries: Option<u32>,
+}
+
+pub struct Supervisor {
+    base_backoff: Duration,
+    max_backoff: Duration,
+    restarts: HashMap<String, Restarts>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self {
+            base_backoff: BASE_BACKOFF,
+            max_backoff: MAX_BACKOFF,
+            restarts: HashMap::new(),
+        }
+    }
+}
+
+impl Supervisor {
+    /// Checks the machines every [`SUPERVISOR_INTERVAL`] for as long as the API runs
+    pub fn spawn(mut self, state: AppState) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SUPERVISOR_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.check(&state).await;
+            }
+        });
+    }
+
+    /// Handles the exited containers of started machines once
+    pub async fn check(&mut self, state: &AppState) {
+        let watched: Vec<Watched> = state.machines.read().unwrap()
+            .values()
+            .filter(|machine| machine.state == MachineState::Started)
+            .map(|machine| Watched {
+                id: machine.id.clone(),
+                region: machine.region.clone(),
+                policy: RestartPolicy::of(&machine.config),
+                max_retries: machine.config.restart.as_ref().and_then(|restart| restart.max_retries),
+            })
+            .collect();
+        // Machines stopped or destroyed through the API start over next time
+        self.restarts.retain(|id, _| watched.iter().any(|machine| &machine.id == id));
+
+        for machine in watched {
+            self.check_machine(state, machine).await;
+        }
+    }
+
+    async fn check_machine(&mut self, state: &AppState, machine: Watched) {
+        let info = match state.docker.get_container_id_by_machine(&machine.id).await {
+            Ok(Some(container_id)) => state.docker.inspect_container(&container_id).await,
+            Ok(None) => return,
+            Err(e) => Err(e),
+        };
+        let info = match info {
+            Ok(info) => info,
+            Err(e) => {
+                debug!(machine_id = %machine.id, "Skipping machine whose container can't be inspected: {}", e);
+                return;
+            }
+        };
+        let Some(container_id) = info.id else { return };
+        let app_name = info.config.as_ref()
+            .and_then(|config| config.labels.as_ref())
+            .and_then(|labels| labels.get("minifly.app_name").cloned())
+            .unwrap_or_default();
+        let container_state = info.state.unwrap_or_default();
+
+        if container_state.running.unwrap_or(false) {
+            let stable = self.restarts.get(&machine.id).is_some_and(|restarts| {
+                restarts.due.is_none() && restarts.last_restart.is_none_or(|at| at.elapsed() >= STABLE_AFTER)
+            });
+            if stable {
+                self.restarts.remove(&machine.id);
+            }
+            return;
+        }
+        let exited = container_state.status.is_some_and(|status| {
+            matches!(status, bollard::models::ContainerStateStatusEnum::EXITED | bollard::models::ContainerStateStatusEnum::DEAD)
+        });
+        if !exited {
+            return;
+        }
+
+        let exit_code = container_state.exit_code.unwrap_or_default();
+        let restarts = self.restarts.entry(machine.id.clone()).or_insert(Restarts {
+            count: 0,
+            due: None,
+            last_restart: None,
+        });
+        let count = restarts.count;
+
+        let exhausted = machine.max_retries.is_some_and(|max| count >= max);
+        if !machine.policy.restarts(exit_code) || exhausted {
+            if exhausted {
+                warn!(machine_id = %machine.id, exit_code, restarts = count, "Machine exhausted its restart retries");
+            } else {
+                info!(machine_id = %machine.id, exit_code, "Machine exited");
+            }
+            self.restarts.remove(&machine.id);
+            mark_stopped(state, &machine, &app_name, exit_code, count).await;
+            return;
+        }
+
+        let Some(due) = restarts.due else {
+            let delay = backoff(count, self.base_backoff, self.max_backoff);
+            restarts.due = Some(Instant::now() + delay);
+            info!(machine_id = %machine.id, exit_code, restarts = count, delay_ms = delay.as_millis() as u64, "Machine exited, restarting after backoff");
+            record(state, &machine.id, "exit", "restarting", exit_event(exit_code, true, count));
+            return;
+        };
+        if Instant::now() < due {
+            return;
+        }
+
+        // A stop through the API may have raced the backoff
+        if state.machines.read().unwrap().get(&machine.id).is_none_or(|m| m.state != MachineState::Started) {
+            self.restarts.remove(&machine.id);
+            return;
+        }
+
+        restarts.count += 1;
+        restarts.last_restart = Some(Instant::now());
+        if let Err(e) = state.docker.start_container(&container_id).await {
+            // A failed start counts as an attempt and backs off further
+            warn!(machine_id = %machine.id, restarts = restarts.count, "Failed to restart container: {}", e);
+            restarts.due = Some(Instant::now() + backoff(restarts.count, self.base_backoff, self.max_backoff));
+            return;
+        }
+        restarts.due = None;
+        let count = restarts.count;
+
+        info!(machine_id = %machine.id, restarts = count, "Restarted machine");
+        crate::handlers::spawn_log_sink(state, &app_name, &machine.id, &container_id, "0");
+        register_dns(state, &machine.id, &app_name, &container_id).await;
+        crate::handlers::record_mapped_ports(state, &machine.id, &container_id).await;
+        record(state, &machine.id, "restart", "started", serde_json::json!({ "restart_count": count }));
+        state.events.publish(crate::events::MachineEvent::new(
+            MachineEventKind::Started,
+            &app_name,
+            &machine.id,
+            &machine.region,
+        ));
+    }
+}
+
+/// Fly's shape for the details of an exit event
+fn exit_event(exit_code: i64, restarting: bool, restart_count: u32) -> serde_json::Value {
+    serde_json::json!({
+        "exit_event": { "exit_code": exit_code, "restarting": restarting },
+        "restart_count": restart_count,
+    })
+}
+
+fn record(state: &AppState, machine_id: &str, event_type: &str, status: &str, request: serde_json::Value) {
+    if let Some(machine) = state.machines.write().unwrap().get_mut(machine_id) {
+        machine.updated_at = Utc::now();
+        machine.push_event(MachineEvent {
+            event_type: event_type.to_string(),
+            status: status.to_string(),
+            source: "flyd".to_string(),
+            timestamp: Utc::now().timestamp_millis() as u64,
+            request: Some(request),
+        }, state.config.max_machine_events);
+    }
+}
+
+async fn mark_stopped(state: &AppState, machine: &Watched, app_name: &str, exit_code: i64, restart_count: u32) {
+    if let Some(stopped) = state.machines.write().unwrap().get_mut(&machine.id) {
+        stopped.state = MachineState::Stopped;
+    }
+    record(state, &machine.id, "exit", "stopped", exit_event(exit_code, false, restart_count));
+
+    if let Err(e) = state.dns_resolver.unregister_machine(app_name, &machine.id).await {
+        warn!("Failed to unregister machine from DNS: {}", e);
+    }
+    state.events.publish(crate::events::MachineEvent::new(
+        MachineEventKind::Stopped,
+        app_name,
+        &machine.id,
+        &machine.region,
+    ));
+    crate::handlers::auto_destroy(state, app_name, &machine.id).await;
+}
+
+/// The container may come back with another address
+async fn register_dns(state: &AppState, machine_id: &str, app_name: &str, container_id: &str) {
+    let ip = state.docker.inspect_container(container_id).await.ok()
+        .and_then(|info| info.network_settings)
+        .and_then(|settings| settings.networks)
+        .and_then(|networks| extract_container_ip(&serde_json::to_value(networks).ok()?));
+    if let Some(ip) = ip {
+        if let Err(e) = state.dns_resolver.register_machine(app_name, machine_id, ip).await {
+            warn!(machine_id = %machine_id, "Failed to register machine with DNS: {}", e);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use minifly_core::models::{Machine, RestartConfig};
+
+    #[test]
+    fn test_policy_and_backoff() {
+        assert!(RestartPolicy::OnFailure.restarts(1));
+        assert!(!RestartPolicy::OnFailure.restarts(0));
+        assert!(RestartPolicy::Always.restarts(0));
+        assert!(!RestartPolicy::No.restarts(137));
+
+        let (base, max) = (Duration::from_secs(1), Duration::from_secs(10));
+        let delays: Vec<_> = (0..5).map(|n| backoff(n, base, max).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 10]);
+        assert_eq!(backoff(u32::MAX, base, max), max);
+    }
+
+    #[tokio::test]
+    async fn test_crashing_machine_stops_after_max_retries() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(data_dir.path()).await;
+        let requests = crate::docker::fake_docker(&data_dir.path().join("docker.sock"), |request| {
+            let labels = serde_json::json!({ "minifly.machine_id": "d8e7f6", "minifly.app_name": "web" });
+            let body = if request.starts_with("POST") {
+                return (204, String::new());
+            } else if request.contains("/containers/json") {
+                serde_json::json!([{ "Id": "abc123", "Labels": labels }])
+            } else {
+                serde_json::json!({
+                    "Id": "abc123",
+                    "State": { "Status": "exited", "Running": false, "ExitCode": 1 },
+                    "Config": { "Labels": labels },
+                })
+            };
+            (200, body.to_string())
+        });
+
+        let mut config: MachineConfig = serde_json::from_value(serde_json::json!({
+            "image": "crasher:latest",
+            "guest": { "cpu_kind": "shared", "cpus": 1, "memory_mb": 256 },
+        })).unwrap();
+        config.restart = Some(RestartConfig { policy: "on-failure".to_string(), max_retries: Some(2) });
+        state.machines.write().unwrap().insert("d8e7f6".to_string(), Machine {
+            id: "d8e7f6".to_string(),
+            name: "web-d8e7f6".to_string(),
+            app_name: "web".to_string(),
+            state: MachineState::Started,
+            region: "local".to_string(),
+            image_ref: crate::handlers::parse_image_ref(&config.image),
+            instance_id: state.generate_instance_id(),
+            private_ip: "fdaa::2".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            config,
+            events: Vec::new(),
+            cordoned: false,
+            checks: Vec::new(),
+            mapped_ports: Vec::new(),
+        });
+
+        let mut supervisor = Supervisor { base_backoff: Duration::ZERO, max_backoff: Duration::ZERO, ..Default::default() };
+        for _ in 0..10 {
+            supervisor.check(&state).await;
+        }
+
+        let machine = state.machines.read().unwrap()["d8e7f6"].clone();
+        assert_eq!(machine.state, MachineState::Stopped);
+        let restarts: Vec<_> = machine.events.iter()
+            .filter(|event| event.event_type == "restart")
+            .map(|event| event.request.as_ref().unwrap()["restart_count"].as_u64().unwrap())
+            .collect();
+        assert_eq!(restarts, [1, 2]);
+        let last = machine.events.last().unwrap();
+        assert_eq!((last.event_type.as_str(), last.status.as_str()), ("exit", "stopped"));
+        assert_eq!(last.request.as_ref().unwrap()["exit_event"]["exit_code"], 1);
+
+        let starts = requests.lock().unwrap().iter().filter(|r| r.contains("/abc123/start")).count();
+        assert_eq!(starts, 2);
+    }
+}
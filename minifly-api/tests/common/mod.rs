@@ -16,6 +16,16 @@ pub fn test_config() -> Config {
         internal_network_prefix: "fdaa:0:".to_string(),
         dns_port: 0, // Let OS assign port
         litefs_port: 0, // Let OS assign port
+        persist_logs: false,
+        log_max_bytes: 10 * 1024 * 1024,
+        readiness_services: vec!["database".to_string(), "docker".to_string()],
+        disk_low_space_bytes: 1024 * 1024 * 1024,
+        litefs_restart_policy: minifly_litefs::process::RestartPolicy::Never,
+        region: "local".to_string(),
+        region_latency: Default::default(),
+        object_storage: None,
+        stable_ports: false,
+        max_machine_events: Some(100),
     }
 }
 
@@ -48,6 +48,7 @@ async fn test_dns_registration() {
         restart: None,
         checks: None,
         auto_destroy: None,
+        kill_timeout: None,
         dns: None,
         processes: None,
         files: None,
@@ -127,6 +128,7 @@ async fn test_dns_cleanup_on_stop() {
         restart: None,
         checks: None,
         auto_destroy: None,
+        kill_timeout: None,
         dns: None,
         processes: None,
         files: None,
@@ -47,6 +47,7 @@ async fn test_fly_env_vars_translation() {
         restart: None,
         checks: None,
         auto_destroy: None,
+        kill_timeout: None,
         dns: None,
         processes: None,
         files: None,
@@ -120,6 +121,7 @@ async fn test_tigris_endpoint_translation() {
         restart: None,
         checks: None,
         auto_destroy: None,
+        kill_timeout: None,
         dns: None,
         processes: None,
         files: None,
@@ -1,12 +1,74 @@
 use anyhow::{Context, Result};
 use minifly_core::models::*;
-use reqwest::{Client, header};
+use minifly_core::SuccessResponse;
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, header};
+use std::time::{Duration, Instant};
+use tracing::debug;
 use crate::config::Config;
 
+/// Environment variable overriding how many attempts idempotent requests get
+pub const RETRIES_ENV: &str = "MINIFLY_API_RETRIES";
+
+/// Request and response bodies longer than this are truncated in the HTTP trace
+const TRACE_BODY_LIMIT: usize = 2048;
+
+/// Headers whose values never appear in the HTTP trace
+const SECRET_HEADERS: [&str; 4] = ["authorization", "proxy-authorization", "cookie", "set-cookie"];
+
+/// Retries of idempotent requests that cannot connect or get a 502, 503 or
+/// 504, e.g. while the API server is still starting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy, with the attempts taken from [`RETRIES_ENV`] if set
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(RETRIES_ENV) {
+            Ok(attempts) => {
+                let max_attempts = attempts.parse::<u32>()
+                    .ok()
+                    .filter(|attempts| *attempts > 0)
+                    .with_context(|| format!("{} must be a positive number, got '{}'", RETRIES_ENV, attempts))?;
+                Ok(Self { max_attempts, ..Self::default() })
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+    
+    /// Delay before retry number `attempt`: doubling from the initial
+    /// backoff up to the maximum, less up to half of it as jitter
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
 #[derive(Clone)]
 pub struct ApiClient {
     client: Client,
     base_url: String,
+    retry: RetryPolicy,
+    /// Headers sent with every request, kept so the HTTP trace can show them
+    default_headers: header::HeaderMap,
+    http_trace: bool,
 }
 
 impl ApiClient {
@@ -25,21 +87,113 @@ impl ApiClient {
         }
         
         let client = Client::builder()
-            .default_headers(headers)
+            .default_headers(headers.clone())
             .build()?;
         
         Ok(Self {
             client,
             base_url: config.api_url.clone(),
+            retry: RetryPolicy::from_env()?,
+            default_headers: headers,
+            http_trace: false,
         })
     }
     
+    /// Logs every request and response at `debug` level, with secret
+    /// headers redacted and long bodies truncated
+    pub fn with_http_trace(mut self, enabled: bool) -> Self {
+        self.http_trace = enabled;
+        self
+    }
+    
+    #[cfg(test)]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+    
+    /// Sends an idempotent request, retrying transient failures with backoff
+    async fn send_idempotent(&self, request: impl Fn() -> RequestBuilder) -> reqwest::Result<Response> {
+        let mut attempt = 1;
+        loop {
+            let result = self.send(request()).await;
+            let transient = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+                ),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !transient || attempt >= self.retry.max_attempts {
+                return result;
+            }
+            
+            let delay = self.retry.backoff(attempt);
+            debug!(attempt, delay_ms = delay.as_millis() as u64, "Retrying API request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+    
+    /// Sends a request, tracing it and its response when enabled
+    async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        if !self.http_trace {
+            return request.send().await;
+        }
+        
+        let (client, request) = request.build_split();
+        let request = request?;
+        let mut headers = self.default_headers.clone();
+        headers.extend(request.headers().clone());
+        debug!(
+            method = %request.method(),
+            url = %request.url(),
+            headers = %format_headers(&headers),
+            body = %request.body().and_then(|body| body.as_bytes()).map(format_body).unwrap_or_default(),
+            "API request"
+        );
+        
+        let response = client.execute(request).await?;
+        let status = response.status();
+        // Streamed responses such as log tails have no length and are left unread
+        if response.content_length().is_none() {
+            debug!(status = status.as_u16(), headers = %format_headers(response.headers()), "API response (streamed)");
+            return Ok(response);
+        }
+        
+        let version = response.version();
+        let response_headers = response.headers().clone();
+        let body = response.bytes().await?;
+        debug!(
+            status = status.as_u16(),
+            headers = %format_headers(&response_headers),
+            body = %format_body(&body),
+            "API response"
+        );
+        
+        let mut rebuilt = http::Response::builder().status(status).version(version);
+        if let Some(headers) = rebuilt.headers_mut() {
+            *headers = response_headers;
+        }
+        Ok(Response::from(rebuilt.body(body).expect("status and headers came from a valid response")))
+    }
+    
     // Apps API
     pub async fn list_apps(&self) -> Result<Vec<AppResponse>> {
-        let resp = self.client
-            .get(format!("{}/v1/apps", self.base_url))
-            .send()
-            .await?;
+        self.list_apps_with_status(None).await
+    }
+    
+    /// Lists apps, only those whose machines are in `status` if given
+    pub async fn list_apps_with_status(&self, status: Option<&str>) -> Result<Vec<AppResponse>> {
+        let url = match status {
+            Some(status) => format!("{}/v1/apps?status={}", self.base_url, status),
+            None => format!("{}/v1/apps", self.base_url),
+        };
+        let resp = self.send_idempotent(|| self.client.get(&url)).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to list apps: {}", resp.text().await?);
+        }
         
         resp.json().await.context("Failed to parse response")
     }
@@ -50,30 +204,44 @@ impl ApiClient {
             org_slug: "personal".to_string(),
         };
         
-        let resp = self.client
-            .post(format!("{}/v1/apps", self.base_url))
-            .json(&req)
-            .send()
-            .await?;
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps", self.base_url))
+                .json(&req)
+        ).await?;
         
         resp.json().await.context("Failed to parse response")
     }
     
-    pub async fn delete_app(&self, name: &str) -> Result<()> {
-        self.client
-            .delete(format!("{}/v1/apps/{}", self.base_url, name))
-            .send()
-            .await?;
+    /// Deletes an app with its machines, and its volumes unless `keep_volumes` is set
+    pub async fn delete_app(&self, name: &str, keep_volumes: bool) -> Result<DeleteAppResponse> {
+        let resp = self.send(
+            self.client
+                .delete(format!("{}/v1/apps/{}?keep_volumes={}", self.base_url, name, keep_volumes))
+        ).await?;
         
-        Ok(())
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to delete app: {}", resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
     }
     
     // Machines API
     pub async fn list_machines(&self, app_name: &str) -> Result<Vec<Machine>> {
-        let resp = self.client
-            .get(format!("{}/v1/apps/{}/machines", self.base_url, app_name))
-            .send()
-            .await?;
+        let resp = self.send_idempotent(|| self.client.get(format!("{}/v1/apps/{}/machines", self.base_url, app_name))).await?;
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    pub async fn get_machine(&self, app_name: &str, machine_id: &str) -> Result<Machine> {
+        let resp = self.send_idempotent(|| {
+            self.client.get(format!("{}/v1/apps/{}/machines/{}", self.base_url, app_name, machine_id))
+        }).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to get machine {}: {}", machine_id, resp.text().await?);
+        }
         
         resp.json().await.context("Failed to parse response")
     }
@@ -81,66 +249,92 @@ impl ApiClient {
     pub async fn create_machine(
         &self,
         app_name: &str,
-        image: &str,
         name: Option<String>,
         region: Option<String>,
+        config: MachineConfig,
     ) -> Result<Machine> {
         let req = CreateMachineRequest {
             name,
             region,
-            config: MachineConfig {
-                image: image.to_string(),
-                guest: GuestConfig {
-                    cpu_kind: "shared".to_string(),
-                    cpus: 1,
-                    memory_mb: 256,
-                    gpu_kind: None,
-                    gpus: None,
-                    kernel_args: None,
-                },
-                env: None,
-                services: None,
-                checks: None,
-                restart: Some(RestartConfig {
-                    policy: "on-failure".to_string(),
-                    max_retries: Some(3),
-                }),
-                auto_destroy: None,
-                dns: None,
-                processes: None,
-                files: None,
-                init: None,
-                mounts: None,
-                containers: None,
-            },
+            config,
             skip_launch: None,
             skip_service_registration: None,
             lease_ttl: None,
         };
         
-        let resp = self.client
-            .post(format!("{}/v1/apps/{}/machines", self.base_url, app_name))
-            .json(&req)
-            .send()
-            .await?;
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines", self.base_url, app_name))
+                .json(&req)
+        ).await?;
         
         resp.json().await.context("Failed to parse response")
     }
     
     pub async fn start_machine(&self, app_name: &str, machine_id: &str) -> Result<StartMachineResponse> {
-        let resp = self.client
-            .post(format!("{}/v1/apps/{}/machines/{}/start", self.base_url, app_name, machine_id))
-            .send()
-            .await?;
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/{}/start", self.base_url, app_name, machine_id))
+        ).await?;
         
         resp.json().await.context("Failed to parse response")
     }
     
     pub async fn stop_machine(&self, app_name: &str, machine_id: &str) -> Result<StopMachineResponse> {
-        let resp = self.client
-            .post(format!("{}/v1/apps/{}/machines/{}/stop", self.base_url, app_name, machine_id))
-            .send()
-            .await?;
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/{}/stop", self.base_url, app_name, machine_id))
+        ).await?;
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// Replaces a machine's config
+    pub async fn update_machine(&self, app_name: &str, machine_id: &str, config: MachineConfig) -> Result<Machine> {
+        let req = UpdateMachineRequest {
+            config,
+            current_version: None,
+            name: None,
+            region: None,
+            skip_launch: None,
+            skip_service_registration: None,
+            lease_ttl: None,
+        };
+        
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/{}", self.base_url, app_name, machine_id))
+                .json(&req)
+        ).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to update machine: {}", resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// Recreates a machine's container from its config
+    pub async fn restart_machine(&self, app_name: &str, machine_id: &str) -> Result<SuccessResponse> {
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/{}/restart", self.base_url, app_name, machine_id))
+        ).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to restart machine: {}", resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// Starts or stops several of an app's machines, returning each one's outcome
+    pub async fn machine_actions(&self, app_name: &str, action: &str, machine_ids: MachineSelection) -> Result<MachineActionResponse> {
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/actions", self.base_url, app_name))
+                .json(&MachineActionRequest { action: action.to_string(), machine_ids })
+        ).await?;
         
         resp.json().await.context("Failed to parse response")
     }
@@ -152,11 +346,154 @@ impl ApiClient {
             format!("{}/v1/apps/{}/machines/{}", self.base_url, app_name, machine_id)
         };
         
-        self.client.delete(url).send().await?;
+        self.send(self.client.delete(url)).await?;
         
         Ok(())
     }
     
+    pub async fn cordon_machine(&self, app_name: &str, machine_id: &str, cordoned: bool) -> Result<SuccessResponse> {
+        let action = if cordoned { "cordon" } else { "uncordon" };
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/{}/{}", self.base_url, app_name, machine_id, action))
+        ).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to {} machine: {}", action, resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// The environment of a machine's container, with secret-looking values
+    /// redacted unless `reveal` is set
+    pub async fn get_machine_env(&self, app_name: &str, machine_id: &str, reveal: bool) -> Result<MachineEnvResponse> {
+        let resp = self.send_idempotent(|| {
+            self.client
+                .get(format!("{}/v1/apps/{}/machines/{}/env", self.base_url, app_name, machine_id))
+                .query(&[("reveal", reveal)])
+        }).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to get machine env: {}", resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// The internal addresses of an app's running machines, by machine ID,
+    /// with the simulated latency to each from `region` (the API's local
+    /// region when unset)
+    pub async fn resolve_app_machines(&self, app_name: &str, region: Option<&str>) -> Result<Vec<ResolvedMachine>> {
+        let resp = self.send_idempotent(|| {
+            let request = self.client.get(format!("{}/v1/apps/{}/dns", self.base_url, app_name));
+            match region {
+                Some(region) => request.header("fly-region", region),
+                None => request,
+            }
+        }).await?;
+        
+        let dns: AppDnsResponse = resp.json().await.context("Failed to parse response")?;
+        Ok(dns.machines)
+    }
+    
+    /// Reports how many connections the proxy `client_id` holds open to a
+    /// machine, renewing its lease on them
+    pub async fn report_connections(&self, app_name: &str, machine_id: &str, client_id: &str, active: u32) -> Result<MachineConnections> {
+        let request = UpdateConnectionsRequest { client_id: client_id.to_string(), active };
+        let resp = self.send_idempotent(|| {
+            self.client
+                .post(format!("{}/v1/apps/{}/machines/{}/connections", self.base_url, app_name, machine_id))
+                .json(&request)
+        }).await?;
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    // Volumes API
+    pub async fn list_volumes(&self, app_name: &str) -> Result<Vec<Volume>> {
+        let resp = self.send_idempotent(|| self.client.get(format!("{}/v1/apps/{}/volumes", self.base_url, app_name))).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to list volumes of {}: {}", app_name, resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// Creates a 1GB volume in the local region
+    pub async fn create_volume(&self, app_name: &str, name: &str) -> Result<Volume> {
+        let req = CreateVolumeRequest {
+            name: name.to_string(),
+            region: "local".to_string(),
+            size_gb: None,
+            encrypted: None,
+            fstype: None,
+            snapshot_id: None,
+            snapshot_retention: None,
+            source_volume_id: None,
+            allow_cross_app_fork: None,
+        };
+        
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/volumes", self.base_url, app_name))
+                .json(&req)
+        ).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to create volume {}: {}", name, resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    // Logs API
+    pub async fn get_app_logs_summary(&self, app_name: &str) -> Result<AppLogsSummary> {
+        let resp = self.send_idempotent(|| self.client.get(format!("{}/v1/apps/{}/logs/summary", self.base_url, app_name))).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to get log summary for {}: {}", app_name, resp.status());
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    // Releases API
+    pub async fn list_releases(&self, app_name: &str) -> Result<Vec<Release>> {
+        let resp = self.send_idempotent(|| self.client.get(format!("{}/v1/apps/{}/releases", self.base_url, app_name))).await?;
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    /// Records a deploy of the given machines, keyed by process group, as a release
+    pub async fn create_release(&self, app_name: &str, req: &CreateReleaseRequest) -> Result<Release> {
+        let resp = self.send_idempotent(|| {
+            self.client
+                .post(format!("{}/v1/apps/{}/releases", self.base_url, app_name))
+                .json(req)
+        }).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to record release: {}", resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
+    pub async fn rollback_release(&self, app_name: &str, version: u32) -> Result<RollbackResponse> {
+        let resp = self.send(
+            self.client
+                .post(format!("{}/v1/apps/{}/releases/{}/rollback", self.base_url, app_name, version))
+        ).await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Rollback failed: {}", resp.text().await?);
+        }
+        
+        resp.json().await.context("Failed to parse response")
+    }
+    
     pub async fn get_machine_app(&self, _machine_id: &str) -> Result<String> {
         // This is a simplified implementation
         // In reality, we'd need to track machine -> app mapping
@@ -171,7 +508,7 @@ impl ApiClient {
             format!("{}/v1{}", self.base_url, path)
         };
         
-        self.client.get(url).send().await
+        self.send_idempotent(|| self.client.get(&url)).await
             .context("Failed to send GET request")
     }
     
@@ -182,23 +519,182 @@ impl ApiClient {
             format!("{}/v1{}", self.base_url, path)
         };
         
-        self.client.post(url)
-            .json(body)
-            .send()
-            .await
+        self.send(self.client.post(url).json(body)).await
             .context("Failed to send POST request")
     }
     
+    /// Polls the health endpoint, backing off between attempts, until the
+    /// API server is healthy or `timeout` has passed
+    pub async fn wait_for_health(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut attempt = 1;
+        loop {
+            if self.health_check().await.unwrap_or(false) {
+                return true;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            tokio::time::sleep(self.retry.backoff(attempt).min(remaining)).await;
+            attempt += 1;
+        }
+    }
+    
     /// Check if the API server is healthy
     pub async fn health_check(&self) -> Result<bool> {
-        let resp = self.client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await;
+        let resp = self.send(
+            self.client
+                .get(format!("{}/v1/health", self.base_url))
+        ).await;
             
         match resp {
             Ok(response) => Ok(response.status().is_success()),
             Err(_) => Ok(false),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Renders headers for the HTTP trace, hiding the values of secret ones
+fn format_headers(headers: &header::HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                "<redacted>"
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Renders a body for the HTTP trace, truncated to [`TRACE_BODY_LIMIT`] bytes
+fn format_body(body: &[u8]) -> String {
+    if body.len() <= TRACE_BODY_LIMIT {
+        return String::from_utf8_lossy(body).into_owned();
+    }
+    format!(
+        "{}... ({} more bytes)",
+        String::from_utf8_lossy(&body[..TRACE_BODY_LIMIT]),
+        body.len() - TRACE_BODY_LIMIT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    fn client(api: &MockServer, retry: RetryPolicy) -> ApiClient {
+        let config = Config { api_url: api.uri(), token: None, ..Default::default() };
+        ApiClient::new(&config).unwrap().with_retry(retry)
+    }
+    
+    async fn flaky_api() -> MockServer {
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&api)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&api)
+            .await;
+        api
+    }
+    
+    #[tokio::test]
+    async fn test_get_retries_until_success() {
+        let api = flaky_api().await;
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        };
+        
+        let apps = client(&api, retry).list_apps().await.unwrap();
+        assert!(apps.is_empty());
+        assert_eq!(api.received_requests().await.unwrap().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_get_gives_up_after_max_attempts() {
+        let api = flaky_api().await;
+        
+        let retry = RetryPolicy { max_attempts: 1, ..RetryPolicy::default() };
+        assert!(client(&api, retry).list_apps().await.is_err());
+        assert_eq!(api.received_requests().await.unwrap().len(), 1);
+    }
+    
+    /// Collects everything the subscriber writes
+    #[derive(Clone, Default)]
+    struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+    
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_http_trace_redacts_authorization() {
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/apps"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({ "name": "web" })))
+            .mount(&api)
+            .await;
+        
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        
+        let config = Config { api_url: api.uri(), token: Some("s3cr3t-token".to_string()), ..Default::default() };
+        let client = ApiClient::new(&config).unwrap().with_http_trace(true);
+        let _ = client.create_app("web").await;
+        
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("API request"), "{}", output);
+        assert!(output.contains("authorization: <redacted>"), "{}", output);
+        assert!(output.contains(r#""app_name":"web""#), "{}", output);
+        assert!(output.contains("status=201"), "{}", output);
+        assert!(!output.contains("s3cr3t-token"), "{}", output);
+    }
+    
+    #[test]
+    fn test_trace_bodies_are_truncated() {
+        let body = vec![b'x'; TRACE_BODY_LIMIT + 10];
+        let formatted = format_body(&body);
+        assert!(formatted.ends_with("... (10 more bytes)"));
+        assert_eq!(formatted.matches('x').count(), TRACE_BODY_LIMIT);
+        assert_eq!(format_body(b"{}"), "{}");
+    }
+    
+    #[test]
+    fn test_backoff_is_capped_and_jittered() {
+        let retry = RetryPolicy::default();
+        for attempt in 1..10 {
+            let delay = retry.backoff(attempt);
+            let cap = (retry.initial_backoff * 2u32.pow(attempt - 1)).min(retry.max_backoff);
+            assert!(delay <= cap && delay >= cap / 2, "attempt {}: {:?}", attempt, delay);
+        }
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use colored::*;
 use tabled::{Table, Tabled};
 use crate::client::ApiClient;
+use minifly_core::models::{Machine, MachineState};
 
 #[derive(Tabled)]
 struct AppRow {
@@ -9,24 +10,30 @@ struct AppRow {
     name: String,
     #[tabled(rename = "STATUS")]
     status: String,
+    #[tabled(rename = "MACHINES")]
+    machines: usize,
     #[tabled(rename = "ORGANIZATION")]
     organization: String,
     #[tabled(rename = "CREATED")]
     created_at: String,
 }
 
-pub async fn list(client: &ApiClient) -> Result<()> {
-    let apps = client.list_apps().await?;
+pub async fn list(client: &ApiClient, status: Option<&str>) -> Result<()> {
+    let apps = client.list_apps_with_status(status).await?;
     
     if apps.is_empty() {
-        println!("No apps found. Create one with: minifly apps create <name>");
+        match status {
+            Some(status) => println!("No apps with status '{}'.", status),
+            None => println!("No apps found. Create one with: minifly apps create <name>"),
+        }
         return Ok(());
     }
     
     let rows: Vec<AppRow> = apps.into_iter()
         .map(|app| AppRow {
             name: app.name,
-            status: app.status,
+            status: app.machine_status.as_str().to_string(),
+            machines: app.machine_count,
             organization: app.organization.slug,
             created_at: app.created_at,
         })
@@ -51,11 +58,16 @@ pub async fn create(client: &ApiClient, name: &str) -> Result<()> {
     Ok(())
 }
 
-pub async fn delete(client: &ApiClient, name: &str) -> Result<()> {
+pub async fn delete(client: &ApiClient, name: &str, keep_volumes: bool) -> Result<()> {
     use dialoguer::Confirm;
     
+    let prompt = if keep_volumes {
+        format!("Delete app '{}' and all of its machines?", name)
+    } else {
+        format!("Delete app '{}' with all of its machines and volumes?", name)
+    };
     let confirm = Confirm::new()
-        .with_prompt(format!("Are you sure you want to delete app '{}'?", name))
+        .with_prompt(prompt)
         .interact()?;
     
     if !confirm {
@@ -65,9 +77,129 @@ pub async fn delete(client: &ApiClient, name: &str) -> Result<()> {
     
     println!("Deleting app {}...", name.yellow());
     
-    client.delete_app(name).await?;
+    let summary = client.delete_app(name, keep_volumes).await?;
     
     println!("{}", "App deleted successfully!".green());
+    println!("Machines destroyed: {}", summary.machines.len());
+    for machine_id in &summary.machines {
+        println!("  • {}", machine_id);
+    }
+    println!("Volumes deleted: {}", summary.deleted_volumes.len());
+    for volume_id in &summary.deleted_volumes {
+        println!("  • {}", volume_id);
+    }
+    if !summary.kept_volumes.is_empty() {
+        println!("Volumes kept: {}", summary.kept_volumes.join(", "));
+    }
+    
+    Ok(())
+}
+
+/// Opens an app in the browser through the host port Docker published for
+/// one of its running machines
+pub async fn open(client: &ApiClient, name: &str) -> Result<()> {
+    let machines = client.list_machines(name).await?;
+    let url = app_url(name, &machines)?;
     
+    if open::that(&url).is_err() {
+        println!("No browser available; open {} manually", url.cyan());
+    } else {
+        println!("🌐 Opened {}", url.cyan());
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Container ports worth opening in a browser, HTTP services first
+fn web_ports(machine: &Machine) -> Vec<u16> {
+    let services = machine.config.services.as_deref().unwrap_or_default();
+    let tcp = || services.iter().filter(|service| service.protocol.eq_ignore_ascii_case("tcp"));
+    let is_http = |service: &&minifly_core::models::ServiceConfig| service.ports.iter()
+        .any(|port| port.handlers.iter().any(|h| h.eq_ignore_ascii_case("http")));
+    
+    let mut ports = Vec::new();
+    for service in tcp().filter(is_http).chain(tcp().filter(|service| !is_http(service))) {
+        if !ports.contains(&service.internal_port) {
+            ports.push(service.internal_port);
+        }
+    }
+    if ports.is_empty() {
+        // Machines without services usually listen where `deploy` expects them
+        ports.push(8080);
+    }
+    ports
+}
+
+/// Host port of a machine's first web port published on this host, as
+/// recorded by the API when the machine started
+pub(crate) fn web_host_port(machine: &Machine) -> Option<u16> {
+    web_ports(machine).into_iter().find_map(|port| machine.host_port(port, "tcp"))
+}
+
+/// URL of the first running machine port published on this host
+fn app_url(app: &str, machines: &[Machine]) -> Result<String> {
+    let running: Vec<&Machine> = machines.iter()
+        .filter(|machine| machine.state == MachineState::Started && !machine.cordoned)
+        .collect();
+    if running.is_empty() {
+        anyhow::bail!("App {} has no running machines; start one with `minifly machines start <id>`", app);
+    }
+    
+    running.iter()
+        .find_map(|machine| web_host_port(machine))
+        .map(|port| format!("http://localhost:{}", port))
+        .ok_or_else(|| anyhow::anyhow!(
+            "No running machine of {} publishes a port on this host; try `minifly proxy --app {} --open`",
+            app,
+            app
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minifly_core::models::{MappedPort, PortConfig};
+
+    fn machine(id: &str, state: MachineState, services: &[(u16, &str)]) -> Machine {
+        let services = services.iter()
+            .map(|&(internal_port, handler)| crate::test_support::service(internal_port, vec![
+                PortConfig { port: 80, handlers: vec![handler.to_string()], force_https: None, tls_options: None },
+            ]))
+            .collect::<Vec<_>>();
+        let mut machine = Machine { state, ..crate::test_support::machine(id) };
+        machine.config.services = (!services.is_empty()).then_some(services);
+        machine
+    }
+
+    fn published(mut machine: Machine, ports: &[(u16, u16)]) -> Machine {
+        machine.mapped_ports = ports.iter()
+            .map(|&(internal_port, host_port)| MappedPort { internal_port, protocol: "tcp".to_string(), host_port })
+            .collect();
+        machine
+    }
+
+    #[test]
+    fn test_app_url_prefers_running_http_services() {
+        let machines = [
+            published(machine("stopped", MachineState::Stopped, &[(3000, "http")]), &[(3000, 40000)]),
+            published(machine("m1", MachineState::Started, &[(5432, "tcp"), (3000, "http")]), &[(3000, 40001), (5432, 40002)]),
+        ];
+        assert_eq!(app_url("web", &machines).unwrap(), "http://localhost:40001");
+        
+        // Only non-HTTP ports published, or a machine without services on 8080
+        let tcp_only = [published(machine("m1", MachineState::Started, &[(5432, "tcp"), (3000, "http")]), &[(5432, 40002)])];
+        assert_eq!(app_url("web", &tcp_only).unwrap(), "http://localhost:40002");
+        let bare = [published(machine("m2", MachineState::Started, &[]), &[(8080, 40003)])];
+        assert_eq!(app_url("web", &bare).unwrap(), "http://localhost:40003");
+    }
+
+    #[test]
+    fn test_app_url_without_running_or_published_machines_fails() {
+        let stopped = [published(machine("m1", MachineState::Stopped, &[(3000, "http")]), &[(3000, 40000)])];
+        let err = app_url("web", &stopped).unwrap_err();
+        assert!(err.to_string().contains("no running machines"));
+        
+        let running = [machine("m1", MachineState::Started, &[(3000, "http")])];
+        let err = app_url("web", &running).unwrap_err();
+        assert!(err.to_string().contains("minifly proxy --app web"));
+    }
+}
@@ -0,0 +1,236 @@
+/// Local certificate authority for the gateway's TLS termination
+///
+/// The first `gateway --tls-port` run creates a CA under the config directory
+/// (`minifly/certs/ca.pem` and `ca-key.pem`). Importing `ca.pem` into a
+/// browser or the system trust store makes it accept the per-app
+/// certificates the gateway issues for `<app>.local` and `<app>.localhost`.
+/// App certificates only live in memory and are reissued on every run.
+///
+/// The CA can only sign certificates for names under `local` and
+/// `localhost`, and its key is readable by its owner only, so trusting it
+/// doesn't let anyone impersonate other sites.
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, Utc};
+use minifly_core::models::TlsOptions;
+use rcgen::{
+    BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, GeneralSubtree, IsCa,
+    KeyPair, KeyUsagePurpose, NameConstraints,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::ServerConfig;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const CA_NAME: &str = "Minifly Local CA";
+
+/// DNS names the CA may sign certificates for, with their subdomains
+const PERMITTED_DOMAINS: [&str; 2] = ["local", "localhost"];
+
+/// App certificates kept at once; the least recently used is dropped beyond this
+const MAX_ISSUED: usize = 64;
+
+/// DER encoding of the name constraints extension's OID, 2.5.29.30
+const NAME_CONSTRAINTS_OID: [u8; 5] = [0x06, 0x03, 0x55, 0x1d, 0x1e];
+
+/// An app's certificate chain and private key
+type AppCert = (Vec<CertificateDer<'static>>, Arc<PrivateKeyDer<'static>>);
+
+/// Where the CA is kept unless told otherwise
+pub fn default_dir() -> Result<PathBuf> {
+    let mut dir = dirs::config_dir().context("Failed to determine config directory")?;
+    dir.push("minifly");
+    dir.push("certs");
+    Ok(dir)
+}
+
+/// The CA that signs app certificates
+pub struct LocalCa {
+    cert: rcgen::Certificate,
+    key: KeyPair,
+    /// Recently issued app certificates, most recently used last
+    issued: Mutex<Vec<(String, AppCert)>>,
+}
+
+impl LocalCa {
+    /// Loads the CA from `dir`, creating it on first use.
+    ///
+    /// A CA created before it was name constrained is replaced, since it
+    /// could sign certificates for any site.
+    pub fn load_or_create(dir: &Path) -> Result<Self> {
+        let (cert_path, key_path) = (dir.join("ca.pem"), dir.join("ca-key.pem"));
+
+        let key = if key_path.exists() && is_name_constrained(&cert_path) {
+            restrict_to_owner(&key_path)
+                .with_context(|| format!("Failed to restrict permissions of {}", key_path.display()))?;
+            let key_pem = std::fs::read_to_string(&key_path)
+                .with_context(|| format!("Failed to read {}", key_path.display()))?;
+            KeyPair::from_pem(&key_pem)
+                .with_context(|| format!("Invalid CA key in {}", key_path.display()))?
+        } else {
+            if cert_path.exists() {
+                tracing::warn!("Replacing the unconstrained local CA in {}; import the new ca.pem", dir.display());
+            }
+            let key = KeyPair::generate()?;
+            let pem = ca_params().self_signed(&key)?.pem();
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            write_private(&key_path, &key.serialize_pem())
+                .with_context(|| format!("Failed to write {}", key_path.display()))?;
+            std::fs::write(&cert_path, pem)
+                .with_context(|| format!("Failed to write {}", cert_path.display()))?;
+            key
+        };
+
+        // Signing only needs the CA's name and key, so re-signing the same
+        // parameters stands in for the certificate on disk
+        let cert = ca_params().self_signed(&key)?;
+        Ok(Self { cert, key, issued: Mutex::default() })
+    }
+
+    /// The certificate chain and key for `app`, issued on first use
+    fn app_cert(&self, app: &str) -> Result<AppCert> {
+        {
+            let mut issued = self.issued.lock().unwrap();
+            if let Some(index) = issued.iter().position(|(name, _)| name == app) {
+                let entry = issued.remove(index);
+                let cert = entry.1.clone();
+                issued.push(entry);
+                return Ok(cert);
+            }
+        }
+
+        let mut params = CertificateParams::new(vec![format!("{}.local", app), format!("{}.localhost", app)])?;
+        params.distinguished_name = DistinguishedName::new();
+        params.distinguished_name.push(DnType::CommonName, format!("{}.local", app));
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        // Browsers refuse server certificates valid for more than 398 days
+        let (from, until) = (Utc::now() - Duration::days(1), Utc::now() + Duration::days(365));
+        params.not_before = rcgen::date_time_ymd(from.year(), from.month() as u8, from.day() as u8);
+        params.not_after = rcgen::date_time_ymd(until.year(), until.month() as u8, until.day() as u8);
+
+        let key = KeyPair::generate()?;
+        let cert = params.signed_by(&key, &self.cert, &self.key)?;
+        let chain = vec![cert.der().clone()];
+        let key = Arc::new(PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())));
+
+        let mut issued = self.issued.lock().unwrap();
+        if issued.len() >= MAX_ISSUED {
+            issued.remove(0);
+        }
+        issued.push((app.to_string(), (chain.clone(), key.clone())));
+        Ok((chain, key))
+    }
+
+    /// A server config presenting `app`'s certificate, restricted to the
+    /// ALPN protocols and TLS versions of the service port's `tls_options`
+    pub fn server_config(&self, app: &str, options: Option<&TlsOptions>) -> Result<Arc<ServerConfig>> {
+        let (chain, key) = self.app_cert(app)?;
+
+        let mut versions: Vec<&'static rustls::SupportedProtocolVersion> = Vec::new();
+        for version in options.map_or(&[][..], |options| &options.versions) {
+            match version.as_str() {
+                "TLSv1.2" => versions.push(&rustls::version::TLS12),
+                "TLSv1.3" => versions.push(&rustls::version::TLS13),
+                other => tracing::warn!("Ignoring unsupported TLS version {}", other),
+            }
+        }
+        if versions.is_empty() {
+            versions = rustls::ALL_VERSIONS.to_vec();
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_protocol_versions(&versions)?
+            .with_no_client_auth()
+            .with_single_cert(chain, key.clone_key())?;
+        if let Some(options) = options {
+            config.alpn_protocols = options.alpn.iter().map(|protocol| protocol.as_bytes().to_vec()).collect();
+        }
+        Ok(Arc::new(config))
+    }
+}
+
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, CA_NAME);
+    params.distinguished_name.push(DnType::OrganizationName, "Minifly");
+    // App certificates are signed directly, never by intermediates
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: PERMITTED_DOMAINS.iter().map(|domain| GeneralSubtree::DnsName(domain.to_string())).collect(),
+        excluded_subtrees: Vec::new(),
+    });
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign, KeyUsagePurpose::DigitalSignature];
+    params
+}
+
+/// Whether the CA certificate at `path` carries name constraints
+fn is_name_constrained(path: &Path) -> bool {
+    CertificateDer::from_pem_file(path)
+        .is_ok_and(|cert| cert.windows(NAME_CONSTRAINTS_OID.len()).any(|bytes| bytes == NAME_CONSTRAINTS_OID))
+}
+
+/// Writes a file only its owner can read
+pub(crate) fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    // An existing file would keep its permissions
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Makes an existing file readable by its owner only
+fn restrict_to_owner(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if std::fs::metadata(path)?.permissions().mode() & 0o077 != 0 {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ca_is_constrained_and_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = LocalCa::load_or_create(dir.path()).unwrap();
+
+        assert!(is_name_constrained(&dir.path().join("ca.pem")));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path().join("ca-key.pem")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        for n in 0..MAX_ISSUED + 10 {
+            ca.app_cert(&format!("app{}", n)).unwrap();
+        }
+        let issued = ca.issued.lock().unwrap();
+        assert_eq!(issued.len(), MAX_ISSUED);
+        assert_eq!(issued.last().unwrap().0, format!("app{}", MAX_ISSUED + 9));
+        drop(issued);
+
+        // Reloading keeps the CA
+        let pem = std::fs::read(dir.path().join("ca.pem")).unwrap();
+        LocalCa::load_or_create(dir.path()).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("ca.pem")).unwrap(), pem);
+    }
+}
@@ -0,0 +1,157 @@
+/// Reports the connections `proxy` and the gateway hold open to machines
+///
+/// The API stops started machines whose services set `autostop` once they
+/// have gone without connections for the service's idle period. Connections
+/// are counted here as they open and close, and the counts are reported in
+/// the background: each report leases them for `CONNECTION_LEASE_SECS`, so
+/// the connections of a proxy that exits stop counting once its lease runs
+/// out.
+use crate::client::ApiClient;
+use minifly_core::models::CONNECTION_LEASE_SECS;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+
+/// How often open connections are reported, well within their lease
+const REPORT_INTERVAL: Duration = Duration::from_secs(CONNECTION_LEASE_SECS / 3);
+
+/// Connections to one machine, keyed by app and machine ID
+type Counters = Mutex<HashMap<(String, String), Counter>>;
+
+#[derive(Debug, Default)]
+struct Counter {
+    open: u32,
+    /// Whether a connection opened or closed since the last report
+    changed: bool,
+}
+
+/// Counts the connections this process holds open to machines and reports
+/// them to the API every [`REPORT_INTERVAL`]
+#[derive(Clone)]
+pub struct ConnectionTracker {
+    counters: Arc<Counters>,
+}
+
+impl ConnectionTracker {
+    /// Starts reporting in the background until the tracker and its
+    /// connections are dropped
+    pub fn spawn(client: &ApiClient) -> Self {
+        Self::spawn_with_interval(client, REPORT_INTERVAL)
+    }
+
+    fn spawn_with_interval(client: &ApiClient, interval: Duration) -> Self {
+        let counters = Arc::new(Counters::default());
+        let weak = Arc::downgrade(&counters);
+        let client = client.clone();
+        let client_id = uuid::Uuid::new_v4().to_string();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(counters) = weak.upgrade() else { break };
+                let due = due_reports(&counters);
+                drop(counters);
+
+                for ((app, machine_id), open) in due {
+                    if let Err(e) = client.report_connections(&app, &machine_id, &client_id, open).await {
+                        debug!(machine_id = %machine_id, "Failed to report connections: {}", e);
+                    }
+                }
+            }
+        });
+        Self { counters }
+    }
+
+    /// Counts a connection to `machine_id` as open until the returned guard
+    /// is dropped
+    pub fn open(&self, app: &str, machine_id: &str) -> Connection {
+        let key = (app.to_string(), machine_id.to_string());
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry(key.clone()).or_default();
+        counter.open += 1;
+        counter.changed = true;
+        Connection { counters: self.counters.clone(), key }
+    }
+}
+
+/// The counts to report: every machine with open connections, renewing
+/// their lease, and every machine whose last connection closed since the
+/// previous report
+fn due_reports(counters: &Counters) -> Vec<((String, String), u32)> {
+    let mut counters = counters.lock().unwrap();
+    let due = counters.iter_mut()
+        .filter(|(_, counter)| counter.open > 0 || counter.changed)
+        .map(|(key, counter)| {
+            counter.changed = false;
+            (key.clone(), counter.open)
+        })
+        .collect();
+    counters.retain(|_, counter| counter.open > 0);
+    due
+}
+
+/// A proxied connection to a machine, counted as open until dropped
+pub struct Connection {
+    counters: Arc<Counters>,
+    key: (String, String),
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry(self.key.clone()).or_default();
+        counter.open = counter.open.saturating_sub(1);
+        counter.changed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_reports_are_coalesced() {
+        let counters = Arc::new(Counters::default());
+        let tracker = ConnectionTracker { counters: counters.clone() };
+        let key = ("web".to_string(), "d8e7f6".to_string());
+
+        let connections: Vec<_> = (0..3).map(|_| tracker.open("web", "d8e7f6")).collect();
+        assert_eq!(due_reports(&counters), vec![(key.clone(), 3)]);
+        // Open connections are reported again to renew their lease
+        assert_eq!(due_reports(&counters), vec![(key.clone(), 3)]);
+
+        drop(connections);
+        assert_eq!(due_reports(&counters), vec![(key, 0)]);
+        assert!(due_reports(&counters).is_empty());
+
+        // A connection that opened and closed between reports still counts
+        drop(tracker.open("web", "d8e7f6"));
+        assert_eq!(due_reports(&counters).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_reported_in_the_background() {
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/apps/web/machines/d8e7f6/connections"))
+            .and(body_partial_json(serde_json::json!({ "active": 2 })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "active": 2 })))
+            .expect(1..)
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+
+        let tracker = ConnectionTracker::spawn_with_interval(&client, Duration::from_millis(10));
+        let _connections = [tracker.open("web", "d8e7f6"), tracker.open("web", "d8e7f6")];
+        for _ in 0..50 {
+            if !api.received_requests().await.unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Mock expectations are verified when the server drops
+    }
+}
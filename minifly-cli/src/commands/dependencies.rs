@@ -1,12 +1,27 @@
 //! Service dependency management for Minifly platform
+//!
+//! Docker, SQLite and the file system are always checked. Consul and MinIO
+//! are checked, as optional dependencies, when the project in the current
+//! directory uses them. More checks can be added with
+//! [`DependencyManager::with_dependency`].
 
 use anyhow::{Result, Context};
 use colored::*;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use crate::client::ApiClient;
 
+/// Consul agent LiteFS uses for its lease, unless `FLY_CONSUL_URL` says otherwise
+const DEFAULT_CONSUL_URL: &str = "http://localhost:8500";
+
+/// MinIO server Tigris and S3 endpoints are translated to
+const MINIO_URL: &str = "http://localhost:9000";
+
 /// Service dependency information
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ServiceDependency {
     pub name: String,
     pub check_fn: CheckFunction,
@@ -15,8 +30,33 @@ pub struct ServiceDependency {
     pub retry_count: u32,
 }
 
+impl ServiceDependency {
+    /// A dependency the platform can't start without
+    pub fn required(name: impl Into<String>, check_fn: CheckFunction) -> Self {
+        Self {
+            name: name.into(),
+            check_fn,
+            required: true,
+            timeout_seconds: 5,
+            retry_count: 2,
+        }
+    }
+
+    /// A dependency whose absence only degrades the platform
+    pub fn optional(name: impl Into<String>, check_fn: CheckFunction) -> Self {
+        Self {
+            required: false,
+            retry_count: 1,
+            ..Self::required(name, check_fn)
+        }
+    }
+}
+
+/// Future resolving to whether a service is available
+pub type CheckFuture = Pin<Box<dyn Future<Output = Result<bool>> + Send>>;
+
 /// Function type for service checks
-pub type CheckFunction = fn() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool>> + Send>>;
+pub type CheckFunction = Arc<dyn Fn() -> CheckFuture + Send + Sync>;
 
 /// Dependency check results
 #[derive(Debug)]
@@ -27,37 +67,74 @@ pub struct DependencyCheckResult {
     pub error: Option<String>,
 }
 
+/// Whether the platform can start, given the dependency check results
+#[derive(Debug, PartialEq, Eq)]
+pub enum DependencyStatus {
+    /// Every dependency is available
+    Ready,
+    /// Only these optional dependencies are unavailable
+    Degraded(Vec<String>),
+    /// These required dependencies are unavailable
+    Missing(Vec<String>),
+}
+
 /// Service dependency manager
 pub struct DependencyManager {
     dependencies: Vec<ServiceDependency>,
 }
 
 impl DependencyManager {
+    /// The built-in checks, plus Consul and MinIO when the project in the
+    /// current directory uses them
     pub fn new() -> Self {
-        Self {
-            dependencies: vec![
-                ServiceDependency {
-                    name: "Docker".to_string(),
-                    check_fn: check_docker,
-                    required: true,
-                    timeout_seconds: 10,
-                    retry_count: 3,
-                },
-                ServiceDependency {
-                    name: "SQLite".to_string(),
-                    check_fn: check_sqlite,
-                    required: true,
-                    timeout_seconds: 5,
-                    retry_count: 2,
-                },
-                ServiceDependency {
-                    name: "File System".to_string(),
-                    check_fn: check_filesystem,
-                    required: true,
-                    timeout_seconds: 5,
-                    retry_count: 1,
-                },
-            ],
+        let mut manager = Self::with_dependencies(vec![
+            ServiceDependency {
+                timeout_seconds: 10,
+                retry_count: 3,
+                ..ServiceDependency::required("Docker", Arc::new(check_docker))
+            },
+            ServiceDependency::required("SQLite", Arc::new(check_sqlite)),
+            ServiceDependency {
+                retry_count: 1,
+                ..ServiceDependency::required("File System", Arc::new(check_filesystem))
+            },
+        ]);
+
+        let project_dir = Path::new(".");
+        if uses_consul(project_dir) {
+            let consul_url = std::env::var("FLY_CONSUL_URL").unwrap_or_else(|_| DEFAULT_CONSUL_URL.to_string());
+            manager = manager.with_dependency(ServiceDependency::optional("Consul", http_check(consul_url)));
+        }
+        if uses_object_storage(project_dir) {
+            manager = manager.with_dependency(ServiceDependency::optional("MinIO", http_check(MINIO_URL)));
+        }
+        manager
+    }
+
+    /// A manager checking exactly `dependencies`
+    pub fn with_dependencies(dependencies: Vec<ServiceDependency>) -> Self {
+        Self { dependencies }
+    }
+
+    /// Adds a dependency to check
+    pub fn with_dependency(mut self, dependency: ServiceDependency) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// Decides whether the platform can start with these check results
+    pub fn evaluate(&self, results: &[DependencyCheckResult]) -> DependencyStatus {
+        let (missing, degraded): (Vec<_>, Vec<_>) = results.iter()
+            .filter(|r| !r.available)
+            .map(|r| r.service.clone())
+            .partition(|service| self.is_required(service));
+
+        if !missing.is_empty() {
+            DependencyStatus::Missing(missing)
+        } else if !degraded.is_empty() {
+            DependencyStatus::Degraded(degraded)
+        } else {
+            DependencyStatus::Ready
         }
     }
     
@@ -154,16 +231,7 @@ impl DependencyManager {
         loop {
             let results = self.check_all_dependencies().await;
             
-            let required_deps_available = results.iter()
-                .filter(|r| {
-                    self.dependencies.iter()
-                        .find(|d| d.name == r.service)
-                        .map(|d| d.required)
-                        .unwrap_or(false)
-                })
-                .all(|r| r.available);
-            
-            if required_deps_available {
+            if !matches!(self.evaluate(&results), DependencyStatus::Missing(_)) {
                 println!("{}", "✅ All required dependencies are available!".green());
                 return Ok(());
             }
@@ -196,7 +264,7 @@ impl DependencyManager {
 }
 
 /// Check if Docker is available
-fn check_docker() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool>> + Send>> {
+fn check_docker() -> CheckFuture {
     Box::pin(async move {
         match tokio::process::Command::new("docker")
             .args(&["version", "--format", "{{.Server.Version}}"])
@@ -210,7 +278,7 @@ fn check_docker() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<b
 }
 
 /// Check if SQLite is available
-fn check_sqlite() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool>> + Send>> {
+fn check_sqlite() -> CheckFuture {
     Box::pin(async move {
         // Check if SQLite CLI is available
         match tokio::process::Command::new("sqlite3")
@@ -225,7 +293,7 @@ fn check_sqlite() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<b
 }
 
 /// Check if file system is accessible
-fn check_filesystem() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool>> + Send>> {
+fn check_filesystem() -> CheckFuture {
     Box::pin(async move {
         // Test creating and removing a temporary file
         let test_file = std::env::temp_dir().join("minifly_test.tmp");
@@ -242,6 +310,38 @@ fn check_filesystem() -> std::pin::Pin<Box<dyn std::future::Future<Output = Resu
     })
 }
 
+/// Checks that an HTTP server answers at `url`, whatever the status
+pub fn http_check(url: impl Into<String>) -> CheckFunction {
+    let url = url.into();
+    Arc::new(move || {
+        let url = url.clone();
+        Box::pin(async move {
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()?;
+            client.get(&url).send().await
+                .with_context(|| format!("No response from {}", url))?;
+            Ok(true)
+        })
+    })
+}
+
+/// Whether the project's LiteFS config takes its lease from Consul
+fn uses_consul(project_dir: &Path) -> bool {
+    ["litefs.yml", "litefs.yaml"].iter()
+        .filter_map(|name| std::fs::read_to_string(project_dir.join(name)).ok())
+        .any(|config| config.contains("consul"))
+}
+
+/// Whether the project talks to Tigris or another S3 endpoint, which minifly
+/// points at MinIO
+fn uses_object_storage(project_dir: &Path) -> bool {
+    const MARKERS: [&str; 3] = ["TIGRIS_", "AWS_ENDPOINT_URL", "BUCKET_NAME"];
+    ["fly.toml", ".fly.secrets"].iter()
+        .filter_map(|name| std::fs::read_to_string(project_dir.join(name)).ok())
+        .any(|contents| MARKERS.iter().any(|marker| contents.contains(marker)))
+}
+
 /// Startup orchestration for the platform
 pub async fn startup_with_dependencies(api_client: &ApiClient, port: u16) -> Result<()> {
     println!("{}", "🚀 Starting Minifly Platform with dependency checks...".blue().bold());
@@ -250,49 +350,16 @@ pub async fn startup_with_dependencies(api_client: &ApiClient, port: u16) -> Res
     
     // Check initial dependencies
     let results = dep_manager.check_all_dependencies().await;
-    let failed_deps: Vec<_> = results.iter()
-        .filter(|r| !r.available)
-        .collect();
-    
-    if !failed_deps.is_empty() {
-        println!("\n{}", "⚠️  Some dependencies are not available:".yellow().bold());
-        for dep in &failed_deps {
-            println!("  • {}: {}", dep.service.red(), 
-                    dep.error.as_ref().unwrap_or(&"Unknown error".to_string()).dimmed());
-        }
-        
-        // Check if any required dependencies are missing
-        let required_missing = failed_deps.iter()
-            .any(|r| dep_manager.dependencies.iter()
-                .find(|d| d.name == r.service)
-                .map(|d| d.required)
-                .unwrap_or(false));
-        
-        if required_missing {
-            println!("\n{}", "❌ Cannot start platform: required dependencies are missing".red().bold());
-            println!("{}", "Please ensure Docker and SQLite are installed and available".yellow());
-            return Err(anyhow::anyhow!("Required dependencies not available"));
-        } else {
-            println!("\n{}", "⚠️  Continuing with degraded functionality...".yellow());
-        }
+    if !report_dependency_status(&dep_manager, &results) {
+        return Err(anyhow::anyhow!("Required dependencies not available"));
     }
     
     // Wait for API server to become available (if starting it)
     println!("\n{}", "🔧 Waiting for API server to start...".blue());
-    let api_start_time = Instant::now();
-    
-    loop {
-        if api_client.health_check().await.unwrap_or(false) {
-            println!("{}", "✅ API server is ready!".green());
-            break;
-        }
-        
-        if api_start_time.elapsed().as_secs() > 30 {
-            return Err(anyhow::anyhow!("Timeout waiting for API server to start"));
-        }
-        
-        tokio::time::sleep(Duration::from_millis(500)).await;
+    if !api_client.wait_for_health(Duration::from_secs(30)).await {
+        return Err(anyhow::anyhow!("Timeout waiting for API server to start"));
     }
+    println!("{}", "✅ API server is ready!".green());
     
     println!("\n{}", "🎉 Platform startup complete!".green().bold());
     println!("  • API server: {}", format!("http://localhost:{}", port).cyan());
@@ -301,10 +368,75 @@ pub async fn startup_with_dependencies(api_client: &ApiClient, port: u16) -> Res
     Ok(())
 }
 
+/// Prints what unavailable dependencies mean for startup, returning whether
+/// the platform can start
+pub fn report_dependency_status(manager: &DependencyManager, results: &[DependencyCheckResult]) -> bool {
+    let status = manager.evaluate(results);
+    if status == DependencyStatus::Ready {
+        return true;
+    }
+    
+    println!("\n{}", "⚠️  Some dependencies are not available:".yellow().bold());
+    for result in results.iter().filter(|r| !r.available) {
+        println!("  • {}: {}", result.service.red(),
+                result.error.as_deref().unwrap_or("Unknown error").dimmed());
+    }
+    
+    match status {
+        DependencyStatus::Missing(_) => {
+            println!("\n{}", "❌ Cannot start platform: required dependencies are missing".red().bold());
+            println!("{}", "Please ensure Docker and SQLite are installed and available".yellow());
+            false
+        }
+        _ => {
+            println!("\n{}", "⚠️  Continuing with degraded functionality...".yellow());
+            true
+        }
+    }
+}
+
 /// Quick dependency check for status commands
 pub async fn quick_dependency_check() -> (bool, Vec<String>) {
     let dep_manager = DependencyManager::new();
     let (total, available, failed) = dep_manager.get_dependency_summary().await;
     
     (total == available, failed)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(available: bool) -> CheckFunction {
+        Arc::new(move || Box::pin(async move { Ok(available) }))
+    }
+
+    #[tokio::test]
+    async fn test_optional_dependency_down_degrades() {
+        let manager = DependencyManager::with_dependencies(vec![
+            ServiceDependency::required("Docker", check(true)),
+        ])
+        .with_dependency(ServiceDependency::optional("MinIO", check(false)));
+
+        let results = manager.check_all_dependencies().await;
+        assert_eq!(manager.evaluate(&results), DependencyStatus::Degraded(vec!["MinIO".to_string()]));
+        assert!(report_dependency_status(&manager, &results), "startup continues");
+
+        let manager = manager.with_dependency(ServiceDependency::required("SQLite", check(false)));
+        let results = manager.check_all_dependencies().await;
+        assert_eq!(manager.evaluate(&results), DependencyStatus::Missing(vec!["SQLite".to_string()]));
+        assert!(!report_dependency_status(&manager, &results));
+    }
+
+    #[test]
+    fn test_optional_services_follow_project_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!uses_consul(dir.path()));
+        assert!(!uses_object_storage(dir.path()));
+
+        std::fs::write(dir.path().join("litefs.yml"), "lease:\n  type: \"consul\"\n").unwrap();
+        std::fs::write(dir.path().join("fly.toml"), "[env]\n  BUCKET_NAME = \"uploads\"\n").unwrap();
+        assert!(uses_consul(dir.path()));
+        assert!(uses_object_storage(dir.path()));
+    }
+}
@@ -4,10 +4,15 @@
 /// - File watching for automatic redeployment
 /// - Enhanced logging with real-time streaming
 /// - Hot reloading of configurations
+/// - Fast reloads that sync changed files into running machines instead of
+///   redeploying, when the image wouldn't change otherwise
+/// - Machine restarts instead of redeploys when only secrets files change
 /// - Development-specific debugging features
 use anyhow::{Context, Result};
 use colored::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::commands::redeploy::{is_secrets_file, Redeployer, WatchFilter};
 use notify::{Watcher, RecursiveMode, watcher};
 use std::sync::mpsc::channel;
 use std::time::Duration;
@@ -17,16 +22,18 @@ use std::time::Duration;
 /// # Arguments
 /// * `path` - Path to the project directory to watch
 /// * `port` - Port number for the API server
+/// * `config_path` - fly.toml to use instead of fly.dev.toml or fly.toml
+/// * `fast_reload` - Sync changes into running machines when no rebuild is needed
 /// 
 /// # Examples
 /// ```
 /// // Start development mode in current directory
-/// dev::handle(".", 4280).await?;
+/// dev::handle(".", 4280, None, false).await?;
 /// 
 /// // Start development mode in specific project directory
-/// dev::handle("./my-app", 4280).await?;
+/// dev::handle("./my-app", 4280, None, true).await?;
 /// ```
-pub async fn handle(path: &str, port: u16, config_path: Option<String>) -> Result<()> {
+pub async fn handle(path: &str, port: u16, config_path: Option<String>, fast_reload: bool) -> Result<()> {
     println!("{}", "🔧 Starting Minifly Development Mode".bold().cyan());
     println!("   Project: {}", path.yellow());
     println!("   Port: {}", port.to_string().yellow());
@@ -68,7 +75,7 @@ pub async fn handle(path: &str, port: u16, config_path: Option<String>) -> Resul
         println!("{}", "🚀 Starting Minifly platform...".cyan());
         
         // Start platform in development mode
-        crate::commands::serve::handle(true, port, true, config_path.clone()).await?;
+        crate::commands::serve::handle(true, port, true, config_path.clone(), None, false).await?;
         
         // Give it a moment to fully start
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -78,7 +85,7 @@ pub async fn handle(path: &str, port: u16, config_path: Option<String>) -> Resul
     
     // Setup file watcher for auto-deployment
     if fly_toml_path.exists() {
-        setup_file_watcher(path, port).await?;
+        setup_file_watcher(path, port, fast_reload).await?;
     }
     
     // Start log streaming for all machines in this project
@@ -105,9 +112,15 @@ pub async fn handle(path: &str, port: u16, config_path: Option<String>) -> Resul
 /// # Arguments
 /// * `path` - Project directory to watch
 /// * `port` - API server port
-async fn setup_file_watcher(path: &str, port: u16) -> Result<()> {
+/// * `fast_reload` - Sync changes into running machines when no rebuild is needed
+async fn setup_file_watcher(path: &str, port: u16, fast_reload: bool) -> Result<()> {
     println!("{}", "👀 Setting up file watcher...".cyan());
     
+    let filter = WatchFilter::load(&dev_fly_toml(path))?;
+    // Deploys change the working directory, so remember where the project is
+    let project = Path::new(path).canonicalize()
+        .with_context(|| format!("Failed to find {}", path))?;
+    let changes: Arc<Mutex<Vec<PathBuf>>> = Arc::default();
     let (tx, rx) = channel();
     let mut watcher = watcher(tx, Duration::from_secs(1))?;
     
@@ -116,8 +129,48 @@ async fn setup_file_watcher(path: &str, port: u16) -> Result<()> {
     
     println!("   ✓ Watching for changes in {}", path.green());
     
+    let pending = changes.clone();
+    let redeployer = Redeployer::spawn(move || {
+        let project = project.clone();
+        let changed = std::mem::take(&mut *pending.lock().unwrap());
+        async move {
+            if changed.is_empty() {
+                return;
+            }
+            if only_secrets_changed(&project, &changed) {
+                println!("\n{}", "🔐 Secrets changed, restarting machines...".yellow());
+                match restart_with_secrets(&project, port).await {
+                    Ok(0) => println!("{}", "No running machines to restart".dimmed()),
+                    Ok(restarted) => println!("{}", format!("✅ Restarted {} machine(s) with the new secrets", restarted).green()),
+                    Err(e) => eprintln!("{}", format!("❌ Restart failed: {}", e).red()),
+                }
+                println!("{}", "👀 Watching for changes...".dimmed());
+                return;
+            }
+            if fast_reload {
+                match fast_reload_project(&project, &changed).await {
+                    Ok(true) => {
+                        println!("{}", "👀 Watching for changes...".dimmed());
+                        return;
+                    }
+                    Ok(false) => {}
+                    Err(e) => println!("{}", format!("⚠️  Fast reload failed, redeploying instead: {}", e).yellow()),
+                }
+            }
+            
+            println!("\n{}", "🔄 File change detected, redeploying...".yellow());
+            
+            if let Err(e) = redeploy_project(&project.to_string_lossy(), port).await {
+                eprintln!("{}", format!("❌ Redeploy failed: {}", e).red());
+            } else {
+                println!("{}", "✅ Redeploy completed".green());
+            }
+            
+            println!("{}", "👀 Watching for changes...".dimmed());
+        }
+    });
+    
     // Spawn a task to handle file change events
-    let path_clone = path.to_string();
     tokio::spawn(async move {
         use notify::DebouncedEvent;
         
@@ -125,24 +178,9 @@ async fn setup_file_watcher(path: &str, port: u16) -> Result<()> {
             match rx.recv() {
                 Ok(event) => {
                     match event {
-                        DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => {
-                            if let Some(filename) = path.file_name() {
-                                if filename == "fly.toml" || 
-                                   filename == "Dockerfile" ||
-                                   filename == "litefs.yml" {
-                                    
-                                    println!("\n{}", "🔄 File change detected, redeploying...".yellow());
-                                    
-                                    // Trigger redeploy
-                                    if let Err(e) = redeploy_project(&path_clone, port).await {
-                                        eprintln!("{}", format!("❌ Redeploy failed: {}", e).red());
-                                    } else {
-                                        println!("{}", "✅ Redeploy completed".green());
-                                    }
-                                    
-                                    println!("{}", "👀 Watching for changes...".dimmed());
-                                }
-                            }
+                        DebouncedEvent::Write(path) | DebouncedEvent::Create(path) if filter.should_redeploy(&path) => {
+                            changes.lock().unwrap().push(path);
+                            redeployer.changed();
                         }
                         _ => {}
                     }
@@ -158,36 +196,226 @@ async fn setup_file_watcher(path: &str, port: u16) -> Result<()> {
     Ok(())
 }
 
+/// The fly.toml dev mode deploys, preferring fly.dev.toml
+fn dev_fly_toml(path: &str) -> std::path::PathBuf {
+    let project_path = Path::new(path);
+    let dev_path = project_path.join("fly.dev.toml");
+    if dev_path.exists() {
+        dev_path
+    } else {
+        project_path.join("fly.toml")
+    }
+}
+
 /// Redeploy the project when files change
 /// 
 /// # Arguments
 /// * `path` - Project directory path
 /// * `port` - API server port
 async fn redeploy_project(path: &str, port: u16) -> Result<()> {
-    // In dev mode, prefer fly.dev.toml
-    let project_path = Path::new(path);
-    let fly_toml_path = {
-        let dev_path = project_path.join("fly.dev.toml");
-        if dev_path.exists() {
-            dev_path
-        } else {
-            project_path.join("fly.toml")
-        }
-    };
+    let fly_toml_path = dev_fly_toml(path);
     
     if fly_toml_path.exists() {
         // Use our deploy command
         let client = crate::client::ApiClient::new(&crate::config::Config {
             api_url: format!("http://localhost:{}", port),
             token: None,
+            ..Default::default()
         })?;
         
         // Set FLY_ENV to dev for the deployment
         std::env::set_var("FLY_ENV", "dev");
         
-        crate::commands::deploy::handle(&client, Some(fly_toml_path.to_string_lossy().to_string()), None, false).await?;
+        crate::commands::deploy::handle(&client, Some(fly_toml_path.to_string_lossy().to_string()), None, None, false, false).await?;
+    }
+    
+    Ok(())
+}
+
+/// Whether a batch of changes only touches secrets files. Those take effect
+/// by restarting the app's machines with a new environment, so they never
+/// need a rebuild.
+fn only_secrets_changed(project: &Path, changed: &[PathBuf]) -> bool {
+    changed.iter().all(|path| path.strip_prefix(project).is_ok_and(is_secrets_file))
+}
+
+/// Restarts the project's running machines with its current secrets
+/// 
+/// # Arguments
+/// * `project` - Project directory path
+/// * `port` - API server port
+async fn restart_with_secrets(project: &Path, port: u16) -> Result<usize> {
+    let fly_toml: toml::Value = toml::from_str(&std::fs::read_to_string(dev_fly_toml(&project.to_string_lossy()))?)?;
+    let app = fly_toml.get("app").and_then(|a| a.as_str()).context("fly.toml has no app name")?;
+    let client = crate::client::ApiClient::new(&crate::config::Config {
+        api_url: format!("http://localhost:{}", port),
+        token: None,
+        ..Default::default()
+    })?;
+    
+    // Read the same secrets files a dev redeploy would
+    std::env::set_var("FLY_ENV", "dev");
+    
+    crate::commands::secrets::restart_with_secrets(&client, project, app).await
+}
+
+/// How a batch of file changes reaches the app's machines with `--fast-reload`
+#[derive(Debug, PartialEq)]
+enum Reload {
+    /// Rebuild the image and redeploy
+    Redeploy,
+    /// Copy the changed files into the running containers under `dest`, where
+    /// the Dockerfile copies the build context, and restart them
+    Sync { dest: String },
+}
+
+/// Files whose changes need a redeploy however the image is built
+fn is_deploy_config(relative: &Path) -> bool {
+    let name = relative.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    name.starts_with("Dockerfile")
+        || name == ".dockerignore"
+        || name.starts_with(".fly.secrets")
+        || (name.starts_with("fly.") && name.ends_with(".toml"))
+        || (name.starts_with("litefs.") && name.ends_with(".yml"))
+}
+
+/// The instructions of a Dockerfile with their arguments, continuation lines joined
+fn dockerfile_instructions(dockerfile: &str) -> Vec<(String, Vec<String>)> {
+    let mut instructions = Vec::new();
+    let mut current = String::new();
+    for line in dockerfile.lines() {
+        let line = line.trim();
+        if current.is_empty() && (line.is_empty() || line.starts_with('#')) {
+            continue;
+        }
+        match line.strip_suffix('\\') {
+            Some(continued) => current.push_str(continued),
+            None => {
+                current.push_str(line);
+                let mut words = current.split_whitespace();
+                if let Some(instruction) = words.next() {
+                    let rest: Vec<&str> = words.collect();
+                    // The exec form, e.g. `COPY [".", "/app"]`
+                    let args = serde_json::from_str::<Vec<String>>(&rest.join(" "))
+                        .unwrap_or_else(|_| rest.iter().map(|w| w.to_string()).collect());
+                    instructions.push((instruction.to_uppercase(), args));
+                }
+                current.clear();
+            }
+        }
+    }
+    instructions
+}
+
+/// Decides whether changes can be synced into running machines: only when the
+/// final build stage copies the whole build context and runs nothing after
+/// that, and the changed files aren't copied on their own before it (such as
+/// dependency manifests that an install step reads)
+fn reload_plan(changed: &[PathBuf], dockerfile: &str) -> Reload {
+    let instructions = dockerfile_instructions(dockerfile);
+    let is_context_copy = |instruction: &str, args: &[String]| {
+        (instruction == "COPY" || instruction == "ADD")
+            && !args.iter().any(|a| a.starts_with("--from"))
+            && args.iter().filter(|a| !a.starts_with("--")).count() == 2
+            && matches!(args.iter().find(|a| !a.starts_with("--")).map(String::as_str), Some("." | "./"))
+    };
+    
+    let final_stage = instructions.iter().rposition(|(instruction, _)| instruction == "FROM").unwrap_or(0);
+    // Earlier stages built from the context could feed anything into the image
+    if instructions[..final_stage].iter().any(|(instruction, args)| is_context_copy(instruction, args)) {
+        return Reload::Redeploy;
+    }
+    
+    let mut workdir = "/".to_string();
+    let mut dest = None;
+    let mut inputs = Vec::new();
+    for (instruction, args) in &instructions[final_stage..] {
+        let paths: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+        match instruction.as_str() {
+            "WORKDIR" if dest.is_none() => {
+                if let Some(dir) = paths.first() {
+                    workdir = Path::new(&workdir).join(dir).to_string_lossy().to_string();
+                }
+            }
+            "COPY" | "ADD" if dest.is_none() && !args.iter().any(|a| a.starts_with("--from")) => {
+                if is_context_copy(instruction, args) {
+                    let target = paths.last().map_or(".", |p| p.as_str());
+                    let joined = Path::new(&workdir).join(target).to_string_lossy().to_string();
+                    let trimmed = joined.strip_suffix("/.").unwrap_or(&joined).trim_end_matches('/');
+                    dest = Some(if trimmed.is_empty() { "/".to_string() } else { trimmed.to_string() });
+                } else if let Some((_, sources)) = paths.split_last() {
+                    inputs.extend(sources.iter().map(|s| s.trim_start_matches("./").trim_end_matches('/').to_string()));
+                }
+            }
+            // Build steps after the copy may depend on any file
+            "RUN" if dest.is_some() => return Reload::Redeploy,
+            _ => {}
+        }
+    }
+    let Some(dest) = dest else {
+        return Reload::Redeploy;
+    };
+    
+    let is_input = |relative: &Path| inputs.iter().any(|input| relative.starts_with(input));
+    if changed.iter().any(|relative| is_deploy_config(relative) || is_input(relative)) {
+        return Reload::Redeploy;
+    }
+    Reload::Sync { dest }
+}
+
+/// Syncs changed files into the app's running containers and restarts them,
+/// returning false if the changes need a redeploy instead
+async fn fast_reload_project(project: &Path, changed: &[PathBuf]) -> Result<bool> {
+    let fly_toml: toml::Value = toml::from_str(&std::fs::read_to_string(dev_fly_toml(&project.to_string_lossy()))?)?;
+    let app = fly_toml.get("app").and_then(|a| a.as_str()).context("fly.toml has no app name")?;
+    let build = fly_toml.get("build");
+    if build.and_then(|b| b.get("image")).is_some() {
+        return Ok(false);
+    }
+    let dockerfile = build.and_then(|b| b.get("dockerfile")).and_then(|d| d.as_str()).unwrap_or("Dockerfile");
+    let Ok(dockerfile) = std::fs::read_to_string(project.join(dockerfile)) else {
+        return Ok(false);
+    };
+    
+    let relative: Vec<PathBuf> = changed.iter()
+        .filter_map(|path| path.strip_prefix(project).ok().map(Path::to_path_buf))
+        .collect();
+    let Reload::Sync { dest } = reload_plan(&relative, &dockerfile) else {
+        return Ok(false);
+    };
+    
+    let output = tokio::process::Command::new("docker")
+        .args(["ps", "--filter", &format!("label=minifly.app_name={}", app), "--format", "{{.Names}}"])
+        .output()
+        .await
+        .context("Failed to run docker")?;
+    let containers: Vec<String> = String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect();
+    if containers.is_empty() {
+        return Ok(false);
     }
     
+    println!("\n{}", format!("⚡ Syncing {} changed file(s) into {}...", relative.len(), app).yellow());
+    for container in &containers {
+        for file in &relative {
+            let target = format!("{}:{}/{}", container, dest.trim_end_matches('/'), file.display());
+            docker(&["cp", &project.join(file).to_string_lossy(), &target]).await?;
+        }
+        docker(&["restart", container]).await?;
+    }
+    println!("{}", format!("✅ Restarted {} machine(s) with the changes", containers.len()).green());
+    Ok(true)
+}
+
+/// Runs a docker command, failing with its error output
+async fn docker(args: &[&str]) -> Result<()> {
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run docker")?;
+    if !output.status.success() {
+        anyhow::bail!("docker {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
     Ok(())
 }
 
@@ -209,4 +437,55 @@ async fn start_log_streaming(_path: &str, _port: u16) -> Result<()> {
     println!("   ✓ {}", "Log streaming ready (implementation pending)".green());
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(files: &[&str]) -> Vec<PathBuf> {
+        files.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_interpreted_apps_sync_source_changes() {
+        let dockerfile = "FROM python:3.12-slim\nWORKDIR /app\nCOPY requirements.txt .\nRUN pip install -r requirements.txt\nCOPY . .\nCMD [\"python\", \"app.py\"]\n";
+        
+        assert_eq!(reload_plan(&changed(&["app.py", "static/site.css"]), dockerfile), Reload::Sync { dest: "/app".to_string() });
+        assert_eq!(reload_plan(&changed(&["app.py", "requirements.txt"]), dockerfile), Reload::Redeploy, "dependencies are installed at build time");
+        assert_eq!(reload_plan(&changed(&["fly.toml"]), dockerfile), Reload::Redeploy);
+        assert_eq!(reload_plan(&changed(&["Dockerfile"]), dockerfile), Reload::Redeploy);
+        
+        let exec_form = "FROM node:20\nCOPY [\".\", \"/srv/\"]\nCMD node /srv/index.js\n";
+        assert_eq!(reload_plan(&changed(&["index.js"]), exec_form), Reload::Sync { dest: "/srv".to_string() });
+    }
+
+    #[test]
+    fn test_secrets_changes_restart_instead_of_rebuilding() {
+        let project = Path::new("/src/web");
+        let absolute = |files: &[&str]| files.iter().map(|file| project.join(file)).collect::<Vec<_>>();
+        
+        assert!(only_secrets_changed(project, &absolute(&[".fly.secrets"])));
+        assert!(only_secrets_changed(project, &absolute(&[".fly.secrets.web", ".fly.secrets.web.dev"])));
+        assert!(!only_secrets_changed(project, &absolute(&[".fly.secrets.web", "app.py"])), "code changes still rebuild");
+        assert!(!only_secrets_changed(project, &absolute(&["config/.fly.secrets"])), "only the project's own secrets files");
+        
+        // Mixed with code changes, secrets still need a redeploy rather than a sync
+        let dockerfile = "FROM python:3.12-slim\nWORKDIR /app\nCOPY . .\n";
+        assert_eq!(reload_plan(&changed(&["app.py", ".fly.secrets.web"]), dockerfile), Reload::Redeploy);
+    }
+
+    #[test]
+    fn test_builds_from_source_redeploy() {
+        // The source is compiled after it's copied
+        let compiled = "FROM rust:1.80\nWORKDIR /app\nCOPY . .\nRUN cargo build --release\nCMD [\"./target/release/app\"]\n";
+        assert_eq!(reload_plan(&changed(&["src/main.rs"]), compiled), Reload::Redeploy);
+        
+        // Only build output reaches the final image
+        let multi_stage = "FROM golang:1.22 AS build\nWORKDIR /src\nCOPY . .\nRUN go build -o /app\n\nFROM alpine\nCOPY --from=build /app /app\nCMD [\"/app\"]\n";
+        assert_eq!(reload_plan(&changed(&["main.go"]), multi_stage), Reload::Redeploy);
+        
+        let no_context_copy = "FROM nginx:alpine\nCOPY nginx.conf /etc/nginx/nginx.conf\n";
+        assert_eq!(reload_plan(&changed(&["index.html"]), no_context_copy), Reload::Redeploy);
+    }
+}
@@ -0,0 +1,289 @@
+/// Diagnostics for a local Minifly setup
+///
+/// `minifly doctor` runs the platform's dependency checks and looks for the
+/// usual causes of a broken setup: ports taken by other processes, lock files
+/// left behind by a crashed server, and minifly containers that no machine
+/// accounts for. The API adopts labeled containers when it starts, so orphans
+/// are containers of destroyed machines whose removal failed, or containers
+/// missing their machine labels. Every problem is printed with a suggested
+/// fix, and the command fails if any was found so CI can use it.
+use anyhow::{Context, Result};
+use colored::*;
+use minifly_core::models::MachineState;
+use std::collections::HashSet;
+use std::net::{TcpListener, UdpSocket};
+use std::path::Path;
+use std::process::Command;
+use crate::client::ApiClient;
+use crate::commands::dependencies::DependencyManager;
+
+/// Lock files a running platform holds, relative to the working directory
+pub(crate) const LOCK_FILES: [&str; 2] = ["data/.minifly.lock", "data/litefs/.lock"];
+
+/// Default DNS port of the API server
+const DNS_PORT: u16 = 5353;
+
+/// Default LiteFS port of the API server
+const LITEFS_PORT: u16 = 20202;
+
+/// A container labeled `minifly.managed=true`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManagedContainer {
+    pub name: String,
+    pub machine_id: Option<String>,
+    pub app_name: Option<String>,
+}
+
+/// Something broken, with how to fix it
+#[derive(Debug)]
+struct Problem {
+    summary: String,
+    fix: String,
+}
+
+impl Problem {
+    fn new(summary: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { summary: summary.into(), fix: fix.into() }
+    }
+}
+
+/// Handle `minifly doctor`
+///
+/// With `clean_orphans`, orphaned containers are force-removed instead of
+/// being reported. Fails when any problem remains.
+pub async fn handle(client: &ApiClient, api_url: &str, clean_orphans: bool) -> Result<()> {
+    println!("{}", "🩺 Checking Minifly setup".bold());
+    let mut problems = Vec::new();
+
+    let dependencies = DependencyManager::new();
+    for result in dependencies.check_all_dependencies().await {
+        if !result.available && dependencies.is_required(&result.service) {
+            problems.push(Problem::new(
+                format!("{} is not available", result.service),
+                dependency_fix(&result.service),
+            ));
+        }
+    }
+
+    let api_running = client.health_check().await.unwrap_or(false);
+    if api_running {
+        println!("{} API server is running at {}", "✓".green(), api_url);
+    } else {
+        println!("{} API server is not running", "ℹ".blue());
+        // A stopped platform should leave its ports free and no lock files behind
+        let api_port = reqwest::Url::parse(api_url).ok().and_then(|url| url.port_or_known_default()).unwrap_or(4280);
+        for (name, port, udp) in [("API", api_port, false), ("DNS", DNS_PORT, true), ("LiteFS", LITEFS_PORT, false)] {
+            if port_in_use(port, udp) {
+                problems.push(Problem::new(
+                    format!("{} port {} is in use by another process", name, port),
+                    format!("Stop the process using it (`lsof -i :{}`) or configure another port", port),
+                ));
+            }
+        }
+        for lock_file in LOCK_FILES {
+            if is_stale_lock(Path::new(lock_file), pid_alive) {
+                problems.push(Problem::new(
+                    format!("Stale lock file {}", lock_file),
+                    format!("Remove it with `rm {}`", lock_file),
+                ));
+            }
+        }
+    }
+
+    match list_managed_containers() {
+        Ok(containers) if api_running => {
+            let known = known_machines(client, &containers).await?;
+            let orphans = find_orphans(&containers, &known);
+            for orphan in orphans {
+                if clean_orphans && remove_container(&orphan.name) {
+                    println!("{} Removed orphaned container {}", "✓".green(), orphan.name);
+                    continue;
+                }
+                problems.push(Problem::new(
+                    format!(
+                        "Orphaned container {} (app: {}, machine: {})",
+                        orphan.name,
+                        orphan.app_name.as_deref().unwrap_or("-"),
+                        orphan.machine_id.as_deref().unwrap_or("-")
+                    ),
+                    "Run `minifly doctor --clean-orphans` to remove it",
+                ));
+            }
+        }
+        Ok(containers) if !containers.is_empty() => println!(
+            "{} {} minifly container(s) will be adopted when the API server starts",
+            "ℹ".blue(),
+            containers.len()
+        ),
+        Ok(_) => {}
+        // Docker being down is already reported by the dependency checks
+        Err(_) => {}
+    }
+
+    if problems.is_empty() {
+        println!("\n{}", "✅ No problems found".green().bold());
+        return Ok(());
+    }
+
+    println!("\n{}", format!("Found {} problem(s):", problems.len()).red().bold());
+    for problem in &problems {
+        println!("  {} {}", "✗".red(), problem.summary);
+        println!("    {} {}", "→".dimmed(), problem.fix);
+    }
+    anyhow::bail!("minifly doctor found {} problem(s)", problems.len())
+}
+
+/// How to fix a missing dependency of the [`DependencyManager`]
+fn dependency_fix(service: &str) -> String {
+    match service {
+        "Docker" => "Start Docker (or Docker Desktop) and check that `docker version` works".to_string(),
+        "SQLite" => "Install the sqlite3 command line tool".to_string(),
+        "File System" => "Check that the temp directory is writable".to_string(),
+        other => format!("Make sure {} is installed and running", other),
+    }
+}
+
+/// Whether another process is bound to `port` on localhost
+fn port_in_use(port: u16, udp: bool) -> bool {
+    if udp {
+        UdpSocket::bind(("127.0.0.1", port)).is_err()
+    } else {
+        TcpListener::bind(("127.0.0.1", port)).is_err()
+    }
+}
+
+/// Whether `path` is a lock file no live process holds
+///
+/// A lock naming a PID is stale once that process is gone; one without a
+/// PID is stale as soon as it exists, since the platform isn't running.
+fn is_stale_lock(path: &Path, pid_alive: impl Fn(u32) -> bool) -> bool {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => contents.trim().parse::<u32>().map_or(true, |pid| !pid_alive(pid)),
+        Err(_) => false,
+    }
+}
+
+fn pid_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn remove_container(name: &str) -> bool {
+    Command::new("docker")
+        .args(["rm", "-f", name])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// IDs of the machines the API knows about, destroyed ones excluded
+///
+/// Besides the registered apps, the apps named by container labels are
+/// listed too, so a machine whose app isn't registered isn't taken for an
+/// orphan.
+async fn known_machines(client: &ApiClient, containers: &[ManagedContainer]) -> Result<HashSet<String>> {
+    let mut apps: HashSet<String> = client.list_apps().await?.into_iter().map(|app| app.name).collect();
+    apps.extend(containers.iter().filter_map(|container| container.app_name.clone()));
+
+    let mut known = HashSet::new();
+    for app in apps {
+        for machine in client.list_machines(&app).await? {
+            if machine.state != MachineState::Destroyed {
+                known.insert(machine.id);
+            }
+        }
+    }
+    Ok(known)
+}
+
+/// Lists every minifly container, running or not
+fn list_managed_containers() -> Result<Vec<ManagedContainer>> {
+    let output = Command::new("docker")
+        .args([
+            "ps",
+            "-a",
+            "--filter",
+            "label=minifly.managed=true",
+            "--format",
+            "{{.Names}}\t{{.Label \"minifly.machine_id\"}}\t{{.Label \"minifly.app_name\"}}",
+        ])
+        .output()
+        .context("Failed to run docker")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(parse_containers(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `docker ps` lines of `name<TAB>machine_id<TAB>app_name`
+fn parse_containers(output: &str) -> Vec<ManagedContainer> {
+    let label = |value: Option<&str>| value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            ManagedContainer {
+                name: fields.next().unwrap_or_default().trim().to_string(),
+                machine_id: label(fields.next()),
+                app_name: label(fields.next()),
+            }
+        })
+        .collect()
+}
+
+/// Containers whose machine the API doesn't know, or that have no machine label
+pub fn find_orphans(containers: &[ManagedContainer], known: &HashSet<String>) -> Vec<ManagedContainer> {
+    containers
+        .iter()
+        .filter(|container| container.machine_id.as_ref().is_none_or(|id| !known.contains(id)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_orphans() {
+        let containers = parse_containers(
+            "minifly-web-d1\td1\tweb\nminifly-web-d2\td2\tweb\nminifly-stray\t\t\n",
+        );
+        assert_eq!(containers[2], ManagedContainer {
+            name: "minifly-stray".to_string(),
+            machine_id: None,
+            app_name: None,
+        });
+
+        let known = HashSet::from(["d1".to_string()]);
+        let orphans: Vec<_> = find_orphans(&containers, &known).into_iter().map(|c| c.name).collect();
+        assert_eq!(orphans, vec!["minifly-web-d2", "minifly-stray"]);
+    }
+
+    #[test]
+    fn test_port_in_use() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(port_in_use(port, false));
+
+        drop(listener);
+        assert!(!port_in_use(port, false));
+    }
+
+    #[test]
+    fn test_stale_lock_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = dir.path().join(".minifly.lock");
+        assert!(!is_stale_lock(&lock, |_| true), "a missing lock is not stale");
+
+        std::fs::write(&lock, "4242\n").unwrap();
+        assert!(!is_stale_lock(&lock, |pid| pid == 4242));
+        assert!(is_stale_lock(&lock, |_| false));
+
+        std::fs::write(&lock, "").unwrap();
+        assert!(is_stale_lock(&lock, |_| true));
+    }
+}
@@ -0,0 +1,545 @@
+/// `minifly gateway`, a single entrypoint for every local app, like Fly's edge
+///
+/// The gateway reads the `Host` header of each HTTP request and sends it to
+/// the app it names: `myapp.local` (or `myapp.localhost`) reaches app
+/// `myapp`'s machines through internal DNS, round-robin. The app's `services`
+/// decide how the request is handled. The service port whose number matches
+/// the gateway's is used, or else the first one whose `tls` handler matches
+/// how the client connected.
+///
+/// Ports with the `http` handler are proxied per request, as HTTP/1.1 or
+/// HTTP/2 with WebSocket upgrades (see [`http_proxy`](super::http_proxy)):
+/// `force_https` answers with a redirect to HTTPS, and the `X-Forwarded-*`
+/// and `Fly-*` headers are added. Ports without it get the connection as
+/// sent, routed by its first request.
+///
+/// With a TLS port, connections there are routed by their server name
+/// instead, and TLS is terminated with a certificate from the local CA in
+/// [`certs`](super::certs), so the machine receives plaintext. Without one,
+/// plain requests to ports that only accept TLS are refused.
+use anyhow::{Context, Result};
+use colored::*;
+use hyper::body::Incoming;
+use hyper::header::{HeaderName, HeaderValue, HOST, LOCATION};
+use hyper::{Request, Response, StatusCode};
+use minifly_core::models::{MachineState, ServiceConfig, TlsOptions};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::LazyConfigAcceptor;
+use crate::client::ApiClient;
+use crate::commands::certs::{self, LocalCa};
+use crate::commands::connections::{Connection, ConnectionTracker};
+use crate::commands::http_proxy::{self, Body, Rewind};
+
+/// Largest request head the gateway reads before giving up on a request
+const MAX_HEAD: usize = 64 * 1024;
+
+/// Host suffixes that name an app
+const HOST_SUFFIXES: [&str; 2] = [".local", ".localhost"];
+
+/// How HTTP/2 connections without TLS begin
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n";
+
+/// Handle `minifly gateway --port <port> [--tls-port <port>]`
+pub async fn handle(client: &ApiClient, port: u16, tls_port: Option<u16>, bind: &str) -> Result<()> {
+    let addr: SocketAddr = format!("{}:{}", bind, port).parse()
+        .with_context(|| format!("Invalid bind address {}", bind))?;
+    let listener = TcpListener::bind(addr).await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    let gateway = Gateway::new(client.clone(), port);
+    let mut tasks = vec![tokio::spawn(serve(listener, gateway.clone()))];
+    println!("{} Gateway listening on http://{}", "✓".green(), addr);
+
+    if let Some(tls_port) = tls_port {
+        let dir = certs::default_dir()?;
+        let ca = LocalCa::load_or_create(&dir)?;
+        let addr = SocketAddr::new(addr.ip(), tls_port);
+        let listener = TcpListener::bind(addr).await
+            .with_context(|| format!("Failed to listen on {}", addr))?;
+        tasks.push(tokio::spawn(serve_tls(listener, Gateway { port: tls_port, ..gateway }, Arc::new(ca))));
+        println!("{} Gateway listening on https://{}", "✓".green(), addr);
+        println!("Trust {} to make browsers accept its certificates", dir.join("ca.pem").display().to_string().cyan());
+    }
+
+    println!("Requests for {} are routed to app {}", "<app>.local".cyan(), "<app>".cyan());
+    println!("{}", "Press Ctrl+C to stop".dimmed());
+
+    tokio::signal::ctrl_c().await?;
+    for task in tasks {
+        task.abort();
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Gateway {
+    client: ApiClient,
+    /// The port clients connect to, matched against the apps' service ports
+    port: u16,
+    /// Round-robin position per app
+    next: Arc<Mutex<HashMap<String, usize>>>,
+    connections: ConnectionTracker,
+}
+
+impl Gateway {
+    fn new(client: ApiClient, port: u16) -> Self {
+        let connections = ConnectionTracker::spawn(&client);
+        Self { client, port, next: Arc::default(), connections }
+    }
+
+    /// How `app` answers on this port, or the reply to send when it can't
+    async fn route(&self, app: &str, tls: bool) -> std::result::Result<Route, Reply> {
+        let machines = self.client.list_machines(app).await
+            .map_err(|e| Reply::new(StatusCode::BAD_GATEWAY, format!("Failed to look up app {}: {}", app, e)))?;
+        machines.iter()
+            .filter(|machine| !matches!(machine.state, MachineState::Destroying | MachineState::Destroyed))
+            .find_map(|machine| route(machine.config.services.as_deref()?, self.port, tls))
+            .ok_or_else(|| Reply::new(StatusCode::BAD_GATEWAY, format!("App {} has no TCP services", app)))
+    }
+
+    /// The next machine address of `app` for a service on `internal_port`,
+    /// with the connection to it counted for `autostop`. The address is the
+    /// host port the service is published on, when it is.
+    ///
+    /// Returns once the simulated latency from `region`, the caller's, to the
+    /// machine's region has passed.
+    async fn backend(&self, app: &str, internal_port: u16, region: Option<&str>) -> std::result::Result<(SocketAddr, Connection), Reply> {
+        let machines = self.client.resolve_app_machines(app, region).await
+            .map_err(|e| Reply::new(StatusCode::BAD_GATEWAY, format!("Failed to resolve app {}: {}", app, e)))?;
+        if machines.is_empty() {
+            return Err(Reply::new(StatusCode::SERVICE_UNAVAILABLE, format!("App {} has no running machines", app)));
+        }
+        let machine = {
+            let mut next = self.next.lock().unwrap();
+            let next = next.entry(app.to_string()).or_default();
+            let machine = machines[*next % machines.len()].clone();
+            *next += 1;
+            machine
+        };
+        let addr = match machine.host_port(internal_port, "tcp") {
+            Some(host_port) => SocketAddr::from(([127, 0, 0, 1], host_port)),
+            // Unpublished ports are only reachable on the container's bridge IP
+            None => SocketAddr::new(machine.ip, internal_port),
+        };
+        let connection = self.connections.open(app, &machine.id);
+        tokio::time::sleep(Duration::from_millis(machine.latency_ms)).await;
+        Ok((addr, connection))
+    }
+
+    /// Proxies one request of an `http` connection to the app its host names
+    async fn proxy_request(self, mut request: Request<Incoming>, peer: SocketAddr, proto: &'static str) -> Response<Body> {
+        // HTTP/2 requests name the host in the URI instead of a Host header
+        let host = request.uri().authority().map(|authority| authority.to_string())
+            .or_else(|| request.headers().get(HOST)?.to_str().ok().map(str::to_string));
+        let Some(host) = host else {
+            return Reply::new(StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+        };
+        let Some(app) = app_for_host(&host) else {
+            return Reply::new(StatusCode::NOT_FOUND, format!("No app for host {}; use <app>.local", host)).into_response();
+        };
+        let route = match self.route(app, proto == "https").await {
+            Ok(route) => route,
+            Err(reply) => return reply.into_response(),
+        };
+
+        if proto == "http" && route.force_https && route.http {
+            let hostname = host.rsplit_once(':').map_or(host.as_str(), |(name, _)| name);
+            let path = request.uri().path_and_query().map_or("/", |path| path.as_str());
+            return Reply::redirect(format!("https://{}{}", hostname, path)).into_response();
+        }
+        if route.http {
+            for (name, value) in forwarded_headers(peer, self.port, proto) {
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    request.headers_mut().insert(HeaderName::from_static(name), value);
+                }
+            }
+        }
+
+        // Like Fly's proxy, the caller may say which region it is in
+        let region = ["fly-prefer-region", "fly-region"].iter()
+            .find_map(|name| request.headers().get(*name)?.to_str().ok())
+            .map(str::to_string);
+        match self.backend(app, route.internal_port, region.as_deref()).await {
+            Ok((backend, connection)) => http_proxy::forward(request, backend, Some(connection)).await,
+            Err(reply) => reply.into_response(),
+        }
+    }
+}
+
+/// How an app's service answers on the gateway's port
+#[derive(Debug, Clone)]
+struct Route {
+    internal_port: u16,
+    http: bool,
+    tls: bool,
+    force_https: bool,
+    tls_options: Option<TlsOptions>,
+}
+
+/// Picks the TCP service port numbered `port`, or else the first one whose
+/// `tls` handler matches `tls`, or else the first one
+fn route(services: &[ServiceConfig], port: u16, tls: bool) -> Option<Route> {
+    let ports: Vec<(&ServiceConfig, _)> = services.iter()
+        .filter(|service| service.protocol.eq_ignore_ascii_case("tcp"))
+        .flat_map(|service| service.ports.iter().map(move |port| (service, port)))
+        .collect();
+    let has = |handlers: &[String], name: &str| handlers.iter().any(|h| h.eq_ignore_ascii_case(name));
+
+    let (service, port) = ports.iter()
+        .find(|(_, p)| p.port == port)
+        .or_else(|| ports.iter().find(|(_, p)| has(&p.handlers, "tls") == tls))
+        .or(ports.first())?;
+    Some(Route {
+        internal_port: service.internal_port,
+        http: has(&port.handlers, "http"),
+        tls: has(&port.handlers, "tls"),
+        force_https: port.force_https.unwrap_or(false),
+        tls_options: port.tls_options.clone(),
+    })
+}
+
+/// The app named by a `Host` header, e.g. `myapp` for `myapp.local:8080`
+fn app_for_host(host: &str) -> Option<&str> {
+    let host = host.rsplit_once(':').map_or(host, |(name, port)| {
+        if port.chars().all(|c| c.is_ascii_digit()) { name } else { host }
+    });
+    HOST_SUFFIXES.iter()
+        .find_map(|suffix| host.strip_suffix(suffix))
+        .filter(|app| !app.is_empty() && !app.contains('.'))
+}
+
+/// The headers Fly's `http` handler adds to requests
+fn forwarded_headers(peer: SocketAddr, port: u16, proto: &str) -> [(&'static str, String); 7] {
+    let client_ip = peer.ip().to_string();
+    [
+        ("fly-client-ip", client_ip.clone()),
+        ("fly-forwarded-port", port.to_string()),
+        ("x-forwarded-for", client_ip),
+        ("x-forwarded-proto", proto.to_string()),
+        ("x-forwarded-port", port.to_string()),
+        ("x-forwarded-ssl", if proto == "https" { "on" } else { "off" }.to_string()),
+        ("via", "1.1 minifly".to_string()),
+    ]
+}
+
+/// A response the gateway makes itself instead of forwarding the request
+struct Reply {
+    status: StatusCode,
+    body: String,
+    location: Option<String>,
+}
+
+impl Reply {
+    fn new(status: StatusCode, body: impl Into<String>) -> Self {
+        Self { status, body: body.into(), location: None }
+    }
+
+    fn redirect(location: String) -> Self {
+        Self { status: StatusCode::MOVED_PERMANENTLY, body: String::new(), location: Some(location) }
+    }
+
+    fn into_response(self) -> Response<Body> {
+        let mut response = http_proxy::text(self.status, self.body);
+        if let Some(location) = self.location.and_then(|location| HeaderValue::from_str(&location).ok()) {
+            response.headers_mut().insert(LOCATION, location);
+        }
+        response
+    }
+
+    /// Writes the response to a connection that isn't served as HTTP and closes it
+    async fn send<S: AsyncWrite + Unpin>(self, stream: &mut S) -> Result<()> {
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.body.len(),
+            self.body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+}
+
+/// Reads the head of the first request on a plain connection, returning its
+/// `Host` header and everything read so far; `None` when the client goes
+/// away first
+async fn read_host<S: AsyncRead + Unpin>(stream: &mut S) -> std::result::Result<Option<(Option<String>, Vec<u8>)>, Reply> {
+    let mut buf = Vec::new();
+    loop {
+        if let Some(len) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let Ok(head) = std::str::from_utf8(&buf[..len]) else {
+                return Err(Reply::new(StatusCode::BAD_REQUEST, "Request head is not valid UTF-8"));
+            };
+            let host = head.split("\r\n")
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+                .map(|(_, value)| value.trim().to_string());
+            return Ok(Some((host, buf)));
+        }
+        if buf.len() > MAX_HEAD {
+            return Err(Reply::new(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, "Request head too large"));
+        }
+        let mut chunk = [0u8; 8192];
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Ok(None),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
+/// Serves a connection of an `http` port request by request
+async fn serve_http<S>(gateway: &Gateway, inbound: S, peer: SocketAddr, proto: &'static str) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let gateway = gateway.clone();
+    http_proxy::serve_connection(inbound, move |request| gateway.clone().proxy_request(request, peer, proto)).await
+}
+
+/// Accepts plain connections and routes each one by its first request
+async fn serve(listener: TcpListener, gateway: Gateway) {
+    loop {
+        let Ok((inbound, peer)) = listener.accept().await else {
+            continue;
+        };
+
+        let gateway = gateway.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_connection(&gateway, inbound, peer).await {
+                eprintln!("{} {}: {}", "✗".red(), peer, e);
+            }
+        });
+    }
+}
+
+async fn serve_connection(gateway: &Gateway, mut inbound: TcpStream, peer: SocketAddr) -> Result<()> {
+    let (host, buf) = match read_host(&mut inbound).await {
+        Ok(Some(read)) => read,
+        Ok(None) => return Ok(()),
+        Err(reply) => return reply.send(&mut inbound).await,
+    };
+    // HTTP/2 without TLS can only be spoken by an http port, and names its host per request
+    if buf.starts_with(H2_PREFACE) {
+        return serve_http(gateway, Rewind::new(buf, inbound), peer, "http").await;
+    }
+
+    let Some(host) = host else {
+        return Reply::new(StatusCode::BAD_REQUEST, "Missing Host header").send(&mut inbound).await;
+    };
+    let Some(app) = app_for_host(&host) else {
+        let msg = format!("No app for host {}; use <app>.local", host);
+        return Reply::new(StatusCode::NOT_FOUND, msg).send(&mut inbound).await;
+    };
+    let route = match gateway.route(app, false).await {
+        Ok(route) => route,
+        Err(reply) => return reply.send(&mut inbound).await,
+    };
+
+    if route.http {
+        return serve_http(gateway, Rewind::new(buf, inbound), peer, "http").await;
+    }
+    if route.tls {
+        let msg = format!("App {} only accepts TLS on this port", app);
+        return Reply::new(StatusCode::BAD_REQUEST, msg).send(&mut inbound).await;
+    }
+    tunnel(gateway, &mut inbound, app, &route, &buf).await
+}
+
+/// Accepts TLS connections and routes each one by its server name
+async fn serve_tls(listener: TcpListener, gateway: Gateway, ca: Arc<LocalCa>) {
+    loop {
+        let Ok((inbound, peer)) = listener.accept().await else {
+            continue;
+        };
+
+        let (gateway, ca) = (gateway.clone(), ca.clone());
+        tokio::spawn(async move {
+            if let Err(e) = serve_tls_connection(&gateway, &ca, inbound, peer).await {
+                eprintln!("{} {}: {}", "✗".red(), peer, e);
+            }
+        });
+    }
+}
+
+async fn serve_tls_connection(gateway: &Gateway, ca: &LocalCa, inbound: TcpStream, peer: SocketAddr) -> Result<()> {
+    let start = LazyConfigAcceptor::new(rustls::server::Acceptor::default(), inbound).await
+        .context("TLS handshake failed")?;
+    let server_name = start.client_hello().server_name().map(str::to_string);
+    let Some(app) = server_name.as_deref().and_then(app_for_host).map(str::to_string) else {
+        anyhow::bail!("No app for TLS server name {:?}; use <app>.local", server_name.unwrap_or_default());
+    };
+
+    // The route is needed before the handshake for its ALPN and TLS versions
+    let route = gateway.route(&app, true).await;
+    let config = ca.server_config(&app, route.as_ref().ok().and_then(|route| route.tls_options.as_ref()))?;
+    let mut inbound = start.into_stream(config).await.context("TLS handshake failed")?;
+    match route {
+        Ok(route) if route.http => serve_http(gateway, inbound, peer, "https").await,
+        Ok(route) => tunnel(gateway, &mut inbound, &app, &route, &[]).await,
+        Err(reply) => reply.send(&mut inbound).await,
+    }
+}
+
+/// Sends what was read so far to the next machine of `app`, then pipes the
+/// rest of the connection
+async fn tunnel<S: AsyncRead + AsyncWrite + Unpin>(
+    gateway: &Gateway,
+    inbound: &mut S,
+    app: &str,
+    route: &Route,
+    read: &[u8],
+) -> Result<()> {
+    let (backend, _connection) = match gateway.backend(app, route.internal_port, None).await {
+        Ok(backend) => backend,
+        Err(reply) => return reply.send(inbound).await,
+    };
+    let mut outbound = match TcpStream::connect(backend).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            let msg = format!("Failed to reach {} at {}: {}", app, backend, e);
+            return Reply::new(StatusCode::BAD_GATEWAY, msg).send(inbound).await;
+        }
+    };
+
+    outbound.write_all(read).await?;
+    let _ = tokio::io::copy_bidirectional(inbound, &mut outbound).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::client;
+    use minifly_core::models::{Machine, PortConfig};
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, ServerName};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn port(port: u16, handlers: &[&str], force_https: bool) -> PortConfig {
+        PortConfig {
+            port,
+            handlers: handlers.iter().map(|h| h.to_string()).collect(),
+            force_https: Some(force_https),
+            tls_options: None,
+        }
+    }
+
+    fn machine(internal_port: u16, ports: Vec<PortConfig>) -> Machine {
+        let mut machine = crate::test_support::machine("m1");
+        machine.config.services = Some(vec![crate::test_support::service(internal_port, ports)]);
+        machine
+    }
+
+    /// Serves `app` from a backend that answers `GET /hello` with `body`
+    /// when the request arrived over `proto`
+    async fn mount_app(api: &MockServer, app: &str, body: &str, proto: &str, ports: Vec<PortConfig>) -> MockServer {
+        let backend = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/hello"))
+            .and(header("x-forwarded-proto", proto))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&backend)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/v1/apps/{}/machines", app)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![machine(8080, ports)]))
+            .mount(api)
+            .await;
+        // The bridge IP isn't reachable, so requests must go through the published port
+        Mock::given(method("GET"))
+            .and(path(format!("/v1/apps/{}/dns", app)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hostname": format!("{}.internal", app),
+                "ips": ["172.31.255.2"],
+                "machines": [{
+                    "id": "d8e7f6",
+                    "ip": "172.31.255.2",
+                    "mapped_ports": [{ "internal_port": 8080, "protocol": "tcp", "host_port": backend.address().port() }]
+                }]
+            })))
+            .mount(api)
+            .await;
+        backend
+    }
+
+    async fn get<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, host: &str) -> String {
+        let request = format!("GET /hello HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", host);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+        response
+    }
+
+    #[tokio::test]
+    async fn test_host_header_routes_to_each_app() {
+        let api = MockServer::start().await;
+        let _alpha = mount_app(&api, "alpha", "from alpha", "http", vec![port(80, &["http"], false)]).await;
+        let _beta = mount_app(&api, "beta", "from beta", "http", vec![port(80, &["http"], false)]).await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/secure/machines"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![machine(8080, vec![port(80, &["http"], true)])]))
+            .mount(&api)
+            .await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Gateway::new(client(&api), 80)));
+        let connect = || TcpStream::connect(addr);
+
+        let response = get(connect().await.unwrap(), "alpha.local").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("from alpha"), "{}", response);
+        let response = get(connect().await.unwrap(), "beta.local:80").await;
+        assert!(response.ends_with("from beta"), "{}", response);
+
+        let response = get(connect().await.unwrap(), "secure.local").await;
+        assert!(response.starts_with("HTTP/1.1 301"), "{}", response);
+        assert!(response.to_lowercase().contains("location: https://secure.local/hello\r\n"), "{}", response);
+
+        assert!(get(connect().await.unwrap(), "example.com").await.starts_with("HTTP/1.1 404"));
+    }
+
+    #[tokio::test]
+    async fn test_https_request_reaches_backend() {
+        let api = MockServer::start().await;
+        let ports = vec![port(80, &["http"], true), port(443, &["tls", "http"], false)];
+        let _web = mount_app(&api, "web", "over tls", "https", ports).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ca = LocalCa::load_or_create(dir.path()).unwrap();
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(CertificateDer::from_pem_file(dir.path().join("ca.pem")).unwrap()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_tls(listener, Gateway::new(client(&api), 443), Arc::new(ca)));
+
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let stream = tokio_rustls::TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("web.local").unwrap(), TcpStream::connect(addr).await.unwrap())
+            .await
+            .expect("the app certificate is signed by the local CA");
+
+        let response = get(stream, "web.local").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("over tls"), "{}", response);
+    }
+
+    #[test]
+    fn test_app_for_host() {
+        assert_eq!(app_for_host("myapp.local"), Some("myapp"));
+        assert_eq!(app_for_host("myapp.localhost:8080"), Some("myapp"));
+        assert_eq!(app_for_host("a.b.local"), None);
+        assert_eq!(app_for_host("localhost"), None);
+    }
+}
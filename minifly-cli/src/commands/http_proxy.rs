@@ -0,0 +1,144 @@
+/// HTTP-aware proxying for `proxy --http` and the gateway
+///
+/// Connections are served as HTTP/1.1 or HTTP/2, told apart by the HTTP/2
+/// connection preface (which ALPN's `h2` leads to over TLS). Each request is
+/// sent to the backend its caller picks, so requests on a kept-alive
+/// connection can go to different machines. Backends are spoken to in
+/// HTTP/1.1. Upgrade requests such as WebSocket handshakes are forwarded, and
+/// once the backend switches protocols both sides are joined into a tunnel.
+use super::connections::Connection;
+use anyhow::Result;
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::header::{HeaderValue, HOST, UPGRADE};
+use hyper::{Request, Response, StatusCode, Uri, Version};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+pub type Body = BoxBody<Bytes, hyper::Error>;
+
+/// Serves HTTP/1.1 and HTTP/2 requests on `stream` with `handler` until the
+/// client closes the connection
+pub async fn serve_connection<S, F, Fut>(stream: S, handler: F) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    F: Fn(Request<Incoming>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response<Body>> + Send + 'static,
+{
+    let service = hyper::service::service_fn(move |request| {
+        let response = handler(request);
+        async move { Ok::<_, Infallible>(response.await) }
+    });
+    hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
+        .serve_connection_with_upgrades(TokioIo::new(stream), service)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Sends `request` to `backend` over HTTP/1.1, tunnelling the connection if
+/// the backend switches protocols. `connection` is held until the backend
+/// connection and any tunnel close.
+pub async fn forward(mut request: Request<Incoming>, backend: SocketAddr, connection: Option<Connection>) -> Response<Body> {
+    let connection = Arc::new(connection);
+    let stream = match TcpStream::connect(backend).await {
+        Ok(stream) => stream,
+        Err(e) => return text(StatusCode::BAD_GATEWAY, format!("Failed to reach {}: {}", backend, e)),
+    };
+    let (mut sender, backend_connection) = match hyper::client::conn::http1::handshake(TokioIo::new(stream)).await {
+        Ok(handshake) => handshake,
+        Err(e) => return text(StatusCode::BAD_GATEWAY, format!("Failed to reach {}: {}", backend, e)),
+    };
+    let held = connection.clone();
+    tokio::spawn(async move {
+        let _ = backend_connection.with_upgrades().await;
+        drop(held);
+    });
+
+    let client_upgrade = request.headers().contains_key(UPGRADE).then(|| hyper::upgrade::on(&mut request));
+
+    // HTTP/2 requests carry the host in the URI, where HTTP/1.1 expects a Host header
+    if let Some(authority) = request.uri().authority().cloned() {
+        if !request.headers().contains_key(HOST) {
+            if let Ok(host) = HeaderValue::from_str(authority.as_str()) {
+                request.headers_mut().insert(HOST, host);
+            }
+        }
+    }
+    let path = request.uri().path_and_query().map_or("/", |path| path.as_str());
+    *request.uri_mut() = path.parse().unwrap_or_else(|_| Uri::from_static("/"));
+    *request.version_mut() = Version::HTTP_11;
+
+    let mut response = match sender.send_request(request).await {
+        Ok(response) => response,
+        Err(e) => return text(StatusCode::BAD_GATEWAY, format!("Request to {} failed: {}", backend, e)),
+    };
+
+    if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+        if let Some(client_upgrade) = client_upgrade {
+            let backend_upgrade = hyper::upgrade::on(&mut response);
+            tokio::spawn(async move {
+                let _held = connection;
+                if let (Ok(client), Ok(backend)) = (client_upgrade.await, backend_upgrade.await) {
+                    let _ = tokio::io::copy_bidirectional(&mut TokioIo::new(client), &mut TokioIo::new(backend)).await;
+                }
+            });
+        }
+    }
+    response.map(|body| body.boxed())
+}
+
+/// A plain-text response made by the proxy itself
+pub fn text(status: StatusCode, body: impl Into<String>) -> Response<Body> {
+    let body = Full::new(Bytes::from(body.into())).map_err(|never| match never {}).boxed();
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert("content-type", HeaderValue::from_static("text/plain"));
+    response
+}
+
+/// A stream that yields bytes already read from it before reading on
+pub struct Rewind<S> {
+    prefix: Bytes,
+    inner: S,
+}
+
+impl<S> Rewind<S> {
+    pub fn new(prefix: Vec<u8>, inner: S) -> Self {
+        Self { prefix: prefix.into(), inner }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Rewind<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if !self.prefix.is_empty() {
+            let n = self.prefix.len().min(buf.remaining());
+            let prefix = self.prefix.split_to(n);
+            buf.put_slice(&prefix);
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Rewind<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
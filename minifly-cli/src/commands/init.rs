@@ -5,11 +5,14 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use crate::commands::templates::TemplateSource;
 use crate::config::Config;
 
 /// Project template information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ProjectTemplate {
+    /// Short name accepted by `init --template`
+    id: String,
     name: String,
     description: String,
     language: String,
@@ -27,7 +30,35 @@ struct ProjectTemplate {
 /// 
 /// # Arguments
 /// * `config` - Current Minifly configuration
-pub async fn handle(config: &Config) -> Result<()> {
+/// * `options` - Choices given on the command line, which skip their prompts
+pub async fn handle(config: &Config, options: InitOptions) -> Result<()> {
+    if let Some(name) = &options.name {
+        validate_app_name(name).map_err(|e| anyhow::anyhow!(e))?;
+    }
+    if let Some(source) = &options.template_repo {
+        return scaffold_custom_template(source, &options);
+    }
+    
+    let non_interactive = options.non_interactive;
+    let template = options.template.as_deref().map(find_template).transpose()?;
+    let details = ProjectDetails { template, name: options.name, description: options.description };
+    
+    if non_interactive {
+        let template = details.template.as_ref().context("--non-interactive needs a --template")?;
+        let (app_name, description) = (details.app_name(), details.description(template));
+        let existing = existing_project_files(Path::new("."), template, &app_name, &description).await?;
+        if !existing.is_empty() {
+            anyhow::bail!(
+                "The template would overwrite existing files: {}; run `minifly init` without --non-interactive to reinitialize",
+                existing.join(", ")
+            );
+        }
+        println!("{} Creating {} project...", "📝".blue(), template.name.bold());
+        scaffold_project(Path::new("."), template, &app_name, &description).await?;
+        show_getting_started();
+        return Ok(());
+    }
+    
     println!("{}", "🚀 Welcome to Minifly!".bold().blue());
     println!("Local Fly.io development simulator with incredible DX\n");
     
@@ -53,6 +84,9 @@ pub async fn handle(config: &Config) -> Result<()> {
             2 => InitMode::Reinitialize,
             _ => return Ok(()),
         }
+    } else if details.template.is_some() {
+        println!("📦 Setting up a new Minifly project!");
+        InitMode::NewProject
     } else {
         println!("📦 Setting up a new Minifly project!");
         let choices = vec![
@@ -81,7 +115,7 @@ pub async fn handle(config: &Config) -> Result<()> {
             println!("\n{}", "✅ Configuration saved!".green());
         }
         InitMode::NewProject => {
-            setup_new_project(&new_config).await?;
+            setup_new_project(&new_config, details).await?;
         }
         InitMode::AddTemplates => {
             add_project_templates().await?;
@@ -92,7 +126,7 @@ pub async fn handle(config: &Config) -> Result<()> {
                 .default(false)
                 .interact()? 
             {
-                setup_new_project(&new_config).await?;
+                setup_new_project(&new_config, details).await?;
             }
         }
     }
@@ -103,6 +137,21 @@ pub async fn handle(config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// Choices for `init` given on the command line
+#[derive(Debug, Default)]
+pub struct InitOptions {
+    /// Built-in template to scaffold
+    pub template: Option<String>,
+    /// Directory or git repository of a custom template to scaffold
+    pub template_repo: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Values of custom template variables, as `KEY=VALUE`
+    pub vars: Vec<String>,
+    /// Scaffold the template without any prompts, leaving the configuration as is
+    pub non_interactive: bool,
+}
+
 /// Initialization modes
 #[derive(Debug)]
 enum InitMode {
@@ -129,6 +178,7 @@ async fn setup_configuration(config: &Config) -> Result<Config> {
     let new_config = Config {
         api_url,
         token: if token.is_empty() { None } else { Some(token) },
+        ..config.clone()
     };
     
     new_config.save().context("Failed to save configuration")?;
@@ -136,8 +186,154 @@ async fn setup_configuration(config: &Config) -> Result<Config> {
     Ok(new_config)
 }
 
+/// Project details given on the command line, prompted for when missing
+struct ProjectDetails {
+    template: Option<ProjectTemplate>,
+    name: Option<String>,
+    description: Option<String>,
+}
+
+impl ProjectDetails {
+    fn app_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| "my-minifly-app".to_string())
+    }
+    
+    fn description(&self, template: &ProjectTemplate) -> String {
+        self.description.clone()
+            .unwrap_or_else(|| format!("A {} application built with Minifly", template.framework))
+    }
+}
+
+fn validate_app_name(name: &str) -> Result<(), &'static str> {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        Ok(())
+    } else {
+        Err("App name can only contain letters, numbers, hyphens, and underscores")
+    }
+}
+
 /// Setup a new project with templates
-async fn setup_new_project(_config: &Config) -> Result<()> {
+async fn setup_new_project(_config: &Config, details: ProjectDetails) -> Result<()> {
+    let selected_template = match &details.template {
+        Some(template) => template.clone(),
+        None => select_template()?,
+    };
+    
+    // Get project details
+    let app_name: String = match &details.name {
+        Some(name) => name.clone(),
+        None => Input::new()
+            .with_prompt("Application name")
+            .default(details.app_name())
+            .validate_with(|input: &String| validate_app_name(input))
+            .interact_text()?,
+    };
+    
+    let description: String = match &details.description {
+        Some(description) => description.clone(),
+        None => Input::new()
+            .with_prompt("Description")
+            .default(details.description(&selected_template))
+            .interact_text()?,
+    };
+    
+    println!("\n{} Creating project files...", "📝".blue());
+    scaffold_project(Path::new("."), &selected_template, &app_name, &description).await
+}
+
+/// Writes a template's files and the data directories into `dir`
+async fn scaffold_project(dir: &Path, template: &ProjectTemplate, app_name: &str, description: &str) -> Result<()> {
+    create_project_files(dir, template, app_name, description).await?;
+    
+    // Create data directories
+    fs::create_dir_all(dir.join("data/litefs")).context("Failed to create data directories")?;
+    fs::create_dir_all(dir.join("data/apps")).context("Failed to create data directories")?;
+    
+    println!("{} Project created successfully!", "✅".green());
+    
+    Ok(())
+}
+
+/// The files of a template that already exist in `dir`, found by writing the
+/// template into a scratch directory first
+async fn existing_project_files(dir: &Path, template: &ProjectTemplate, app_name: &str, description: &str) -> Result<Vec<String>> {
+    let scratch = tempfile::tempdir().context("Failed to create a scratch directory")?;
+    create_project_files(scratch.path(), template, app_name, description).await?;
+    
+    let mut existing = Vec::new();
+    for entry in ignore::WalkBuilder::new(scratch.path()).standard_filters(false).build() {
+        let entry = entry.context("Failed to read scratch directory")?;
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(scratch.path())?;
+        if dir.join(relative).exists() {
+            existing.push(relative.display().to_string());
+        }
+    }
+    existing.sort();
+    Ok(existing)
+}
+
+/// Scaffolds a project from a custom template directory or git repository
+fn scaffold_custom_template(source: &str, options: &InitOptions) -> Result<()> {
+    println!("{} Fetching template {}...", "📥".blue(), source.cyan());
+    let template = TemplateSource::open(source)?;
+    
+    let mut given = HashMap::new();
+    for var in &options.vars {
+        let (key, value) = var.split_once('=')
+            .with_context(|| format!("Invalid --var '{}'; expected KEY=VALUE", var))?;
+        given.insert(key.trim().to_string(), value.to_string());
+    }
+    let ask = |name: &str, prompt: &str, default: Option<String>| -> Result<String> {
+        if options.non_interactive {
+            return default.with_context(|| format!("Template variable {} has no default; pass --var {}=<value>", name, name));
+        }
+        let mut input = Input::<String>::new().with_prompt(prompt);
+        if let Some(default) = default {
+            input = input.default(default);
+        }
+        Ok(input.interact_text()?)
+    };
+    
+    let app_name = match &options.name {
+        Some(name) => name.clone(),
+        None if options.non_interactive => "my-minifly-app".to_string(),
+        None => Input::new()
+            .with_prompt("Application name")
+            .default("my-minifly-app".to_string())
+            .validate_with(|input: &String| validate_app_name(input))
+            .interact_text()?,
+    };
+    let default_description = template.manifest.description.clone()
+        .unwrap_or_else(|| format!("A {} application built with Minifly", template.name()));
+    let description = match &options.description {
+        Some(description) => description.clone(),
+        None => ask("description", "Description", Some(default_description))?,
+    };
+    
+    let mut variables = HashMap::from([
+        ("app_name".to_string(), app_name),
+        ("description".to_string(), description),
+    ]);
+    for (name, variable) in &template.manifest.variables {
+        let value = match given.remove(name) {
+            Some(value) => value,
+            None => ask(name, variable.prompt.as_deref().unwrap_or(name), variable.default.clone())?,
+        };
+        variables.insert(name.clone(), value);
+    }
+    variables.extend(given);
+    
+    let files = template.scaffold(Path::new("."), &variables)?;
+    println!("{} Created {} files from the {} template", "✅".green(), files.len(), template.name().bold());
+    show_getting_started();
+    Ok(())
+}
+
+/// Prompts for one of the available templates
+fn select_template() -> Result<ProjectTemplate> {
     println!("\n{}", "📋 Project Template Selection".bold());
     
     let templates = get_available_templates();
@@ -150,7 +346,7 @@ async fn setup_new_project(_config: &Config) -> Result<()> {
             template.name.bold(),
             template.language.green()
         );
-        println!("     {}", template.description.dimmed());
+        println!("     {} (--template {})", template.description.dimmed(), template.id);
         if !template.features.is_empty() {
             println!("     Features: {}", template.features.join(", ").blue());
         }
@@ -163,37 +359,7 @@ async fn setup_new_project(_config: &Config) -> Result<()> {
         .default(0)
         .interact()?;
     
-    let selected_template = &templates[selection];
-    
-    // Get project details
-    let app_name: String = Input::new()
-        .with_prompt("Application name")
-        .default("my-minifly-app".to_string())
-        .validate_with(|input: &String| -> Result<(), &str> {
-            if input.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
-                Ok(())
-            } else {
-                Err("App name can only contain letters, numbers, hyphens, and underscores")
-            }
-        })
-        .interact_text()?;
-    
-    let description: String = Input::new()
-        .with_prompt("Description")
-        .default(format!("A {} application built with Minifly", selected_template.framework))
-        .interact_text()?;
-    
-    // Generate project files
-    println!("\n{} Creating project files...", "📝".blue());
-    create_project_files(selected_template, &app_name, &description).await?;
-    
-    // Create data directories
-    fs::create_dir_all("data/litefs").context("Failed to create data directories")?;
-    fs::create_dir_all("data/apps").context("Failed to create data directories")?;
-    
-    println!("{} Project created successfully!", "✅".green());
-    
-    Ok(())
+    Ok(templates[selection].clone())
 }
 
 /// Add project templates to existing project
@@ -215,11 +381,11 @@ async fn add_project_templates() -> Result<()> {
             .interact()? 
         {
             match template {
-                "Docker Compose configuration" => create_docker_compose().await?,
-                "LiteFS configuration" => create_litefs_config().await?,
-                "Multi-tenant example" => create_multitenant_example().await?,
-                "GitHub Actions workflow" => create_github_workflow().await?,
-                "Development scripts" => create_dev_scripts().await?,
+                "Docker Compose configuration" => create_docker_compose(Path::new(".")).await?,
+                "LiteFS configuration" => create_litefs_config(Path::new(".")).await?,
+                "Multi-tenant example" => create_multitenant_example(Path::new(".")).await?,
+                "GitHub Actions workflow" => create_github_workflow(Path::new(".")).await?,
+                "Development scripts" => create_dev_scripts(Path::new(".")).await?,
                 _ => {}
             }
         }
@@ -233,6 +399,7 @@ async fn add_project_templates() -> Result<()> {
 fn get_available_templates() -> Vec<ProjectTemplate> {
     vec![
         ProjectTemplate {
+            id: "rust-axum".to_string(),
             name: "Rust + Axum + LiteFS".to_string(),
             description: "Multi-tenant web app with Axum, Askama templates, and LiteFS".to_string(),
             language: "Rust".to_string(),
@@ -246,6 +413,7 @@ fn get_available_templates() -> Vec<ProjectTemplate> {
             files: HashMap::new(),
         },
         ProjectTemplate {
+            id: "node-express".to_string(),
             name: "Node.js + Express + SQLite".to_string(),
             description: "Express.js web server with SQLite database".to_string(),
             language: "JavaScript".to_string(),
@@ -259,6 +427,7 @@ fn get_available_templates() -> Vec<ProjectTemplate> {
             files: HashMap::new(),
         },
         ProjectTemplate {
+            id: "python-fastapi".to_string(),
             name: "Python + FastAPI + SQLite".to_string(),
             description: "FastAPI application with async SQLite support".to_string(),
             language: "Python".to_string(),
@@ -272,6 +441,7 @@ fn get_available_templates() -> Vec<ProjectTemplate> {
             files: HashMap::new(),
         },
         ProjectTemplate {
+            id: "go-gin".to_string(),
             name: "Go + Gin + SQLite".to_string(),
             description: "Gin web framework with SQLite database".to_string(),
             language: "Go".to_string(),
@@ -285,6 +455,7 @@ fn get_available_templates() -> Vec<ProjectTemplate> {
             files: HashMap::new(),
         },
         ProjectTemplate {
+            id: "minimal".to_string(),
             name: "Minimal Docker".to_string(),
             description: "Basic Docker setup for any language".to_string(),
             language: "Any".to_string(),
@@ -299,20 +470,30 @@ fn get_available_templates() -> Vec<ProjectTemplate> {
     ]
 }
 
+/// Finds a template by its short name (e.g. `rust-axum`) or full name
+fn find_template(name: &str) -> Result<ProjectTemplate> {
+    let templates = get_available_templates();
+    let ids: Vec<&str> = templates.iter().map(|t| t.id.as_str()).collect();
+    let ids = ids.join(", ");
+    templates.into_iter()
+        .find(|t| t.id.eq_ignore_ascii_case(name) || t.name.eq_ignore_ascii_case(name))
+        .with_context(|| format!("Unknown template '{}'; available templates: {}", name, ids))
+}
+
 /// Create project files based on template
-async fn create_project_files(template: &ProjectTemplate, app_name: &str, description: &str) -> Result<()> {
+async fn create_project_files(dir: &Path, template: &ProjectTemplate, app_name: &str, description: &str) -> Result<()> {
     match template.name.as_str() {
-        "Rust + Axum + LiteFS" => create_rust_axum_project(app_name, description).await,
-        "Node.js + Express + SQLite" => create_node_express_project(app_name, description).await,
-        "Python + FastAPI + SQLite" => create_python_fastapi_project(app_name, description).await,
-        "Go + Gin + SQLite" => create_go_gin_project(app_name, description).await,
-        "Minimal Docker" => create_minimal_docker_project(app_name, description).await,
+        "Rust + Axum + LiteFS" => create_rust_axum_project(dir, app_name, description).await,
+        "Node.js + Express + SQLite" => create_node_express_project(dir, app_name, description).await,
+        "Python + FastAPI + SQLite" => create_python_fastapi_project(dir, app_name, description).await,
+        "Go + Gin + SQLite" => create_go_gin_project(dir, app_name, description).await,
+        "Minimal Docker" => create_minimal_docker_project(dir, app_name, description).await,
         _ => Ok(()),
     }
 }
 
 /// Create Rust + Axum project
-async fn create_rust_axum_project(app_name: &str, description: &str) -> Result<()> {
+async fn create_rust_axum_project(dir: &Path, app_name: &str, description: &str) -> Result<()> {
     // Cargo.toml
     let cargo_toml = format!(r#"[package]
 name = "{}"
@@ -336,7 +517,7 @@ tracing-subscriber = "0.3"
 uuid = {{ version = "1.10", features = ["v4", "serde"] }}
 "#, app_name, description);
     
-    fs::write("Cargo.toml", cargo_toml).context("Failed to write Cargo.toml")?;
+    fs::write(dir.join("Cargo.toml"), cargo_toml).context("Failed to write Cargo.toml")?;
     
     // src/main.rs
     let main_rs = r#"use axum::{
@@ -348,7 +529,8 @@ uuid = {{ version = "1.10", features = ["v4", "serde"] }}
 };
 use askama::Template;
 use serde::{Deserialize, Serialize};
-use sqlx::{SqlitePool, Row};
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool, Row};
+use std::str::FromStr;
 use std::sync::Arc;
 use tower_http::{services::ServeDir, trace::TraceLayer};
 use tracing::{info, warn};
@@ -383,7 +565,8 @@ async fn main() -> anyhow::Result<()> {
     let database_url = std::env::var("DATABASE_URL")
         .unwrap_or_else(|_| "sqlite:./data/app.db".to_string());
     
-    let db = SqlitePool::connect(&database_url).await?;
+    let options = SqliteConnectOptions::from_str(&database_url)?.create_if_missing(true);
+    let db = SqlitePool::connect_with(options).await?;
     
     // Run migrations
     sqlx::migrate!("./migrations").run(&db).await?;
@@ -487,11 +670,11 @@ async fn health_check() -> Json<serde_json::Value> {
 }
 "#;
     
-    fs::create_dir_all("src").context("Failed to create src directory")?;
-    fs::write("src/main.rs", main_rs).context("Failed to write main.rs")?;
+    fs::create_dir_all(dir.join("src")).context("Failed to create src directory")?;
+    fs::write(dir.join("src/main.rs"), main_rs).context("Failed to write main.rs")?;
     
     // Create templates directory and index template
-    fs::create_dir_all("templates").context("Failed to create templates directory")?;
+    fs::create_dir_all(dir.join("templates")).context("Failed to create templates directory")?;
     let index_template = r#"<!DOCTYPE html>
 <html>
 <head>
@@ -526,10 +709,10 @@ async fn health_check() -> Json<serde_json::Value> {
 </body>
 </html>"#;
     
-    fs::write("templates/index.html", index_template).context("Failed to write template")?;
+    fs::write(dir.join("templates/index.html"), index_template).context("Failed to write template")?;
     
     // Create migrations
-    fs::create_dir_all("migrations").context("Failed to create migrations directory")?;
+    fs::create_dir_all(dir.join("migrations")).context("Failed to create migrations directory")?;
     let migration = r#"CREATE TABLE IF NOT EXISTS users (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     name TEXT NOT NULL,
@@ -540,17 +723,41 @@ async fn health_check() -> Json<serde_json::Value> {
 
 CREATE INDEX idx_users_tenant_id ON users(tenant_id);"#;
     
-    fs::write("migrations/001_initial.sql", migration).context("Failed to write migration")?;
+    fs::write(dir.join("migrations/001_initial.sql"), migration).context("Failed to write migration")?;
     
     // Create common project files
-    create_common_files(app_name, description).await?;
+    // Dockerfile: dependencies are built in their own layer so code changes
+    // don't rebuild them
+    let dockerfile = format!(r#"FROM rust:1-slim-bookworm AS builder
+WORKDIR /app
+
+COPY Cargo.toml ./
+RUN mkdir src && echo "fn main() {{}}" > src/main.rs && cargo build --release && rm -rf src
+
+COPY . .
+RUN touch src/main.rs && cargo build --release
+
+FROM debian:bookworm-slim
+RUN apt-get update && apt-get install -y --no-install-recommends ca-certificates && rm -rf /var/lib/apt/lists/*
+RUN mkdir -p /data
+WORKDIR /app
+COPY --from=builder /app/target/release/{app} /usr/local/bin/{app}
+
+# fly.toml's internal_port
+ENV PORT=8080
+EXPOSE 8080
+CMD ["{app}"]
+"#, app = app_name);
+    write_docker_files(dir, &dockerfile, &["target/"])?;
+    
+    create_common_files(dir, app_name, description).await?;
     
     println!("   ✓ Created Rust project with Axum + LiteFS");
     Ok(())
 }
 
 /// Create Node.js + Express project
-async fn create_node_express_project(app_name: &str, description: &str) -> Result<()> {
+async fn create_node_express_project(dir: &Path, app_name: &str, description: &str) -> Result<()> {
     let package_json = format!(r#"{{
   "name": "{}",
   "version": "0.1.0",
@@ -573,7 +780,7 @@ async fn create_node_express_project(app_name: &str, description: &str) -> Resul
   }}
 }}"#, app_name, description);
     
-    fs::write("package.json", package_json).context("Failed to write package.json")?;
+    fs::write(dir.join("package.json"), package_json).context("Failed to write package.json")?;
     
     let server_js = r#"import express from 'express';
 import sqlite3 from 'sqlite3';
@@ -699,24 +906,44 @@ app.listen(port, '0.0.0.0', () => {
 });
 "#;
     
-    fs::create_dir_all("src").context("Failed to create src directory")?;
-    fs::write("src/server.js", server_js).context("Failed to write server.js")?;
+    fs::create_dir_all(dir.join("src")).context("Failed to create src directory")?;
+    fs::write(dir.join("src/server.js"), server_js).context("Failed to write server.js")?;
+    
+    // Dockerfile: the app's files are copied last, with nothing run after
+    // them, so `minifly dev --fast-reload` can sync changes into the container
+    let dockerfile = r#"FROM node:20-slim AS deps
+WORKDIR /app
+COPY package*.json ./
+RUN npm install --omit=dev
+
+FROM node:20-slim
+RUN mkdir -p /data /app/data
+WORKDIR /app
+COPY --from=deps /app/node_modules ./node_modules
+COPY . .
+
+# fly.toml's internal_port
+ENV NODE_ENV=production PORT=8080
+EXPOSE 8080
+CMD ["node", "src/server.js"]
+"#;
+    write_docker_files(dir, dockerfile, &["node_modules/", "npm-debug.log*"])?;
     
-    create_common_files(app_name, description).await?;
+    create_common_files(dir, app_name, description).await?;
     
     println!("   ✓ Created Node.js project with Express + SQLite");
     Ok(())
 }
 
 /// Create Python + FastAPI project
-async fn create_python_fastapi_project(app_name: &str, description: &str) -> Result<()> {
+async fn create_python_fastapi_project(dir: &Path, app_name: &str, description: &str) -> Result<()> {
     let requirements_txt = r#"fastapi==0.104.1
 uvicorn[standard]==0.24.0
 aiosqlite==0.19.0
 pydantic==2.5.0
 "#;
     
-    fs::write("requirements.txt", requirements_txt).context("Failed to write requirements.txt")?;
+    fs::write(dir.join("requirements.txt"), requirements_txt).context("Failed to write requirements.txt")?;
     
     let main_py = format!(r#"from fastapi import FastAPI, HTTPException, Path
 from fastapi.responses import HTMLResponse
@@ -732,7 +959,8 @@ app = FastAPI(
     version="0.1.0"
 )
 
-DATABASE_URL = os.getenv("DATABASE_URL", "./data/app.db")
+# DATABASE_URL is a sqlite:// URL, aiosqlite wants a path
+DATABASE_URL = os.getenv("DATABASE_URL", "./data/app.db").removeprefix("sqlite://")
 
 class User(BaseModel):
     id: int
@@ -857,18 +1085,38 @@ if __name__ == "__main__":
     uvicorn.run(app, host="0.0.0.0", port=port)
 "#, app_name, description);
     
-    fs::write("main.py", main_py).context("Failed to write main.py")?;
+    fs::write(dir.join("main.py"), main_py).context("Failed to write main.py")?;
+    
+    // Dockerfile: the app's files are copied last, with nothing run after
+    // them, so `minifly dev --fast-reload` can sync changes into the container
+    let dockerfile = r#"FROM python:3.12-slim AS builder
+WORKDIR /app
+COPY requirements.txt ./
+RUN pip install --no-cache-dir --prefix=/install -r requirements.txt
+
+FROM python:3.12-slim
+RUN mkdir -p /data /app/data
+WORKDIR /app
+COPY --from=builder /install /usr/local
+COPY . .
+
+# fly.toml's internal_port
+ENV PORT=8080 PYTHONUNBUFFERED=1
+EXPOSE 8080
+CMD ["python", "main.py"]
+"#;
+    write_docker_files(dir, dockerfile, &["__pycache__/", "*.pyc", ".venv/", "venv/"])?;
     
-    create_common_files(app_name, description).await?;
+    create_common_files(dir, app_name, description).await?;
     
     println!("   ✓ Created Python project with FastAPI + SQLite");
     Ok(())
 }
 
 /// Create Go + Gin project
-async fn create_go_gin_project(app_name: &str, description: &str) -> Result<()> {
+async fn create_go_gin_project(dir: &Path, app_name: &str, description: &str) -> Result<()> {
     let go_mod = format!("module {}\n\ngo 1.21\n\nrequire (\n    github.com/gin-gonic/gin v1.9.1\n    github.com/mattn/go-sqlite3 v1.14.18\n)\n", app_name);
-    fs::write("go.mod", go_mod).context("Failed to write go.mod")?;
+    fs::write(dir.join("go.mod"), go_mod).context("Failed to write go.mod")?;
     
     let main_go = r#"package main
 
@@ -878,38 +1126,39 @@ import (
     "log"
     "net/http"
     "os"
-    "strconv"
+    "strings"
     "time"
 
     "github.com/gin-gonic/gin"
     _ "github.com/mattn/go-sqlite3"
 )
 
-type User struct {{
+type User struct {
     ID       int    `json:"id" db:"id"`
     Name     string `json:"name" db:"name"`
     Email    string `json:"email" db:"email"`
     TenantID string `json:"tenant_id" db:"tenant_id"`
-}}
+}
 
-type CreateUserRequest struct {{
+type CreateUserRequest struct {
     Name  string `json:"name" binding:"required"`
     Email string `json:"email" binding:"required"`
-}}
+}
 
 var db *sql.DB
 
-func initDB() {{
+func initDB() {
     var err error
-    dbPath := os.Getenv("DATABASE_URL")
-    if dbPath == "" {{
+    // DATABASE_URL is a sqlite:// URL, go-sqlite3 wants a path
+    dbPath := strings.TrimPrefix(os.Getenv("DATABASE_URL"), "sqlite://")
+    if dbPath == "" {
         dbPath = "./data/app.db"
-    }}
+    }
     
     db, err = sql.Open("sqlite3", dbPath)
-    if err != nil {{
+    if err != nil {
         log.Fatal("Failed to connect to database:", err)
-    }}
+    }
     
     // Create table
     _, err = db.Exec(`
@@ -921,24 +1170,24 @@ func initDB() {{
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
         )
     `)
-    if err != nil {{
+    if err != nil {
         log.Fatal("Failed to create table:", err)
-    }}
+    }
     
     // Create index
     _, err = db.Exec("CREATE INDEX IF NOT EXISTS idx_users_tenant_id ON users(tenant_id)")
-    if err != nil {{
+    if err != nil {
         log.Fatal("Failed to create index:", err)
-    }}
-}}
+    }
+}
 
-func main() {{
+func main() {
     initDB()
     defer db.Close()
     
     r := gin.Default()
     
-    r.GET("/", func(c *gin.Context) {{
+    r.GET("/", func(c *gin.Context) {
         c.Header("Content-Type", "text/html")
         c.String(http.StatusOK, `
             <html>
@@ -950,50 +1199,50 @@ func main() {{
                 </body>
             </html>
         `)
-    }})
+    })
     
-    r.GET("/tenant/:tenantId", func(c *gin.Context) {{
+    r.GET("/tenant/:tenantId", func(c *gin.Context) {
         tenantID := c.Param("tenantId")
         
         rows, err := db.Query("SELECT id, name, email, tenant_id FROM users WHERE tenant_id = ? ORDER BY id DESC", tenantID)
-        if err != nil {{
-            c.JSON(http.StatusInternalServerError, gin.H{{"error": "Database error"}})
+        if err != nil {
+            c.JSON(http.StatusInternalServerError, gin.H{"error": "Database error"})
             return
-        }}
+        }
         defer rows.Close()
         
         var users []User
-        for rows.Next() {{
+        for rows.Next() {
             var user User
             err := rows.Scan(&user.ID, &user.Name, &user.Email, &user.TenantID)
-            if err != nil {{
+            if err != nil {
                 continue
-            }}
+            }
             users = append(users, user)
-        }}
+        }
         
         userList := ""
-        for _, user := range users {{
+        for _, user := range users {
             userList += fmt.Sprintf(`
                 <div style="border: 1px solid #ddd; padding: 10px; margin: 10px 0;">
-                    <strong>%%s</strong> - %%s
-                    <small>(ID: %%d)</small>
+                    <strong>%s</strong> - %s
+                    <small>(ID: %d)</small>
                 </div>
             `, user.Name, user.Email, user.ID)
-        }}
+        }
         
-        if userList == "" {{
+        if userList == "" {
             userList = "<p>No users yet.</p>"
-        }}
+        }
         
         html := fmt.Sprintf(`
             <html>
-                <head><title>%%s - Minifly App</title></head>
+                <head><title>%s - Minifly App</title></head>
                 <body>
-                    <h1>Welcome to <span style="background: #0066ff; color: white; padding: 4px 8px; border-radius: 4px;">%%s</span></h1>
+                    <h1>Welcome to <span style="background: #0066ff; color: white; padding: 4px 8px; border-radius: 4px;">%s</span></h1>
                     <p>This is a multi-tenant Go application!</p>
-                    <h2>Users (%%d)</h2>
-                    %%s
+                    <h2>Users (%d)</h2>
+                    %s
                     <hr>
                     <p><a href="/">← Back to Home</a></p>
                 </body>
@@ -1002,73 +1251,92 @@ func main() {{
         
         c.Header("Content-Type", "text/html")
         c.String(http.StatusOK, html)
-    }})
+    })
     
-    r.POST("/tenant/:tenantId/users", func(c *gin.Context) {{
+    r.POST("/tenant/:tenantId/users", func(c *gin.Context) {
         tenantID := c.Param("tenantId")
         
         var req CreateUserRequest
-        if err := c.ShouldBindJSON(&req); err != nil {{
-            c.JSON(http.StatusBadRequest, gin.H{{"error": err.Error()}})
+        if err := c.ShouldBindJSON(&req); err != nil {
+            c.JSON(http.StatusBadRequest, gin.H{"error": err.Error()})
             return
-        }}
+        }
         
         result, err := db.Exec("INSERT INTO users (name, email, tenant_id) VALUES (?, ?, ?)",
             req.Name, req.Email, tenantID)
-        if err != nil {{
-            c.JSON(http.StatusInternalServerError, gin.H{{"error": "Database error"}})
+        if err != nil {
+            c.JSON(http.StatusInternalServerError, gin.H{"error": "Database error"})
             return
-        }}
+        }
         
         userID, _ := result.LastInsertId()
         
-        user := User{{
+        user := User{
             ID:       int(userID),
             Name:     req.Name,
             Email:    req.Email,
             TenantID: tenantID,
-        }}
+        }
         
         c.JSON(http.StatusCreated, user)
-    }})
+    })
     
-    r.GET("/health", func(c *gin.Context) {{
-        c.JSON(http.StatusOK, gin.H{{
+    r.GET("/health", func(c *gin.Context) {
+        c.JSON(http.StatusOK, gin.H{
             "status":    "healthy",
             "service":   "minifly-go-example",
             "timestamp": time.Now().Format(time.RFC3339),
-        }})
-    }})
+        })
+    })
     
     port := os.Getenv("PORT")
-    if port == "" {{
+    if port == "" {
         port = "8080"
-    }}
+    }
     
-    log.Printf("🚀 Server starting on port %%s", port)
-    log.Printf("📍 Region: %%s", getEnv("FLY_REGION", "local"))
+    log.Printf("🚀 Server starting on port %s", port)
+    log.Printf("📍 Region: %s", getEnv("FLY_REGION", "local"))
     
     r.Run(":" + port)
-}}
+}
 
-func getEnv(key, defaultValue string) string {{
-    if value := os.Getenv(key); value != "" {{
+func getEnv(key, defaultValue string) string {
+    if value := os.Getenv(key); value != "" {
         return value
-    }}
+    }
     return defaultValue
-}}
+}
 "#;
     
-    fs::write("main.go", main_go).context("Failed to write main.go")?;
+    fs::write(dir.join("main.go"), main_go).context("Failed to write main.go")?;
+    
+    // Dockerfile: go-sqlite3 needs cgo, so the binary is built against the
+    // same glibc it runs with
+    let dockerfile = r#"FROM golang:1.21-bookworm AS builder
+WORKDIR /app
+COPY . .
+RUN go mod tidy && CGO_ENABLED=1 go build -o /app/server .
+
+FROM debian:bookworm-slim
+RUN apt-get update && apt-get install -y --no-install-recommends ca-certificates && rm -rf /var/lib/apt/lists/*
+RUN mkdir -p /data
+COPY --from=builder /app/server /usr/local/bin/server
+
+# fly.toml's internal_port
+ENV PORT=8080 GIN_MODE=release
+EXPOSE 8080
+CMD ["server"]
+"#;
+    write_docker_files(dir, dockerfile, &["server"])?;
     
-    create_common_files(app_name, description).await?;
+    create_common_files(dir, app_name, description).await?;
     
     println!("   ✓ Created Go project with Gin + SQLite");
     Ok(())
 }
 
 /// Create minimal Docker project
-async fn create_minimal_docker_project(app_name: &str, description: &str) -> Result<()> {
+async fn create_minimal_docker_project(dir: &Path, app_name: &str, description: &str) -> Result<()> {
     let dockerfile = r#"# Choose your base image
 FROM alpine:latest
 
@@ -1092,16 +1360,28 @@ HEALTHCHECK --interval=30s --timeout=3s --start-period=5s --retries=3 \
 CMD ["echo", "Configure this Dockerfile for your application"]
 "#;
     
-    fs::write("Dockerfile", dockerfile).context("Failed to write Dockerfile")?;
+    write_docker_files(dir, dockerfile, &[])?;
     
-    create_common_files(app_name, description).await?;
+    create_common_files(dir, app_name, description).await?;
     
     println!("   ✓ Created minimal Docker project");
     Ok(())
 }
 
+/// Files no template's image needs, kept out of the Docker build context
+const COMMON_DOCKERIGNORE: &[&str] = &[".git", ".fly.secrets*", "data/", "docker-compose.yml", "README.md"];
+
+/// Writes a template's Dockerfile, and a .dockerignore with the common
+/// entries and the language's own `ignored` ones
+fn write_docker_files(dir: &Path, dockerfile: &str, ignored: &[&str]) -> Result<()> {
+    fs::write(dir.join("Dockerfile"), dockerfile).context("Failed to write Dockerfile")?;
+    let dockerignore: String = COMMON_DOCKERIGNORE.iter().chain(ignored).map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join(".dockerignore"), dockerignore).context("Failed to write .dockerignore")?;
+    Ok(())
+}
+
 /// Create common project files (fly.toml, litefs.yml, docker-compose.yml, etc.)
-async fn create_common_files(app_name: &str, description: &str) -> Result<()> {
+async fn create_common_files(dir: &Path, app_name: &str, description: &str) -> Result<()> {
     // fly.toml
     let fly_toml = format!(r#"app = "{}"
 primary_region = "sjc"
@@ -1129,13 +1409,13 @@ primary_region = "sjc"
   url_prefix = "/static/"
 "#, app_name);
     
-    fs::write("fly.toml", fly_toml).context("Failed to write fly.toml")?;
+    fs::write(dir.join("fly.toml"), fly_toml).context("Failed to write fly.toml")?;
     
     // LiteFS configuration
-    create_litefs_config().await?;
+    create_litefs_config(dir).await?;
     
     // Docker Compose for local development
-    create_docker_compose().await?;
+    create_docker_compose(dir).await?;
     
     // README.md
     let readme = format!(r#"# {}
@@ -1222,7 +1502,7 @@ curl -X POST http://localhost:8080/tenant/acme/users \
 - [LiteFS Documentation](https://fly.io/docs/litefs)
 "#, app_name, description, app_name);
     
-    fs::write("README.md", readme).context("Failed to write README.md")?;
+    fs::write(dir.join("README.md"), readme).context("Failed to write README.md")?;
     
     // .gitignore
     let gitignore = r#"# Dependencies
@@ -1264,13 +1544,13 @@ logs/
 *.pid.lock
 "#;
     
-    fs::write(".gitignore", gitignore).context("Failed to write .gitignore")?;
+    fs::write(dir.join(".gitignore"), gitignore).context("Failed to write .gitignore")?;
     
     Ok(())
 }
 
 /// Create LiteFS configuration
-async fn create_litefs_config() -> Result<()> {
+async fn create_litefs_config(dir: &Path) -> Result<()> {
     let litefs_yml = r#"# LiteFS configuration for distributed SQLite
 fuse:
   dir: "/litefs"
@@ -1283,14 +1563,14 @@ proxy:
   target: "localhost:8080"
   db: "app.db"
 
+# A static lease needs no Consul: the machine with candidate set to true is
+# always primary and the others replicate from the advertise-url. Switch to a
+# consul lease for automatic failover in production.
 lease:
-  type: "consul"
+  type: "static"
+  advertise-url: "http://localhost:20202"
   candidate: true
   promote: true
-  
-  consul:
-    url: "http://localhost:8500"
-    key: "minifly/leader"
 
 exec:
   - cmd: "/app/server"
@@ -1300,12 +1580,12 @@ log:
   level: "INFO"
 "#;
     
-    fs::write("litefs.yml", litefs_yml).context("Failed to write litefs.yml")?;
+    fs::write(dir.join("litefs.yml"), litefs_yml).context("Failed to write litefs.yml")?;
     Ok(())
 }
 
 /// Create Docker Compose configuration
-async fn create_docker_compose() -> Result<()> {
+async fn create_docker_compose(dir: &Path) -> Result<()> {
     let docker_compose = r#"version: '3.8'
 
 services:
@@ -1319,19 +1599,6 @@ services:
       - PORT=8080
     volumes:
       - ./data:/data
-    depends_on:
-      - consul
-    restart: unless-stopped
-
-  consul:
-    image: consul:1.15
-    ports:
-      - "8500:8500"
-    command: >
-      consul agent -dev -ui -client=0.0.0.0
-      -log-level=INFO
-    environment:
-      - CONSUL_BIND_INTERFACE=eth0
     restart: unless-stopped
 
 volumes:
@@ -1339,13 +1606,13 @@ volumes:
     driver: local
 "#;
     
-    fs::write("docker-compose.yml", docker_compose).context("Failed to write docker-compose.yml")?;
+    fs::write(dir.join("docker-compose.yml"), docker_compose).context("Failed to write docker-compose.yml")?;
     Ok(())
 }
 
 /// Create multi-tenant example
-async fn create_multitenant_example() -> Result<()> {
-    fs::create_dir_all("examples").context("Failed to create examples directory")?;
+async fn create_multitenant_example(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir.join("examples")).context("Failed to create examples directory")?;
     
     let example_script = r#"#!/bin/bash
 # Multi-tenant example script
@@ -1394,17 +1661,17 @@ echo "  - http://localhost:8080/tenant/widgets-inc"
 echo "  - http://localhost:8080/tenant/startup-xyz"
 "#;
     
-    fs::write("examples/setup-tenants.sh", example_script).context("Failed to write example script")?;
+    fs::write(dir.join("examples/setup-tenants.sh"), example_script).context("Failed to write example script")?;
     
     // Make script executable on Unix systems
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata("examples/setup-tenants.sh")
+        let mut perms = fs::metadata(dir.join("examples/setup-tenants.sh"))
             .context("Failed to get script metadata")?
             .permissions();
         perms.set_mode(0o755);
-        fs::set_permissions("examples/setup-tenants.sh", perms)
+        fs::set_permissions(dir.join("examples/setup-tenants.sh"), perms)
             .context("Failed to set script permissions")?;
     }
     
@@ -1412,8 +1679,8 @@ echo "  - http://localhost:8080/tenant/startup-xyz"
 }
 
 /// Create GitHub Actions workflow
-async fn create_github_workflow() -> Result<()> {
-    fs::create_dir_all(".github/workflows").context("Failed to create workflows directory")?;
+async fn create_github_workflow(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir.join(".github/workflows")).context("Failed to create workflows directory")?;
     
     let workflow = r#"name: Deploy to Fly.io
 
@@ -1435,13 +1702,13 @@ jobs:
           FLY_API_TOKEN: ${{ secrets.FLY_API_TOKEN }}
 "#;
     
-    fs::write(".github/workflows/fly.yml", workflow).context("Failed to write GitHub workflow")?;
+    fs::write(dir.join(".github/workflows/fly.yml"), workflow).context("Failed to write GitHub workflow")?;
     Ok(())
 }
 
 /// Create development scripts
-async fn create_dev_scripts() -> Result<()> {
-    fs::create_dir_all("scripts").context("Failed to create scripts directory")?;
+async fn create_dev_scripts(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir.join("scripts")).context("Failed to create scripts directory")?;
     
     let dev_script = r#"#!/bin/bash
 # Development environment setup script
@@ -1491,17 +1758,17 @@ echo ""
 echo "To stop: kill $MINIFLY_PID"
 "#;
     
-    fs::write("scripts/dev-setup.sh", dev_script).context("Failed to write dev script")?;
+    fs::write(dir.join("scripts/dev-setup.sh"), dev_script).context("Failed to write dev script")?;
     
     // Make script executable on Unix systems
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata("scripts/dev-setup.sh")
+        let mut perms = fs::metadata(dir.join("scripts/dev-setup.sh"))
             .context("Failed to get script metadata")?
             .permissions();
         perms.set_mode(0o755);
-        fs::set_permissions("scripts/dev-setup.sh", perms)
+        fs::set_permissions(dir.join("scripts/dev-setup.sh"), perms)
             .context("Failed to set script permissions")?;
     }
     
@@ -1529,4 +1796,72 @@ fn show_getting_started() {
     println!("  • Development: {}", "./scripts/dev-setup.sh".blue());
     
     println!("\n{}", "✨ Happy coding with Minifly!".green());
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scaffold_template_without_prompts() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = find_template("node-express").unwrap();
+        
+        scaffold_project(dir.path(), &template, "my-api", "An API").await.unwrap();
+        
+        for file in ["package.json", "src/server.js", "Dockerfile", ".dockerignore", "fly.toml", "litefs.yml", "docker-compose.yml", "README.md", "data/litefs"] {
+            assert!(dir.path().join(file).exists(), "{} was not created", file);
+        }
+        let fly_toml = fs::read_to_string(dir.path().join("fly.toml")).unwrap();
+        assert!(fly_toml.starts_with("app = \"my-api\""), "{}", fly_toml);
+        
+        assert_eq!(find_template("Minimal Docker").unwrap().id, "minimal");
+        let err = find_template("php-laravel").unwrap_err().to_string();
+        assert!(err.contains("rust-axum, node-express"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_existing_project_files_are_found_before_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = find_template("node-express").unwrap();
+        assert!(existing_project_files(dir.path(), &template, "my-api", "An API").await.unwrap().is_empty());
+        
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        fs::write(dir.path().join("src/server.js"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let existing = existing_project_files(dir.path(), &template, "my-api", "An API").await.unwrap();
+        assert_eq!(existing, vec!["package.json", "src/server.js"]);
+        assert_eq!(fs::read_to_string(dir.path().join("package.json")).unwrap(), "{}");
+    }
+
+    #[tokio::test]
+    async fn test_every_template_writes_a_dockerfile() {
+        let base_images = [
+            ("rust-axum", "FROM rust:"),
+            ("node-express", "FROM node:"),
+            ("python-fastapi", "FROM python:"),
+            ("go-gin", "FROM golang:"),
+            ("minimal", "FROM alpine:"),
+        ];
+        assert_eq!(base_images.len(), get_available_templates().len());
+        
+        for (id, base_image) in base_images {
+            let dir = tempfile::tempdir().unwrap();
+            scaffold_project(dir.path(), &find_template(id).unwrap(), "my-app", "An app").await.unwrap();
+            
+            let dockerfile = fs::read_to_string(dir.path().join("Dockerfile")).unwrap();
+            let first_stage = dockerfile.lines().find(|line| line.starts_with("FROM ")).unwrap();
+            assert!(first_stage.starts_with(base_image), "{}: {}", id, first_stage);
+            assert!(dockerfile.contains("EXPOSE 8080"), "{} listens on fly.toml's internal_port", id);
+            let dockerignore = fs::read_to_string(dir.path().join(".dockerignore")).unwrap();
+            assert!(dockerignore.lines().any(|line| line == "data/"), "{}: {}", id, dockerignore);
+            
+            // main.go isn't run through format!, so nothing in it is escaped
+            if id == "go-gin" {
+                let main_go = fs::read_to_string(dir.path().join("main.go")).unwrap();
+                assert!(!main_go.contains("%%") && !main_go.contains("{{"), "{}", main_go);
+            }
+        }
+    }
+}
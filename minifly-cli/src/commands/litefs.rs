@@ -0,0 +1,116 @@
+//! LiteFS database inspection.
+//!
+//! `minifly litefs shell` opens a machine's SQLite database with `sqlite3`,
+//! finding it in the volume directory the API server bind-mounts into the
+//! machine.
+
+use anyhow::{bail, Context, Result};
+use colored::*;
+use minifly_core::models::{Machine, MountConfig};
+use minifly_core::volumes;
+use std::path::PathBuf;
+use std::process::Command;
+use crate::client::ApiClient;
+
+/// Opens an interactive `sqlite3` session on a machine's database, or runs
+/// a single statement when `sql` is given
+pub async fn shell(
+    client: &ApiClient,
+    machine_id: &str,
+    app: Option<String>,
+    db: &str,
+    sql: Option<String>,
+) -> Result<()> {
+    let (app, machine) = find_machine(client, machine_id, app).await?;
+    let db_path = database_path(&app, machine_id, machine.config.mounts.as_deref(), db)?;
+    
+    if !db_path.exists() {
+        bail!("Database {} does not exist yet", db_path.display());
+    }
+    
+    let mut command = Command::new("sqlite3");
+    command.arg(&db_path);
+    match sql {
+        Some(sql) => {
+            command.arg(sql);
+        }
+        None => println!("Opening {} ({})", db_path.display().to_string().yellow(), "type .quit to exit".dimmed()),
+    }
+    
+    let status = command.status().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => anyhow::anyhow!("sqlite3 is not installed; install it to inspect LiteFS databases"),
+        _ => anyhow::Error::new(e).context("Failed to run sqlite3"),
+    })?;
+    if !status.success() {
+        bail!("sqlite3 exited with {}", status);
+    }
+    
+    Ok(())
+}
+
+/// Finds a machine and the app it belongs to, searching every app unless one is given
+async fn find_machine(client: &ApiClient, machine_id: &str, app: Option<String>) -> Result<(String, Machine)> {
+    let apps = match app {
+        Some(app) => vec![app],
+        None => client.list_apps().await?.into_iter().map(|app| app.name).collect(),
+    };
+    
+    for app in apps {
+        let machines = client.list_machines(&app).await
+            .with_context(|| format!("Failed to list machines of app {}", app))?;
+        if let Some(machine) = machines.into_iter().find(|m| m.id == machine_id) {
+            return Ok((app, machine));
+        }
+    }
+    
+    bail!("Machine {} not found", machine_id)
+}
+
+/// Host path of a machine's database file.
+///
+/// Uses the volume mounted at `/litefs` or a data directory, falling back
+/// to the machine's first volume.
+fn database_path(
+    app: &str,
+    machine_id: &str,
+    mounts: Option<&[MountConfig]>,
+    db: &str,
+) -> Result<PathBuf> {
+    let mounts = mounts.unwrap_or_default();
+    let Some(mount) = mounts.iter().find(|m| volumes::is_database_mount(&m.path)).or(mounts.first()) else {
+        bail!(
+            "Machine {} has no volume mounted, so it has no LiteFS database. \
+             Add a [mounts] section to fly.toml and redeploy.",
+            machine_id
+        );
+    };
+    
+    Ok(volumes::mounted_volume_path(app, &mount.volume).join(db))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn mount(volume: &str, path: &str) -> MountConfig {
+        MountConfig { volume: volume.to_string(), path: path.to_string() }
+    }
+    
+    #[test]
+    #[serial_test::serial]
+    fn test_database_path_uses_database_volume() {
+        // Databases are opened where the API server mounts volumes from
+        std::env::set_var("MINIFLY_DATA_DIR", "/var/minifly");
+        let mounts = [mount("vol_uploads", "/uploads"), mount("vol_litefs", "/litefs")];
+        
+        let path = database_path("web", "m1", Some(&mounts), volumes::DATABASE_FILE).unwrap();
+        assert_eq!(path, PathBuf::from("/var/minifly/minifly-data/web/volumes/vol_litefs/app.db"));
+        
+        let path = database_path("web", "m1", Some(&mounts[..1]), "other.db").unwrap();
+        assert_eq!(path, PathBuf::from("/var/minifly/minifly-data/web/volumes/vol_uploads/other.db"));
+        
+        let err = database_path("web", "m1", None, volumes::DATABASE_FILE).unwrap_err();
+        assert!(err.to_string().contains("no volume mounted"));
+        std::env::remove_var("MINIFLY_DATA_DIR");
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::{Result, Context};
 use colored::*;
 use futures::StreamExt;
+use regex::Regex;
 use serde::Deserialize;
+use std::collections::VecDeque;
 use std::io::{self, Write};
 use crate::client::ApiClient;
 
@@ -18,35 +20,241 @@ struct LogEntry {
     correlation_id: Option<String>,
 }
 
+/// Log severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Parses a level from a JSON log field, a name or a pino/bunyan number
+    fn from_json(value: &serde_json::Value) -> Option<Self> {
+        match value {
+            serde_json::Value::String(level) => Level::parse(level),
+            serde_json::Value::Number(level) => match level.as_u64()? {
+                50.. => Some(Level::Error),
+                40..=49 => Some(Level::Warn),
+                30..=39 => Some(Level::Info),
+                _ => Some(Level::Debug),
+            },
+            _ => None,
+        }
+    }
+    
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+    
+    fn parse(level: &str) -> Option<Self> {
+        match level.trim().to_lowercase().as_str() {
+            "trace" | "debug" => Some(Level::Debug),
+            "info" | "notice" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" | "err" | "fatal" | "critical" | "panic" => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Client-side filter for `--level` and `--grep`
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    min_level: Option<Level>,
+    pattern: Option<Regex>,
+}
+
+impl LogFilter {
+    /// Builds a filter showing lines at `level` or above that match the `grep` regex
+    pub fn new(level: Option<String>, grep: Option<String>) -> Result<Self> {
+        let min_level = level
+            .map(|l| Level::parse(&l).ok_or_else(|| anyhow::anyhow!("Unknown log level '{}' (use debug, info, warn or error)", l)))
+            .transpose()?;
+        let pattern = grep
+            .map(|p| Regex::new(&p).with_context(|| format!("Invalid --grep pattern '{}'", p)))
+            .transpose()?;
+        
+        Ok(Self { min_level, pattern })
+    }
+    
+    fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(min_level) = self.min_level {
+            if line_level(&entry.message, &entry.level) < min_level {
+                return false;
+            }
+        }
+        
+        self.pattern.as_ref().is_none_or(|p| p.is_match(&entry.message))
+    }
+}
+
+/// Determines a log line's level.
+/// 
+/// JSON lines use their `level` (or `lvl`/`severity`) field. Plain lines use
+/// the first level word in the text, falling back to the level the API guessed.
+fn line_level(message: &str, fallback: &str) -> Level {
+    if let Ok(serde_json::Value::Object(fields)) = serde_json::from_str::<serde_json::Value>(message) {
+        let level = LEVEL_KEYS.iter()
+            .find_map(|key| fields.get(*key))
+            .and_then(Level::from_json);
+        if let Some(level) = level {
+            return level;
+        }
+    }
+    
+    message
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .find_map(Level::parse)
+        .or_else(|| Level::parse(fallback))
+        .unwrap_or(Level::Info)
+}
+
+const LEVEL_KEYS: [&str; 3] = ["level", "lvl", "severity"];
+const TIME_KEYS: [&str; 4] = ["time", "timestamp", "ts", "@timestamp"];
+const MESSAGE_KEYS: [&str; 3] = ["msg", "message", "event"];
+/// Fields of pino and bunyan lines that are the same on every line
+const NOISE_KEYS: [&str; 3] = ["v", "pid", "hostname"];
+
+/// A JSON log line taken apart for `--pretty`
+#[derive(Debug, PartialEq)]
+struct JsonLine {
+    timestamp: Option<String>,
+    level: Option<Level>,
+    message: String,
+    /// Remaining fields as `key=value`, sorted by key
+    fields: Vec<String>,
+}
+
+impl JsonLine {
+    /// Parses a JSON object line; other lines aren't JSON logs
+    fn parse(line: &str) -> Option<Self> {
+        let serde_json::Value::Object(mut fields) = serde_json::from_str(line.trim()).ok()? else {
+            return None;
+        };
+        let mut take = |keys: &[&str]| keys.iter().find_map(|key| fields.remove(*key));
+        
+        let level = take(&LEVEL_KEYS).as_ref().and_then(Level::from_json);
+        let timestamp = take(&TIME_KEYS).and_then(|time| match time {
+            serde_json::Value::String(time) => Some(time),
+            // pino writes epoch milliseconds, others seconds
+            serde_json::Value::Number(time) => {
+                let time = time.as_f64()?;
+                let millis = if time > 1e12 { time } else { time * 1000.0 };
+                chrono::DateTime::from_timestamp_millis(millis as i64).map(|time| time.to_rfc3339())
+            }
+            _ => None,
+        });
+        let message = match take(&MESSAGE_KEYS) {
+            Some(serde_json::Value::String(message)) => message,
+            Some(message) => message.to_string(),
+            None => String::new(),
+        };
+        let fields = fields.into_iter()
+            .filter(|(key, _)| !NOISE_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) if !value.contains(char::is_whitespace) && !value.is_empty() => format!("{}={}", key, value),
+                value => format!("{}={}", key, value),
+            })
+            .collect();
+        
+        Some(Self { timestamp, level, message, fields })
+    }
+}
+
+/// Lines shown without `--follow`, `--tail` or a time window
+pub const DEFAULT_TAIL: usize = 100;
+
+/// Which logs to request from the API
+#[derive(Debug, Default, Clone)]
+pub struct LogsOptions {
+    /// Keep streaming new lines as they are written
+    pub follow: bool,
+    /// Number of recent lines to show; [`DEFAULT_TAIL`] unless following or
+    /// given a time window
+    pub tail: Option<usize>,
+    /// Only machines in this region
+    pub region: Option<String>,
+    /// Start of the time window (RFC3339, Unix seconds, or relative like `10m`)
+    pub since: Option<String>,
+    /// End of the time window, in the same formats as `since`
+    pub until: Option<String>,
+    /// Reformat JSON log lines into timestamp, level, message and fields
+    pub pretty: bool,
+}
+
+/// Handle `minifly logs -a <app>`, streaming interleaved logs from all of an app's machines
+/// 
+/// Each line is prefixed with the region and machine ID it came from, as with
+/// `fly logs -a`.
+pub async fn handle_app(
+    client: &ApiClient,
+    app_name: &str,
+    options: &LogsOptions,
+    filter: &LogFilter,
+) -> Result<()> {
+    if let Some(ref region_filter) = options.region {
+        println!("Filtering logs for region: {}", region_filter.cyan());
+    }
+    
+    if options.follow {
+        println!("🔄 Streaming logs for all machines of app {} (following)...", app_name.yellow());
+        println!("{}", "Press Ctrl+C to stop".dimmed());
+    } else {
+        println!("📄 Getting recent logs for all machines of app {}...", app_name.yellow());
+    }
+    
+    let url = logs_url(&format!("/apps/{}/logs", app_name), options);
+    stream_logs(client, &url, &format!("App {}", app_name), filter, options).await.map(|_| ())
+}
+
 /// Handle the logs command with real-time streaming
 /// 
 /// # Arguments
 /// * `client` - API client for communicating with Minifly API
 /// * `machine_id` - Machine ID to get logs from
-/// * `follow` - Whether to follow log output (stream in real-time)
-/// * `region` - Optional region filter for logs
+/// * `options` - Follow mode, region and time window to request
+/// * `filter` - Level and pattern filter applied to each line
 /// 
 /// # Examples
 /// ```
-/// // Get last 50 lines of logs
-/// logs::handle(&client, "abc123", false, None).await?;
+/// // Get the most recent logs
+/// logs::handle(&client, "abc123", &LogsOptions::default(), &LogFilter::default()).await?;
 /// 
 /// // Follow logs in real-time
-/// logs::handle(&client, "abc123", true, None).await?;
+/// let follow = LogsOptions { follow: true, ..Default::default() };
+/// logs::handle(&client, "abc123", &follow, &LogFilter::default()).await?;
+/// 
+/// // Errors mentioning "timeout" from the last 5 minutes
+/// let recent = LogsOptions { since: Some("5m".to_string()), ..Default::default() };
+/// let filter = LogFilter::new(Some("error".to_string()), Some("timeout".to_string()))?;
+/// logs::handle(&client, "abc123", &recent, &filter).await?;
 /// 
-/// // Filter by region
-/// logs::handle(&client, "abc123", true, Some("sjc".to_string())).await?;
+/// // JSON lines reformatted for reading
+/// let pretty = LogsOptions { pretty: true, ..Default::default() };
+/// logs::handle(&client, "abc123", &pretty, &LogFilter::default()).await?;
 /// ```
-pub async fn handle(client: &ApiClient, machine_id: &str, follow: bool, region: Option<String>) -> Result<()> {
+pub async fn handle(
+    client: &ApiClient,
+    machine_id: &str,
+    options: &LogsOptions,
+    filter: &LogFilter,
+) -> Result<()> {
     // First, get the app name for this machine
     let app_name = client.get_machine_app(machine_id).await
         .context("Failed to get app name for machine")?;
     
-    if let Some(ref region_filter) = region {
+    if let Some(ref region_filter) = options.region {
         println!("Filtering logs for region: {}", region_filter.cyan());
     }
     
-    if follow {
+    if options.follow {
         println!("🔄 Streaming logs for machine {} (following)...", machine_id.yellow());
         println!("{}", "Press Ctrl+C to stop".dimmed());
     } else {
@@ -67,40 +275,60 @@ pub async fn handle(client: &ApiClient, machine_id: &str, follow: bool, region:
     }
     
     // Start streaming logs
-    stream_logs(client, &app_name, machine_id, follow, region).await
+    let url = logs_url(&format!("/apps/{}/machines/{}/logs", app_name, machine_id), options);
+    stream_logs(client, &url, &format!("Machine {}", machine_id), filter, options).await.map(|_| ())
 }
 
-/// Stream logs from the API server
-async fn stream_logs(
-    client: &ApiClient, 
-    app_name: &str,
-    machine_id: &str, 
-    follow: bool, 
-    region: Option<String>
-) -> Result<()> {
-    let mut url = format!("/apps/{}/machines/{}/logs?timestamps=true&include_levels=true", app_name, machine_id);
+/// Builds a log stream URL with the query parameters shared by all log endpoints
+fn logs_url(path: &str, options: &LogsOptions) -> String {
+    let mut url = format!("{}?timestamps=true&include_levels=true", path);
     
-    if follow {
+    if options.follow {
         url.push_str("&follow=true");
-    } else {
-        url.push_str("&tail=100"); // Get last 100 lines
+    }
+    if let Some(tail) = tail(options) {
+        url.push_str(&format!("&tail={}", tail));
     }
     
-    if let Some(region_filter) = region {
+    if let Some(ref region_filter) = options.region {
         url.push_str(&format!("&region={}", region_filter));
     }
     
+    // `+` in an RFC3339 offset would otherwise decode as a space
+    if let Some(ref since) = options.since {
+        url.push_str(&format!("&since={}", since.replace('+', "%2B")));
+    }
+    if let Some(ref until) = options.until {
+        url.push_str(&format!("&until={}", until.replace('+', "%2B")));
+    }
+    
+    url
+}
+
+/// The `--tail` to request, if any
+fn tail(options: &LogsOptions) -> Option<usize> {
+    options.tail.or((!options.follow && options.since.is_none()).then_some(DEFAULT_TAIL))
+}
+
+/// Stream logs from the API server, returning how many lines were shown.
+/// 
+/// Without `--follow`, the stream ends once the recent lines are sent, and
+/// only the last `--tail` lines that pass the filter are shown.
+async fn stream_logs(client: &ApiClient, url: &str, source: &str, filter: &LogFilter, options: &LogsOptions) -> Result<usize> {
     println!("🔗 Connecting to log stream...");
     
     // Create SSE client for streaming
-    let response = client.get(&url).await
+    let response = client.get(url).await
         .context("Failed to connect to log stream")?;
     
     if !response.status().is_success() {
         if response.status() == 404 {
-            println!("{} Machine {} not found or no logs available", 
-                "❌".red(), machine_id.yellow());
-            return Ok(());
+            println!("{} {} not found or no logs available", "❌".red(), source.yellow());
+            return Ok(0);
+        } else if response.status() == 400 {
+            return Err(anyhow::anyhow!(
+                "Invalid --since/--until value; use RFC3339 (2024-06-22T10:00:00Z), Unix seconds, or a duration like 10m"
+            ));
         } else {
             return Err(anyhow::anyhow!("Failed to get logs: HTTP {}", response.status()));
         }
@@ -111,6 +339,10 @@ async fn stream_logs(
     // Process the SSE stream
     let mut stream = response.bytes_stream();
     let mut buffer = String::new();
+    // Without --follow, lines are held back so only the last `tail` are shown
+    let batch_size = if options.follow { None } else { tail(options) };
+    let mut batch = VecDeque::new();
+    let mut shown = 0;
     
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result.context("Failed to read from log stream")?;
@@ -135,7 +367,21 @@ async fn stream_logs(
                 // Try to parse as log entry
                 match serde_json::from_str::<LogEntry>(data) {
                     Ok(log_entry) => {
-                        display_log_entry(&log_entry);
+                        if filter.matches(&log_entry) {
+                            let line = format_log_entry(&log_entry, options.pretty);
+                            match batch_size {
+                                Some(size) => {
+                                    batch.push_back(line);
+                                    if batch.len() > size {
+                                        batch.pop_front();
+                                    }
+                                }
+                                None => {
+                                    println!("{}", line);
+                                    shown += 1;
+                                }
+                            }
+                        }
                     }
                     Err(_) => {
                         // Fallback for non-JSON data
@@ -149,15 +395,38 @@ async fn stream_logs(
         io::stdout().flush().ok();
     }
     
+    for line in &batch {
+        println!("{}", line);
+    }
+    shown += batch.len();
+    
     println!("\n📡 Log stream ended");
-    Ok(())
+    Ok(shown)
 }
 
-/// Display a formatted log entry
-fn display_log_entry(entry: &LogEntry) {
-    let timestamp = parse_and_format_timestamp(&entry.timestamp);
+/// Formats a log entry for display.
+/// 
+/// With `pretty`, a JSON line's own timestamp and level replace the entry's,
+/// and its message is shown followed by its other fields. Other lines are
+/// shown as they are.
+fn format_log_entry(entry: &LogEntry, pretty: bool) -> String {
+    let json = if pretty { JsonLine::parse(&entry.message) } else { None };
+    let (timestamp, level, message) = match &json {
+        Some(line) => (
+            line.timestamp.as_deref().unwrap_or(&entry.timestamp),
+            line.level.map_or(entry.level.as_str(), |level| level.as_str()),
+            if line.fields.is_empty() {
+                line.message.white().to_string()
+            } else {
+                format!("{} {}", format!("{:<40}", line.message).white(), line.fields.join(" ").dimmed())
+            },
+        ),
+        None => (entry.timestamp.as_str(), entry.level.as_str(), entry.message.white().to_string()),
+    };
+    
+    let timestamp = parse_and_format_timestamp(timestamp);
     let region_badge = format!("[{}]", entry.region).blue().bold();
-    let level_badge = format_log_level(&entry.level);
+    let level_badge = format_log_level(level);
     let machine_id = entry.machine_id.get(..8).unwrap_or(&entry.machine_id).green();
     let stream_indicator = match entry.stream.as_str() {
         "stderr" => "⚠".red(),
@@ -172,15 +441,15 @@ fn display_log_entry(entry: &LogEntry) {
         "".normal()
     };
     
-    println!("{} {} {} {} {} {}{}",
+    format!("{} {} {} {} {} {}{}",
         region_badge,
         timestamp.dimmed(),
         level_badge,
         machine_id,
         stream_indicator,
-        entry.message.white(),
+        message,
         correlation
-    );
+    )
 }
 
 /// Format log level with appropriate colors
@@ -253,6 +522,121 @@ mod tests {
         assert_eq!(formatted2, "10:30:00");
     }
 
+    fn entry(message: &str, level: &str) -> LogEntry {
+        LogEntry {
+            timestamp: "2024-06-22T10:30:00Z".to_string(),
+            level: level.to_string(),
+            region: "local".to_string(),
+            machine_id: "abc123".to_string(),
+            app_name: "test-app".to_string(),
+            message: message.to_string(),
+            stream: "stdout".to_string(),
+            correlation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_log_filter_over_mixed_stream() {
+        let stream = [
+            entry(r#"{"level":"debug","msg":"cache warm"}"#, "debug"),
+            entry(r#"{"level":"error","msg":"db timeout after 5s"}"#, "error"),
+            entry(r#"{"severity":"WARNING","msg":"slow request /api/users"}"#, "info"),
+            entry("2024-06-22 INFO GET /api/users 200", "info"),
+            entry("ERROR: upstream timeout", "error"),
+            // The API's guess is used when a plain line names no level
+            entry("listening on :8080", "info"),
+        ];
+        let shown = |filter: &LogFilter| stream.iter().filter(|e| filter.matches(e)).count();
+        
+        assert_eq!(shown(&LogFilter::default()), 6);
+        assert_eq!(shown(&LogFilter::new(Some("warn".to_string()), None).unwrap()), 3);
+        assert_eq!(shown(&LogFilter::new(Some("error".to_string()), None).unwrap()), 2);
+        assert_eq!(shown(&LogFilter::new(None, Some(r"/api/\w+".to_string())).unwrap()), 2);
+        assert_eq!(shown(&LogFilter::new(Some("error".to_string()), Some("timeout".to_string())).unwrap()), 2);
+        
+        assert!(LogFilter::new(Some("loud".to_string()), None).is_err());
+        assert!(LogFilter::new(None, Some("(unclosed".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_pretty_formats_json_lines_and_passes_plain_lines_through() {
+        colored::control::set_override(false);
+        
+        let pino = entry(r#"{"level":50,"time":1719052200123,"pid":7,"hostname":"box","msg":"db timeout","retries":3,"query":"select 1"}"#, "info");
+        assert_eq!(
+            format_log_entry(&pino, true),
+            format!("[local] 10:30:00.123 ERROR abc123 → {:<40} query=\"select 1\" retries=3", "db timeout")
+        );
+        
+        let bunyan = entry(r#"{"severity":"warning","timestamp":"2024-06-22T10:31:00Z","message":"slow request"}"#, "info");
+        assert_eq!(format_log_entry(&bunyan, true), "[local] 10:31:00.000 WARN  abc123 → slow request");
+        
+        // Plain lines and JSON that isn't an object are shown as they are
+        for plain in ["GET /api/users 200", "[1, 2, 3]", "{not json"] {
+            assert_eq!(format_log_entry(&entry(plain, "info"), true), format!("[local] 10:30:00.000 INFO  abc123 → {}", plain));
+        }
+        
+        // Without --pretty, JSON lines are left alone too
+        let raw = format_log_entry(&pino, false);
+        assert!(raw.ends_with(&pino.message), "{}", raw);
+        assert!(raw.contains("INFO"));
+    }
+
+    #[test]
+    fn test_logs_url() {
+        let follow = LogsOptions { follow: true, region: Some("sjc".to_string()), ..Default::default() };
+        assert_eq!(
+            logs_url("/apps/web/logs", &follow),
+            "/apps/web/logs?timestamps=true&include_levels=true&follow=true&region=sjc"
+        );
+        assert!(logs_url("/apps/web/machines/m1/logs", &LogsOptions::default()).ends_with("&tail=100"));
+        let follow_tail = LogsOptions { follow: true, tail: Some(20), ..Default::default() };
+        assert!(logs_url("/apps/web/logs", &follow_tail).ends_with("&follow=true&tail=20"));
+        
+        // A time window replaces the default tail
+        let window = LogsOptions {
+            since: Some("2024-06-22T10:00:00+02:00".to_string()),
+            until: Some("5m".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            logs_url("/apps/web/logs", &window),
+            "/apps/web/logs?timestamps=true&include_levels=true&since=2024-06-22T10:00:00%2B02:00&until=5m"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_follow_shows_at_most_tail_lines_and_returns() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        // An API that ignores `tail` and sends everything it has
+        let body: String = (0..15)
+            .map(|i| format!("data: {}\n\n", serde_json::json!({
+                "timestamp": "2024-06-22T10:30:00Z", "level": "info", "region": "local",
+                "machine_id": "abc123", "app_name": "web", "message": format!("line {}", i),
+                "stream": "stdout", "correlation_id": null,
+            })))
+            .collect();
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/web/logs"))
+            .and(query_param("tail", "10"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+        
+        let options = LogsOptions { tail: Some(10), ..Default::default() };
+        let url = logs_url("/apps/web/logs", &options);
+        let shown = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            stream_logs(&client, &url, "App web", &LogFilter::default(), &options),
+        ).await.expect("the stream should end without --follow").unwrap();
+        assert_eq!(shown, 10);
+    }
+
     #[test]
     fn test_format_log_level() {
         assert!(format!("{}", format_log_level("error")).contains("ERROR"));
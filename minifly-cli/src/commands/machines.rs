@@ -1,7 +1,12 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use colored::*;
+use minifly_core::models::{FileConfig, InitConfig, MachineConfig, MachineSelection, MountConfig, RestartConfig};
+use std::collections::HashMap;
+use std::future::Future;
 use tabled::{Table, Tabled};
 use crate::client::ApiClient;
+use crate::commands::deploy::{self, OutputFormat};
 
 #[derive(Tabled)]
 struct MachineRow {
@@ -31,7 +36,11 @@ pub async fn list(client: &ApiClient, app: &str) -> Result<()> {
         .map(|m| MachineRow {
             id: m.id,
             name: m.name,
-            state: format!("{:?}", m.state).to_lowercase(),
+            state: if m.cordoned {
+                format!("{:?} (cordoned)", m.state).to_lowercase()
+            } else {
+                format!("{:?}", m.state).to_lowercase()
+            },
             region: m.region,
             image: format!("{}:{}", m.image_ref.repository, m.image_ref.tag),
             ip: m.private_ip,
@@ -47,13 +56,13 @@ pub async fn list(client: &ApiClient, app: &str) -> Result<()> {
 pub async fn create(
     client: &ApiClient,
     app: &str,
-    image: &str,
     name: Option<String>,
     region: Option<String>,
+    config: MachineConfig,
 ) -> Result<()> {
     println!("Creating machine for app {}...", app.yellow());
     
-    let machine = client.create_machine(app, image, name, region).await?;
+    let machine = client.create_machine(app, name, region, config).await?;
     
     println!("{}", "Machine created successfully!".green());
     println!("ID: {}", machine.id);
@@ -65,6 +74,146 @@ pub async fn create(
     Ok(())
 }
 
+/// Builds the config of `machines create` from its `--env`, `--volume` and
+/// `--file` flags, like `fly machine run`
+pub fn machine_config(image: &str, env: &[String], volumes: &[String], files: &[String]) -> Result<MachineConfig> {
+    let env = env
+        .iter()
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+            _ => bail!("Invalid --env '{}' - expected KEY=VALUE", pair),
+        })
+        .collect::<Result<HashMap<_, _>>>()?;
+    
+    let mounts = volumes
+        .iter()
+        .map(|volume| match volume.split_once(':') {
+            Some((name, path)) if !name.is_empty() && path.starts_with('/') => Ok(MountConfig {
+                volume: name.to_string(),
+                path: path.to_string(),
+            }),
+            _ => bail!("Invalid --volume '{}' - expected NAME:/absolute/path", volume),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    let files = files
+        .iter()
+        .map(|file| {
+            let Some((guest_path, local_file)) = file.split_once('=').filter(|(guest, _)| guest.starts_with('/')) else {
+                bail!("Invalid --file '{}' - expected /guest/path=LOCAL_FILE", file);
+            };
+            let contents = std::fs::read(local_file)
+                .with_context(|| format!("Failed to read {}", local_file))?;
+            // Machine files carry their contents base64-encoded, as on Fly.io
+            Ok(FileConfig {
+                guest_path: guest_path.to_string(),
+                raw_value: Some(STANDARD.encode(contents)),
+                secret_name: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    Ok(MachineConfig {
+        env: (!env.is_empty()).then_some(env),
+        files: (!files.is_empty()).then_some(files),
+        mounts: (!mounts.is_empty()).then_some(mounts),
+        ..MachineConfig::for_image(image)
+    })
+}
+
+/// Exit code of a `machines run` interrupted with Ctrl-C, as for SIGINT
+const INTERRUPTED_EXIT_CODE: i64 = 130;
+
+/// Handle `machines run`, the local `fly machine run --rm`
+///
+/// Creates a machine that runs `command` (or the image's default) once,
+/// streams its output, destroys it when the process exits and returns the
+/// process's exit code. Interrupting with Ctrl-C destroys the machine too.
+///
+/// The machine doesn't set `auto_destroy`: the API would remove the container
+/// as soon as it exits, racing the wait for its exit code.
+pub async fn run(
+    client: &ApiClient,
+    app: &str,
+    name: Option<String>,
+    region: Option<String>,
+    config: MachineConfig,
+    command: Vec<String>,
+) -> Result<i64> {
+    run_with(client, app, name, region, config, command, wait_for_exit).await
+}
+
+async fn run_with<F, Fut>(
+    client: &ApiClient,
+    app: &str,
+    name: Option<String>,
+    region: Option<String>,
+    mut config: MachineConfig,
+    command: Vec<String>,
+    wait: F,
+) -> Result<i64>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<i64>>,
+{
+    config.auto_destroy = Some(false);
+    // The process runs once; the API must not restart it
+    config.restart = Some(RestartConfig { policy: "no".to_string(), max_retries: None });
+    if !command.is_empty() {
+        config.init = Some(InitConfig { exec: None, entrypoint: None, cmd: Some(command) });
+    }
+    
+    deploy::ensure_app_exists(client, app, OutputFormat::Human).await?;
+    let machine = client.create_machine(app, name, region, config).await?;
+    println!("{} Running machine {} ({})", "→".blue(), machine.id.yellow(), machine.config.image);
+    
+    let exit_code = tokio::select! {
+        exit_code = wait(format!("minifly-{}-{}", app, machine.id)) => exit_code,
+        _ = tokio::signal::ctrl_c() => {
+            println!("{} Interrupted, destroying machine {}", "→".blue(), machine.id);
+            client.delete_machine(app, &machine.id, true).await?;
+            return Ok(INTERRUPTED_EXIT_CODE);
+        }
+    };
+    
+    // Destroy the machine even when waiting failed, so nothing is left behind
+    client.delete_machine(app, &machine.id, true).await?;
+    let exit_code = exit_code?;
+    
+    let summary = format!("Machine {} exited with code {} and was destroyed", machine.id, exit_code);
+    if exit_code == 0 {
+        println!("{} {}", "✓".green(), summary);
+    } else {
+        println!("{} {}", "✗".red(), summary);
+    }
+    Ok(exit_code)
+}
+
+/// Streams a container's output until it exits, returning its exit code
+async fn wait_for_exit(container: String) -> Result<i64> {
+    let mut logs = tokio::process::Command::new("docker")
+        .args(["logs", "--follow", &container])
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to run docker")?;
+    
+    let output = tokio::process::Command::new("docker")
+        .args(["wait", &container])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to run docker")?;
+    if !output.status.success() {
+        let _ = logs.kill().await;
+        bail!("Failed to wait for {}: {}", container, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    // The log stream ends with the container
+    let _ = logs.wait().await;
+    
+    String::from_utf8_lossy(&output.stdout).trim().parse()
+        .with_context(|| format!("Unexpected docker wait output for {}", container))
+}
+
 pub async fn start(client: &ApiClient, machine_id: &str) -> Result<()> {
     println!("Starting machine {}...", machine_id.yellow());
     
@@ -90,6 +239,99 @@ pub async fn stop(client: &ApiClient, machine_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// Stops every started machine of an app in one request, reporting each
+/// machine and failing if any of them couldn't be stopped
+pub async fn stop_all(client: &ApiClient, app: &str) -> Result<()> {
+    println!("Stopping all machines of {}...", app.yellow());
+    
+    let resp = client.machine_actions(app, "stop", MachineSelection::All).await?;
+    if resp.results.is_empty() {
+        println!("No started machines");
+        return Ok(());
+    }
+    
+    let mut failed = 0;
+    for result in &resp.results {
+        match &result.error {
+            None => println!("{} {}", "✓".green(), result.machine_id),
+            Some(error) => {
+                failed += 1;
+                println!("{} {}: {}", "✗".red(), result.machine_id, error);
+            }
+        }
+    }
+    
+    if failed > 0 {
+        bail!("Failed to stop {} of {} machines", failed, resp.results.len());
+    }
+    println!("{}", format!("Stopped {} machines", resp.results.len()).green());
+    Ok(())
+}
+
+/// Cordons or uncordons a machine, controlling whether it receives traffic
+pub async fn cordon(client: &ApiClient, machine_id: &str, cordoned: bool) -> Result<()> {
+    let app = client.get_machine_app(machine_id).await?;
+    let resp = client.cordon_machine(&app, machine_id, cordoned).await?;
+    
+    if resp.ok {
+        if cordoned {
+            println!("{} Machine {} cordoned; it will no longer receive traffic", "✓".green(), machine_id.yellow());
+        } else {
+            println!("{} Machine {} uncordoned", "✓".green(), machine_id.yellow());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Prints the environment a machine's container runs with, including the
+/// `FLY_*` variables and secrets Minifly injected
+pub async fn env(client: &ApiClient, machine_id: &str, app: Option<String>, reveal: bool) -> Result<()> {
+    let app = match app {
+        Some(app) => app,
+        None => client.get_machine_app(machine_id).await?,
+    };
+    let resp = client.get_machine_env(&app, machine_id, reveal).await?;
+    
+    for (key, value) in &resp.env {
+        if resp.redacted.contains(key) {
+            println!("{}={}", key.green(), value.dimmed());
+        } else {
+            println!("{}={}", key.green(), value);
+        }
+    }
+    if !resp.redacted.is_empty() {
+        eprintln!("\n{} secret values redacted; pass --reveal to show them", resp.redacted.len());
+    }
+    
+    Ok(())
+}
+
+/// Opens an interactive shell in a running machine's container, returning
+/// the shell's exit code
+pub async fn ssh(machine_id: &str, shell: &str) -> Result<i32> {
+    let output = tokio::process::Command::new("docker")
+        .args(["ps", "--filter", &format!("label=minifly.machine_id={}", machine_id), "--format", "{{.Names}}"])
+        .output()
+        .await
+        .context("Failed to run docker")?;
+    if !output.status.success() {
+        bail!("Failed to find machine {}: {}", machine_id, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let Some(container) = String::from_utf8_lossy(&output.stdout).lines().next().map(str::to_string) else {
+        bail!("Machine {} has no running container; start it with `minifly machines start {}`", machine_id, machine_id);
+    };
+    
+    // Without a terminal, e.g. when piped into, the shell still reads stdin
+    let tty = std::io::IsTerminal::is_terminal(&std::io::stdin());
+    let status = tokio::process::Command::new("docker")
+        .args(["exec", if tty { "-it" } else { "-i" }, &container, shell])
+        .status()
+        .await
+        .context("Failed to run docker")?;
+    Ok(status.code().unwrap_or(1))
+}
+
 pub async fn delete(client: &ApiClient, machine_id: &str, force: bool) -> Result<()> {
     use dialoguer::Confirm;
     
@@ -112,4 +354,114 @@ pub async fn delete(client: &ApiClient, machine_id: &str, force: bool) -> Result
     println!("{}", "Machine deleted successfully!".green());
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_create_flags_map_to_machine_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let local_file = dir.path().join("app.conf");
+        std::fs::write(&local_file, "listen = 8080\n").unwrap();
+        
+        let config = machine_config(
+            "nginx:alpine",
+            &["LOG_LEVEL=debug".to_string(), "DSN=postgres://h/db?a=b".to_string()],
+            &["data:/var/lib/data".to_string()],
+            &[format!("/etc/app.conf={}", local_file.display())],
+        ).unwrap();
+        
+        let env = config.env.unwrap();
+        assert_eq!(env["LOG_LEVEL"], "debug");
+        assert_eq!(env["DSN"], "postgres://h/db?a=b");
+        
+        let mounts = config.mounts.unwrap();
+        assert_eq!(mounts[0].volume, "data");
+        assert_eq!(mounts[0].path, "/var/lib/data");
+        
+        let files = config.files.unwrap();
+        assert_eq!(files[0].guest_path, "/etc/app.conf");
+        assert_eq!(STANDARD.decode(files[0].raw_value.as_ref().unwrap()).unwrap(), b"listen = 8080\n");
+        
+        let bare = machine_config("nginx:alpine", &[], &[], &[]).unwrap();
+        assert!(bare.env.is_none() && bare.mounts.is_none() && bare.files.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_run_destroys_machine_and_returns_exit_code() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "name": "jobs" })))
+            .mount(&api)
+            .await;
+        let machine = minifly_core::models::Machine { app_name: "jobs".to_string(), ..crate::test_support::machine("e1") };
+        Mock::given(method("POST"))
+            .and(path("/v1/apps/jobs/machines"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(machine))
+            .expect(1)
+            .mount(&api)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/apps/jobs/machines/e1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+            .expect(1)
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+        
+        let config = machine_config("alpine", &["MODE=once".to_string()], &[], &[]).unwrap();
+        let command = vec!["sh".to_string(), "-c".to_string(), "exit 3".to_string()];
+        let exit_code = run_with(&client, "jobs", None, None, config, command, |container| async move {
+            assert_eq!(container, "minifly-jobs-e1");
+            Ok(3)
+        }).await.unwrap();
+        assert_eq!(exit_code, 3);
+        
+        let requests = api.received_requests().await.unwrap();
+        let create = requests.iter().find(|r| r.method.as_str() == "POST").unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&create.body).unwrap();
+        // The CLI destroys the machine itself once it has the exit code
+        assert_eq!(body["config"]["auto_destroy"], false);
+        assert_eq!(body["config"]["restart"]["policy"], "no");
+        assert_eq!(body["config"]["init"]["cmd"], serde_json::json!(["sh", "-c", "exit 3"]));
+        assert_eq!(body["config"]["env"]["MODE"], "once");
+        // The machine is destroyed once the command completes
+        assert_eq!(requests.last().unwrap().method.as_str(), "DELETE");
+    }
+    
+    #[tokio::test]
+    async fn test_stop_all_fails_when_a_machine_fails() {
+        use wiremock::matchers::{body_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/apps/web/machines/actions"))
+            .and(body_json(serde_json::json!({ "action": "stop", "machine_ids": "all" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "results": [
+                { "machine_id": "d1", "ok": true },
+                { "machine_id": "d2", "ok": false, "error": "Docker error: Failed to stop container" },
+            ] })))
+            .expect(1)
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+        
+        let err = stop_all(&client, "web").await.unwrap_err();
+        assert_eq!(err.to_string(), "Failed to stop 1 of 2 machines");
+    }
+    
+    #[test]
+    fn test_create_flags_reject_malformed_values() {
+        assert!(machine_config("nginx", &["NO_EQUALS".to_string()], &[], &[]).is_err());
+        assert!(machine_config("nginx", &[], &["data".to_string()], &[]).is_err());
+        assert!(machine_config("nginx", &[], &["data:relative".to_string()], &[]).is_err());
+        assert!(machine_config("nginx", &[], &[], &["/etc/missing=/no/such/file".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+/// Local object storage for `minifly serve --minio`
+///
+/// Apps written against Tigris or S3 need an endpoint, credentials and a
+/// bucket before they can start. `serve --minio` runs a MinIO container with
+/// its data under `data/minio`, creates the configured bucket, and hands the
+/// credentials to the API server, which injects them into every machine.
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Name of the container `serve` manages
+const CONTAINER_NAME: &str = "minifly-minio";
+
+const IMAGE: &str = "minio/minio";
+
+/// Region MinIO signs requests for unless configured otherwise
+const SIGNING_REGION: &str = "us-east-1";
+
+/// SHA-256 of an empty body, sent with every bodiless request
+const EMPTY_PAYLOAD_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// How to reach the managed MinIO and which bucket apps use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinioConfig {
+    pub access_key: String,
+    pub secret_key: String,
+    pub bucket: String,
+    pub port: u16,
+}
+
+impl MinioConfig {
+    pub fn new(bucket: impl Into<String>) -> Self {
+        Self {
+            access_key: "minifly".to_string(),
+            secret_key: "minifly-secret".to_string(),
+            bucket: bucket.into(),
+            port: 9000,
+        }
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("http://localhost:{}", self.port)
+    }
+
+    /// Environment the API server reads the storage settings from
+    pub fn api_env(&self) -> [(&'static str, String); 4] {
+        [
+            ("MINIFLY_MINIO_ENDPOINT", self.endpoint()),
+            ("MINIFLY_MINIO_ACCESS_KEY", self.access_key.clone()),
+            ("MINIFLY_MINIO_SECRET_KEY", self.secret_key.clone()),
+            ("MINIFLY_MINIO_BUCKET", self.bucket.clone()),
+        ]
+    }
+}
+
+/// Starts MinIO (reusing a running container), waits for it and creates the bucket
+pub async fn start(config: &MinioConfig, data_dir: &Path) -> Result<()> {
+    println!("   • Starting MinIO on port {}...", config.port);
+    if !container_running()? {
+        // A stopped container may hold other credentials, so start from scratch
+        let _ = Command::new("docker").args(["rm", "-f", CONTAINER_NAME]).output();
+
+        let data_dir = data_dir.join("minio");
+        std::fs::create_dir_all(&data_dir)
+            .with_context(|| format!("Failed to create {}", data_dir.display()))?;
+        let data_dir = data_dir.canonicalize()?;
+
+        let output = Command::new("docker")
+            .args(["run", "-d", "--name", CONTAINER_NAME, "--label", "minifly.service=minio"])
+            .args(["-p", &format!("{}:9000", config.port)])
+            .args(["-e", &format!("MINIO_ROOT_USER={}", config.access_key)])
+            .args(["-e", &format!("MINIO_ROOT_PASSWORD={}", config.secret_key)])
+            .args(["-v", &format!("{}:/data", data_dir.display())])
+            .args([IMAGE, "server", "/data"])
+            .output()
+            .context("Failed to run docker")?;
+        if !output.status.success() {
+            bail!("Failed to start MinIO: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+    }
+
+    let client = reqwest::Client::new();
+    wait_until_live(&client, &config.endpoint(), Duration::from_secs(30)).await?;
+    if ensure_bucket(&client, config).await? {
+        println!("   ✓ Created bucket {}", config.bucket);
+    }
+    Ok(())
+}
+
+/// Stops and removes the MinIO container; its data stays in `data/minio`
+pub fn stop() {
+    let _ = Command::new("docker").args(["rm", "-f", CONTAINER_NAME]).output();
+}
+
+fn container_running() -> Result<bool> {
+    let output = Command::new("docker")
+        .args(["ps", "-q", "--filter", &format!("name=^{}$", CONTAINER_NAME)])
+        .output()
+        .context("Failed to run docker")?;
+    Ok(output.status.success() && !output.stdout.trim_ascii().is_empty())
+}
+
+async fn wait_until_live(client: &reqwest::Client, endpoint: &str, timeout: Duration) -> Result<()> {
+    let url = format!("{}/minio/health/live", endpoint);
+    let deadline = tokio::time::Instant::now() + timeout;
+    while tokio::time::Instant::now() < deadline {
+        if client.get(&url).send().await.is_ok_and(|response| response.status().is_success()) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    bail!("Timeout waiting for MinIO at {}", endpoint)
+}
+
+/// Creates the configured bucket unless it exists, returning whether it was created
+pub async fn ensure_bucket(client: &reqwest::Client, config: &MinioConfig) -> Result<bool> {
+    if bucket_exists(client, config).await? {
+        return Ok(false);
+    }
+
+    let response = signed_request(client, config, reqwest::Method::PUT).send().await
+        .context("Failed to reach MinIO")?;
+    if !response.status().is_success() {
+        let status = response.status();
+        bail!("Failed to create bucket {}: {} {}", config.bucket, status, response.text().await.unwrap_or_default());
+    }
+    Ok(true)
+}
+
+pub async fn bucket_exists(client: &reqwest::Client, config: &MinioConfig) -> Result<bool> {
+    let response = signed_request(client, config, reqwest::Method::HEAD).send().await
+        .context("Failed to reach MinIO")?;
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        reqwest::StatusCode::NOT_FOUND => Ok(false),
+        status => bail!("Failed to check bucket {}: {}", config.bucket, status),
+    }
+}
+
+/// A bodiless request on the bucket, signed with AWS Signature Version 4
+fn signed_request(client: &reqwest::Client, config: &MinioConfig, method: reqwest::Method) -> reqwest::RequestBuilder {
+    let now = Utc::now();
+    let host = format!("localhost:{}", config.port);
+    let path = format!("/{}", config.bucket);
+    let authorization = authorization(config, method.as_str(), &host, &path, now);
+
+    client
+        .request(method, format!("{}{}", config.endpoint(), path))
+        .header("host", host)
+        .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+        .header("x-amz-content-sha256", EMPTY_PAYLOAD_SHA256)
+        .header("authorization", authorization)
+}
+
+/// The `Authorization` header for a bodiless request to `host` + `path`
+fn authorization(config: &MinioConfig, method: &str, host: &str, path: &str, now: DateTime<Utc>) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, SIGNING_REGION);
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+    let canonical_request = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, path, host, EMPTY_PAYLOAD_SHA256, amz_date, signed_headers, EMPTY_PAYLOAD_SHA256
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let mut key = hmac_sha256(format!("AWS4{}", config.secret_key).as_bytes(), &date);
+    for part in [SIGNING_REGION, "s3", "aws4_request"] {
+        key = hmac_sha256(&key, part);
+    }
+    let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header_regex, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_bucket_created_and_reachable_after_start() {
+        let server = MockServer::start().await;
+        let mut config = MinioConfig::new("uploads");
+        config.port = server.address().port();
+
+        Mock::given(method("GET"))
+            .and(path("/minio/health/live"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        // The bucket is missing until the signed PUT creates it
+        Mock::given(method("HEAD"))
+            .and(path("/uploads"))
+            .respond_with(ResponseTemplate::new(404))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/uploads"))
+            .and(header_regex("authorization", r"^AWS4-HMAC-SHA256 Credential=minifly/\d{8}/us-east-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature=[0-9a-f]{64}$"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/uploads"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        wait_until_live(&client, &config.endpoint(), Duration::from_secs(5)).await.unwrap();
+        assert!(ensure_bucket(&client, &config).await.unwrap());
+        assert!(bucket_exists(&client, &config).await.unwrap());
+        // Serving again finds the bucket instead of recreating it
+        assert!(!ensure_bucket(&client, &config).await.unwrap());
+    }
+}
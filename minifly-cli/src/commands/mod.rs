@@ -6,26 +6,52 @@
 //! ## Commands
 //!
 //! - [`apps`] - Application management (create, list, delete)
+//! - [`certs`] - Local certificate authority for the gateway's TLS termination
+//! - [`connections`] - Reporting proxied connections for `autostop`
+//! - [`compose`] - Docker Compose import for `deploy --compose`
 //! - [`deploy`] - Application deployment with production config compatibility
 //! - [`dev`] - Development mode with auto-reload
+//! - [`doctor`] - Setup diagnostics, such as orphaned containers
+//! - [`gateway`] - Host-based routing to apps, like Fly's edge
+//! - [`http_proxy`] - HTTP-aware proxying with WebSocket and HTTP/2 support
 //! - [`init`] - Project initialization
+//! - [`litefs`] - LiteFS database inspection
 //! - [`logs`] - Log viewing and streaming
 //! - [`machines`] - Machine lifecycle management
+//! - [`minio`] - Local object storage for `serve --minio`
 //! - [`proxy`] - Service proxying
+//! - [`redeploy`] - Coalesced redeploys for watch modes
+//! - [`releases`] - Release history and rollback
+//! - [`scale`] - Set the number of machines of an app
 //! - [`secrets`] - Secrets management (.fly.secrets files)
 //! - [`serve`] - Start the Minifly platform
 //! - [`status`] - Platform status monitoring
 //! - [`stop`] - Stop the platform
+//! - [`templates`] - Custom project templates for `init --template-repo`
+//! - [`watch`] - Live terminal dashboard
 
 pub mod apps;
+pub mod certs;
+pub mod compose;
+pub mod connections;
 pub mod dependencies;
 pub mod deploy;
 pub mod dev;
+pub mod doctor;
+pub mod gateway;
+pub mod http_proxy;
 pub mod init;
+pub mod litefs;
 pub mod logs;
+pub mod minio;
 pub mod machines;
 pub mod proxy;
+pub mod redeploy;
+pub mod releases;
+pub mod scale;
 pub mod secrets;
 pub mod serve;
 pub mod status;
-pub mod stop;
\ No newline at end of file
+pub mod stop;
+pub mod templates;
+pub mod watch;
\ No newline at end of file
@@ -1,18 +1,659 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
+use hyper::StatusCode;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::{mpsc, Mutex};
 use crate::client::ApiClient;
+use crate::commands::connections::{Connection, ConnectionTracker};
+use crate::commands::http_proxy;
 
-pub async fn handle(_client: &ApiClient, machine_id: &str, port: u16) -> Result<()> {
-    println!("Setting up proxy to machine {} on port {}...", machine_id.yellow(), port.to_string().yellow());
-    
-    // TODO: Implement proxy functionality
-    // This would:
-    // 1. Find the machine's exposed ports
-    // 2. Set up a local TCP proxy
-    // 3. Forward traffic between local port and container
-    
-    println!("{}", "Proxy not yet implemented".red());
-    println!("This would create a proxy to access services running in the machine.");
+/// Transport protocol of a proxied port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+/// A `-p` mapping: `8080`, `3000:8080` or `5353:53/udp`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortMapping {
+    pub local: u16,
+    pub remote: u16,
+    pub protocol: Protocol,
+}
+
+impl FromStr for PortMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (ports, protocol) = match s.split_once('/') {
+            Some((ports, "tcp")) => (ports, Protocol::Tcp),
+            Some((ports, "udp")) => (ports, Protocol::Udp),
+            Some((_, other)) => return Err(format!("unknown protocol '{}' (use tcp or udp)", other)),
+            None => (s, Protocol::Tcp),
+        };
+
+        let parse = |port: &str| port.parse::<u16>().map_err(|_| format!("invalid port '{}'", port));
+        let (local, remote) = match ports.split_once(':') {
+            Some((local, remote)) => (parse(local)?, parse(remote)?),
+            None => (parse(ports)?, parse(ports)?),
+        };
+
+        Ok(Self { local, remote, protocol })
+    }
+}
+
+/// What the proxy forwards to
+pub enum ProxyTarget {
+    /// A single machine by ID
+    Machine(String),
+    /// Every running, uncordoned machine of an app, round-robin
+    App(String),
+}
+
+/// Where a proxied port sends each new connection
+#[derive(Clone)]
+enum Targets {
+    /// A single fixed address
+    Fixed(SocketAddr),
+    /// An app's machines, re-resolved through internal DNS for every
+    /// connection so machines that stop, start or get cordoned are picked up
+    App {
+        client: ApiClient,
+        app: String,
+        port: u16,
+        protocol: Protocol,
+        next: Arc<AtomicUsize>,
+        connections: ConnectionTracker,
+    },
+}
+
+impl Targets {
+    /// The next target, with the connection to it counted for `autostop`
+    /// when it's one of an app's machines. Returns once the simulated
+    /// latency to the machine's region has passed.
+    async fn next(&self) -> Result<(SocketAddr, Option<Connection>)> {
+        match self {
+            Targets::Fixed(addr) => Ok((*addr, None)),
+            Targets::App { client, app, port, protocol, next, connections } => {
+                let machines = client.resolve_app_machines(app, None).await?;
+                if machines.is_empty() {
+                    anyhow::bail!("App {} has no running machines", app);
+                }
+                let machine = &machines[next.fetch_add(1, Ordering::Relaxed) % machines.len()];
+                let addr = match machine.host_port(*port, protocol.as_str()) {
+                    Some(host_port) => SocketAddr::from(([127, 0, 0, 1], host_port)),
+                    // Unpublished ports are only reachable on the container's bridge IP
+                    None => SocketAddr::new(machine.ip, *port),
+                };
+                let connection = connections.open(app, &machine.id);
+                tokio::time::sleep(Duration::from_millis(machine.latency_ms)).await;
+                Ok((addr, Some(connection)))
+            }
+        }
+    }
+}
+
+/// Handle `minifly proxy <machine-id> -p LOCAL:REMOTE[/udp]...`
+///
+/// Each mapping forwards a local port to the machine. The target is the host
+/// port the API recorded for the container port, or the container's own IP
+/// when the port isn't published. Proxying to an app instead spreads new
+/// connections across its machines, the same way.
+///
+/// With `http`, TCP ports are proxied as HTTP instead of raw bytes, so each
+/// request to an app goes to the next machine and WebSocket and HTTP/2
+/// clients work. With `open_browser`, the first TCP port is opened in the
+/// default browser once the proxy is listening.
+pub async fn handle(
+    client: &ApiClient,
+    target: ProxyTarget,
+    ports: Vec<PortMapping>,
+    bind: &str,
+    http: bool,
+    open_browser: bool,
+) -> Result<()> {
+    let ports = if ports.is_empty() {
+        vec![PortMapping { local: 8080, remote: 8080, protocol: Protocol::Tcp }]
+    } else {
+        ports
+    };
+
+    let name = match &target {
+        ProxyTarget::Machine(machine_id) => {
+            println!("Setting up proxy to machine {}...", machine_id.yellow());
+            machine_id.clone()
+        }
+        ProxyTarget::App(app) => {
+            println!("Setting up proxy to app {} (round-robin across machines)...", app.yellow());
+            format!("{}.internal", app)
+        }
+    };
+
+    let connections = ConnectionTracker::spawn(client);
+    let mut tasks = Vec::new();
+    for mapping in ports.iter().copied() {
+        let targets = match &target {
+            ProxyTarget::Machine(machine_id) => Targets::Fixed(machine_route(client, machine_id, mapping).await?),
+            ProxyTarget::App(app) => Targets::App {
+                client: client.clone(),
+                app: app.clone(),
+                port: mapping.remote,
+                protocol: mapping.protocol,
+                next: Arc::default(),
+                connections: connections.clone(),
+            },
+        };
+        let local = bind_addr(bind, mapping.local).await?;
+
+        let via = match &targets {
+            Targets::Fixed(addr) => format!(" (via {})", addr),
+            Targets::App { .. } => String::new(),
+        };
+        match mapping.protocol {
+            Protocol::Tcp => {
+                let listener = TcpListener::bind(local).await
+                    .with_context(|| format!("Failed to listen on {}", local))?;
+                if http {
+                    tasks.push(tokio::spawn(serve_http(listener, targets)));
+                } else {
+                    tasks.push(tokio::spawn(serve_tcp(listener, targets)));
+                }
+            }
+            Protocol::Udp => {
+                let socket = UdpSocket::bind(local).await
+                    .with_context(|| format!("Failed to bind {}/udp", local))?;
+                tasks.push(tokio::spawn(serve_udp(socket, targets)));
+            }
+        }
+
+        let protocol = match mapping.protocol {
+            Protocol::Tcp if http => "http",
+            protocol => protocol.as_str(),
+        };
+        println!(
+            "{} Proxying {}/{} -> {}:{}{}",
+            "✓".green(),
+            local,
+            protocol,
+            name,
+            mapping.remote,
+            via
+        );
+    }
+
+    if bind != "127.0.0.1" && bind != "localhost" {
+        println!("{}", "⚠️  Warning: Proxy accessible from other network interfaces".yellow());
+    }
     
+    if open_browser {
+        match browser_url(bind, &ports) {
+            Some(url) => {
+                if open::that(&url).is_err() {
+                    println!("No browser available; open {} manually", url.cyan());
+                } else {
+                    println!("🌐 Opened {}", url.cyan());
+                }
+            }
+            None => println!("{}", "--open needs a TCP port mapping".yellow()),
+        }
+    }
+    println!("{}", "Press Ctrl+C to stop".dimmed());
+
+    tokio::signal::ctrl_c().await?;
+    for task in tasks {
+        task.abort();
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// URL of the first TCP mapping, as a browser on this host would reach it
+fn browser_url(bind: &str, ports: &[PortMapping]) -> Option<String> {
+    let mapping = ports.iter().find(|m| m.protocol == Protocol::Tcp)?;
+    let host = match bind {
+        "127.0.0.1" | "0.0.0.0" | "::" | "::1" => "localhost",
+        host => host,
+    };
+
+    Some(format!("http://{}:{}", host, mapping.local))
+}
+
+/// Resolves the address to listen on, which may be a hostname such as `localhost`
+async fn bind_addr(bind: &str, port: u16) -> Result<SocketAddr> {
+    tokio::net::lookup_host((bind, port)).await
+        .with_context(|| format!("Invalid bind address {}", bind))?
+        .next()
+        .with_context(|| format!("Bind address {} resolved to no addresses", bind))
+}
+
+/// Resolves where to send traffic for one of a machine's ports, from the
+/// host ports the API recorded for it
+async fn machine_route(client: &ApiClient, machine_id: &str, mapping: PortMapping) -> Result<SocketAddr> {
+    let app = client.get_machine_app(machine_id).await?;
+    let machine = client.get_machine(&app, machine_id).await?;
+    if let Some(host_port) = machine.host_port(mapping.remote, mapping.protocol.as_str()) {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], host_port)));
+    }
+
+    // Unpublished ports are only reachable on the container's bridge IP
+    let ip = client.resolve_app_machines(&machine.app_name, None).await?
+        .into_iter()
+        .find(|resolved| resolved.id == machine.id)
+        .map(|resolved| resolved.ip)
+        .with_context(|| format!("Machine {} is not running", machine_id))?;
+
+    Ok(SocketAddr::new(ip, mapping.remote))
+}
+
+/// Accepts TCP connections and pipes each one to the next target
+async fn serve_tcp(listener: TcpListener, targets: Targets) {
+    loop {
+        let Ok((mut inbound, peer)) = listener.accept().await else {
+            continue;
+        };
+
+        let targets = targets.clone();
+        tokio::spawn(async move {
+            let (target, connection) = match targets.next().await {
+                Ok(target) => target,
+                Err(e) => {
+                    eprintln!("{} {}: {}", "✗".red(), peer, e);
+                    return;
+                }
+            };
+            let mut outbound = match TcpStream::connect(target).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("{} {} -> {}: {}", "✗".red(), peer, target, e);
+                    return;
+                }
+            };
+            let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+            drop(connection);
+        });
+    }
+}
+
+/// Serves HTTP on each connection, sending every request to the next target
+async fn serve_http(listener: TcpListener, targets: Targets) {
+    loop {
+        let Ok((inbound, peer)) = listener.accept().await else {
+            continue;
+        };
+
+        let targets = targets.clone();
+        tokio::spawn(async move {
+            let result = http_proxy::serve_connection(inbound, move |request| {
+                let targets = targets.clone();
+                async move {
+                    match targets.next().await {
+                        Ok((target, connection)) => http_proxy::forward(request, target, connection).await,
+                        Err(e) => http_proxy::text(StatusCode::BAD_GATEWAY, e.to_string()),
+                    }
+                }
+            }).await;
+            if let Err(e) = result {
+                eprintln!("{} {}: {}", "✗".red(), peer, e);
+            }
+        });
+    }
+}
+
+/// Datagrams waiting to be relayed for each client of a UDP port
+type UdpSessions = Arc<Mutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>;
+
+/// Datagrams a client session holds while its upstream is connecting
+const UDP_SESSION_BACKLOG: usize = 64;
+
+/// How long a UDP session lasts without datagrams in either direction
+const UDP_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Relays UDP datagrams to the target.
+///
+/// Each client address gets its own upstream socket so replies can be routed
+/// back to the client that sent the request. With several targets, each new
+/// client is assigned the next one. Sessions end after
+/// [`UDP_SESSION_IDLE_TIMEOUT`] without traffic, and the client's next
+/// datagram starts a new one.
+async fn serve_udp(socket: UdpSocket, targets: Targets) {
+    serve_udp_with_idle_timeout(socket, targets, UDP_SESSION_IDLE_TIMEOUT).await
+}
+
+async fn serve_udp_with_idle_timeout(socket: UdpSocket, targets: Targets, idle_timeout: Duration) {
+    let socket = Arc::new(socket);
+    let sessions: UdpSessions = Arc::default();
+    let mut buf = vec![0u8; 65535];
+
+    loop {
+        let Ok((len, client)) = socket.recv_from(&mut buf).await else {
+            continue;
+        };
+
+        let mut active = sessions.lock().await;
+        // A session that just went idle may not have removed itself yet
+        if active.get(&client).is_none_or(mpsc::Sender::is_closed) {
+            let (sender, datagrams) = mpsc::channel(UDP_SESSION_BACKLOG);
+            let session = UdpSession { socket: socket.clone(), client, datagrams, idle_timeout };
+            tokio::spawn(relay_udp_session(session, targets.clone(), sessions.clone()));
+            active.insert(client, sender);
+        }
+        // Like the network, a session that can't keep up drops datagrams
+        let _ = active[&client].try_send(buf[..len].to_vec());
+    }
+}
+
+/// One client's datagrams and where to send its replies
+struct UdpSession {
+    socket: Arc<UdpSocket>,
+    client: SocketAddr,
+    datagrams: mpsc::Receiver<Vec<u8>>,
+    idle_timeout: Duration,
+}
+
+/// Connects a client's session to the next target and relays datagrams both
+/// ways until it goes idle, so resolving the target holds up no other client
+async fn relay_udp_session(session: UdpSession, targets: Targets, sessions: UdpSessions) {
+    let UdpSession { socket, client, mut datagrams, idle_timeout } = session;
+    match connect_udp(&targets).await {
+        Ok(upstream) => {
+            let upstream = Arc::new(upstream);
+            let reply_from = upstream.clone();
+            let (replied, mut replies_seen) = mpsc::channel(1);
+            let replies = tokio::spawn(async move {
+                let mut buf = vec![0u8; 65535];
+                while let Ok(len) = reply_from.recv(&mut buf).await {
+                    let _ = socket.send_to(&buf[..len], client).await;
+                    let _ = replied.try_send(());
+                }
+            });
+            loop {
+                tokio::select! {
+                    datagram = datagrams.recv() => match datagram {
+                        Some(datagram) => { let _ = upstream.send(&datagram).await; }
+                        None => break,
+                    },
+                    Some(()) = replies_seen.recv() => {}
+                    _ = tokio::time::sleep(idle_timeout) => break,
+                }
+            }
+            replies.abort();
+        }
+        Err(e) => eprintln!("{} {}/udp: {}", "✗".red(), client, e),
+    }
+
+    // Leave a newer session for the same client in place
+    datagrams.close();
+    let mut active = sessions.lock().await;
+    if active.get(&client).is_some_and(mpsc::Sender::is_closed) {
+        active.remove(&client);
+    }
+}
+
+/// Opens an upstream socket connected to the next target
+async fn connect_udp(targets: &Targets) -> Result<UdpSocket> {
+    // UDP has no connections to count, so a new session only counts as activity
+    let (target, _) = targets.next().await?;
+    let bind_addr: SocketAddr = if target.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { "[::]:0".parse().unwrap() };
+    let upstream = UdpSocket::bind(bind_addr).await?;
+    upstream.connect(target).await?;
+    Ok(upstream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_parse_port_mapping() {
+        assert_eq!("8080".parse(), Ok(PortMapping { local: 8080, remote: 8080, protocol: Protocol::Tcp }));
+        assert_eq!("3000:8080".parse(), Ok(PortMapping { local: 3000, remote: 8080, protocol: Protocol::Tcp }));
+        assert_eq!("5353:53/udp".parse(), Ok(PortMapping { local: 5353, remote: 53, protocol: Protocol::Udp }));
+        assert!("3000:http".parse::<PortMapping>().is_err());
+        assert!("53/sctp".parse::<PortMapping>().is_err());
+    }
+
+    #[test]
+    fn test_browser_url_uses_first_tcp_port() {
+        let ports: Vec<PortMapping> = ["5353:53/udp", "3000:8080", "9000"].iter().map(|p| p.parse().unwrap()).collect();
+
+        assert_eq!(browser_url("127.0.0.1", &ports).as_deref(), Some("http://localhost:3000"));
+        assert_eq!(browser_url("0.0.0.0", &ports).as_deref(), Some("http://localhost:3000"));
+        assert_eq!(browser_url("192.168.1.20", &ports).as_deref(), Some("http://192.168.1.20:3000"));
+        assert!(browser_url("127.0.0.1", &ports[..1]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bind_address_may_be_a_hostname() {
+        assert!(bind_addr("localhost", 8080).await.unwrap().ip().is_loopback());
+        assert_eq!(bind_addr("0.0.0.0", 8080).await.unwrap(), SocketAddr::from(([0, 0, 0, 0], 8080)));
+        assert!(bind_addr("not a host", 8080).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_bytes_round_trip_through_proxy() {
+        // Echo backend standing in for the container
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            let n = stream.read(&mut buf).await.unwrap();
+            stream.write_all(&buf[..n]).await.unwrap();
+        });
+
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(serve_tcp(proxy, Targets::Fixed(backend_addr)));
+
+        let mut client = TcpStream::connect(proxy_addr).await.unwrap();
+        client.write_all(b"hello through the proxy").await.unwrap();
+        let mut buf = [0u8; 64];
+        let n = client.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"hello through the proxy");
+    }
+
+    #[tokio::test]
+    async fn test_udp_datagrams_round_trip_through_proxy() {
+        let backend = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (n, from) = backend.recv_from(&mut buf).await.unwrap();
+            backend.send_to(&buf[..n], from).await.unwrap();
+        });
+
+        let proxy = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(serve_udp(proxy, Targets::Fixed(backend_addr)));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(b"ping", proxy_addr).await.unwrap();
+        let mut buf = [0u8; 64];
+        let (n, from) = tokio::time::timeout(std::time::Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        assert_eq!(from, proxy_addr);
+    }
+
+    #[tokio::test]
+    async fn test_idle_udp_sessions_expire() {
+        // Echoes each datagram and reports which upstream socket sent it
+        let backend = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        let (senders, mut upstreams) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            while let Ok((n, from)) = backend.recv_from(&mut buf).await {
+                backend.send_to(&buf[..n], from).await.unwrap();
+                senders.send(from).unwrap();
+            }
+        });
+
+        let proxy = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        let idle_timeout = Duration::from_millis(200);
+        tokio::spawn(serve_udp_with_idle_timeout(proxy, Targets::Fixed(backend_addr), idle_timeout));
+
+        // Pings through the proxy, returning the upstream socket that relayed it
+        async fn ping(client: &UdpSocket, proxy: SocketAddr, upstreams: &mut mpsc::UnboundedReceiver<SocketAddr>) -> SocketAddr {
+            client.send_to(b"ping", proxy).await.unwrap();
+            let mut buf = [0u8; 64];
+            tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf)).await.unwrap().unwrap();
+            upstreams.recv().await.unwrap()
+        }
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let first = ping(&client, proxy_addr, &mut upstreams).await;
+        tokio::time::sleep(idle_timeout / 4).await;
+        assert_eq!(ping(&client, proxy_addr, &mut upstreams).await, first, "an active session keeps its upstream socket");
+
+        tokio::time::sleep(idle_timeout * 3).await;
+        assert_ne!(ping(&client, proxy_addr, &mut upstreams).await, first, "an idle session is replaced");
+    }
+
+    #[tokio::test]
+    async fn test_app_proxy_alternates_between_machines() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // Two machines of the app, with port 8080 published on different host ports
+        let mut host_ports = Vec::new();
+        for name in ["machine-a", "machine-b"] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            host_ports.push(listener.local_addr().unwrap().port());
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    stream.write_all(name.as_bytes()).await.unwrap();
+                }
+            });
+        }
+        let published = |host_port: u16| serde_json::json!([{ "internal_port": 8080, "protocol": "tcp", "host_port": host_port }]);
+
+        // Bridge IPs aren't reachable from every host, so the proxy dials the published ports
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/web/dns"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hostname": "web.internal",
+                "ips": ["172.31.255.2", "172.31.255.3"],
+                "machines": [
+                    { "id": "a1", "ip": "172.31.255.2", "mapped_ports": published(host_ports[0]) },
+                    { "id": "b2", "ip": "172.31.255.3", "mapped_ports": published(host_ports[1]) },
+                ]
+            })))
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        let connections = ConnectionTracker::spawn(&client);
+        tokio::spawn(serve_tcp(proxy, Targets::App {
+            client,
+            app: "web".to_string(),
+            port: 8080,
+            protocol: Protocol::Tcp,
+            next: Arc::default(),
+            connections,
+        }));
+
+        let mut served = Vec::new();
+        for _ in 0..4 {
+            let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).await.unwrap();
+            served.push(reply);
+        }
+        assert_eq!(served, vec!["machine-a", "machine-b", "machine-a", "machine-b"]);
+
+        // Connections are counted locally and reported in the background,
+        // not with a request of their own
+        let requests = api.received_requests().await.unwrap();
+        assert!(requests.iter().all(|request| request.method.as_str() == "GET"));
+    }
+
+    #[tokio::test]
+    async fn test_machine_route_uses_recorded_host_ports() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mut machine = crate::test_support::machine("a1");
+        machine.mapped_ports = vec![minifly_core::models::MappedPort {
+            internal_port: 8080,
+            protocol: "tcp".to_string(),
+            host_port: 32768,
+        }];
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/default-app/machines/a1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&machine))
+            .mount(&api)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/web/dns"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hostname": "web.internal",
+                "ips": ["172.31.255.2"],
+                "machines": [{ "id": "a1", "ip": "172.31.255.2" }]
+            })))
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+
+        let route = |remote, protocol| machine_route(&client, "a1", PortMapping { local: 9000, remote, protocol });
+        assert_eq!(route(8080, Protocol::Tcp).await.unwrap(), "127.0.0.1:32768".parse().unwrap());
+        // Unpublished ports go to the container's bridge IP
+        assert_eq!(route(8080, Protocol::Udp).await.unwrap(), "172.31.255.2:8080".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_through_http_proxy() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        // A WebSocket echo server
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = backend.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        if message.is_text() {
+                            ws.send(message).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(serve_http(proxy, Targets::Fixed(backend_addr)));
+
+        let (mut ws, response) = tokio_tungstenite::connect_async(format!("ws://{}/chat", proxy_addr)).await.unwrap();
+        assert_eq!(response.status(), 101);
+        ws.send(Message::text("hello")).await.unwrap();
+        assert_eq!(ws.next().await.unwrap().unwrap(), Message::text("hello"));
+        ws.send(Message::text("again")).await.unwrap();
+        assert_eq!(ws.next().await.unwrap().unwrap(), Message::text("again"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Redeploys for the watch modes of `deploy`, `dev` and `serve --dev`
+//!
+//! [`WatchFilter`] decides which changed files trigger a redeploy: files the
+//! project's `.gitignore` and `.ignore` files ignore never do, as in a walk
+//! of the project that honors the ignore files of every directory, nor do
+//! build output, dependencies and editor temp files. `[minifly.watch]` in
+//! fly.toml can narrow that down with gitignore-style patterns. Changes to the
+//! deployed fly.toml and to the project's `.fly.secrets*` files, which are
+//! usually gitignored, always count.
+//!
+//! ```toml
+//! [minifly.watch]
+//! include = ["src/", "*.toml", "Dockerfile"]
+//! exclude = ["*.md"]
+//! ```
+//!
+//! A single build or save can touch many files, and changes keep arriving
+//! while a redeploy runs. Redeploys run one at a time: changes reported while
+//! one is running are coalesced into exactly one more redeploy after it, so
+//! the last change is always deployed without a redeploy per file event.
+
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Files that never trigger a redeploy, even in projects without a
+/// `.gitignore`: version control, build output, dependencies and editor
+/// temp files
+const ALWAYS_EXCLUDED: &[&str] = &[
+    ".git/", "target/", "node_modules/",
+    "*.swp", "*.swx", "*~", ".#*", "#*#", "4913", ".DS_Store",
+];
+
+/// Ignore files read in each directory, later ones taking precedence
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+#[derive(Debug, Default, Deserialize)]
+struct FlyToml {
+    #[serde(default)]
+    minifly: MiniflySection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MiniflySection {
+    #[serde(default)]
+    watch: WatchConfig,
+}
+
+/// `[minifly.watch]` in fly.toml
+#[derive(Debug, Default, Deserialize)]
+struct WatchConfig {
+    /// Only changes to matching files trigger a redeploy, if set
+    #[serde(default)]
+    include: Vec<String>,
+    /// Changes to matching files never trigger a redeploy
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// Whether a path relative to a project is one of its secrets files
+pub fn is_secrets_file(relative: &Path) -> bool {
+    relative.parent().is_some_and(|parent| parent.as_os_str().is_empty())
+        && relative.to_str().is_some_and(|name| name.starts_with(".fly.secrets"))
+}
+
+/// Decides which changed files in a project trigger a redeploy
+pub struct WatchFilter {
+    root: PathBuf,
+    /// The fly.toml being deployed, whose changes always count
+    fly_toml: Option<PathBuf>,
+    include: Option<Gitignore>,
+    exclude: Gitignore,
+    /// The ignore files of each directory looked at so far
+    ignore_files: Mutex<HashMap<PathBuf, Arc<Gitignore>>>,
+}
+
+impl WatchFilter {
+    /// Loads the filter for the project of a fly.toml, from its
+    /// `[minifly.watch]` section, if it exists yet, and the project's ignore files
+    pub fn load(fly_toml_path: &Path) -> Result<Self> {
+        let root = fly_toml_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let config: FlyToml = if fly_toml_path.exists() {
+            let content = std::fs::read_to_string(fly_toml_path)
+                .with_context(|| format!("Failed to read {}", fly_toml_path.display()))?;
+            toml::from_str(&content)
+                .with_context(|| format!("Invalid [minifly.watch] in {}", fly_toml_path.display()))?
+        } else {
+            FlyToml::default()
+        };
+        let mut filter = Self::new(root, config.minifly.watch)?;
+        filter.fly_toml = fly_toml_path.canonicalize().ok();
+        Ok(filter)
+    }
+
+    fn new(root: &Path, config: WatchConfig) -> Result<Self> {
+        let root = root.canonicalize()
+            .with_context(|| format!("Failed to find {}", root.display()))?;
+        let patterns = |patterns: &mut dyn Iterator<Item = &str>| -> Result<Gitignore> {
+            let mut builder = GitignoreBuilder::new(&root);
+            for pattern in patterns {
+                builder.add_line(None, pattern)
+                    .with_context(|| format!("Invalid watch pattern: {}", pattern))?;
+            }
+            Ok(builder.build()?)
+        };
+        
+        let include = if config.include.is_empty() {
+            None
+        } else {
+            Some(patterns(&mut config.include.iter().map(String::as_str))?)
+        };
+        let exclude = patterns(&mut ALWAYS_EXCLUDED.iter().copied().chain(config.exclude.iter().map(String::as_str)))?;
+        Ok(Self { root, fly_toml: None, include, exclude, ignore_files: Mutex::default() })
+    }
+
+    /// Whether a change to `path` should trigger a redeploy
+    pub fn should_redeploy(&self, path: &Path) -> bool {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if self.fly_toml.as_ref() == Some(&path) {
+            return true;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if is_secrets_file(relative) {
+            return true;
+        }
+        if relative.file_name().and_then(|name| name.to_str()).is_some_and(|name| IGNORE_FILES.contains(&name)) {
+            // Pick up the edited ignore file the next time it applies
+            if let Some(dir) = path.parent() {
+                self.ignore_files.lock().unwrap().remove(dir);
+            }
+        }
+        let is_dir = path.is_dir();
+        let matches = |patterns: &Gitignore| patterns.matched_path_or_any_parents(relative, is_dir).is_ignore();
+        
+        if matches(&self.exclude) || self.is_ignored(&path, is_dir) {
+            return false;
+        }
+        self.include.as_ref().is_none_or(matches)
+    }
+
+    /// Whether a walk of the project from its root would skip `path`: the
+    /// path or one of its directories is ignored
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut current = self.root.clone();
+        let mut components = path.strip_prefix(&self.root).unwrap_or(path).components().peekable();
+        while let Some(component) = components.next() {
+            current.push(component);
+            let current_is_dir = components.peek().is_some() || is_dir;
+            if self.ignore_match(&current, current_is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether the ignore files of the directories above `path` ignore it,
+    /// the closest ones taking precedence
+    fn ignore_match(&self, path: &Path, is_dir: bool) -> bool {
+        for dir in path.ancestors().skip(1) {
+            match self.ignore_files(dir).matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+            if dir == self.root {
+                break;
+            }
+        }
+        false
+    }
+
+    /// The patterns of the ignore files in `dir`
+    fn ignore_files(&self, dir: &Path) -> Arc<Gitignore> {
+        let mut cache = self.ignore_files.lock().unwrap();
+        cache.entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let mut builder = GitignoreBuilder::new(dir);
+                for file in IGNORE_FILES.iter().map(|name| dir.join(name)).filter(|file| file.exists()) {
+                    if let Some(error) = builder.add(&file) {
+                        tracing::warn!("Failed to read {}: {}", file.display(), error);
+                    }
+                }
+                Arc::new(builder.build().unwrap_or_else(|_| Gitignore::empty()))
+            })
+            .clone()
+    }
+}
+
+/// Triggers redeploys from file changes
+#[derive(Clone)]
+pub struct Redeployer {
+    pending: Arc<Notify>,
+}
+
+impl Redeployer {
+    /// Runs `redeploy` in the background whenever changes are pending
+    pub fn spawn<F, Fut>(mut redeploy: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let pending = Arc::new(Notify::new());
+        let notified = pending.clone();
+        tokio::spawn(async move {
+            loop {
+                // A change made while the previous redeploy ran leaves a
+                // permit behind, however many there were
+                notified.notified().await;
+                redeploy().await;
+            }
+        });
+        Self { pending }
+    }
+
+    /// Reports a change to deploy
+    pub fn changed(&self) {
+        self.pending.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch_filter_globs_and_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["src/main.rs", "src/main.rs.swp", "target/debug/app", "README.md", "fly.toml", "notes.log"] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(dir.path().join(".gitignore"), "/target\n*.log\n").unwrap();
+        let changed = |filter: &WatchFilter, file: &str| filter.should_redeploy(&dir.path().join(file));
+        
+        let all = WatchFilter::new(dir.path(), WatchConfig::default()).unwrap();
+        assert!(changed(&all, "src/main.rs"));
+        assert!(changed(&all, "README.md"));
+        assert!(!changed(&all, "src/main.rs.swp"), "editor temp files are ignored");
+        assert!(!changed(&all, "target/debug/app"), ".gitignore is respected");
+        assert!(!changed(&all, "notes.log"));
+        let outside = tempfile::tempdir().unwrap();
+        assert!(!all.should_redeploy(outside.path()));
+        
+        let config = WatchConfig { include: vec!["src/".into(), "*.toml".into()], exclude: vec!["main.rs".into()] };
+        let narrowed = WatchFilter::new(dir.path(), config).unwrap();
+        assert!(changed(&narrowed, "fly.toml"));
+        assert!(!changed(&narrowed, "README.md"), "only included files count");
+        assert!(!changed(&narrowed, "src/main.rs"), "excludes win over includes");
+    }
+
+    #[test]
+    fn test_watch_filter_walks_nested_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            "src/main.rs", "src/gen/out.rs", "logs/keep.log", "logs/other.log",
+            "vendor/lib.rs", "target/debug/app", "web/node_modules/react/index.js",
+        ];
+        for file in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let changed = |filter: &WatchFilter, file: &str| filter.should_redeploy(&dir.path().join(file));
+        
+        // Build output and dependencies never count, even without a .gitignore
+        let filter = WatchFilter::new(dir.path(), WatchConfig::default()).unwrap();
+        assert!(changed(&filter, "src/main.rs"));
+        assert!(!changed(&filter, "target/debug/app"));
+        assert!(!changed(&filter, "web/node_modules/react/index.js"));
+        
+        std::fs::write(dir.path().join(".gitignore"), "*.log\n/vendor\n").unwrap();
+        std::fs::write(dir.path().join("src/gen/.gitignore"), "*.rs\n").unwrap();
+        std::fs::write(dir.path().join("logs/.ignore"), "!keep.log\n").unwrap();
+        std::fs::write(dir.path().join("vendor/.gitignore"), "!lib.rs\n").unwrap();
+        let filter = WatchFilter::new(dir.path(), WatchConfig::default()).unwrap();
+        assert!(!changed(&filter, "src/gen/out.rs"), "nested ignore files apply");
+        assert!(changed(&filter, "logs/keep.log"), "closer ignore files take precedence");
+        assert!(!changed(&filter, "logs/other.log"));
+        assert!(!changed(&filter, "vendor/lib.rs"), "files in ignored directories stay ignored");
+        
+        // Edited ignore files apply to the following changes
+        std::fs::write(dir.path().join("src/gen/.gitignore"), "").unwrap();
+        filter.should_redeploy(&dir.path().join("src/gen/.gitignore"));
+        assert!(changed(&filter, "src/gen/out.rs"));
+    }
+
+    #[test]
+    fn test_watch_filter_reads_fly_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let fly_toml = dir.path().join("fly.toml");
+        std::fs::write(&fly_toml, "app = \"web\"\n\n[minifly.watch]\ninclude = [\"*.py\"]\n").unwrap();
+        std::fs::write(dir.path().join("app.py"), "").unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+        
+        let filter = WatchFilter::load(&fly_toml).unwrap();
+        assert!(filter.should_redeploy(&dir.path().join("app.py")));
+        assert!(!filter.should_redeploy(&dir.path().join("README.md")));
+        assert!(filter.should_redeploy(&fly_toml), "the deployed fly.toml always counts");
+        
+        std::fs::write(dir.path().join(".gitignore"), ".fly.secrets*\n").unwrap();
+        std::fs::write(dir.path().join(".fly.secrets.web"), "API_KEY=1\n").unwrap();
+        let filter = WatchFilter::load(&fly_toml).unwrap();
+        assert!(filter.should_redeploy(&dir.path().join(".fly.secrets.web")), "secrets files count though gitignored");
+    }
+
+    #[tokio::test]
+    async fn test_changes_during_a_redeploy_coalesce_into_one() {
+        let redeploys = Arc::new(AtomicUsize::new(0));
+        let counter = redeploys.clone();
+        let redeployer = Redeployer::spawn(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100))
+        });
+
+        redeployer.changed();
+        while redeploys.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        for _ in 0..20 {
+            redeployer.changed();
+        }
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(redeploys.load(Ordering::SeqCst), 2, "one trailing redeploy for every change made during the first");
+    }
+}
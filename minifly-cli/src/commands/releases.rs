@@ -0,0 +1,77 @@
+use anyhow::Result;
+use colored::*;
+use tabled::{Table, Tabled};
+use crate::client::ApiClient;
+use crate::commands::secrets::get_app_name_from_fly_toml;
+
+#[derive(Tabled)]
+struct ReleaseRow {
+    #[tabled(rename = "VERSION")]
+    version: String,
+    #[tabled(rename = "IMAGE")]
+    image: String,
+    #[tabled(rename = "DESCRIPTION")]
+    description: String,
+    #[tabled(rename = "CREATED")]
+    created_at: String,
+}
+
+/// Resolve the app name from the argument or the local fly.toml
+async fn resolve_app(app: Option<String>) -> Result<String> {
+    match app {
+        Some(app) => Ok(app),
+        None => get_app_name_from_fly_toml().await,
+    }
+}
+
+pub async fn list(client: &ApiClient, app: Option<String>) -> Result<()> {
+    let app = resolve_app(app).await?;
+    let releases = client.list_releases(&app).await?;
+    
+    if releases.is_empty() {
+        println!("No releases found for app '{}'. Deploy it with: minifly deploy", app);
+        return Ok(());
+    }
+    
+    let rows: Vec<ReleaseRow> = releases.into_iter()
+        .map(|r| ReleaseRow {
+            version: format!("v{}", r.version),
+            image: r.image,
+            description: r.description,
+            created_at: r.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        })
+        .collect();
+    
+    let table = Table::new(rows);
+    println!("{}", table);
+    
+    Ok(())
+}
+
+pub async fn rollback(client: &ApiClient, app: Option<String>, version: u32) -> Result<()> {
+    let app = resolve_app(app).await?;
+    
+    println!("Rolling back app {} to v{}...", app.yellow(), version);
+    
+    let response = client.rollback_release(&app, version).await?;
+    
+    for machine in &response.machines {
+        match &machine.error {
+            None => println!("{} {} ({})", "✓".green(), machine.machine_id, machine.process_group),
+            Some(error) => println!("{} {} ({}): {}", "✗".red(), machine.machine_id, machine.process_group, error),
+        }
+    }
+    
+    match response.release {
+        Some(release) => {
+            println!("{}", "Rollback complete!".green());
+            println!("Release: v{}", release.version);
+            println!("Image: {}", release.image);
+            Ok(())
+        }
+        None => {
+            let failed = response.machines.iter().filter(|m| m.error.is_some()).count();
+            anyhow::bail!("{} of {} machines failed to roll back; run the rollback again to retry them", failed, response.machines.len())
+        }
+    }
+}
@@ -0,0 +1,125 @@
+/// `minifly scale`, the local `fly scale count`
+use anyhow::{bail, Result};
+use colored::*;
+use minifly_core::models::{Machine, MachineState};
+use crate::client::ApiClient;
+use crate::commands::machines;
+
+/// Creates or destroys machines until `app` has `count` of them
+///
+/// With `region`, only machines in that region are counted and new machines
+/// are created there. New machines clone the config of an existing machine,
+/// preferring one in the same region; an app without machines needs `image`.
+/// Scaling down destroys the newest machines first.
+pub async fn handle(
+    client: &ApiClient,
+    app: &str,
+    count: usize,
+    region: Option<String>,
+    image: Option<String>,
+) -> Result<()> {
+    let machines: Vec<Machine> = client.list_machines(app).await?
+        .into_iter()
+        .filter(|machine| !matches!(machine.state, MachineState::Destroying | MachineState::Destroyed))
+        .collect();
+    let mut scoped: Vec<&Machine> = machines.iter()
+        .filter(|machine| region.as_ref().is_none_or(|region| &machine.region == region))
+        .collect();
+    let scope = region.as_deref().map_or_else(String::new, |region| format!(" in {}", region));
+
+    if scoped.len() == count {
+        println!("{} App {} already has {} machine(s){}", "✓".green(), app.yellow(), count, scope);
+        return Ok(());
+    }
+
+    if scoped.len() < count {
+        let template = scoped.first().copied().or(machines.first());
+        let (config, template_region) = match (template, image) {
+            (Some(template), _) => (template.config.clone(), template.region.clone()),
+            (None, Some(image)) => (machines::machine_config(&image, &[], &[], &[])?, String::new()),
+            (None, None) => bail!("App {} has no machines to clone; pass --image to scale it from scratch", app),
+        };
+        // Without --region new machines join the template's region
+        let new_region = region.clone().or_else(|| (!template_region.is_empty()).then_some(template_region));
+
+        println!("Scaling {}{} from {} to {} machine(s)...", app.yellow(), scope, scoped.len(), count);
+        for _ in scoped.len()..count {
+            let machine = client.create_machine(app, None, new_region.clone(), config.clone()).await?;
+            println!("  {} Created {} in {}", "+".green(), machine.id, machine.region);
+        }
+    } else {
+        println!("Scaling {}{} from {} to {} machine(s)...", app.yellow(), scope, scoped.len(), count);
+        scoped.sort_by_key(|machine| std::cmp::Reverse(machine.created_at));
+        for machine in &scoped[..scoped.len() - count] {
+            client.delete_machine(app, &machine.id, true).await?;
+            println!("  {} Destroyed {} in {}", "-".red(), machine.id, machine.region);
+        }
+    }
+
+    println!("{} App {} now has {} machine(s){}", "✓".green(), app.yellow(), count, scope);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use minifly_core::models::CreateMachineRequest;
+    use std::sync::{Arc, Mutex};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Machines API of one app, keeping the machines it creates
+    #[derive(Clone, Default)]
+    struct FakeMachines(Arc<Mutex<Vec<Machine>>>);
+
+    impl Respond for FakeMachines {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let mut machines = self.0.lock().unwrap();
+            match request.method.as_str() {
+                "POST" => {
+                    let req: CreateMachineRequest = serde_json::from_slice(&request.body).unwrap();
+                    let id = format!("m{}", machines.len() + 1);
+                    let machine = Machine {
+                        region: req.region.unwrap_or_else(|| "local".to_string()),
+                        config: req.config,
+                        created_at: Utc::now(),
+                        ..crate::test_support::machine(&id)
+                    };
+                    machines.push(machine.clone());
+                    ResponseTemplate::new(200).set_body_json(machine)
+                }
+                "DELETE" => {
+                    let id = request.url.path_segments().unwrap().next_back().unwrap().to_string();
+                    machines.retain(|machine| machine.id != id);
+                    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true }))
+                }
+                _ => ResponseTemplate::new(200).set_body_json(&*machines),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scale_up_and_back_down() {
+        let api = MockServer::start().await;
+        let fake = FakeMachines::default();
+        Mock::given(wiremock::matchers::path_regex("^/v1/apps/web/machines"))
+            .respond_with(fake.clone())
+            .mount(&api)
+            .await;
+        let client = crate::test_support::client(&api);
+
+        assert!(handle(&client, "web", 3, None, None).await.is_err(), "no template and no image");
+
+        handle(&client, "web", 1, Some("ord".to_string()), Some("nginx:alpine".to_string())).await.unwrap();
+        handle(&client, "web", 3, None, None).await.unwrap();
+        {
+            let machines = fake.0.lock().unwrap();
+            assert_eq!(machines.len(), 3);
+            assert!(machines.iter().all(|m| m.config.image == "nginx:alpine" && m.region == "ord"));
+        }
+
+        handle(&client, "web", 1, None, None).await.unwrap();
+        let ids: Vec<_> = fake.0.lock().unwrap().iter().map(|m| m.id.clone()).collect();
+        assert_eq!(ids, ["m1"], "the oldest machine is kept");
+    }
+}
@@ -18,6 +18,9 @@ use std::sync::Arc;
 use tracing::{info, warn, error};
 use crate::client::ApiClient;
 use crate::commands::dependencies;
+use crate::commands::doctor::LOCK_FILES;
+use crate::commands::minio::{self, MinioConfig};
+use crate::commands::redeploy::{Redeployer, WatchFilter};
 
 // Global deployment mutex to prevent concurrent deployments
 lazy_static::lazy_static! {
@@ -30,25 +33,35 @@ lazy_static::lazy_static! {
 /// * `daemon` - Run in background as daemon process
 /// * `port` - Port number for the API server (default: 4280)
 /// * `dev` - Enable development mode with enhanced logging, auto-deployment, and file watching
+/// * `minio` - Run a MinIO container with this bucket and give machines its credentials
+/// * `stable_ports` - Publish machine ports on host ports derived from the app, not random ones
 /// 
 /// # Features
 /// - **Auto-deployment**: Automatically detects and deploys projects with fly.toml
-/// - **Port allocation**: Docker automatically assigns available ports to avoid conflicts
+/// - **Port allocation**: Docker automatically assigns available ports to avoid conflicts,
+///   or with `stable_ports` each app keeps the same host ports across redeploys
 /// - **File watching**: In dev mode, automatically redeploys on file changes
 /// - **Graceful shutdown**: Properly handles Ctrl+C with full cleanup
 /// 
 /// # Examples
 /// ```
 /// // Start in foreground
-/// serve::handle(false, 4280, false).await?;
+/// serve::handle(false, 4280, false, None, None, false).await?;
 /// 
 /// // Start as daemon in development mode
-/// serve::handle(true, 4280, true).await?;
+/// serve::handle(true, 4280, true, None, None, false).await?;
 /// 
 /// // Start with auto-deployment in a project directory
 /// // cd examples/basic-app && minifly serve --dev
 /// ```
-pub async fn handle(daemon: bool, port: u16, dev: bool, config_path: Option<String>) -> Result<()> {
+pub async fn handle(
+    daemon: bool,
+    port: u16,
+    dev: bool,
+    config_path: Option<String>,
+    minio: Option<MinioConfig>,
+    stable_ports: bool,
+) -> Result<()> {
     println!("{}", "🚀 Starting Minifly Platform".bold().blue());
     
     if dev {
@@ -70,28 +83,8 @@ pub async fn handle(daemon: bool, port: u16, dev: bool, config_path: Option<Stri
     let dep_manager = dependencies::DependencyManager::new();
     let results = dep_manager.check_all_dependencies().await;
     
-    let failed_deps: Vec<_> = results.iter()
-        .filter(|r| !r.available)
-        .collect();
-    
-    if !failed_deps.is_empty() {
-        println!("\n{}", "⚠️  Some dependencies are not available:".yellow().bold());
-        for dep in &failed_deps {
-            println!("  • {}: {}", dep.service.red(), 
-                    dep.error.as_ref().unwrap_or(&"Unknown error".to_string()).dimmed());
-        }
-        
-        // Check if any required dependencies are missing
-        let required_missing = failed_deps.iter()
-            .any(|r| dep_manager.is_required(&r.service));
-        
-        if required_missing {
-            println!("\n{}", "❌ Cannot start platform: required dependencies are missing".red().bold());
-            println!("{}", "Please ensure Docker and SQLite are installed and available".yellow());
-            return Err(anyhow::anyhow!("Required dependencies not available"));
-        } else {
-            println!("\n{}", "⚠️  Continuing with degraded functionality...".yellow());
-        }
+    if !dependencies::report_dependency_status(&dep_manager, &results) {
+        return Err(anyhow::anyhow!("Required dependencies not available"));
     }
     
     // Create necessary directories
@@ -100,8 +93,13 @@ pub async fn handle(daemon: bool, port: u16, dev: bool, config_path: Option<Stri
     // Start services in order
     println!("\n{}", "📦 Starting services...".cyan());
     
+    // MinIO goes first so the bucket exists before any machine starts
+    if let Some(minio) = &minio {
+        minio::start(minio, std::path::Path::new("data")).await?;
+    }
+    
     // 1. Start API server
-    start_api_server(port, daemon, dev).await?;
+    start_api_server(port, daemon, dev, minio.as_ref(), stable_ports).await?;
     
     // 2. Wait for API server to be ready
     wait_for_service_ready(port, "API Server").await?;
@@ -116,6 +114,9 @@ pub async fn handle(daemon: bool, port: u16, dev: bool, config_path: Option<Stri
         println!("   API Server: {}", format!("http://localhost:{}", port).blue());
         println!("   Health Check: {}", format!("http://localhost:{}/health", port).blue());
         println!("   LiteFS: {}", "Ready".green());
+        if let Some(minio) = &minio {
+            println!("   MinIO: {} (bucket: {})", minio.endpoint().blue(), minio.bucket);
+        }
         
         // Check if we're in a project directory and auto-deploy
         if let Some(project_info) = detect_project_config(dev, config_path).await? {
@@ -127,7 +128,10 @@ pub async fn handle(daemon: bool, port: u16, dev: bool, config_path: Option<Stri
             match auto_deploy_current_project(port, &project_info, dev).await {
                 Ok(app_url) => {
                     println!("{}", "✅ Application deployed successfully!".green().bold());
-                    println!("🔗 Access your app at: {}", app_url.blue().bold());
+                    match app_url {
+                        Some(app_url) => println!("🔗 Access your app at: {}", app_url.blue().bold()),
+                        None => println!("🔗 No ports are published; reach your app with: minifly proxy --app {}", project_info.app_name),
+                    }
                     
                     if dev {
                         // Setup development mode features
@@ -159,6 +163,10 @@ pub async fn handle(daemon: bool, port: u16, dev: bool, config_path: Option<Stri
             
             // Graceful shutdown
             shutdown_platform(port).await?;
+            if minio.is_some() {
+                minio::stop();
+                println!("   ✓ MinIO stopped");
+            }
             
             // Force exit to ensure background tasks don't prevent termination
             std::process::exit(0);
@@ -224,7 +232,9 @@ async fn setup_api_database() -> Result<()> {
 /// * `port` - Port number for the API server
 /// * `daemon` - Whether to run as daemon
 /// * `dev` - Whether to enable development mode
-async fn start_api_server(port: u16, daemon: bool, dev: bool) -> Result<()> {
+/// * `minio` - Object storage to inject into machines
+/// * `stable_ports` - Whether machines get stable host ports
+async fn start_api_server(port: u16, daemon: bool, dev: bool, minio: Option<&MinioConfig>, stable_ports: bool) -> Result<()> {
     println!("   • Starting API Server on port {}...", port.to_string().yellow());
     
     // Ensure database directory exists and set up database
@@ -240,6 +250,12 @@ async fn start_api_server(port: u16, daemon: bool, dev: bool) -> Result<()> {
     cmd.env("MINIFLY_API_PORT", port.to_string())
         .env("MINIFLY_DATABASE_URL", format!("sqlite:{}/minifly.db", data_dir.display()))
         .env("MINIFLY_DATA_DIR", data_dir.to_string_lossy().to_string());
+    if let Some(minio) = minio {
+        cmd.envs(minio.api_env());
+    }
+    if stable_ports {
+        cmd.env("MINIFLY_STABLE_PORTS", "1");
+    }
     
     if dev {
         cmd.env("RUST_LOG", "debug,minifly_api=trace,tower_http=debug");
@@ -272,21 +288,16 @@ async fn start_api_server(port: u16, daemon: bool, dev: bool) -> Result<()> {
 /// * `port` - Port number to check
 /// * `service_name` - Name of the service for logging
 async fn wait_for_service_ready(port: u16, service_name: &str) -> Result<()> {
-    let max_attempts = 30;
-    let mut attempts = 0;
+    let config = crate::config::Config {
+        api_url: format!("http://localhost:{}", port),
+        token: None,
+        ..Default::default()
+    };
     
-    while attempts < max_attempts {
-        if is_platform_running(port).await {
-            println!("   ✓ {} is ready", service_name.green());
-            return Ok(());
-        }
-        
-        attempts += 1;
-        if attempts % 5 == 0 {
-            println!("   ⏳ Waiting for {} (attempt {}/{})", service_name, attempts, max_attempts);
-        }
-        
-        sleep(Duration::from_secs(1)).await;
+    println!("   ⏳ Waiting for {}", service_name);
+    if ApiClient::new(&config)?.wait_for_health(Duration::from_secs(30)).await {
+        println!("   ✓ {} is ready", service_name.green());
+        return Ok(());
     }
     
     bail!("Timeout waiting for {} to become ready", service_name);
@@ -597,8 +608,7 @@ async fn cleanup_resources() -> Result<()> {
     // Clean up any temporary files or state
     
     // Remove any stale lock files
-    let lock_files = ["data/.minifly.lock", "data/litefs/.lock"];
-    for lock_file in &lock_files {
+    for lock_file in LOCK_FILES {
         if tokio::fs::metadata(lock_file).await.is_ok() {
             let _ = tokio::fs::remove_file(lock_file).await;
         }
@@ -692,104 +702,6 @@ async fn detect_project_config(dev: bool, config_path: Option<String>) -> Result
     }))
 }
 
-/// Get the URL for a deployed app by querying the actual assigned port
-/// 
-/// Since Docker automatically assigns available ports to avoid conflicts,
-/// this function queries the Docker container to find the actual port mapping.
-/// 
-/// # Arguments
-/// * `api_client` - API client for making requests (currently unused, kept for future API integration)
-/// * `app_name` - Name of the app
-/// 
-/// # Returns
-/// * `Ok(String)` - URL where the app is accessible with the actual port
-/// * `Err(...)` - Failed to get app URL
-/// 
-/// # Example
-/// ```
-/// let url = get_deployed_app_url(&api_client, "example-app").await?;
-/// // Returns: "http://localhost:32768"
-/// ```
-async fn get_deployed_app_url(api_client: &ApiClient, app_name: &str) -> Result<String> {
-    use std::process::Command;
-    
-    // Wait a bit for container to be fully started and port to be assigned
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-    
-    // Try multiple methods to get the port
-    
-    
-    // Method 2: Get the container ID first, then get port
-    let container_id_output = Command::new("docker")
-        .args(&["ps", "-q", "--filter", &format!("name=minifly-{}", app_name), "--latest"])
-        .output();
-        
-    if let Ok(output) = container_id_output {
-        if output.status.success() {
-            let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !container_id.is_empty() {
-                // Now get the port for this specific container
-                let port_output = Command::new("docker")
-                    .args(&["port", &container_id])
-                    .output();
-                    
-                if let Ok(output) = port_output {
-                    if output.status.success() {
-                        let ports = String::from_utf8_lossy(&output.stdout);
-                        // Parse output like "8080/tcp -> 0.0.0.0:32768"
-                        for line in ports.lines() {
-                            if let Some(mapping) = line.split(" -> ").nth(1) {
-                                if let Some(port) = mapping.split(':').last() {
-                                    let port = port.trim();
-                                    if !port.is_empty() && port.chars().all(|c| c.is_numeric()) {
-                                        return Ok(format!("http://localhost:{}", port));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    // Method 3: Original method with better parsing
-    let output = Command::new("docker")
-        .args(&["ps", "--filter", &format!("name=minifly-{}", app_name), "--format", "{{.Ports}}"])
-        .output()
-        .context("Failed to run docker ps command")?;
-    
-    if output.status.success() {
-        let ports_output = String::from_utf8_lossy(&output.stdout);
-        
-        // Parse various port formats Docker might output
-        for line in ports_output.lines() {
-            // Handle "0.0.0.0:32769->8080/tcp" format
-            if line.contains("->") {
-                if let Some(host_part) = line.split("->").next() {
-                    // Extract port from "0.0.0.0:32769" or ":::32769" format
-                    let port = host_part
-                        .split(':')
-                        .last()
-                        .unwrap_or("")
-                        .trim()
-                        .split(',')  // Handle multiple port mappings
-                        .next()
-                        .unwrap_or("")
-                        .trim();
-                    
-                    if !port.is_empty() && port.chars().all(|c| c.is_numeric()) {
-                        return Ok(format!("http://localhost:{}", port));
-                    }
-                }
-            }
-        }
-    }
-    
-    // If we still couldn't get it, provide helpful instructions
-    Ok("http://localhost (run 'docker ps' to find the port)".to_string())
-}
-
 /// Auto-deploy the current project using detected configuration
 /// 
 /// # Arguments
@@ -798,9 +710,10 @@ async fn get_deployed_app_url(api_client: &ApiClient, app_name: &str) -> Result<
 /// * `dev` - Whether in development mode
 /// 
 /// # Returns
-/// * `Ok(String)` - URL where the app is accessible
+/// * `Ok(Some(String))` - URL where the app is accessible
+/// * `Ok(None)` - The app publishes no ports on this host
 /// * `Err(...)` - Deployment failed
-async fn auto_deploy_current_project(port: u16, project_info: &ProjectInfo, _dev: bool) -> Result<String> {
+async fn auto_deploy_current_project(port: u16, project_info: &ProjectInfo, _dev: bool) -> Result<Option<String>> {
     let config_file = project_info.fly_toml_path.file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("fly.toml");
@@ -813,6 +726,7 @@ async fn auto_deploy_current_project(port: u16, project_info: &ProjectInfo, _dev
     let config = crate::config::Config {
         api_url: format!("http://localhost:{}", port),
         token: None,
+        ..Default::default()
     };
     let api_client = ApiClient::new(&config)?;
     
@@ -846,6 +760,7 @@ async fn setup_project_file_watcher(project_info: &ProjectInfo, port: u16) -> Re
     use notify::{Watcher, RecursiveMode, watcher};
     use std::sync::mpsc::channel;
     
+    let filter = WatchFilter::load(&project_info.fly_toml_path)?;
     let (tx, rx) = channel();
     let mut watcher = watcher(tx, Duration::from_secs(1))
         .context("Failed to create file watcher")?;
@@ -857,8 +772,25 @@ async fn setup_project_file_watcher(project_info: &ProjectInfo, port: u16) -> Re
     watcher.watch(project_dir, RecursiveMode::Recursive)
         .context("Failed to start watching directory")?;
     
-    // Spawn task to handle file change events
     let project_info_clone = project_info.clone();
+    let redeployer = Redeployer::spawn(move || {
+        let project_info = project_info_clone.clone();
+        async move {
+            println!("\n{}", "🔄 File change detected, redeploying...".yellow());
+            
+            // Wait for any other deployment, such as the initial one, to finish
+            let _lock = DEPLOYMENT_MUTEX.lock().await;
+            if let Err(e) = redeploy_project(&project_info, port).await {
+                eprintln!("{}", format!("❌ Redeploy failed: {}", e).red());
+            } else {
+                println!("{}", "✅ Redeploy completed".green());
+            }
+            
+            println!("{}", "👀 Watching for changes...".dimmed());
+        }
+    });
+    
+    // Spawn task to handle file change events
     tokio::spawn(async move {
         use notify::DebouncedEvent;
         
@@ -867,21 +799,8 @@ async fn setup_project_file_watcher(project_info: &ProjectInfo, port: u16) -> Re
                 Ok(event) => {
                     match event {
                         DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => {
-                            if should_trigger_redeploy(&path) {
-                                println!("\n{}", "🔄 File change detected, redeploying...".yellow());
-                                
-                                // Acquire deployment lock to prevent concurrent deployments
-                                if let Ok(_lock) = DEPLOYMENT_MUTEX.try_lock() {
-                                    if let Err(e) = redeploy_project(&project_info_clone, port).await {
-                                        eprintln!("{}", format!("❌ Redeploy failed: {}", e).red());
-                                    } else {
-                                        println!("{}", "✅ Redeploy completed".green());
-                                    }
-                                } else {
-                                    println!("{}", "⏳ Another deployment is in progress, skipping...".yellow());
-                                }
-                                
-                                println!("{}", "👀 Watching for changes...".dimmed());
+                            if filter.should_redeploy(&path) {
+                                redeployer.changed();
                             }
                         }
                         _ => {}
@@ -898,30 +817,17 @@ async fn setup_project_file_watcher(project_info: &ProjectInfo, port: u16) -> Re
     Ok(())
 }
 
-/// Determine if a file change should trigger redeployment
-fn should_trigger_redeploy(path: &std::path::Path) -> bool {
-    if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-        match filename {
-            "fly.toml" | "Dockerfile" | "litefs.yml" => true,
-            name if name.ends_with(".rs") => true,
-            name if name.ends_with(".toml") => true,
-            _ => false,
-        }
-    } else {
-        false
-    }
-}
-
 /// Redeploy the project after file changes
 async fn redeploy_project(project_info: &ProjectInfo, port: u16) -> Result<()> {
     let config = crate::config::Config {
         api_url: format!("http://localhost:{}", port),
         token: None,
+        ..Default::default()
     };
     let api_client = ApiClient::new(&config)?;
     
     let fly_toml_path = project_info.fly_toml_path.to_string_lossy().to_string();
-    crate::commands::deploy::handle(&api_client, Some(fly_toml_path), None, false).await?;
+    crate::commands::deploy::handle(&api_client, Some(fly_toml_path), None, None, false, false).await?;
     
     Ok(())
 }
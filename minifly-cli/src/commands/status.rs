@@ -8,10 +8,11 @@
 /// - Recent activity and events
 use anyhow::Result;
 use colored::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tabled::{Table, Tabled};
 use serde::{Deserialize, Serialize};
 use crate::client::ApiClient;
+use minifly_core::models::AppLogsSummary;
 
 /// Status information for display
 #[derive(Tabled)]
@@ -26,6 +27,21 @@ struct ServiceStatus {
     info: String,
 }
 
+/// App log health for display
+#[derive(Tabled)]
+struct LogHealthRow {
+    #[tabled(rename = "App")]
+    app: String,
+    #[tabled(rename = "Lines")]
+    lines: usize,
+    #[tabled(rename = "Errors")]
+    errors: String,
+    #[tabled(rename = "Warnings")]
+    warnings: String,
+    #[tabled(rename = "Last Activity")]
+    last_activity: String,
+}
+
 /// Machine summary for region display
 #[derive(Tabled)]
 struct RegionSummary {
@@ -61,14 +77,156 @@ struct ServiceHealth {
     details: HashMap<String, serde_json::Value>,
 }
 
+/// Machine-readable platform status printed by `minifly status --json`.
+/// 
+/// Scripts and integration tests assert on this document, so fields may be
+/// added but existing ones are never renamed or removed. Maps are sorted by
+/// key so the output is stable between runs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusReport {
+    /// Whether the API server answered its health check
+    pub api_reachable: bool,
+    /// Overall health: `healthy`, `degraded`, `unhealthy` or `unknown`
+    pub health: String,
+    /// API server version, if reachable
+    pub version: Option<String>,
+    /// API server uptime in seconds, if reachable
+    pub uptime_seconds: Option<u64>,
+    /// Health of each platform service (database, docker, ...) keyed by name
+    pub services: BTreeMap<String, String>,
+    /// Apps and their machines
+    pub apps: Vec<AppStatus>,
+    /// Number of machines in the `started` state across all apps
+    pub running_machines: usize,
+    /// Machine IPs registered with internal DNS, keyed by app name
+    pub dns: BTreeMap<String, Vec<String>>,
+}
+
+/// An app in [`StatusReport`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppStatus {
+    pub name: String,
+    pub status: String,
+    pub machines: Vec<MachineStatus>,
+    /// Levels of the machines' recent log lines, if the API could read them
+    #[serde(default)]
+    pub logs: Option<LogHealth>,
+}
+
+/// Log health of an app in [`StatusReport`], over its machines' recent lines
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogHealth {
+    pub lines: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    /// Timestamp of the newest line of any machine
+    pub last_activity: Option<String>,
+}
+
+impl From<AppLogsSummary> for LogHealth {
+    fn from(summary: AppLogsSummary) -> Self {
+        Self {
+            lines: summary.line_count,
+            errors: summary.error_count,
+            warnings: summary.warn_count,
+            last_activity: summary.last_activity,
+        }
+    }
+}
+
+/// A machine in [`StatusReport`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MachineStatus {
+    pub id: String,
+    pub name: String,
+    /// Lowercase machine state as used by the API, e.g. `started`
+    pub state: String,
+    pub region: String,
+    pub image: String,
+}
+
+/// Gathers a [`StatusReport`] from the health, admin status and apps endpoints.
+/// 
+/// An unreachable API yields a report with `api_reachable: false` rather than an error.
+pub async fn collect_report(client: &ApiClient) -> StatusReport {
+    let mut report = StatusReport {
+        api_reachable: false,
+        health: "unknown".to_string(),
+        version: None,
+        uptime_seconds: None,
+        services: BTreeMap::new(),
+        apps: Vec::new(),
+        running_machines: 0,
+        dns: BTreeMap::new(),
+    };
+    
+    let (api_status, _) = check_api_status(client).await;
+    if !api_status {
+        return report;
+    }
+    report.api_reachable = true;
+    
+    if let Ok(response) = client.get("/health/comprehensive").await {
+        if let Ok(health) = response.json::<HealthResponse>().await {
+            report.health = health.status;
+            report.services = health.services.into_iter()
+                .map(|(name, service)| (name, service.status))
+                .collect();
+        }
+    }
+    
+    if let Ok(response) = client.get("/admin/status").await {
+        if let Ok(status) = response.json::<serde_json::Value>().await {
+            report.version = status["server"]["version"].as_str().map(str::to_string);
+            report.uptime_seconds = status["server"]["uptime_seconds"].as_u64();
+            if let Ok(dns) = serde_json::from_value(status["dns"]["registrations"].clone()) {
+                report.dns = dns;
+            }
+        }
+    }
+    
+    if let Ok(apps) = client.list_apps().await {
+        for app in apps {
+            let machines: Vec<MachineStatus> = client.list_machines(&app.name).await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| MachineStatus {
+                    id: m.id,
+                    name: m.name,
+                    state: m.state.as_str().to_string(),
+                    region: m.region,
+                    image: m.config.image,
+                })
+                .collect();
+            
+            let logs = client.get_app_logs_summary(&app.name).await.ok().map(LogHealth::from);
+            
+            report.running_machines += machines.iter().filter(|m| m.state == "started").count();
+            report.apps.push(AppStatus { name: app.name, status: app.status, machines, logs });
+        }
+        report.apps.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    
+    report
+}
+
 /// Handle the enhanced status command
 /// 
 /// Displays comprehensive platform status including:
 /// - Service health with region information
 /// - Resource counts by region
 /// - Docker and dependency status
+/// - Log errors and warnings by app
 /// - Recent activity
-pub async fn handle(client: &ApiClient) -> Result<()> {
+/// 
+/// With `json`, prints a [`StatusReport`] instead.
+pub async fn handle(client: &ApiClient, json: bool) -> Result<()> {
+    if json {
+        let report = collect_report(client).await;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    
     println!("{}", "🚀 Minifly Platform Status".bold().blue());
     println!("{}", "========================".blue());
     
@@ -95,10 +253,15 @@ pub async fn handle(client: &ApiClient) -> Result<()> {
         show_resource_summary(client).await?;
     }
     
-    // 5. Show system information
+    // 5. Show log health by app
+    if api_status {
+        show_log_health(client).await;
+    }
+    
+    // 6. Show system information
     show_system_info().await;
     
-    // 6. Show recent activity
+    // 7. Show recent activity
     if api_status {
         show_recent_activity(client).await?;
     }
@@ -300,6 +463,46 @@ async fn show_resource_summary(client: &ApiClient) -> Result<()> {
     Ok(())
 }
 
+/// Show error and warning counts of each app's recent log lines
+async fn show_log_health(client: &ApiClient) {
+    println!("\n{}", "📝 Log Health".bold());
+    
+    let Ok(apps) = client.list_apps().await else {
+        println!("  Unable to fetch log health");
+        return;
+    };
+    
+    let mut rows = Vec::new();
+    for app in apps {
+        let Ok(summary) = client.get_app_logs_summary(&app.name).await else {
+            continue;
+        };
+        if summary.machines.is_empty() {
+            continue;
+        }
+        let count = |count: usize, color: Color| {
+            if count > 0 { count.to_string().color(color).to_string() } else { "0".to_string() }
+        };
+        rows.push(LogHealthRow {
+            app: app.name,
+            lines: summary.line_count,
+            errors: count(summary.error_count, Color::Red),
+            warnings: count(summary.warn_count, Color::Yellow),
+            last_activity: summary.last_activity
+                .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(&timestamp).ok())
+                .map(|time| time.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        });
+    }
+    
+    if rows.is_empty() {
+        println!("  No machine logs yet");
+    } else {
+        rows.sort_by(|a, b| a.app.cmp(&b.app));
+        println!("{}", Table::new(rows));
+    }
+}
+
 /// Show system information
 async fn show_system_info() {
     println!("\n{}", "⚙️  System Information".bold());
@@ -361,4 +564,80 @@ async fn show_recent_activity(client: &ApiClient) -> Result<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::client_for;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_status_json_is_parseable() {
+        let server = MockServer::start().await;
+        let mock = |route: &str, body: serde_json::Value| {
+            Mock::given(method("GET")).and(path(route)).respond_with(ResponseTemplate::new(200).set_body_json(body))
+        };
+        
+        mock("/v1/health/comprehensive", serde_json::json!({
+            "status": "healthy",
+            "version": "0.2.2",
+            "uptime_seconds": 42,
+            "timestamp": "2024-06-22T10:30:00Z",
+            "services": {
+                "docker": {
+                    "status": "healthy",
+                    "message": "ok",
+                    "last_checked": "2024-06-22T10:30:00Z",
+                    "response_time_ms": 3,
+                    "details": {}
+                }
+            },
+            "summary": "All services healthy"
+        })).mount(&server).await;
+        mock("/v1/admin/status", serde_json::json!({
+            "server": { "version": "0.2.2", "uptime_seconds": 42 },
+            "dns": { "registrations": { "web": ["172.19.0.2"] } }
+        })).mount(&server).await;
+        mock("/v1/apps", serde_json::json!([{
+            "id": "app_1",
+            "name": "web",
+            "organization": { "id": "org_1", "slug": "personal", "name": "Personal" },
+            "status": "deployed",
+            "created_at": "2024-06-22T10:00:00Z"
+        }])).mount(&server).await;
+        mock("/v1/apps/web/machines", serde_json::json!([])).mount(&server).await;
+        mock("/v1/apps/web/logs/summary", serde_json::json!({
+            "app_name": "web",
+            "line_count": 12,
+            "error_count": 2,
+            "warn_count": 1,
+            "last_activity": "2024-06-22T10:29:59Z",
+            "machines": []
+        })).mount(&server).await;
+        
+        let report = collect_report(&client_for(&server.uri())).await;
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        
+        assert_eq!(parsed["api_reachable"], true);
+        assert_eq!(parsed["health"], "healthy");
+        assert_eq!(parsed["version"], "0.2.2");
+        assert_eq!(parsed["services"]["docker"], "healthy");
+        assert_eq!(parsed["apps"][0]["name"], "web");
+        assert_eq!(parsed["apps"][0]["logs"]["errors"], 2);
+        assert_eq!(parsed["running_machines"], 0);
+        assert_eq!(parsed["dns"]["web"][0], "172.19.0.2");
+    }
+
+    #[tokio::test]
+    async fn test_status_json_when_api_unreachable() {
+        let report = collect_report(&client_for("http://127.0.0.1:1")).await;
+        let parsed: serde_json::Value = serde_json::to_value(&report).unwrap();
+        
+        assert_eq!(parsed["api_reachable"], false);
+        assert_eq!(parsed["health"], "unknown");
+        assert!(parsed["apps"].as_array().unwrap().is_empty());
+    }
+}
@@ -5,30 +5,171 @@
 /// - Shutdown LiteFS processes
 /// - Stop the API server
 /// - Clean up resources
-use anyhow::Result;
+/// 
+/// What would be stopped is gathered into a [`StopPlan`] first, so
+/// `--dry-run` can print it without touching anything and the graceful path
+/// can ask for confirmation before acting on it.
+/// 
+/// `--force` never talks to the API server, so it works when the server is
+/// wedged: it removes every container labeled `minifly.managed=true`, kills
+/// LiteFS and API server processes and deletes the platform's lock files.
+use anyhow::{Context, Result};
 use colored::*;
+use std::fmt;
+use std::future::Future;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 use std::process::Command;
 use std::time::Duration;
 use tokio::time::sleep;
+use crate::commands::doctor::LOCK_FILES;
+
+/// Label Minifly puts on every container it creates
+const MANAGED_LABEL: &str = "minifly.managed=true";
+
+/// Everything `minifly stop` acts on
+#[derive(Debug, Default, Clone, PartialEq)]
+struct StopPlan {
+    /// Whether containers are removed directly instead of stopping machines
+    /// through the API
+    force: bool,
+    /// Started machines, as `(app, machine ID)`
+    machines: Vec<(String, String)>,
+    /// Containers labeled `minifly.managed=true`: running ones, or every one
+    /// when forced
+    containers: Vec<String>,
+    /// PIDs of LiteFS processes
+    litefs_pids: Vec<u32>,
+    /// PIDs of API server processes
+    api_pids: Vec<u32>,
+    /// Lock files to delete, when forced
+    lock_files: Vec<PathBuf>,
+}
+
+impl StopPlan {
+    /// Lists the targets besides machines with `run`, which runs a command
+    /// and returns its output. Only `docker ps` and `pgrep` are run.
+    fn gather(machines: Vec<(String, String)>, force: bool, run: impl Fn(&str, &[&str]) -> Option<String>) -> Self {
+        let pids = |pattern: &str| -> Vec<u32> {
+            run("pgrep", &["-f", pattern])
+                .map(|output| output.lines().filter_map(|pid| pid.trim().parse().ok()).collect())
+                .unwrap_or_default()
+        };
+        let mut ps = vec!["ps", "--format", "{{.Names}}\t{{.Labels}}"];
+        if force {
+            ps.insert(1, "-a");
+        }
+        let containers = run("docker", &ps)
+            .map(|output| managed_containers(&output))
+            .unwrap_or_default();
+        let lock_files = if force {
+            LOCK_FILES.iter().map(PathBuf::from).filter(|path| path.exists()).collect()
+        } else {
+            Vec::new()
+        };
+        
+        Self {
+            force,
+            machines,
+            containers,
+            litefs_pids: pids("litefs"),
+            api_pids: pids("minifly-api"),
+            lock_files,
+        }
+    }
+}
+
+impl fmt::Display for StopPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.force {
+            writeln!(f, "Machines to stop ({}):", self.machines.len())?;
+            for (app, machine_id) in &self.machines {
+                writeln!(f, "  • {} ({})", machine_id, app)?;
+            }
+        }
+        let action = if self.force { "remove" } else { "stop" };
+        writeln!(f, "Containers to {} ({}):", action, self.containers.len())?;
+        for name in &self.containers {
+            writeln!(f, "  • {}", name)?;
+        }
+        writeln!(f, "LiteFS processes to stop ({}):", self.litefs_pids.len())?;
+        for pid in &self.litefs_pids {
+            writeln!(f, "  • {}", pid)?;
+        }
+        writeln!(f, "API server processes to stop ({}):", self.api_pids.len())?;
+        for pid in &self.api_pids {
+            writeln!(f, "  • {}", pid)?;
+        }
+        if self.force {
+            write!(f, "Lock files to delete ({}):", self.lock_files.len())?;
+            for path in &self.lock_files {
+                write!(f, "\n  • {}", path.display())?;
+            }
+            Ok(())
+        } else {
+            write!(f, "Stopped containers and volumes labeled minifly are pruned")
+        }
+    }
+}
+
+/// Names of the containers labeled `minifly.managed=true`, from `docker ps`
+/// lines of `name<TAB>labels` with comma-separated `key=value` labels
+fn managed_containers(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(_, labels)| labels.split(',').any(|label| label.trim() == MANAGED_LABEL))
+        .map(|(name, _)| name.trim().to_string())
+        .collect()
+}
+
+/// Runs a command, returning its standard output if it could be started
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs a command, returning whether it succeeded
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
 
 /// Handle the stop command to shutdown the Minifly platform
 /// 
 /// # Arguments
-/// * `force` - Force stop all services without graceful shutdown
+/// * `force` - Remove everything directly with Docker, without the API server
+/// * `dry_run` - Only print what would be stopped
+/// * `yes` - Don't ask for confirmation before a graceful shutdown
 /// 
 /// # Examples
 /// ```
-/// // Graceful shutdown
-/// stop::handle(false).await?;
+/// // Graceful shutdown, after confirming
+/// stop::handle(false, false, false).await?;
+/// 
+/// // List what a shutdown would stop
+/// stop::handle(false, true, false).await?;
 /// 
 /// // Force shutdown
-/// stop::handle(true).await?;
+/// stop::handle(true, false, false).await?;
 /// ```
-pub async fn handle(force: bool) -> Result<()> {
+pub async fn handle(force: bool, dry_run: bool, yes: bool) -> Result<()> {
     println!("{}", "🛑 Stopping Minifly Platform".bold().red());
     
     if force {
-        println!("{}", "⚡ Force mode enabled".yellow());
+        println!("{}", "⚡ Force mode enabled, bypassing the API server".yellow());
+        let plan = StopPlan::gather(Vec::new(), true, run_command);
+        if dry_run {
+            println!("{}", "📋 Dry run, nothing will be stopped".cyan());
+            println!("{}", plan);
+            return Ok(());
+        }
+        return force_stop(&plan);
     }
     
     // Check if platform is running
@@ -38,21 +179,49 @@ pub async fn handle(force: bool) -> Result<()> {
         return Ok(());
     }
     
+    let plan = StopPlan::gather(started_machines(default_port).await, false, run_command);
+    if dry_run {
+        println!("{}", "📋 Dry run, nothing will be stopped".cyan());
+        println!("{}", plan);
+        return Ok(());
+    }
+    
+    // Scripts and service managers can't answer a prompt
+    if !yes && std::io::stdin().is_terminal() {
+        println!("{}", plan);
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt("Stop the Minifly platform?")
+            .interact()?;
+        if !confirmed {
+            println!("Stop cancelled.");
+            return Ok(());
+        }
+    }
+    
     println!("{}", "📋 Stopping services...".cyan());
     
     // 1. Stop all running machines
-    stop_all_machines(default_port, force).await?;
+    let summary = stop_all_machines(&plan.machines, |app_name, machine_id| async move {
+        stop_machine(default_port, &app_name, &machine_id).await
+    }).await;
     
     // 2. Stop LiteFS processes
-    stop_litefs(force).await?;
+    stop_litefs().await?;
     
     // 3. Stop API server
-    stop_api_server(default_port, force).await?;
+    stop_api_server(default_port).await?;
     
     // 4. Cleanup resources
     cleanup_resources().await?;
     
+    if !summary.failed.is_empty() {
+        println!("\n{}", format!("⚠️  {}", summary).red().bold());
+        println!("   Their containers may still be running; remove them with {}", "minifly stop --force".cyan());
+        anyhow::bail!("{} machine(s) failed to stop", summary.failed.len());
+    }
+    
     println!("\n{}", "✅ Minifly platform stopped successfully".green().bold());
+    println!("   {}", summary);
     
     Ok(())
 }
@@ -71,46 +240,92 @@ async fn is_platform_running(port: u16) -> bool {
     }
 }
 
-/// Stop all running machines
+/// The started machines of every app, as `(app, machine ID)`
 /// 
 /// # Arguments
 /// * `port` - API server port
-/// * `force` - Whether to force stop machines
-async fn stop_all_machines(port: u16, force: bool) -> Result<()> {
-    println!("   • Stopping running machines...");
-    
+async fn started_machines(port: u16) -> Vec<(String, String)> {
     let client = reqwest::Client::new();
     let apps_url = format!("http://localhost:{}/v1/apps", port);
+    let mut started = Vec::new();
     
     // Get all apps
-    match client.get(&apps_url).send().await {
-        Ok(response) => {
-            if let Ok(apps) = response.json::<Vec<serde_json::Value>>().await {
-                for app in apps {
-                    if let Some(app_name) = app["name"].as_str() {
-                        // Get machines for this app
-                        let machines_url = format!("http://localhost:{}/v1/apps/{}/machines", port, app_name);
-                        
-                        if let Ok(machines_response) = client.get(&machines_url).send().await {
-                            if let Ok(machines) = machines_response.json::<Vec<serde_json::Value>>().await {
-                                for machine in machines {
-                                    if let Some(machine_id) = machine["id"].as_str() {
-                                        stop_machine(port, app_name, machine_id, force).await?;
-                                    }
-                                }
-                            }
-                        }
+    let Ok(response) = client.get(&apps_url).send().await else {
+        println!("     ⚠️  Could not retrieve running machines");
+        return started;
+    };
+    let Ok(apps) = response.json::<Vec<serde_json::Value>>().await else {
+        return started;
+    };
+    for app in apps {
+        let Some(app_name) = app["name"].as_str() else {
+            continue;
+        };
+        // Get machines for this app
+        let machines_url = format!("http://localhost:{}/v1/apps/{}/machines", port, app_name);
+        if let Ok(machines_response) = client.get(&machines_url).send().await {
+            if let Ok(machines) = machines_response.json::<Vec<serde_json::Value>>().await {
+                for machine in machines {
+                    if let (Some(machine_id), Some("started")) = (machine["id"].as_str(), machine["state"].as_str()) {
+                        started.push((app_name.to_string(), machine_id.to_string()));
                     }
                 }
             }
         }
-        Err(_) => {
-            println!("     ⚠️  Could not retrieve running machines");
+    }
+    
+    started
+}
+
+/// How stopping the platform's machines went
+#[derive(Debug, Default, PartialEq)]
+struct StopSummary {
+    /// Machines that stopped, as `(app, machine ID)`
+    stopped: Vec<(String, String)>,
+    /// Machines that didn't, as `(app, machine ID, reason)`
+    failed: Vec<(String, String, String)>,
+}
+
+impl fmt::Display for StopSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} machine(s) stopped", self.stopped.len())?;
+        if !self.failed.is_empty() {
+            write!(f, ", {} failed to stop:", self.failed.len())?;
+            for (app_name, machine_id, reason) in &self.failed {
+                write!(f, "\n  • {} ({}): {}", machine_id, app_name, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stop all running machines, carrying on past failures
+/// 
+/// # Arguments
+/// * `machines` - Machines to stop, as `(app, machine ID)`
+/// * `stop` - Stops one machine, given its app and ID
+async fn stop_all_machines<F, Fut>(machines: &[(String, String)], stop: F) -> StopSummary
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    println!("   • Stopping running machines...");
+    
+    let mut summary = StopSummary::default();
+    for (app_name, machine_id) in machines {
+        match stop(app_name.clone(), machine_id.clone()).await {
+            Ok(()) => {
+                println!("     ✓ Stopped machine {} ({})", machine_id, app_name);
+                summary.stopped.push((app_name.clone(), machine_id.clone()));
+            }
+            Err(e) => {
+                println!("     {}", format!("⚠️  Failed to stop machine {} ({}): {}", machine_id, app_name, e).yellow());
+                summary.failed.push((app_name.clone(), machine_id.clone(), e.to_string()));
+            }
         }
     }
     
-    println!("     ✓ All machines stopped");
-    Ok(())
+    summary
 }
 
 /// Stop a specific machine
@@ -119,49 +334,30 @@ async fn stop_all_machines(port: u16, force: bool) -> Result<()> {
 /// * `port` - API server port
 /// * `app_name` - Application name
 /// * `machine_id` - Machine ID to stop
-/// * `force` - Whether to force stop
-async fn stop_machine(port: u16, app_name: &str, machine_id: &str, force: bool) -> Result<()> {
+async fn stop_machine(port: u16, app_name: &str, machine_id: &str) -> Result<()> {
     let client = reqwest::Client::new();
     let stop_url = format!(
         "http://localhost:{}/v1/apps/{}/machines/{}/stop", 
         port, app_name, machine_id
     );
     
-    let mut request = client.post(&stop_url);
+    let request = client.post(&stop_url).json(&serde_json::json!({
+        "signal": "SIGTERM", 
+        "timeout": 30
+    }));
     
-    if force {
-        request = request.json(&serde_json::json!({
-            "signal": "SIGKILL",
-            "timeout": 1
-        }));
-    } else {
-        request = request.json(&serde_json::json!({
-            "signal": "SIGTERM", 
-            "timeout": 30
-        }));
-    }
-    
-    match request.send().await {
-        Ok(response) => {
-            if response.status().is_success() {
-                println!("     ✓ Stopped machine {} ({})", machine_id, app_name);
-            } else {
-                println!("     ⚠️  Failed to stop machine {} ({})", machine_id, app_name);
-            }
-        }
-        Err(_) => {
-            println!("     ⚠️  Could not communicate with machine {} ({})", machine_id, app_name);
-        }
+    let response = request.send().await.context("Could not reach the API server")?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} {}", status, body.trim());
     }
     
     Ok(())
 }
 
 /// Stop LiteFS processes
-/// 
-/// # Arguments
-/// * `force` - Whether to force stop LiteFS
-async fn stop_litefs(force: bool) -> Result<()> {
+async fn stop_litefs() -> Result<()> {
     println!("   • Stopping LiteFS...");
     
     // Find and stop LiteFS processes
@@ -175,10 +371,8 @@ async fn stop_litefs(force: bool) -> Result<()> {
             let pids = String::from_utf8_lossy(&result.stdout);
             for pid in pids.lines() {
                 if let Ok(_pid_num) = pid.trim().parse::<u32>() {
-                    let signal = if force { "KILL" } else { "TERM" };
-                    
                     let kill_result = Command::new("kill")
-                        .arg(format!("-{}", signal))
+                        .arg("-TERM")
                         .arg(pid.trim())
                         .output();
                     
@@ -202,14 +396,13 @@ async fn stop_litefs(force: bool) -> Result<()> {
 /// 
 /// # Arguments
 /// * `port` - API server port
-/// * `force` - Whether to force stop
-async fn stop_api_server(port: u16, force: bool) -> Result<()> {
+async fn stop_api_server(port: u16) -> Result<()> {
     println!("   • Stopping API server...");
     
     // First try graceful shutdown if API is responsive
-    if !force && is_platform_running(port).await {
+    if is_platform_running(port).await {
         let client = reqwest::Client::new();
-        let shutdown_url = format!("http://localhost:{}/admin/shutdown", port);
+        let shutdown_url = format!("http://localhost:{}/v1/admin/shutdown", port);
         
         if let Ok(_) = client.post(&shutdown_url).send().await {
             println!("     ✓ API server graceful shutdown initiated");
@@ -236,10 +429,8 @@ async fn stop_api_server(port: u16, force: bool) -> Result<()> {
             let pids = String::from_utf8_lossy(&result.stdout);
             for pid in pids.lines() {
                 if let Ok(_) = pid.trim().parse::<u32>() {
-                    let signal = if force { "KILL" } else { "TERM" };
-                    
                     let kill_result = Command::new("kill")
-                        .arg(format!("-{}", signal))
+                        .arg("-TERM")
                         .arg(pid.trim())
                         .output();
                     
@@ -258,6 +449,47 @@ async fn stop_api_server(port: u16, force: bool) -> Result<()> {
     Ok(())
 }
 
+/// Removes everything in a forced plan without the API server, carrying on
+/// past failures and failing at the end if anything is left
+fn force_stop(plan: &StopPlan) -> Result<()> {
+    let mut failures = Vec::new();
+    
+    println!("   • Removing containers...");
+    for name in &plan.containers {
+        if succeeds("docker", &["rm", "-f", name]) {
+            println!("     ✓ Removed container {}", name);
+        } else {
+            failures.push(format!("container {}", name));
+        }
+    }
+    
+    println!("   • Killing LiteFS and API server processes...");
+    for pid in plan.litefs_pids.iter().chain(&plan.api_pids) {
+        if succeeds("kill", &["-KILL", &pid.to_string()]) {
+            println!("     ✓ Killed process {}", pid);
+        } else {
+            failures.push(format!("process {}", pid));
+        }
+    }
+    
+    println!("   • Deleting lock files...");
+    for path in &plan.lock_files {
+        match std::fs::remove_file(path) {
+            Ok(()) => println!("     ✓ Deleted {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(_) => failures.push(format!("lock file {}", path.display())),
+        }
+    }
+    
+    if !failures.is_empty() {
+        println!("\n{}", format!("⚠️  Could not remove {}", failures.join(", ")).red().bold());
+        anyhow::bail!("{} target(s) could not be removed", failures.len());
+    }
+    
+    println!("\n{}", "✅ Minifly platform force stopped".green().bold());
+    Ok(())
+}
+
 /// Clean up resources and temporary files
 async fn cleanup_resources() -> Result<()> {
     println!("   • Cleaning up resources...");
@@ -275,4 +507,83 @@ async fn cleanup_resources() -> Result<()> {
     println!("     ✓ Resources cleaned up");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_dry_run_lists_targets_without_touching_docker() {
+        let commands = RefCell::new(Vec::new());
+        let run = |program: &str, args: &[&str]| {
+            commands.borrow_mut().push(format!("{} {}", program, args.join(" ")));
+            match (program, args.last().copied()) {
+                ("docker", _) => Some("minifly-web-d1\tminifly.managed=true\nminifly-worker-d2\tminifly.managed=true\n".to_string()),
+                ("pgrep", Some("litefs")) => Some("4242\n".to_string()),
+                ("pgrep", Some("minifly-api")) => Some("1001\n1002\n".to_string()),
+                _ => None,
+            }
+        };
+        
+        let machines = vec![("web".to_string(), "d1".to_string()), ("worker".to_string(), "d2".to_string())];
+        let plan = StopPlan::gather(machines.clone(), false, run);
+        assert_eq!(plan, StopPlan {
+            force: false,
+            machines,
+            containers: vec!["minifly-web-d1".to_string(), "minifly-worker-d2".to_string()],
+            litefs_pids: vec![4242],
+            api_pids: vec![1001, 1002],
+            lock_files: Vec::new(),
+        });
+        
+        let listing = plan.to_string();
+        for target in ["d1 (web)", "d2 (worker)", "minifly-web-d1", "4242", "1002"] {
+            assert!(listing.contains(target), "{} missing from:\n{}", target, listing);
+        }
+        
+        // Only read-only listings ran
+        let commands = commands.into_inner();
+        assert_eq!(commands.len(), 3);
+        assert!(commands.iter().all(|c| c.starts_with("docker ps ") || c.starts_with("pgrep ")), "{:?}", commands);
+    }
+
+    #[tokio::test]
+    async fn test_failed_machine_stop_is_reported() {
+        let machines: Vec<(String, String)> = ["d1", "d2", "d3"].iter()
+            .map(|id| ("web".to_string(), id.to_string()))
+            .collect();
+        
+        let summary = stop_all_machines(&machines, |_, machine_id| async move {
+            if machine_id == "d2" {
+                anyhow::bail!("500 Internal Server Error");
+            }
+            Ok(())
+        }).await;
+        
+        assert_eq!(summary.stopped, vec![("web".to_string(), "d1".to_string()), ("web".to_string(), "d3".to_string())]);
+        assert_eq!(summary.failed, vec![("web".to_string(), "d2".to_string(), "500 Internal Server Error".to_string())]);
+        assert_eq!(summary.to_string(), "2 machine(s) stopped, 1 failed to stop:\n  • d2 (web): 500 Internal Server Error");
+    }
+
+    #[test]
+    fn test_force_selects_containers_by_managed_label() {
+        let ps = "minifly-web-d1\tminifly.app_name=web,minifly.managed=true,minifly.machine_id=d1\n\
+                  postgres\tcom.docker.compose.project=other\n\
+                  minifly-stray\tminifly.managed=false\n\
+                  minifly-old-d9\tminifly.managed=true\n\
+                  lookalike\tnote=minifly.managed=true-ish\n";
+        assert_eq!(managed_containers(ps), vec!["minifly-web-d1", "minifly-old-d9"]);
+        
+        let listings = RefCell::new(Vec::new());
+        let plan = StopPlan::gather(Vec::new(), true, |program, args| {
+            listings.borrow_mut().push(format!("{} {}", program, args.join(" ")));
+            (program == "docker").then(|| ps.to_string())
+        });
+        assert_eq!(plan.containers, vec!["minifly-web-d1", "minifly-old-d9"]);
+        assert!(plan.to_string().contains("Containers to remove (2):"));
+        // Stopped containers are removed too
+        assert!(listings.into_inner().iter().any(|c| c.starts_with("docker ps -a ")));
+    }
+}
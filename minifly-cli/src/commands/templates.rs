@@ -0,0 +1,225 @@
+/// Custom project templates for `init --template-repo`
+///
+/// A template is a directory, local or in a git repository, whose files are
+/// copied into the new project. `{{app_name}}` and `{{description}}` are
+/// replaced in every text file and file name, along with the variables the
+/// template declares in a `minifly-template.toml` at its root:
+///
+/// ```toml
+/// name = "Team starter"
+/// description = "Axum service with our CI setup"
+///
+/// [variables.port]
+/// prompt = "Port the app listens on"
+/// default = "8080"
+/// ```
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// The manifest file of a template, which isn't copied
+pub const MANIFEST: &str = "minifly-template.toml";
+
+/// `minifly-template.toml`
+#[derive(Debug, Default, Deserialize)]
+pub struct Manifest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Variables substituted besides `app_name` and `description`
+    #[serde(default)]
+    pub variables: BTreeMap<String, Variable>,
+}
+
+/// A variable a template declares
+#[derive(Debug, Default, Deserialize)]
+pub struct Variable {
+    /// Shown when prompting for the value
+    pub prompt: Option<String>,
+    /// Used when no value is given; variables without one must be given
+    pub default: Option<String>,
+}
+
+/// A template directory, deleted afterwards if it was cloned
+pub struct TemplateSource {
+    root: PathBuf,
+    pub manifest: Manifest,
+    _clone: Option<tempfile::TempDir>,
+}
+
+impl TemplateSource {
+    /// Opens a local template directory, or clones a git repository given by
+    /// an `https://`, `ssh://` or `git@` URL or one ending in `.git`
+    pub fn open(source: &str) -> Result<Self> {
+        let (root, clone) = if is_git_url(source) {
+            let clone = tempfile::tempdir().context("Failed to create a directory to clone the template into")?;
+            let output = Command::new("git")
+                .args(["clone", "--depth", "1", "--quiet", "--", source])
+                .arg(clone.path())
+                .output()
+                .context("Failed to run git; is it installed?")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "Failed to clone template repository {}: {}",
+                    source,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            (clone.path().to_path_buf(), Some(clone))
+        } else {
+            let root = PathBuf::from(source);
+            if !root.is_dir() {
+                anyhow::bail!("Template directory {} doesn't exist", root.display());
+            }
+            (root, None)
+        };
+
+        let manifest_path = root.join(MANIFEST);
+        let manifest = if manifest_path.exists() {
+            let content = fs::read_to_string(&manifest_path)
+                .with_context(|| format!("Failed to read {}", MANIFEST))?;
+            toml::from_str(&content).with_context(|| format!("Invalid {}", MANIFEST))?
+        } else {
+            Manifest::default()
+        };
+        Ok(Self { root, manifest, _clone: clone })
+    }
+
+    /// The template's name, for messages
+    pub fn name(&self) -> String {
+        self.manifest.name.clone()
+            .or_else(|| self.root.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "custom".to_string())
+    }
+
+    /// Copies the template into `dir`, substituting `variables`, and returns
+    /// the files written. Nothing is written if any of them already exists.
+    pub fn scaffold(&self, dir: &Path, variables: &HashMap<String, String>) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let walker = ignore::WalkBuilder::new(&self.root)
+            .standard_filters(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walker {
+            let entry = entry.context("Failed to read template directory")?;
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&self.root)?;
+            if relative == Path::new(MANIFEST) {
+                continue;
+            }
+            // Variables come from the command line and the template, so they
+            // must not lead out of the project
+            let target = PathBuf::from(substitute(&relative.to_string_lossy(), variables));
+            let is_inside = target.components().next().is_some()
+                && target.components().all(|component| matches!(component, Component::Normal(_)));
+            if !is_inside {
+                anyhow::bail!("Template file {} would be written outside the project as {}", relative.display(), target.display());
+            }
+            files.push((entry.path().to_path_buf(), dir.join(target)));
+        }
+        files.sort();
+
+        let existing: Vec<String> = files.iter()
+            .filter(|(_, target)| target.exists())
+            .map(|(_, target)| target.strip_prefix(dir).unwrap_or(target).display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            anyhow::bail!("The template would overwrite existing files: {}", existing.join(", "));
+        }
+
+        for (source, target) in &files {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            let content = fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+            // Binary files are copied as they are
+            let content = match String::from_utf8(content) {
+                Ok(text) => substitute(&text, variables).into_bytes(),
+                Err(e) => e.into_bytes(),
+            };
+            fs::write(target, content).with_context(|| format!("Failed to write {}", target.display()))?;
+            // Keep scripts executable
+            let permissions = fs::metadata(source)?.permissions();
+            fs::set_permissions(target, permissions)?;
+        }
+        Ok(files.into_iter().map(|(_, target)| target).collect())
+    }
+}
+
+fn is_git_url(source: &str) -> bool {
+    ["https://", "http://", "ssh://", "git@", "git://"].iter().any(|prefix| source.starts_with(prefix))
+        || source.ends_with(".git")
+}
+
+/// Replaces `{{name}}` (or `{{ name }}`) placeholders, leaving unknown ones as they are
+fn substitute(text: &str, variables: &HashMap<String, String>) -> String {
+    lazy_static::lazy_static! {
+        static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap();
+    }
+    PLACEHOLDER
+        .replace_all(text, |captures: &Captures| {
+            variables.get(&captures[1]).cloned().unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaffold_from_local_template() {
+        let template = tempfile::tempdir().unwrap();
+        fs::create_dir_all(template.path().join("src")).unwrap();
+        fs::write(template.path().join(MANIFEST), "name = \"starter\"\n\n[variables.port]\ndefault = \"8080\"\n").unwrap();
+        fs::write(template.path().join("fly.toml"), "app = \"{{app_name}}\"\n# {{ description }}\n[http_service]\n  internal_port = {{port}}\n").unwrap();
+        fs::write(template.path().join("src/{{app_name}}.txt"), "{{unknown}}").unwrap();
+        fs::write(template.path().join("logo.bin"), [0xff, 0xfe, b'{', b'{']).unwrap();
+
+        let source = TemplateSource::open(&template.path().to_string_lossy()).unwrap();
+        assert_eq!(source.name(), "starter");
+        assert_eq!(source.manifest.variables["port"].default.as_deref(), Some("8080"));
+
+        let project = tempfile::tempdir().unwrap();
+        let variables = HashMap::from([
+            ("app_name".to_string(), "shop".to_string()),
+            ("description".to_string(), "A shop".to_string()),
+            ("port".to_string(), "3000".to_string()),
+        ]);
+        let written = source.scaffold(project.path(), &variables).unwrap();
+        assert_eq!(written.len(), 3, "the manifest isn't copied");
+
+        let fly_toml = fs::read_to_string(project.path().join("fly.toml")).unwrap();
+        assert_eq!(fly_toml, "app = \"shop\"\n# A shop\n[http_service]\n  internal_port = 3000\n");
+        assert_eq!(fs::read_to_string(project.path().join("src/shop.txt")).unwrap(), "{{unknown}}");
+        assert_eq!(fs::read(project.path().join("logo.bin")).unwrap(), [0xff, 0xfe, b'{', b'{']);
+
+        let err = source.scaffold(project.path(), &variables).unwrap_err();
+        assert!(err.to_string().contains("would overwrite existing files: fly.toml"), "{}", err);
+        assert!(TemplateSource::open("/no/such/template").is_err());
+    }
+
+    #[test]
+    fn test_scaffold_stays_inside_project() {
+        let template = tempfile::tempdir().unwrap();
+        fs::write(template.path().join("README.md"), "# {{app_name}}\n").unwrap();
+        fs::write(template.path().join("{{app_name}}.txt"), "").unwrap();
+        let source = TemplateSource::open(&template.path().to_string_lossy()).unwrap();
+
+        let parent = tempfile::tempdir().unwrap();
+        let project = parent.path().join("project");
+        fs::create_dir(&project).unwrap();
+        for app_name in ["../escaped", "/tmp/escaped", "src/../../escaped"] {
+            let variables = HashMap::from([("app_name".to_string(), app_name.to_string())]);
+            let err = source.scaffold(&project, &variables).unwrap_err();
+            assert!(err.to_string().contains("outside the project"), "{}", err);
+        }
+        assert!(!parent.path().join("escaped.txt").exists());
+        assert_eq!(fs::read_dir(&project).unwrap().count(), 0, "nothing is written");
+    }
+}
@@ -0,0 +1,392 @@
+//! `minifly watch`: a live terminal dashboard for a local deployment.
+//!
+//! Machines and their states are polled from the API every second, CPU and
+//! memory come from `docker stats`, and the selected machine's logs can be
+//! tailed in the bottom panel. If the API goes away the last known machines
+//! stay on screen under a reconnecting banner.
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use futures::StreamExt;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use crate::client::ApiClient;
+
+/// How often machines and stats are refreshed
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Log lines kept for the tailed machine
+const MAX_LOG_LINES: usize = 200;
+
+/// A machine as shown on the dashboard
+#[derive(Debug, Clone)]
+struct WatchedMachine {
+    app: String,
+    id: String,
+    name: String,
+    state: String,
+    region: String,
+}
+
+/// The latest view of the platform from the API
+#[derive(Debug, Clone, Default)]
+struct Snapshot {
+    connected: bool,
+    machines: Vec<WatchedMachine>,
+}
+
+/// CPU and memory usage keyed by container name
+type Stats = HashMap<String, (String, String)>;
+
+/// Everything needed to draw one frame
+#[derive(Debug, Default)]
+struct Dashboard {
+    snapshot: Snapshot,
+    stats: Stats,
+    selected: usize,
+    tailing: Option<String>,
+    logs: Vec<String>,
+}
+
+/// Handle `minifly watch`
+///
+/// Keys: `↑`/`↓` (or `k`/`j`) select a machine, `Enter` tails its logs,
+/// `q` or `Esc` quits.
+pub async fn handle(client: &ApiClient, app: Option<String>) -> Result<()> {
+    let (snapshot_tx, snapshot_rx) = watch::channel(Snapshot::default());
+    let stats = Arc::new(Mutex::new(Stats::new()));
+
+    let poller = tokio::spawn(poll_machines(client.clone(), app, snapshot_tx));
+    let stats_poller = tokio::spawn(poll_stats(stats.clone()));
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, client, snapshot_rx, stats).await;
+    ratatui::restore();
+
+    poller.abort();
+    stats_poller.abort();
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: &ApiClient,
+    snapshot_rx: watch::Receiver<Snapshot>,
+    stats: Arc<Mutex<Stats>>,
+) -> Result<()> {
+    let logs = Arc::new(Mutex::new(VecDeque::new()));
+    let mut tail: Option<JoinHandle<()>> = None;
+    let mut dashboard = Dashboard::default();
+
+    loop {
+        dashboard.snapshot = snapshot_rx.borrow().clone();
+        dashboard.stats = stats.lock().unwrap().clone();
+        dashboard.logs = logs.lock().unwrap().iter().cloned().collect();
+        dashboard.selected = dashboard.selected.min(dashboard.snapshot.machines.len().saturating_sub(1));
+
+        terminal.draw(|frame| draw(frame, &dashboard))?;
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Up | KeyCode::Char('k') => {
+                dashboard.selected = dashboard.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                dashboard.selected += 1;
+            }
+            KeyCode::Enter => {
+                if let Some(machine) = dashboard.snapshot.machines.get(dashboard.selected) {
+                    if let Some(task) = tail.take() {
+                        task.abort();
+                    }
+                    logs.lock().unwrap().clear();
+                    dashboard.tailing = Some(machine.id.clone());
+                    tail = Some(tokio::spawn(tail_logs(
+                        client.clone(),
+                        machine.app.clone(),
+                        machine.id.clone(),
+                        logs.clone(),
+                    )));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(task) = tail {
+        task.abort();
+    }
+    Ok(())
+}
+
+/// Refreshes the machine list every second, keeping the last known machines
+/// while the API is unreachable
+async fn poll_machines(client: ApiClient, app: Option<String>, tx: watch::Sender<Snapshot>) {
+    loop {
+        let snapshot = match fetch_machines(&client, app.as_deref()).await {
+            Ok(machines) => Snapshot { connected: true, machines },
+            Err(_) => Snapshot { connected: false, machines: tx.borrow().machines.clone() },
+        };
+
+        if tx.send(snapshot).is_err() {
+            return;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn fetch_machines(client: &ApiClient, app: Option<&str>) -> Result<Vec<WatchedMachine>> {
+    let apps = match app {
+        Some(app) => vec![app.to_string()],
+        None => client.list_apps().await?.into_iter().map(|a| a.name).collect(),
+    };
+
+    let mut machines = Vec::new();
+    for app in apps {
+        for machine in client.list_machines(&app).await? {
+            machines.push(WatchedMachine {
+                app: app.clone(),
+                id: machine.id,
+                name: machine.name,
+                state: machine.state.as_str().to_string(),
+                region: machine.region,
+            });
+        }
+    }
+    machines.sort_by(|a, b| a.app.cmp(&b.app).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(machines)
+}
+
+/// Refreshes container CPU and memory usage from `docker stats`
+async fn poll_stats(stats: Arc<Mutex<Stats>>) {
+    loop {
+        let output = tokio::process::Command::new("docker")
+            .args(["stats", "--no-stream", "--format", "{{.Name}}\t{{.CPUPerc}}\t{{.MemUsage}}"])
+            .output()
+            .await;
+
+        if let Ok(output) = output {
+            if output.status.success() {
+                *stats.lock().unwrap() = parse_docker_stats(&String::from_utf8_lossy(&output.stdout));
+            }
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Parses tab-separated `name, cpu, memory` lines from `docker stats`
+fn parse_docker_stats(output: &str) -> Stats {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.trim();
+            let cpu = fields.next()?.trim();
+            let memory = fields.next()?.trim();
+            Some((name.to_string(), (cpu.to_string(), memory.to_string())))
+        })
+        .collect()
+}
+
+/// Follows a machine's log stream into the shared log buffer
+async fn tail_logs(client: ApiClient, app: String, machine_id: String, logs: Arc<Mutex<VecDeque<String>>>) {
+    let url = format!(
+        "/apps/{}/machines/{}/logs?follow=true&tail=50&timestamps=true&include_levels=true",
+        app, machine_id
+    );
+    let push = |line: String| {
+        let mut logs = logs.lock().unwrap();
+        logs.push_back(line);
+        while logs.len() > MAX_LOG_LINES {
+            logs.pop_front();
+        }
+    };
+
+    let response = match client.get(&url).await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => return push(format!("Failed to stream logs: HTTP {}", response.status())),
+        Err(e) => return push(format!("Failed to stream logs: {}", e)),
+    };
+
+    let mut stream = response.bytes_stream();
+    let mut buffer = String::new();
+    while let Some(Ok(chunk)) = stream.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+        while let Some(line_end) = buffer.find('\n') {
+            let line = buffer[..line_end].trim().to_string();
+            buffer.drain(..=line_end);
+
+            if let Some(entry) = line.strip_prefix("data: ").and_then(format_log_event) {
+                push(entry);
+            }
+        }
+    }
+}
+
+/// Formats an SSE log event as `HH:MM:SS LEVEL message`
+fn format_log_event(data: &str) -> Option<String> {
+    let entry: serde_json::Value = serde_json::from_str(data).ok()?;
+    let message = entry["message"].as_str()?;
+    let level = entry["level"].as_str().unwrap_or("info").to_uppercase();
+    let time = entry["timestamp"].as_str()
+        .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+
+    Some(format!("{} {:5} {}", time, level, message))
+}
+
+fn state_color(state: &str) -> Color {
+    match state {
+        "started" => Color::Green,
+        "starting" | "stopping" | "suspending" | "destroying" => Color::Yellow,
+        "destroyed" => Color::Red,
+        _ => Color::DarkGray,
+    }
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard) {
+    let [header, machines, logs, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(5),
+        Constraint::Percentage(40),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let snapshot = &dashboard.snapshot;
+    let banner = if snapshot.connected {
+        let running = snapshot.machines.iter().filter(|m| m.state == "started").count();
+        Line::from(format!(
+            " Minifly watch: {} machines, {} running",
+            snapshot.machines.len(),
+            running
+        ))
+        .style(Style::new().bold().fg(Color::Cyan))
+    } else {
+        Line::from(" ⚠ API unreachable, reconnecting...").style(Style::new().bold().fg(Color::White).bg(Color::Red))
+    };
+    frame.render_widget(banner, header);
+
+    let rows = snapshot.machines.iter().map(|m| {
+        let (cpu, memory) = dashboard.stats
+            .get(&format!("minifly-{}-{}", m.app, m.id))
+            .cloned()
+            .unwrap_or_else(|| ("-".to_string(), "-".to_string()));
+        Row::new(vec![
+            m.app.clone(),
+            m.id.clone(),
+            m.name.clone(),
+            m.state.clone(),
+            m.region.clone(),
+            cpu,
+            memory,
+        ])
+        .style(Style::new().fg(state_color(&m.state)))
+    });
+    let table = Table::new(rows, [
+        Constraint::Length(16),
+        Constraint::Length(16),
+        Constraint::Min(16),
+        Constraint::Length(10),
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Length(22),
+    ])
+    .header(Row::new(vec!["APP", "ID", "NAME", "STATE", "REGION", "CPU", "MEMORY"]).bold())
+    .block(Block::bordered().title(" Machines "))
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default().with_selected(Some(dashboard.selected));
+    frame.render_stateful_widget(table, machines, &mut state);
+
+    let title = match &dashboard.tailing {
+        Some(id) => format!(" Logs: {} ", id),
+        None => " Logs (press Enter to tail the selected machine) ".to_string(),
+    };
+    let visible = logs.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = dashboard.logs.iter()
+        .skip(dashboard.logs.len().saturating_sub(visible))
+        .map(|l| Line::from(l.as_str()))
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), logs);
+
+    frame.render_widget(
+        Line::from(" q quit  ↑/↓ select  Enter tail logs").style(Style::new().fg(Color::DarkGray)),
+        footer,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn render(dashboard: &Dashboard) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, dashboard)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_dashboard_shows_machines_and_reconnecting_banner() {
+        let mut dashboard = Dashboard {
+            snapshot: Snapshot {
+                connected: true,
+                machines: vec![WatchedMachine {
+                    app: "web".to_string(),
+                    id: "d891234567890".to_string(),
+                    name: "web-d891234567890".to_string(),
+                    state: "started".to_string(),
+                    region: "sjc".to_string(),
+                }],
+            },
+            stats: parse_docker_stats("minifly-web-d891234567890\t1.25%\t32MiB / 256MiB\nother\t0.00%\t1MiB / 1GiB\n"),
+            ..Default::default()
+        };
+
+        let screen = render(&dashboard);
+        assert!(screen.contains("1 machines, 1 running"));
+        assert!(screen.contains("d891234567890"));
+        assert!(screen.contains("1.25%"));
+
+        // Losing the API keeps the last machines under a banner
+        dashboard.snapshot.connected = false;
+        let screen = render(&dashboard);
+        assert!(screen.contains("API unreachable, reconnecting"));
+        assert!(screen.contains("d891234567890"));
+    }
+
+    #[test]
+    fn test_format_log_event() {
+        let line = format_log_event(r#"{"timestamp":"2024-06-22T10:30:00Z","level":"warn","message":"slow query"}"#);
+        assert_eq!(line.as_deref(), Some("10:30:00 WARN  slow query"));
+        assert!(format_log_event("heartbeat").is_none());
+    }
+}
@@ -1,13 +1,30 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Environment variable selecting a profile when `--profile` is not given
+pub const PROFILE_ENV: &str = "MINIFLY_PROFILE";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub api_url: String,
     pub token: Option<String>,
+    /// Named platforms, each with its own `api_url` and `token`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Profile applied by [`Config::with_profile`], if any
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// Settings of one named profile, e.g. `[profiles.staging]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub api_url: String,
+    pub token: Option<String>,
 }
 
 impl Default for Config {
@@ -15,10 +32,18 @@ impl Default for Config {
         Self {
             api_url: "http://localhost:4280".to_string(),
             token: None,
+            profiles: BTreeMap::new(),
+            profile: None,
         }
     }
 }
 
+/// Picks the profile to use: `--profile`, then `MINIFLY_PROFILE`, otherwise
+/// none, meaning the top-level settings
+pub fn select_profile(flag: Option<String>, env: Option<String>) -> Option<String> {
+    flag.or(env).filter(|profile| !profile.is_empty())
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
@@ -33,14 +58,43 @@ impl Config {
         }
     }
     
+    /// Uses a named profile's `api_url` and `token` instead of the top-level ones
+    pub fn with_profile(mut self, name: &str) -> Result<Self> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            if available.is_empty() {
+                bail!("Unknown profile '{}'; add a [profiles.{}] section to the config file", name, name);
+            }
+            bail!("Unknown profile '{}'; available profiles: {}", name, available.join(", "));
+        };
+        
+        self.api_url = profile.api_url;
+        self.token = profile.token;
+        self.profile = Some(name.to_string());
+        Ok(self)
+    }
+    
+    /// Writes the configuration, updating only the active profile if one is selected
     pub fn save(&self) -> Result<()> {
+        let config = match &self.profile {
+            Some(name) => {
+                let mut config = Self::load()?;
+                config.profiles.insert(name.clone(), Profile {
+                    api_url: self.api_url.clone(),
+                    token: self.token.clone(),
+                });
+                config
+            }
+            None => self.clone(),
+        };
+        
         let config_path = Self::config_path()?;
         let config_dir = config_path.parent().unwrap();
         
         fs::create_dir_all(config_dir)
             .context("Failed to create config directory")?;
         
-        let content = toml::to_string_pretty(self)
+        let content = toml::to_string_pretty(&config)
             .context("Failed to serialize config")?;
         
         fs::write(&config_path, content)
@@ -49,6 +103,37 @@ impl Config {
         Ok(())
     }
     
+    /// Checks the configuration before any command uses it, so mistakes are
+    /// reported up front instead of as confusing request failures
+    pub fn validate(&self) -> Result<()> {
+        self.validate_settings()?;
+        if let Ok(data_dir) = std::env::var("MINIFLY_DATA_DIR") {
+            check_writable(Path::new(&data_dir), "MINIFLY_DATA_DIR")?;
+        }
+        Ok(())
+    }
+    
+    fn validate_settings(&self) -> Result<()> {
+        let hint = "set it with --api-url or api_url in the config file";
+        let url = reqwest::Url::parse(&self.api_url)
+            .map_err(|e| anyhow::anyhow!("api_url is not a valid URL: '{}' ({}); {}", self.api_url, e, hint))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("api_url must use http or https, not '{}'; {}", url.scheme(), hint);
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            bail!("api_url has no host: '{}'; {}", self.api_url, hint);
+        }
+        if url.port() == Some(0) {
+            bail!("api_url has port 0, which cannot be connected to; use the API server's port (default 4280)");
+        }
+        
+        if self.token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+            bail!("token is set but empty; remove it from the config file or pass a value with --token");
+        }
+        
+        Ok(())
+    }
+    
     fn config_path() -> Result<PathBuf> {
         let mut path = config_dir()
             .context("Failed to determine config directory")?;
@@ -56,4 +141,102 @@ impl Config {
         path.push("config.toml");
         Ok(path)
     }
-}
\ No newline at end of file
+}
+
+/// Checks that `dir` can be written to, or created if it does not exist yet
+fn check_writable(dir: &Path, name: &str) -> Result<()> {
+    if dir.exists() {
+        if !dir.is_dir() {
+            bail!("{} is not a directory: {}", name, dir.display());
+        }
+        let probe = dir.join(".minifly-write-check");
+        fs::write(&probe, b"")
+            .with_context(|| format!("{} is not writable: {}", name, dir.display()))?;
+        let _ = fs::remove_file(&probe);
+        return Ok(());
+    }
+    
+    let parent = dir.ancestors().skip(1).find(|ancestor| ancestor.exists());
+    match parent {
+        Some(parent) if parent.metadata().is_ok_and(|m| !m.permissions().readonly()) => Ok(()),
+        _ => bail!("{} does not exist and cannot be created: {}", name, dir.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn config(api_url: &str, token: Option<&str>) -> Config {
+        Config {
+            api_url: api_url.to_string(),
+            token: token.map(str::to_string),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_default_config_is_valid() {
+        Config::default().validate_settings().unwrap();
+        config("https://minifly.example.com", Some("secret")).validate_settings().unwrap();
+    }
+    
+    #[test]
+    fn test_invalid_configs_are_explained() {
+        let cases = [
+            (config("localhost:4280", None), "api_url must use http or https"),
+            (config("not a url", None), "api_url is not a valid URL"),
+            (config("ftp://localhost", None), "api_url must use http or https"),
+            (config("http://localhost:0", None), "port 0"),
+            (config("http://localhost:4280", Some("  ")), "token is set but empty"),
+        ];
+        for (config, expected) in cases {
+            let err = config.validate_settings().unwrap_err().to_string();
+            assert!(err.contains(expected), "{}: {}", config.api_url, err);
+        }
+    }
+    
+    #[test]
+    fn test_profile_resolution_precedence() {
+        let config: Config = toml::from_str(r#"
+            api_url = "http://localhost:4280"
+
+            [profiles.staging]
+            api_url = "http://localhost:5280"
+            token = "staging-token"
+
+            [profiles.test]
+            api_url = "http://localhost:6280"
+        "#).unwrap();
+        let resolve = |flag: Option<&str>, env: Option<&str>| {
+            match select_profile(flag.map(String::from), env.map(String::from)) {
+                Some(profile) => config.clone().with_profile(&profile).unwrap(),
+                None => config.clone(),
+            }
+        };
+        
+        // The flag wins over the environment, which wins over the defaults
+        let flagged = resolve(Some("staging"), Some("test"));
+        assert_eq!(flagged.api_url, "http://localhost:5280");
+        assert_eq!(flagged.token.as_deref(), Some("staging-token"));
+        assert_eq!(flagged.profile.as_deref(), Some("staging"));
+        assert_eq!(resolve(None, Some("test")).api_url, "http://localhost:6280");
+        assert_eq!(resolve(None, None).api_url, "http://localhost:4280");
+        assert_eq!(resolve(None, Some("")).api_url, "http://localhost:4280");
+        
+        let err = config.with_profile("prod").unwrap_err().to_string();
+        assert!(err.contains("available profiles: staging, test"), "{}", err);
+    }
+    
+    #[test]
+    fn test_check_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        check_writable(dir.path(), "MINIFLY_DATA_DIR").unwrap();
+        check_writable(&dir.path().join("new/nested"), "MINIFLY_DATA_DIR").unwrap();
+        
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        let err = check_writable(&file, "MINIFLY_DATA_DIR").unwrap_err();
+        assert!(err.to_string().contains("not a directory"));
+    }
+}
@@ -1,12 +1,14 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use minifly_logging::{LoggingConfig, LogFormat};
 
 mod commands;
 mod config;
 mod client;
+#[cfg(test)]
+mod test_support;
 
-use commands::{apps, deploy, dev, init, logs, machines, proxy, secrets, serve, status, stop};
+use commands::{apps, compose, deploy, dev, doctor, gateway, init, litefs, logs, machines, proxy, releases, scale, secrets, serve, status, stop, watch};
 use config::Config;
 
 #[derive(Parser)]
@@ -16,17 +18,41 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
     
-    #[arg(short, long, global = true, help = "API endpoint")]
+    #[arg(long, global = true, help = "API endpoint")]
     api_url: Option<String>,
     
     #[arg(short, long, global = true, help = "Authentication token")]
     token: Option<String>,
+    
+    #[arg(long, global = true, help = "Config profile to use (defaults to MINIFLY_PROFILE)")]
+    profile: Option<String>,
+    
+    #[arg(long, global = true, help = "Enable debug logging, including every API request and response")]
+    debug: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Initialize Minifly environment
-    Init,
+    Init {
+        #[arg(long, group = "template_source", help = "Project template to scaffold: rust-axum, node-express, python-fastapi, go-gin or minimal")]
+        template: Option<String>,
+        
+        #[arg(long, group = "template_source", value_name = "GIT_URL_OR_PATH", help = "Scaffold a custom template from a git repository or local directory")]
+        template_repo: Option<String>,
+        
+        #[arg(long, help = "Application name")]
+        name: Option<String>,
+        
+        #[arg(long, help = "Project description")]
+        description: Option<String>,
+        
+        #[arg(long = "var", value_name = "KEY=VALUE", requires = "template_repo", help = "Value for a custom template variable; repeat for more")]
+        vars: Vec<String>,
+        
+        #[arg(long, requires = "template_source", help = "Scaffold the template without prompting; the configuration is left as is")]
+        non_interactive: bool,
+    },
     
     /// Start the Minifly platform (API server + LiteFS)
     Serve {
@@ -41,6 +67,15 @@ enum Commands {
         
         #[arg(short, long, help = "Path to fly.toml configuration file for auto-deployment", value_name = "FILE")]
         config: Option<String>,
+        
+        #[arg(long, help = "Run a local MinIO and give machines S3/Tigris credentials for it")]
+        minio: bool,
+        
+        #[arg(long, help = "Bucket to create in MinIO", default_value = "minifly", requires = "minio")]
+        minio_bucket: String,
+        
+        #[arg(long, help = "Publish machine ports on host ports that stay the same across redeploys")]
+        stable_ports: bool,
     },
     
     /// Development mode with auto-reload and log streaming
@@ -53,12 +88,21 @@ enum Commands {
         
         #[arg(short, long, help = "Path to fly.toml configuration file", value_name = "FILE")]
         config: Option<String>,
+        
+        #[arg(long, help = "Sync changed files into running machines and restart them instead of redeploying, when no rebuild is needed")]
+        fast_reload: bool,
     },
     
     /// Stop the Minifly platform
     Stop {
-        #[arg(short, long, help = "Force stop all services")]
+        #[arg(short, long, help = "Remove all minifly containers and processes directly, without the API server")]
         force: bool,
+        
+        #[arg(long, help = "List what would be stopped without stopping anything")]
+        dry_run: bool,
+        
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
     },
     
     /// Manage applications
@@ -77,45 +121,161 @@ enum Commands {
         #[arg(short = 'l', long, help = "Path to litefs.yml configuration file", value_name = "FILE")]
         litefs_config: Option<String>,
         
+        #[arg(long, help = "Deploy strategy: rolling or immediate (overrides [deploy] strategy)")]
+        strategy: Option<String>,
+        
         #[arg(short, long, help = "Watch for changes and auto-redeploy")]
         watch: bool,
+        
+        #[arg(long, help = "Validate fly.toml and exit without deploying")]
+        check: bool,
+        
+        #[arg(long, help = "Create a machine per service of a Docker Compose file", value_name = "FILE", conflicts_with_all = ["config", "check", "watch"])]
+        compose: Option<String>,
+        
+        #[arg(long, help = "Print the deploy result as JSON (also MINIFLY_OUTPUT=json)", conflicts_with_all = ["watch", "check", "compose"])]
+        json: bool,
+    },
+    
+    /// View an app's release history
+    #[command(subcommand)]
+    Releases(ReleasesCommands),
+    
+    /// Roll an app back to a previous release
+    Rollback {
+        #[arg(help = "Release version to roll back to")]
+        version: u32,
+        
+        #[arg(short, long, help = "Application name (uses fly.toml if not provided)")]
+        app: Option<String>,
+    },
+    
+    /// Create or destroy machines until an app has the given count
+    Scale {
+        #[arg(help = "Application name")]
+        app: String,
+        
+        #[arg(help = "Number of machines")]
+        count: usize,
+        
+        #[arg(short, long, help = "Only count and create machines in this region")]
+        region: Option<String>,
+        
+        #[arg(short, long, help = "Image for new machines when the app has none to clone")]
+        image: Option<String>,
     },
     
     /// View logs from machines
     Logs {
-        #[arg(help = "Machine ID")]
-        machine_id: String,
+        #[arg(help = "Machine ID (omit to stream every machine of --app)")]
+        machine_id: Option<String>,
+        
+        #[arg(short, long, help = "Stream logs from all machines of an app (uses fly.toml if no machine ID is given)")]
+        app: Option<String>,
         
         #[arg(short, long, help = "Follow log output")]
         follow: bool,
         
+        #[arg(long, conflicts_with = "follow", help = "Print the recent lines and exit (the default)")]
+        no_follow: bool,
+        
+        #[arg(long, value_name = "N", help = "Number of recent lines to show [default: 100 unless following or given --since]")]
+        tail: Option<usize>,
+        
         #[arg(short, long, help = "Show logs from specific region")]
         region: Option<String>,
+        
+        #[arg(long, help = "Only show lines at this level or above (debug, info, warn, error)")]
+        level: Option<String>,
+        
+        #[arg(long, value_name = "PATTERN", help = "Only show lines matching this regex")]
+        grep: Option<String>,
+        
+        #[arg(long, value_name = "TIME", help = "Only show logs after this time (e.g. 10m, 2h, 2024-06-22T10:00:00Z)")]
+        since: Option<String>,
+        
+        #[arg(long, value_name = "TIME", help = "Only show logs before this time")]
+        until: Option<String>,
+        
+        #[arg(long, help = "Reformat JSON log lines into timestamp, level, message and fields")]
+        pretty: bool,
     },
     
     /// Proxy to a running service
     Proxy {
-        #[arg(help = "Machine ID")]
-        machine_id: String,
+        #[arg(help = "Machine ID", required_unless_present = "app")]
+        machine_id: Option<String>,
+        
+        #[arg(short, long, conflicts_with = "machine_id", help = "Proxy to all machines of an app, round-robin")]
+        app: Option<String>,
+        
+        #[arg(short, long = "port", value_name = "LOCAL:REMOTE[/udp]", help = "Port mapping; repeat for more ports (default: 8080:8080)")]
+        ports: Vec<proxy::PortMapping>,
         
-        #[arg(short, long, default_value = "8080")]
+        #[arg(long, default_value = "127.0.0.1", help = "Local address to listen on")]
+        bind: String,
+        
+        #[arg(long, help = "Proxy TCP ports as HTTP, routing each request and supporting WebSockets and HTTP/2")]
+        http: bool,
+        
+        #[arg(long, help = "Open the proxied app in the default browser")]
+        open: bool,
+    },
+    
+    /// Route HTTP requests to apps by Host header, like Fly's edge
+    Gateway {
+        #[arg(short, long, default_value = "80", help = "Port to listen on")]
         port: u16,
+        
+        #[arg(long, help = "Also listen for HTTPS on this port, terminating TLS with local certificates")]
+        tls_port: Option<u16>,
+        
+        #[arg(long, default_value = "127.0.0.1", help = "Local address to listen on")]
+        bind: String,
     },
     
     /// Show Minifly status
-    Status,
+    Status {
+        #[arg(long, help = "Print the status as a single JSON document")]
+        json: bool,
+    },
+    
+    /// Diagnose setup problems; exits non-zero if any are found
+    Doctor {
+        #[arg(long, help = "Remove containers that no machine accounts for")]
+        clean_orphans: bool,
+    },
+    
+    /// Live dashboard of machines, resource usage and logs
+    Watch {
+        #[arg(short, long, help = "Only show machines of this app")]
+        app: Option<String>,
+    },
     
     /// Manage application secrets
     Secrets {
         #[command(subcommand)]
         action: SecretsCommands,
     },
+    
+    /// Inspect LiteFS databases
+    #[command(subcommand)]
+    Litefs(LitefsCommands),
+    
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum, help = "Shell to complete for")]
+        shell: clap_complete::Shell,
+    },
 }
 
 #[derive(Subcommand)]
 enum AppsCommands {
     /// List all applications
-    List,
+    List {
+        #[arg(long, help = "Only list apps in this status", value_parser = ["pending", "running", "partial", "stopped"])]
+        status: Option<String>,
+    },
     
     /// Create a new application
     Create {
@@ -123,10 +283,19 @@ enum AppsCommands {
         name: String,
     },
     
-    /// Delete an application
+    /// Delete an application with its machines and volumes
     Delete {
         #[arg(help = "Application name")]
         name: String,
+        
+        #[arg(long, help = "Keep the app's volumes and their data")]
+        keep_volumes: bool,
+    },
+    
+    /// Open an app's web port in the browser
+    Open {
+        #[arg(help = "Application name")]
+        name: String,
     },
 }
 
@@ -151,6 +320,39 @@ enum MachinesCommands {
         
         #[arg(short, long, help = "Region")]
         region: Option<String>,
+        
+        #[arg(short, long = "env", value_name = "KEY=VALUE", help = "Environment variable (repeatable)")]
+        env: Vec<String>,
+        
+        #[arg(short, long = "volume", value_name = "NAME:PATH", help = "Mount a volume at a path (repeatable)")]
+        volume: Vec<String>,
+        
+        #[arg(short, long = "file", value_name = "GUEST_PATH=LOCAL_FILE", help = "Write a local file into the machine (repeatable)")]
+        file: Vec<String>,
+    },
+    
+    /// Run a command in a new machine that is destroyed when it exits
+    Run {
+        #[arg(short, long, help = "Application name")]
+        app: String,
+        
+        #[arg(help = "Docker image")]
+        image: String,
+        
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, help = "Command to run instead of the image's default")]
+        command: Vec<String>,
+        
+        #[arg(short, long, help = "Machine name")]
+        name: Option<String>,
+        
+        #[arg(short, long, help = "Region")]
+        region: Option<String>,
+        
+        #[arg(short, long = "env", value_name = "KEY=VALUE", help = "Environment variable (repeatable)")]
+        env: Vec<String>,
+        
+        #[arg(short, long = "volume", value_name = "NAME:PATH", help = "Mount a volume at a path (repeatable)")]
+        volume: Vec<String>,
     },
     
     /// Start a machine
@@ -159,12 +361,51 @@ enum MachinesCommands {
         machine_id: String,
     },
     
-    /// Stop a machine
+    /// Stop a machine, or every started machine of an app with --all
     Stop {
+        #[arg(help = "Machine ID", required_unless_present = "all")]
+        machine_id: Option<String>,
+        
+        #[arg(long, requires = "app", conflicts_with = "machine_id", help = "Stop every started machine of the app")]
+        all: bool,
+        
+        #[arg(long, help = "Application name (with --all)")]
+        app: Option<String>,
+    },
+    
+    /// Stop routing traffic to a machine while it keeps running
+    Cordon {
+        #[arg(help = "Machine ID")]
+        machine_id: String,
+    },
+    
+    /// Resume routing traffic to a cordoned machine
+    Uncordon {
         #[arg(help = "Machine ID")]
         machine_id: String,
     },
     
+    /// Show the environment variables of a machine's container
+    Env {
+        #[arg(help = "Machine ID")]
+        machine_id: String,
+        
+        #[arg(short, long, help = "Application name")]
+        app: Option<String>,
+        
+        #[arg(long, help = "Print secret values instead of redacting them")]
+        reveal: bool,
+    },
+    
+    /// Open an interactive shell in a running machine
+    Ssh {
+        #[arg(help = "Machine ID")]
+        machine_id: String,
+        
+        #[arg(long, default_value = "/bin/sh", help = "Shell to run")]
+        shell: String,
+    },
+    
     /// Delete a machine
     Delete {
         #[arg(help = "Machine ID")]
@@ -175,6 +416,15 @@ enum MachinesCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum ReleasesCommands {
+    /// List releases for an app
+    List {
+        #[arg(short, long, help = "Application name (uses fly.toml if not provided)")]
+        app: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 enum SecretsCommands {
     /// Set one or more secrets
@@ -194,12 +444,57 @@ enum SecretsCommands {
         #[arg(help = "Secret keys to remove", required = true)]
         keys: Vec<String>,
     },
+    
+    /// Import secrets from a dotenv file
+    Import {
+        #[arg(help = "Path to a .env file")]
+        file: String,
+    },
+    
+    /// Print secrets in dotenv format
+    Export {
+        #[arg(help = "App name (optional, uses fly.toml if not provided)")]
+        app: Option<String>,
+        
+        #[arg(long, help = "Print secret values instead of redacting them")]
+        reveal: bool,
+    },
+    
+    /// Compare the secrets files with the secrets running machines were started with
+    Diff {
+        #[arg(help = "App name (optional, uses fly.toml if not provided)")]
+        app: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LitefsCommands {
+    /// Open a machine's SQLite database with sqlite3
+    Shell {
+        #[arg(help = "Machine ID")]
+        machine_id: String,
+        
+        #[arg(short, long, help = "Application name (searches every app if not provided)")]
+        app: Option<String>,
+        
+        #[arg(long, help = "Database file within the volume", default_value = "app.db")]
+        db: String,
+        
+        #[arg(long, help = "Run a single SQL statement instead of an interactive shell")]
+        sql: Option<String>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     
+    // Completions need neither logging nor a valid configuration
+    if let Commands::Completions { shell } = cli.command {
+        write_completions(shell, &mut std::io::stdout());
+        return Ok(());
+    }
+    
     // Initialize structured logging for CLI
     let log_format = if std::env::var("MINIFLY_LOG_JSON").is_ok() {
         LogFormat::Json
@@ -209,14 +504,18 @@ async fn main() -> Result<()> {
     
     let logging_config = LoggingConfig::from_env("minifly-cli")
         .with_format(log_format)
-        .with_level("minifly_cli=info,minifly_logging=warn");
+        .with_level("minifly_cli=debug,minifly_logging=warn");
     
-    if std::env::var("MINIFLY_DEBUG").is_ok() {
+    let debug = cli.debug || std::env::var("MINIFLY_DEBUG").is_ok();
+    if debug {
         minifly_logging::init_logging(logging_config)?;
     }
     
     // Load configuration
     let mut config = Config::load()?;
+    if let Some(profile) = config::select_profile(cli.profile, std::env::var(config::PROFILE_ENV).ok()) {
+        config = config.with_profile(&profile)?;
+    }
     
     // Override with CLI arguments
     if let Some(api_url) = cli.api_url {
@@ -225,62 +524,135 @@ async fn main() -> Result<()> {
     if let Some(token) = cli.token {
         config.token = Some(token);
     }
+    config.validate()?;
     
     // Create API client
-    let client = client::ApiClient::new(&config)?;
+    let client = client::ApiClient::new(&config)?.with_http_trace(debug);
     
     match cli.command {
-        Commands::Init => {
-            init::handle(&config).await?;
+        Commands::Init { template, template_repo, name, description, vars, non_interactive } => {
+            let options = init::InitOptions { template, template_repo, name, description, vars, non_interactive };
+            init::handle(&config, options).await?;
         }
-        Commands::Serve { daemon, port, dev, config } => {
-            serve::handle(daemon, port, dev, config).await?;
+        Commands::Serve { daemon, port, dev, config, minio, minio_bucket, stable_ports } => {
+            let minio = minio.then(|| commands::minio::MinioConfig::new(minio_bucket));
+            serve::handle(daemon, port, dev, config, minio, stable_ports).await?;
         }
-        Commands::Dev { path, port, config } => {
-            dev::handle(&path, port, config).await?;
+        Commands::Dev { path, port, config, fast_reload } => {
+            dev::handle(&path, port, config, fast_reload).await?;
         }
-        Commands::Stop { force } => {
-            stop::handle(force).await?;
+        Commands::Stop { force, dry_run, yes } => {
+            stop::handle(force, dry_run, yes).await?;
         }
         Commands::Apps(cmd) => match cmd {
-            AppsCommands::List => {
-                apps::list(&client).await?;
+            AppsCommands::List { status } => {
+                apps::list(&client, status.as_deref()).await?;
             }
             AppsCommands::Create { name } => {
                 apps::create(&client, &name).await?;
             }
-            AppsCommands::Delete { name } => {
-                apps::delete(&client, &name).await?;
+            AppsCommands::Delete { name, keep_volumes } => {
+                apps::delete(&client, &name, keep_volumes).await?;
+            }
+            AppsCommands::Open { name } => {
+                apps::open(&client, &name).await?;
             }
         },
         Commands::Machines(cmd) => match cmd {
             MachinesCommands::List { app } => {
                 machines::list(&client, &app).await?;
             }
-            MachinesCommands::Create { app, image, name, region } => {
-                machines::create(&client, &app, &image, name, region).await?;
+            MachinesCommands::Create { app, image, name, region, env, volume, file } => {
+                let config = machines::machine_config(&image, &env, &volume, &file)?;
+                machines::create(&client, &app, name, region, config).await?;
+            }
+            MachinesCommands::Run { app, image, command, name, region, env, volume } => {
+                let config = machines::machine_config(&image, &env, &volume, &[])?;
+                let exit_code = machines::run(&client, &app, name, region, config, command).await?;
+                if exit_code != 0 {
+                    std::process::exit(i32::try_from(exit_code).unwrap_or(1));
+                }
             }
             MachinesCommands::Start { machine_id } => {
                 machines::start(&client, &machine_id).await?;
             }
-            MachinesCommands::Stop { machine_id } => {
-                machines::stop(&client, &machine_id).await?;
+            MachinesCommands::Stop { machine_id, all, app } => match (machine_id, app) {
+                (Some(machine_id), _) if !all => machines::stop(&client, &machine_id).await?,
+                (_, Some(app)) => machines::stop_all(&client, &app).await?,
+                _ => unreachable!("clap requires a machine ID or --all with --app"),
+            },
+            MachinesCommands::Cordon { machine_id } => {
+                machines::cordon(&client, &machine_id, true).await?;
+            }
+            MachinesCommands::Uncordon { machine_id } => {
+                machines::cordon(&client, &machine_id, false).await?;
+            }
+            MachinesCommands::Env { machine_id, app, reveal } => {
+                machines::env(&client, &machine_id, app, reveal).await?;
+            }
+            MachinesCommands::Ssh { machine_id, shell } => {
+                let exit_code = machines::ssh(&machine_id, &shell).await?;
+                if exit_code != 0 {
+                    std::process::exit(exit_code);
+                }
             }
             MachinesCommands::Delete { machine_id, force } => {
                 machines::delete(&client, &machine_id, force).await?;
             }
         },
-        Commands::Deploy { config, litefs_config, watch } => {
-            deploy::handle(&client, config, litefs_config, watch).await?;
+        Commands::Deploy { config, litefs_config, strategy, watch, check, compose, json } => {
+            if let Some(compose) = compose {
+                compose::deploy(&client, &compose).await?;
+            } else if check {
+                deploy::check(config).await?;
+            } else {
+                deploy::handle(&client, config, litefs_config, strategy, watch, json).await?;
+            }
+        }
+        Commands::Releases(cmd) => match cmd {
+            ReleasesCommands::List { app } => {
+                releases::list(&client, app).await?;
+            }
+        },
+        Commands::Rollback { version, app } => {
+            releases::rollback(&client, app, version).await?;
+        }
+        Commands::Scale { app, count, region, image } => {
+            scale::handle(&client, &app, count, region, image).await?;
+        }
+        Commands::Logs { machine_id, app, follow, no_follow: _, tail, region, level, grep, since, until, pretty } => {
+            let filter = logs::LogFilter::new(level, grep)?;
+            let options = logs::LogsOptions { follow, tail, region, since, until, pretty };
+            match machine_id {
+                Some(machine_id) => logs::handle(&client, &machine_id, &options, &filter).await?,
+                None => {
+                    let app = match app {
+                        Some(app) => app,
+                        None => secrets::get_app_name_from_fly_toml().await?,
+                    };
+                    logs::handle_app(&client, &app, &options, &filter).await?;
+                }
+            }
+        }
+        Commands::Proxy { machine_id, app, ports, bind, http, open } => {
+            let target = match (machine_id, app) {
+                (_, Some(app)) => proxy::ProxyTarget::App(app),
+                (Some(machine_id), None) => proxy::ProxyTarget::Machine(machine_id),
+                (None, None) => unreachable!("clap requires a machine ID or --app"),
+            };
+            proxy::handle(&client, target, ports, &bind, http, open).await?;
+        }
+        Commands::Gateway { port, tls_port, bind } => {
+            gateway::handle(&client, port, tls_port, &bind).await?;
         }
-        Commands::Logs { machine_id, follow, region } => {
-            logs::handle(&client, &machine_id, follow, region).await?;
+        Commands::Status { json } => {
+            status::handle(&client, json).await?;
         }
-        Commands::Proxy { machine_id, port } => {
-            proxy::handle(&client, &machine_id, port).await?;
+        Commands::Doctor { clean_orphans } => {
+            doctor::handle(&client, &config.api_url, clean_orphans).await?;
         }
-        Commands::Status => {
-            status::handle(&client).await?;
+        Commands::Watch { app } => {
+            watch::handle(&client, app).await?;
         }
         Commands::Secrets { action } => match action {
             SecretsCommands::Set { secrets } => {
@@ -293,8 +665,50 @@ async fn main() -> Result<()> {
             SecretsCommands::Remove { keys } => {
                 secrets::handle("remove", keys).await?;
             }
+            SecretsCommands::Import { file } => {
+                secrets::handle("import", vec![file]).await?;
+            }
+            SecretsCommands::Export { app, reveal } => {
+                secrets::export(app, reveal).await?;
+            }
+            SecretsCommands::Diff { app } => {
+                secrets::diff(&client, app).await?;
+            }
+        },
+        Commands::Litefs(cmd) => match cmd {
+            LitefsCommands::Shell { machine_id, app, db, sql } => {
+                litefs::shell(&client, &machine_id, app, &db, sql).await?;
+            }
         },
+        Commands::Completions { .. } => {
+            // Handled before the configuration is loaded
+        }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Writes the completion script for `shell`, covering every subcommand
+fn write_completions(shell: clap_complete::Shell, out: &mut impl std::io::Write) {
+    clap_complete::generate(shell, &mut Cli::command(), "minifly", out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bash_completions_cover_nested_subcommands() {
+        let mut script = Vec::new();
+        write_completions(clap_complete::Shell::Bash, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        
+        for command in ["apps", "machines", "secrets", "deploy", "serve", "completions"] {
+            assert!(script.contains(command), "{} is not completed", command);
+        }
+        // Nested subcommands and their flags
+        assert!(script.contains("minifly__subcmd__machines__subcmd__ssh"));
+        assert!(script.contains("minifly__subcmd__secrets__subcmd__set"));
+        assert!(script.contains("--template-repo"));
+    }
+}
@@ -0,0 +1,55 @@
+//! Fixtures shared by the command tests
+
+use chrono::{TimeZone, Utc};
+use minifly_core::models::{ImageRef, Machine, MachineState, PortConfig, ServiceConfig};
+use wiremock::MockServer;
+use crate::client::ApiClient;
+
+/// A client of the API `api` mocks
+pub fn client(api: &MockServer) -> ApiClient {
+    client_for(&api.uri())
+}
+
+/// A client of the API at `url`
+pub fn client_for(url: &str) -> ApiClient {
+    ApiClient::new(&crate::config::Config { api_url: url.to_string(), token: None, ..Default::default() }).unwrap()
+}
+
+/// A started machine of app `web` running `nginx:alpine`, without services
+pub fn machine(id: &str) -> Machine {
+    let created_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    Machine {
+        id: id.to_string(),
+        name: format!("web-{}", id),
+        app_name: "web".to_string(),
+        state: MachineState::Started,
+        region: "local".to_string(),
+        image_ref: ImageRef {
+            registry: "docker.io".to_string(),
+            repository: "nginx".to_string(),
+            tag: "alpine".to_string(),
+            digest: None,
+        },
+        instance_id: "01H".to_string(),
+        private_ip: "fdaa::2".to_string(),
+        created_at,
+        updated_at: created_at,
+        config: crate::commands::machines::machine_config("nginx:alpine", &[], &[], &[]).unwrap(),
+        events: Vec::new(),
+        cordoned: false,
+        checks: Vec::new(),
+        mapped_ports: Vec::new(),
+    }
+}
+
+/// A TCP service on `internal_port`, published on `ports`
+pub fn service(internal_port: u16, ports: Vec<PortConfig>) -> ServiceConfig {
+    ServiceConfig {
+        ports,
+        protocol: "tcp".to_string(),
+        internal_port,
+        autostop: None,
+        autostart: None,
+        force_instance_description: None,
+    }
+}
@@ -8,12 +8,21 @@ pub enum Error {
     #[error("App not found: {0}")]
     AppNotFound(String),
     
+    #[error("Volume not found: {0}")]
+    VolumeNotFound(String),
+    
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
     
+    #[error("Invalid machine config: {0}")]
+    InvalidMachineConfig(String),
+    
     #[error("Docker error: {0}")]
     DockerError(String),
     
+    #[error("Docker unavailable: {0}")]
+    DockerUnavailable(String),
+    
     #[error("Database error: {0}")]
     DatabaseError(String),
     
@@ -29,6 +38,12 @@ pub enum Error {
     #[error("Invalid lease nonce")]
     InvalidLeaseNonce,
     
+    #[error("Lease has expired")]
+    LeaseExpired,
+    
+    #[error("Version conflict: {0}")]
+    VersionConflict(String),
+    
     #[error("Resource not found")]
     NotFound,
     
@@ -45,4 +60,30 @@ pub enum Error {
     Anyhow(#[from] anyhow::Error),
 }
 
+impl Error {
+    /// Returns a stable, machine readable code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::MachineNotFound(_) => "machine_not_found",
+            Error::AppNotFound(_) => "app_not_found",
+            Error::VolumeNotFound(_) => "volume_not_found",
+            Error::InvalidConfiguration(_) => "invalid_configuration",
+            Error::InvalidMachineConfig(_) => "invalid_machine_config",
+            Error::DockerError(_) => "docker_error",
+            Error::DockerUnavailable(_) => "docker_unavailable",
+            Error::DatabaseError(_) => "database_error",
+            Error::NetworkError(_) => "network_error",
+            Error::AuthenticationFailed => "unauthorized",
+            Error::LeaseConflict => "lease_conflict",
+            Error::InvalidLeaseNonce => "invalid_lease_nonce",
+            Error::LeaseExpired => "lease_expired",
+            Error::VersionConflict(_) => "version_conflict",
+            Error::NotFound => "not_found",
+            Error::BadRequest(_) => "bad_request",
+            Error::Internal(_) | Error::Anyhow(_) => "internal_error",
+            Error::LiteFSError(_) => "litefs_error",
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
@@ -1,6 +1,9 @@
 pub mod error;
 pub mod models;
+pub mod region;
+pub mod secrets;
 pub mod types;
+pub mod volumes;
 
 pub use error::{Error, Result};
 pub use types::*;
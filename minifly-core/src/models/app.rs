@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use super::{MachineState, MappedPort};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct App {
@@ -33,6 +34,107 @@ pub struct AppResponse {
     pub organization: Organization,
     pub status: String,
     pub created_at: String,
+    /// Machines belonging to the app, excluding destroyed ones
+    #[serde(default)]
+    pub machine_count: usize,
+    /// Status summarised from the app's machine states
+    #[serde(default)]
+    pub machine_status: AppMachineStatus,
+}
+
+/// An app's status summarised from its machines' states
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AppMachineStatus {
+    /// The app has no machines yet
+    #[default]
+    Pending,
+    /// Every machine is started
+    Running,
+    /// Some machines are started and some are not
+    Partial,
+    /// No machine is started
+    Stopped,
+}
+
+impl AppMachineStatus {
+    /// Summarises machine states, ignoring destroyed machines
+    pub fn from_states<'a>(states: impl IntoIterator<Item = &'a MachineState>) -> Self {
+        let (mut total, mut started) = (0, 0);
+        for state in states {
+            match state {
+                MachineState::Destroying | MachineState::Destroyed => continue,
+                MachineState::Started => started += 1,
+                _ => {}
+            }
+            total += 1;
+        }
+
+        match (total, started) {
+            (0, _) => Self::Pending,
+            (total, started) if started == total => Self::Running,
+            (_, 0) => Self::Stopped,
+            _ => Self::Partial,
+        }
+    }
+
+    /// Returns the status as it appears in API payloads, e.g. `running`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Partial => "partial",
+            Self::Stopped => "stopped",
+        }
+    }
+}
+
+/// What deleting an app cleaned up
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeleteAppResponse {
+    pub ok: bool,
+    /// Machines destroyed along with their containers
+    pub machines: Vec<String>,
+    /// Volumes deleted along with their data
+    pub deleted_volumes: Vec<String>,
+    /// Volumes left in place because they were asked to be kept
+    pub kept_volumes: Vec<String>,
+}
+
+/// Addresses `<app>.internal` resolves to: running machines that aren't cordoned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDnsResponse {
+    pub hostname: String,
+    pub ips: Vec<std::net::IpAddr>,
+    /// The machine behind each of `ips`
+    #[serde(default)]
+    pub machines: Vec<ResolvedMachine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedMachine {
+    pub id: String,
+    pub ip: std::net::IpAddr,
+    /// Simulated latency from the resolving request's region to the
+    /// machine's, which proxies hold traffic to the machine back by
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub latency_ms: u64,
+    /// Host ports the machine's services are published on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mapped_ports: Vec<MappedPort>,
+}
+
+impl ResolvedMachine {
+    /// Returns the host port a service port is published on, e.g. `(8080, "tcp")`.
+    pub fn host_port(&self, internal_port: u16, protocol: &str) -> Option<u16> {
+        self.mapped_ports.iter()
+            .find(|port| port.internal_port == internal_port && port.protocol.eq_ignore_ascii_case(protocol))
+            .map(|port| port.host_port)
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,4 +142,22 @@ pub struct Organization {
     pub id: String,
     pub slug: String,
     pub name: String,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machine_status_from_mixed_states() {
+        use MachineState::*;
+
+        assert_eq!(AppMachineStatus::from_states(&[]), AppMachineStatus::Pending);
+        assert_eq!(AppMachineStatus::from_states(&[Started, Started]), AppMachineStatus::Running);
+        assert_eq!(AppMachineStatus::from_states(&[Started, Stopped, Suspended]), AppMachineStatus::Partial);
+        assert_eq!(AppMachineStatus::from_states(&[Stopped, Created]), AppMachineStatus::Stopped);
+        // Destroyed machines don't count towards the app's status
+        assert_eq!(AppMachineStatus::from_states(&[Started, Destroyed]), AppMachineStatus::Running);
+        assert_eq!(AppMachineStatus::from_states(&[Destroyed]), AppMachineStatus::Pending);
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,10 +10,28 @@ pub struct Lease {
     pub version: String,
 }
 
+impl Lease {
+    /// Whether `expires_at` (Unix seconds) has passed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().timestamp()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateLeaseRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// Who takes the lease, e.g. a user or process name; `minifly@local`
+    /// when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+/// Extends a lease to `ttl` seconds from now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenewLeaseRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<u32>,
 }
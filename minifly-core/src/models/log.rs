@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// A line of machine output, as streamed by the logs endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Timestamp of the log entry
+    pub timestamp: String,
+    /// Log level (info, warn, error, debug)
+    pub level: String,
+    /// Region where the log originated
+    pub region: String,
+    /// Machine ID that generated the log
+    pub machine_id: String,
+    /// App name
+    pub app_name: String,
+    /// Raw log message
+    pub message: String,
+    /// Stream type (stdout, stderr)
+    pub stream: String,
+    /// Correlation ID for request tracking
+    pub correlation_id: Option<String>,
+}
+
+/// Log health of every machine of an app, from the app log summary endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppLogsSummary {
+    pub app_name: String,
+    /// Lines sampled across all machines
+    pub line_count: usize,
+    pub error_count: usize,
+    pub warn_count: usize,
+    /// Timestamp of the newest line of any machine
+    pub last_activity: Option<String>,
+    pub machines: Vec<MachineLogsSummary>,
+}
+
+/// Log health of one machine in an [`AppLogsSummary`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MachineLogsSummary {
+    pub machine_id: String,
+    pub region: String,
+    /// Recent lines sampled from the machine
+    pub line_count: usize,
+    pub error_count: usize,
+    pub warn_count: usize,
+    /// Timestamp of the machine's newest line
+    pub last_activity: Option<String>,
+}
+
+impl AppLogsSummary {
+    /// Adds up the machines' counts
+    pub fn new(app_name: &str, machines: Vec<MachineLogsSummary>) -> Self {
+        Self {
+            app_name: app_name.to_string(),
+            line_count: machines.iter().map(|m| m.line_count).sum(),
+            error_count: machines.iter().map(|m| m.error_count).sum(),
+            warn_count: machines.iter().map(|m| m.warn_count).sum(),
+            last_activity: latest(machines.iter().filter_map(|m| m.last_activity.as_deref())),
+            machines,
+        }
+    }
+}
+
+/// The latest of some RFC3339 timestamps
+pub fn latest<'a>(timestamps: impl Iterator<Item = &'a str>) -> Option<String> {
+    timestamps
+        .filter_map(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).ok().map(|time| (time, timestamp)))
+        .max_by_key(|(time, _)| *time)
+        .map(|(_, timestamp)| timestamp.to_string())
+}
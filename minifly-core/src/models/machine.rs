@@ -6,6 +6,9 @@ use std::collections::HashMap;
 pub struct Machine {
     pub id: String,
     pub name: String,
+    /// App the machine belongs to, whatever it is named
+    #[serde(default)]
+    pub app_name: String,
     pub state: MachineState,
     pub region: String,
     pub image_ref: ImageRef,
@@ -15,6 +18,42 @@ pub struct Machine {
     pub updated_at: DateTime<Utc>,
     pub config: MachineConfig,
     pub events: Vec<MachineEvent>,
+    /// Cordoned machines keep running but receive no traffic
+    #[serde(default)]
+    pub cordoned: bool,
+    /// Latest result of each of `config.checks`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<CheckStatus>,
+    /// Host ports the machine's services are published on while it runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mapped_ports: Vec<MappedPort>,
+}
+
+impl Machine {
+    /// Returns the host port a service port is published on, e.g. `(8080, "tcp")`.
+    pub fn host_port(&self, internal_port: u16, protocol: &str) -> Option<u16> {
+        self.mapped_ports.iter()
+            .find(|port| port.internal_port == internal_port && port.protocol.eq_ignore_ascii_case(protocol))
+            .map(|port| port.host_port)
+    }
+
+    /// Records an event, dropping the oldest ones beyond `max_events`.
+    pub fn push_event(&mut self, event: MachineEvent, max_events: Option<usize>) {
+        self.events.push(event);
+        if let Some(max_events) = max_events {
+            let excess = self.events.len().saturating_sub(max_events);
+            self.events.drain(..excess);
+        }
+    }
+}
+
+/// A service port of a machine published on the host
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MappedPort {
+    pub internal_port: u16,
+    /// `tcp` or `udp`
+    pub protocol: String,
+    pub host_port: u16,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -31,6 +70,23 @@ pub enum MachineState {
     Suspended,
 }
 
+impl MachineState {
+    /// Returns the state as it appears in API payloads, e.g. `started`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MachineState::Created => "created",
+            MachineState::Starting => "starting",
+            MachineState::Started => "started",
+            MachineState::Stopping => "stopping",
+            MachineState::Stopped => "stopped",
+            MachineState::Destroying => "destroying",
+            MachineState::Destroyed => "destroyed",
+            MachineState::Suspending => "suspending",
+            MachineState::Suspended => "suspended",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageRef {
     pub registry: String,
@@ -53,6 +109,9 @@ pub struct MachineConfig {
     pub restart: Option<RestartConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auto_destroy: Option<bool>,
+    /// Seconds a stopping machine gets to shut down before it is killed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kill_timeout: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dns: Option<DnsConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -67,6 +126,50 @@ pub struct MachineConfig {
     pub containers: Option<Vec<ContainerConfig>>,
 }
 
+impl MachineConfig {
+    /// A config running `image` on a shared 1 CPU / 256 MB guest that is
+    /// restarted up to 3 times on failure, with every other setting unset
+    pub fn for_image(image: impl Into<String>) -> Self {
+        Self {
+            image: image.into(),
+            guest: GuestConfig {
+                cpu_kind: "shared".to_string(),
+                cpus: 1,
+                memory_mb: 256,
+                gpu_kind: None,
+                gpus: None,
+                kernel_args: None,
+            },
+            env: None,
+            services: None,
+            checks: None,
+            restart: Some(RestartConfig {
+                policy: "on-failure".to_string(),
+                max_retries: Some(3),
+            }),
+            auto_destroy: None,
+            kill_timeout: None,
+            dns: None,
+            processes: None,
+            files: None,
+            init: None,
+            mounts: None,
+            containers: None,
+        }
+    }
+
+    /// Rejects configs whose settings contradict each other
+    pub fn validate(&self) -> crate::Result<()> {
+        if let Some(init) = &self.init {
+            init.validate()?;
+        }
+        for (name, check) in self.checks.iter().flatten() {
+            check.config().map_err(|msg| crate::Error::InvalidMachineConfig(format!("checks.{}: {}", name, msg)))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GuestConfig {
     pub cpu_kind: String,
@@ -133,6 +236,68 @@ pub struct HealthCheck {
     pub tls_skip_verify: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, Vec<String>>>,
+    /// Status an `http` check expects; any 2xx passes when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_status: Option<u16>,
+    /// Command a `script` check runs in the machine; exiting 0 passes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+}
+
+/// What a [`HealthCheck`] probes, depending on its `type`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckConfig {
+    Http {
+        port: u16,
+        method: String,
+        path: String,
+        expected_status: Option<u16>,
+    },
+    Tcp {
+        port: u16,
+    },
+    Script {
+        command: Vec<String>,
+    },
+}
+
+impl HealthCheck {
+    /// The settings of the check's type, or why it can't run
+    ///
+    /// `http` checks default to `GET /`.
+    pub fn config(&self) -> Result<CheckConfig, String> {
+        let port = || self.port.ok_or_else(|| format!("{} checks need a port", self.check_type));
+        match self.check_type.as_str() {
+            "http" => Ok(CheckConfig::Http {
+                port: port()?,
+                method: self.method.clone().unwrap_or_else(|| "GET".to_string()).to_uppercase(),
+                path: self.path.clone().unwrap_or_else(|| "/".to_string()),
+                expected_status: self.expected_status,
+            }),
+            "tcp" => Ok(CheckConfig::Tcp { port: port()? }),
+            "script" => match &self.command {
+                Some(command) if !command.is_empty() => Ok(CheckConfig::Script { command: command.clone() }),
+                _ => Err("script checks need a command".to_string()),
+            },
+            other => Err(format!("unknown check type '{}'; expected http, tcp or script", other)),
+        }
+    }
+}
+
+/// The latest result of one of a machine's checks
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CheckStatus {
+    pub name: String,
+    /// `passing` or `critical`
+    pub status: String,
+    pub output: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CheckStatus {
+    pub fn passing(&self) -> bool {
+        self.status == "passing"
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -196,6 +361,27 @@ pub struct InitConfig {
     pub cmd: Option<Vec<String>>,
 }
 
+impl InitConfig {
+    /// Checks that every override takes effect
+    ///
+    /// `exec` replaces the image's CMD on its own, so it can't be combined
+    /// with `entrypoint` or `cmd`. Those two compose: `entrypoint` replaces
+    /// the image's ENTRYPOINT and `cmd` its CMD, and either may be given
+    /// alone. Empty commands are rejected rather than silently ignored.
+    pub fn validate(&self) -> crate::Result<()> {
+        let invalid = |msg: &str| Err(crate::Error::InvalidMachineConfig(msg.to_string()));
+        if self.exec.is_some() && (self.entrypoint.is_some() || self.cmd.is_some()) {
+            return invalid("init.exec can't be combined with init.entrypoint or init.cmd");
+        }
+        for (field, command) in [("exec", &self.exec), ("entrypoint", &self.entrypoint), ("cmd", &self.cmd)] {
+            if command.as_ref().is_some_and(|command| command.is_empty()) {
+                return invalid(&format!("init.{} must not be empty", field));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MountConfig {
     pub volume: String,
@@ -239,6 +425,9 @@ pub struct MachineEvent {
     pub status: String,
     pub source: String,
     pub timestamp: u64,
+    /// Details of the event, e.g. the exit code and restart count of an exit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -293,6 +482,99 @@ pub struct StopMachineResponse {
     pub ok: bool,
 }
 
+/// The environment of a machine's container
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MachineEnvResponse {
+    /// Variables by name, with the values of `redacted` ones replaced
+    pub env: std::collections::BTreeMap<String, String>,
+    /// Names of the variables whose values were redacted
+    #[serde(default)]
+    pub redacted: Vec<String>,
+    /// Names of the variables injected from the app's secrets files when
+    /// the container was created
+    #[serde(default)]
+    pub secrets: Vec<String>,
+}
+
+/// Connections proxies hold open to a machine, which `autostop` acts on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MachineConnections {
+    pub active: u32,
+    /// When the last open connection closed; unset while any are open
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_since: Option<DateTime<Utc>>,
+}
+
+/// How long a proxy's report of its open connections holds. Proxies report
+/// again well before it runs out; the connections of a proxy that stops
+/// reporting count as closed once it has.
+pub const CONNECTION_LEASE_SECS: u64 = 30;
+
+/// Reports how many connections a proxy currently holds open to a machine,
+/// leasing them for [`CONNECTION_LEASE_SECS`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateConnectionsRequest {
+    /// Identifies the reporting proxy process
+    pub client_id: String,
+    pub active: u32,
+}
+
+/// Applies `action` (`start` or `stop`) to several of an app's machines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MachineActionRequest {
+    pub action: String,
+    pub machine_ids: MachineSelection,
+}
+
+/// The machines a bulk action applies to: the listed IDs, or `"all"`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MachineSelection {
+    All,
+    Ids(Vec<String>),
+}
+
+impl Serialize for MachineSelection {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MachineSelection::All => serializer.serialize_str("all"),
+            MachineSelection::Ids(ids) => ids.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MachineSelection {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Keyword(String),
+            Ids(Vec<String>),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Keyword(keyword) if keyword == "all" => Ok(MachineSelection::All),
+            Raw::Keyword(other) => Err(serde::de::Error::custom(format!(
+                "expected a list of machine IDs or \"all\", got {:?}",
+                other
+            ))),
+            Raw::Ids(ids) => Ok(MachineSelection::Ids(ids)),
+        }
+    }
+}
+
+/// The outcome of a bulk action, one result per machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MachineActionResponse {
+    pub results: Vec<MachineActionResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MachineActionResult {
+    pub machine_id: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WaitMachineQuery {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -301,4 +583,55 @@ pub struct WaitMachineQuery {
     pub timeout: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub state: Option<String>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init(exec: Option<&[&str]>, entrypoint: Option<&[&str]>, cmd: Option<&[&str]>) -> InitConfig {
+        let command = |args: Option<&[&str]>| args.map(|args| args.iter().map(|a| a.to_string()).collect());
+        InitConfig { exec: command(exec), entrypoint: command(entrypoint), cmd: command(cmd) }
+    }
+
+    #[test]
+    fn test_init_validation() {
+        assert!(init(Some(&["/app/server"]), None, None).validate().is_ok());
+        assert!(init(None, Some(&["/entrypoint.sh"]), Some(&["serve"])).validate().is_ok());
+        assert!(init(None, None, Some(&["serve"])).validate().is_ok());
+
+        for conflicting in [
+            init(Some(&["/app/server"]), Some(&["/entrypoint.sh"]), None),
+            init(Some(&["/app/server"]), None, Some(&["serve"])),
+            init(None, Some(&[]), None),
+        ] {
+            assert!(matches!(conflicting.validate(), Err(crate::Error::InvalidMachineConfig(_))));
+        }
+    }
+
+    #[test]
+    fn test_check_config_by_type() {
+        let check = |value: serde_json::Value| serde_json::from_value::<HealthCheck>(value).unwrap().config();
+
+        assert_eq!(
+            check(serde_json::json!({ "type": "http", "port": 8080, "path": "/healthz", "method": "head" })),
+            Ok(CheckConfig::Http { port: 8080, method: "HEAD".to_string(), path: "/healthz".to_string(), expected_status: None })
+        );
+        assert_eq!(check(serde_json::json!({ "type": "tcp", "port": 5432 })), Ok(CheckConfig::Tcp { port: 5432 }));
+        assert_eq!(
+            check(serde_json::json!({ "type": "script", "command": ["pg_isready"] })),
+            Ok(CheckConfig::Script { command: vec!["pg_isready".to_string()] })
+        );
+        assert!(check(serde_json::json!({ "type": "tcp" })).is_err());
+        assert!(check(serde_json::json!({ "type": "script", "command": [] })).is_err());
+        assert!(check(serde_json::json!({ "type": "grpc", "port": 50051 })).is_err());
+    }
+
+    #[test]
+    fn test_machine_selection_json() {
+        let parse = |json: &str| serde_json::from_str::<MachineSelection>(json);
+        assert_eq!(parse(r#""all""#).unwrap(), MachineSelection::All);
+        assert_eq!(parse(r#"["d1","d2"]"#).unwrap(), MachineSelection::Ids(vec!["d1".to_string(), "d2".to_string()]));
+        assert!(parse(r#""some""#).is_err());
+        assert_eq!(serde_json::to_string(&MachineSelection::All).unwrap(), r#""all""#);
+    }
+}
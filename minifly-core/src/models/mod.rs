@@ -2,8 +2,12 @@ pub mod app;
 pub mod machine;
 pub mod volume;
 pub mod lease;
+pub mod log;
+pub mod release;
 
 pub use app::*;
 pub use machine::*;
 pub use volume::*;
-pub use lease::*;
\ No newline at end of file
+pub use lease::*;
+pub use log::*;
+pub use release::*;
\ No newline at end of file
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use super::MachineConfig;
+
+/// Process group of machines deployed without a `[processes]` section
+pub const DEFAULT_PROCESS_GROUP: &str = "app";
+
+/// Machine metadata key naming the process group a machine was deployed for
+pub const PROCESS_GROUP_METADATA_KEY: &str = "fly_process_group";
+
+/// A recorded deploy of an app: the image and the machine config each of its
+/// process groups ran with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub version: u32,
+    /// Image of the default process group, or of the first one
+    pub image: String,
+    /// Machine config of each process group
+    pub configs: BTreeMap<String, MachineConfig>,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Records a deploy as a release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReleaseRequest {
+    pub description: String,
+    /// IDs of the machines deployed for each process group. The release
+    /// stores the config they run.
+    pub process_groups: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackResponse {
+    /// The new release created by the rollback, if every machine was rolled back
+    pub release: Option<Release>,
+    /// Result for each machine the rollback applied to
+    pub machines: Vec<RollbackMachineResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackMachineResult {
+    pub machine_id: String,
+    pub process_group: String,
+    /// Why the machine kept its config, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
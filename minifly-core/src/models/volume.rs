@@ -7,6 +7,14 @@ pub struct Volume {
     pub name: String,
     pub state: VolumeState,
     pub size_gb: u32,
+    #[serde(default)]
+    pub block_size: u64,
+    #[serde(default)]
+    pub blocks: u64,
+    #[serde(default)]
+    pub blocks_free: u64,
+    #[serde(default)]
+    pub blocks_avail: u64,
     pub region: String,
     pub zone: String,
     pub encrypted: bool,
@@ -15,6 +23,29 @@ pub struct Volume {
     pub created_at: DateTime<Utc>,
 }
 
+/// Block size reported for local volumes, matching ext4 defaults on Fly
+pub const VOLUME_BLOCK_SIZE: u64 = 4096;
+
+impl Volume {
+    /// Returns the number of blocks a volume of `size_gb` occupies.
+    pub fn blocks_for_size(size_gb: u32) -> u64 {
+        size_gb as u64 * 1024 * 1024 * 1024 / VOLUME_BLOCK_SIZE
+    }
+
+    /// Resizes the volume, recomputing block counts while keeping the
+    /// number of used blocks unchanged.
+    pub fn set_size(&mut self, size_gb: u32) {
+        let used = self.blocks.saturating_sub(self.blocks_free);
+        let blocks = Self::blocks_for_size(size_gb);
+
+        self.size_gb = size_gb;
+        self.block_size = VOLUME_BLOCK_SIZE;
+        self.blocks = blocks;
+        self.blocks_free = blocks.saturating_sub(used);
+        self.blocks_avail = self.blocks_free;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum VolumeState {
@@ -39,6 +70,12 @@ pub struct CreateVolumeRequest {
     pub snapshot_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshot_retention: Option<u32>,
+    /// Volume to fork; its data is copied into the new volume
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_volume_id: Option<String>,
+    /// Allow `source_volume_id` to belong to a different app
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_cross_app_fork: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +83,12 @@ pub struct ExtendVolumeRequest {
     pub size_gb: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtendVolumeResponse {
+    pub volume: Volume,
+    pub needs_restart: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttachVolumeRequest {
     pub machine_id: String,
@@ -0,0 +1,68 @@
+//! Simulated latency between regions.
+//!
+//! The API server reads it from `MINIFLY_REGION_LATENCY` and reports the
+//! delay to each machine when resolving an app, and the proxies hold back
+//! traffic to machines in another region than the caller's by that delay.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Simulated latency between regions
+///
+/// Traffic to a machine in another region than the caller's is delayed by
+/// `base_ms` plus the extra delay of both regions. Traffic within one region
+/// is never delayed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionLatency {
+    /// Delay added to all cross-region traffic
+    #[serde(default)]
+    pub base_ms: u64,
+    /// Extra delay for traffic to or from a region
+    #[serde(default)]
+    pub regions: HashMap<String, u64>,
+}
+
+impl RegionLatency {
+    /// Parses `base[,region=ms...]`, e.g. `40,syd=150`
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let mut latency = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let parse_ms = |ms: &str| {
+                ms.trim().parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("Invalid latency '{}' in '{}'; expected milliseconds", ms, entry))
+            };
+            match entry.split_once('=') {
+                Some((region, ms)) => {
+                    latency.regions.insert(region.trim().to_ascii_lowercase(), parse_ms(ms)?);
+                }
+                None => latency.base_ms = parse_ms(entry)?,
+            }
+        }
+        Ok(latency)
+    }
+
+    /// Delay for traffic from region `from` to a machine in region `to`
+    pub fn delay(&self, from: &str, to: &str) -> Duration {
+        if from == to {
+            return Duration::ZERO;
+        }
+        let extra = |region: &str| self.regions.get(region).copied().unwrap_or(0);
+        Duration::from_millis(self.base_ms + extra(from) + extra(to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_region_latency() {
+        let latency = RegionLatency::parse("40, syd=150").unwrap();
+        assert_eq!(latency.base_ms, 40);
+        assert_eq!(latency.regions["syd"], 150);
+        assert_eq!(latency.delay("ord", "syd"), Duration::from_millis(190));
+        assert_eq!(latency.delay("syd", "syd"), Duration::ZERO);
+        assert!(RegionLatency::parse("syd=slow").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! At-rest encryption for `.fly.secrets` files.
+//!
+//! Encrypted files start with a header line naming the key they were
+//! encrypted with, followed by a single base64 line holding the salt, nonce
+//! and AES-256-GCM ciphertext of the plaintext `KEY=VALUE` contents:
+//!
+//! ```text
+//! # minifly-encrypted-secrets v1 passphrase
+//! 3q2+7w...
+//! ```
+//!
+//! Files without the header are legacy plaintext and are read unchanged.
+//!
+//! An app's secrets are layered from up to four files, most specific first
+//! (see [`secrets_files`]); a key is taken from the first file defining it.
+
+use crate::{Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+/// Prefix of the first line of an encrypted secrets file
+pub const ENCRYPTED_HEADER: &str = "# minifly-encrypted-secrets v1";
+
+/// Environment variable holding a passphrase to encrypt secrets with
+pub const SECRETS_KEY_ENV: &str = "MINIFLY_SECRETS_KEY";
+
+/// Environment variable that turns on encryption with the local machine key
+pub const ENCRYPT_SECRETS_ENV: &str = "MINIFLY_ENCRYPT_SECRETS";
+
+/// Environment variables naming the environment, as for `fly.<env>.toml`
+pub const ENVIRONMENT_VARS: [&str; 2] = ["FLY_ENV", "MINIFLY_ENV"];
+
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Key material used to encrypt a secrets file
+#[derive(Clone)]
+pub enum SecretsKey {
+    /// A passphrase, stretched with PBKDF2 using a per-file salt
+    Passphrase(String),
+    /// A random 256-bit key stored on this machine
+    MachineKey([u8; 32]),
+}
+
+impl SecretsKey {
+    fn kind(&self) -> &'static str {
+        match self {
+            SecretsKey::Passphrase(_) => "passphrase",
+            SecretsKey::MachineKey(_) => "machine-key",
+        }
+    }
+
+    fn derive(&self, salt: &[u8]) -> [u8; 32] {
+        match self {
+            SecretsKey::Passphrase(passphrase) => {
+                let mut key = [0u8; 32];
+                pbkdf2::derive(
+                    pbkdf2::PBKDF2_HMAC_SHA256,
+                    NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+                    salt,
+                    passphrase.as_bytes(),
+                    &mut key,
+                );
+                key
+            }
+            SecretsKey::MachineKey(key) => *key,
+        }
+    }
+
+    /// Path of the local machine key, `~/.config/minifly/secrets.key` by default
+    pub fn machine_key_path() -> Result<PathBuf> {
+        let mut path = dirs::config_dir()
+            .ok_or_else(|| Error::InvalidConfiguration("Failed to determine config directory".to_string()))?;
+        path.push("minifly");
+        path.push("secrets.key");
+        Ok(path)
+    }
+
+    /// Loads the key to encrypt with: the `MINIFLY_SECRETS_KEY` passphrase if
+    /// set, otherwise the local machine key, which is created on first use.
+    pub fn load_or_create() -> Result<Self> {
+        if let Ok(passphrase) = std::env::var(SECRETS_KEY_ENV) {
+            return Ok(SecretsKey::Passphrase(passphrase));
+        }
+
+        let path = Self::machine_key_path()?;
+        if !path.exists() {
+            let mut key = [0u8; 32];
+            SystemRandom::new()
+                .fill(&mut key)
+                .map_err(|_| Error::Internal("Failed to generate secrets key".to_string()))?;
+
+            write_machine_key(&path, &key)
+                .map_err(|e| Error::Internal(format!("Failed to write secrets key {}: {}", path.display(), e)))?;
+        }
+
+        Self::load_machine_key()
+    }
+
+    fn load_machine_key() -> Result<Self> {
+        let path = Self::machine_key_path()?;
+        let contents = std::fs::read_to_string(&path).map_err(|e| {
+            Error::InvalidConfiguration(format!("Failed to read secrets key {}: {}", path.display(), e))
+        })?;
+
+        let bytes = hex::decode(contents.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| Error::InvalidConfiguration(format!("Invalid secrets key in {}", path.display())))?;
+
+        Ok(SecretsKey::MachineKey(bytes))
+    }
+}
+
+fn write_machine_key(path: &std::path::Path, key: &[u8; 32]) -> std::io::Result<()> {
+    use std::io::Write;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Created owner-only, so the key is never readable by others
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(hex::encode(key).as_bytes())
+}
+
+/// Returns true if the contents are an encrypted secrets file
+pub fn is_encrypted(contents: &str) -> bool {
+    contents.starts_with(ENCRYPTED_HEADER)
+}
+
+/// Returns true if newly written secrets files should be encrypted
+pub fn encryption_enabled() -> bool {
+    std::env::var(SECRETS_KEY_ENV).is_ok()
+        || std::env::var(ENCRYPT_SECRETS_ENV).is_ok_and(|v| v == "1" || v == "true")
+}
+
+/// Encrypts plaintext secrets file contents
+pub fn encrypt(plaintext: &str, key: &SecretsKey) -> Result<String> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| Error::Internal("Failed to generate nonce".to_string()))?;
+
+    let mut in_out = plaintext.as_bytes().to_vec();
+    aead_key(key, &salt)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| Error::Internal("Failed to encrypt secrets".to_string()))?;
+
+    let mut blob = Vec::with_capacity(SALT_LEN + NONCE_LEN + in_out.len());
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&in_out);
+
+    Ok(format!("{} {}\n{}\n", ENCRYPTED_HEADER, key.kind(), STANDARD.encode(blob)))
+}
+
+/// Decrypts an encrypted secrets file with the given key
+pub fn decrypt(contents: &str, key: &SecretsKey) -> Result<String> {
+    let mut lines = contents.lines();
+    let header = lines.next().unwrap_or_default();
+    let kind = header.strip_prefix(ENCRYPTED_HEADER).map(str::trim);
+    if kind != Some(key.kind()) {
+        return Err(Error::InvalidConfiguration(format!(
+            "Secrets file was encrypted with a {} key",
+            kind.unwrap_or("unknown")
+        )));
+    }
+
+    let blob = STANDARD
+        .decode(lines.collect::<String>().trim())
+        .map_err(|e| Error::InvalidConfiguration(format!("Corrupt encrypted secrets file: {}", e)))?;
+    if blob.len() < SALT_LEN + NONCE_LEN {
+        return Err(Error::InvalidConfiguration("Corrupt encrypted secrets file".to_string()));
+    }
+
+    let (salt, rest) = blob.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| Error::InvalidConfiguration("Corrupt encrypted secrets file".to_string()))?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = aead_key(key, salt)?
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| Error::InvalidConfiguration("Failed to decrypt secrets - wrong key?".to_string()))?;
+
+    String::from_utf8(plaintext.to_vec())
+        .map_err(|_| Error::InvalidConfiguration("Decrypted secrets are not valid UTF-8".to_string()))
+}
+
+/// Returns the plaintext of a secrets file, decrypting it if needed.
+///
+/// Passphrase-encrypted files need `MINIFLY_SECRETS_KEY`; others use the
+/// local machine key. Legacy plaintext files are returned unchanged.
+pub fn decode_secrets_file(contents: &str) -> Result<String> {
+    if !is_encrypted(contents) {
+        return Ok(contents.to_string());
+    }
+
+    let header = contents.lines().next().unwrap_or_default();
+    let key = if header.ends_with("passphrase") {
+        let passphrase = std::env::var(SECRETS_KEY_ENV).map_err(|_| {
+            Error::InvalidConfiguration(format!("Secrets file is passphrase-encrypted; set {}", SECRETS_KEY_ENV))
+        })?;
+        SecretsKey::Passphrase(passphrase)
+    } else {
+        SecretsKey::load_machine_key()?
+    };
+
+    decrypt(contents, &key)
+}
+
+/// Parses secrets from file contents in KEY=VALUE format.
+///
+/// Supports:
+/// - Comments starting with #
+/// - Empty lines (ignored)
+/// - KEY=VALUE pairs with optional whitespace
+/// - An optional `export ` prefix, as written by many `.env` files
+/// - Values can contain = characters
+/// - Double-quoted values may contain escaped quotes (`\"`)
+///
+/// Fails if a line is not in KEY=VALUE format or has an empty key.
+pub fn parse_secrets(contents: &str, secrets: &mut HashMap<String, String>) -> Result<()> {
+    for (line_num, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        // Skip empty lines and comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(Error::InvalidConfiguration(format!(
+                "Invalid format at line {} - expected KEY=VALUE",
+                line_num + 1
+            )));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() {
+            return Err(Error::InvalidConfiguration(format!("Empty key at line {}", line_num + 1)));
+        }
+
+        // Remove quotes if present
+        let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            unescape(&value[1..value.len() - 1])
+        } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+            value[1..value.len() - 1].to_string()
+        } else {
+            value.to_string()
+        };
+
+        secrets.insert(key.to_string(), value);
+    }
+
+    Ok(())
+}
+
+/// Resolves the `\\`, `\"`, `\n` and `\r` escapes of a double-quoted value,
+/// keeping other backslashes as written
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c @ ('"' | '\\')) => unescaped.push(c),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// The environment named by `FLY_ENV` or `MINIFLY_ENV`, lowercased
+pub fn current_environment() -> Option<String> {
+    ENVIRONMENT_VARS
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .map(|env| env.to_lowercase())
+        .filter(|env| !env.is_empty())
+}
+
+/// Secrets files of an app, highest precedence first:
+///
+/// 1. `.fly.secrets.<app>.<env>`
+/// 2. `.fly.secrets.<env>`
+/// 3. `.fly.secrets.<app>`
+/// 4. `.fly.secrets`
+///
+/// Environment files beat app files so an environment can override secrets
+/// set with `minifly secrets set`, which writes `.fly.secrets.<app>`.
+pub fn secrets_files(app_name: &str, environment: Option<&str>) -> Vec<String> {
+    let mut files = Vec::with_capacity(4);
+    if let Some(env) = environment {
+        files.push(format!(".fly.secrets.{}.{}", app_name, env));
+        files.push(format!(".fly.secrets.{}", env));
+    }
+    files.push(format!(".fly.secrets.{}", app_name));
+    files.push(".fly.secrets".to_string());
+    files
+}
+
+/// Whether an environment variable probably holds a secret, judging by its
+/// name: `*SECRET*`, `*KEY*`, `*TOKEN*`, `*PASSWORD*`, `*API*`,
+/// `DATABASE_URL` or `DATABASE_PATH`
+pub fn looks_secret(key: &str) -> bool {
+    let key = key.to_uppercase();
+    ["SECRET", "KEY", "TOKEN", "PASSWORD", "API"].iter().any(|word| key.contains(word))
+        || key == "DATABASE_URL"
+        || key == "DATABASE_PATH"
+}
+
+fn aead_key(key: &SecretsKey, salt: &[u8]) -> Result<LessSafeKey> {
+    let unbound = UnboundKey::new(&AES_256_GCM, &key.derive(salt))
+        .map_err(|_| Error::Internal("Invalid secrets key".to_string()))?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &str = "# Minifly secrets file\nDATABASE_URL=postgres://localhost/app\nAPI_KEY=\"with spaces\"\n";
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        for key in [SecretsKey::Passphrase("correct horse".to_string()), SecretsKey::MachineKey([7; 32])] {
+            let encrypted = encrypt(PLAINTEXT, &key).unwrap();
+
+            assert!(is_encrypted(&encrypted));
+            assert!(!encrypted.contains("postgres"));
+            assert_eq!(decrypt(&encrypted, &key).unwrap(), PLAINTEXT);
+        }
+
+        let encrypted = encrypt(PLAINTEXT, &SecretsKey::Passphrase("correct horse".to_string())).unwrap();
+        assert!(decrypt(&encrypted, &SecretsKey::Passphrase("wrong".to_string())).is_err());
+        assert!(decrypt(&encrypted, &SecretsKey::MachineKey([7; 32])).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_machine_key_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("minifly").join("secrets.key");
+        write_machine_key(&path, &[7; 32]).unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), hex::encode([7; 32]));
+    }
+
+    #[test]
+    fn test_legacy_plaintext_passes_through() {
+        assert!(!is_encrypted(PLAINTEXT));
+        assert_eq!(decode_secrets_file(PLAINTEXT).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_secrets_files_order() {
+        assert_eq!(
+            secrets_files("web", Some("staging")),
+            vec![".fly.secrets.web.staging", ".fly.secrets.staging", ".fly.secrets.web", ".fly.secrets"]
+        );
+        assert_eq!(secrets_files("web", None), vec![".fly.secrets.web", ".fly.secrets"]);
+    }
+
+    #[test]
+    fn test_parse_secrets() {
+        let contents = r#"
+# This is a comment
+DATABASE_URL=postgres://localhost/mydb
+API_KEY=abc123
+
+# Another comment
+SECRET_KEY="with spaces"
+QUOTED='single quotes'
+EMPTY=
+"#;
+
+        let mut secrets = HashMap::new();
+        parse_secrets(contents, &mut secrets).unwrap();
+
+        assert_eq!(secrets.get("DATABASE_URL").unwrap(), "postgres://localhost/mydb");
+        assert_eq!(secrets.get("API_KEY").unwrap(), "abc123");
+        assert_eq!(secrets.get("SECRET_KEY").unwrap(), "with spaces");
+        assert_eq!(secrets.get("QUOTED").unwrap(), "single quotes");
+        assert_eq!(secrets.get("EMPTY").unwrap(), "");
+        assert_eq!(secrets.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_secrets_invalid_format() {
+        let contents = "INVALID_LINE_NO_EQUALS";
+        let mut secrets = HashMap::new();
+        let result = parse_secrets(contents, &mut secrets);
+        assert!(result.is_err());
+    }
+}
@@ -11,6 +11,15 @@ pub struct ApiError {
     pub message: String,
 }
 
+/// Error body returned by the API, matching the shape used by Fly.io
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// Human readable error message
+    pub error: String,
+    /// Machine readable error code, e.g. `machine_not_found`
+    pub status: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuccessResponse {
     pub ok: bool,
@@ -0,0 +1,56 @@
+//! Host paths backing machine volumes.
+//!
+//! The API server bind-mounts these directories into containers, and the CLI
+//! reads them directly (release commands, `minifly litefs shell`), so both
+//! must compute them the same way.
+
+use std::path::{Path, PathBuf};
+
+/// SQLite database created in volumes mounted for LiteFS or app data
+pub const DATABASE_FILE: &str = "app.db";
+
+/// Directory volumes live under when no data directory is configured:
+/// `MINIFLY_DATA_DIR`, or `/tmp`
+pub fn default_data_dir() -> PathBuf {
+    std::env::var("MINIFLY_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("/tmp"))
+}
+
+/// Returns the host directory backing a volume of an app.
+///
+/// Directories are keyed by volume ID, so volumes sharing a name, such as a
+/// fork and its source, never share data.
+pub fn volume_host_path(data_dir: &Path, app_name: &str, volume_id: &str) -> PathBuf {
+    data_dir.join("minifly-data").join(app_name).join("volumes").join(volume_id)
+}
+
+/// Returns the host directory the API server mounts for a volume of an app,
+/// under [`default_data_dir`].
+///
+/// Anything that reads or writes volume data outside a machine resolves it
+/// here so it sees the same files the machine does.
+pub fn mounted_volume_path(app_name: &str, volume_id: &str) -> PathBuf {
+    volume_host_path(&default_data_dir(), app_name, volume_id)
+}
+
+/// Whether a volume mounted at `mount_path` holds the app's SQLite database
+pub fn is_database_mount(mount_path: &str) -> bool {
+    mount_path == "/litefs" || mount_path.contains("data")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_host_path() {
+        assert_eq!(
+            volume_host_path(Path::new("/var/minifly"), "web", "vol_1234"),
+            PathBuf::from("/var/minifly/minifly-data/web/volumes/vol_1234")
+        );
+        assert!(is_database_mount("/litefs"));
+        assert!(is_database_mount("/data"));
+        assert!(!is_database_mount("/uploads"));
+    }
+}
@@ -5,6 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing::{info, warn};
 
 /// Main LiteFS configuration structure.
@@ -70,12 +71,29 @@ pub struct ProxyConfig {
 pub struct LeaseConfig {
     #[serde(rename = "type", default = "default_lease_type")]
     pub lease_type: String,
+    #[serde(rename = "advertise-url", alias = "advertise_url")]
     pub advertise_url: Option<String>,
     pub candidate: Option<bool>,
     pub promote: Option<bool>,
     pub demote: Option<bool>,
 }
 
+impl LeaseConfig {
+    /// Static lease where `primary_hostname` always holds the lease.
+    ///
+    /// Every node advertises the primary's URL, so replicas know where to
+    /// replicate from without Consul.
+    pub fn static_lease(primary_hostname: &str, is_primary: bool) -> Self {
+        Self {
+            lease_type: LEASE_STATIC.to_string(),
+            advertise_url: Some(advertise_url(primary_hostname)),
+            candidate: Some(is_primary),
+            promote: Some(is_primary),
+            demote: Some(false),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogConfig {
     #[serde(default = "default_log_format")]
@@ -100,6 +118,55 @@ pub struct StaticConfig {
     pub advertise_url: String,
 }
 
+/// Environment variable setting the simulated replication lag, e.g. `500ms` or `2s`
+pub const REPLICATION_LAG_ENV: &str = "MINIFLY_LITEFS_REPLICATION_LAG";
+
+/// Simulated replication between local LiteFS nodes.
+///
+/// This is Minifly's own setting rather than part of `litefs.yml`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplicationConfig {
+    /// How long a change on the primary takes to become visible on replicas
+    pub lag: Duration,
+}
+
+impl ReplicationConfig {
+    pub fn with_lag(lag: Duration) -> Self {
+        Self { lag }
+    }
+    
+    /// Reads the lag from [`REPLICATION_LAG_ENV`], defaulting to none
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        match std::env::var(REPLICATION_LAG_ENV) {
+            Ok(value) => Ok(Self::with_lag(parse_lag(&value)?)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+/// Parses `250ms`, `1.5s` or a bare number of milliseconds
+fn parse_lag(value: &str) -> Result<Duration, anyhow::Error> {
+    let value = value.trim();
+    let parsed = if let Some(ms) = value.strip_suffix("ms") {
+        ms.trim().parse().map(Duration::from_millis).ok()
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.trim().parse().ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+    } else {
+        value.parse().map(Duration::from_millis).ok()
+    };
+    
+    parsed.ok_or_else(|| anyhow::anyhow!("Invalid replication lag '{}' (expected e.g. 500ms or 2s)", value))
+}
+
+/// Lease backend with a fixed primary, used for local development
+pub const LEASE_STATIC: &str = "static";
+/// Lease backend electing the primary through Consul
+pub const LEASE_CONSUL: &str = "consul";
+
+fn advertise_url(hostname: &str) -> String {
+    format!("http://{}:20202", hostname)
+}
+
 // Default functions for serde
 fn default_debug() -> bool { false }
 fn default_allow_other() -> bool { false }
@@ -134,7 +201,7 @@ impl Default for LiteFSConfig {
             }),
             lease: LeaseConfig {
                 lease_type: "static".to_string(),
-                advertise_url: None,
+                advertise_url: Some("http://localhost:20202".to_string()),
                 candidate: Some(true),
                 promote: Some(true),
                 demote: Some(false),
@@ -173,13 +240,7 @@ impl LiteFSConfig {
                 db: "db".to_string(),
                 passthrough: vec![],
             }),
-            lease: LeaseConfig {
-                lease_type: "static".to_string(),
-                advertise_url: Some(format!("http://{}:20202", machine_id)),
-                candidate: Some(is_primary),
-                promote: Some(is_primary),
-                demote: Some(false),
-            },
+            lease: LeaseConfig::static_lease(machine_id, is_primary),
             log: Some(LogConfig {
                 format: "text".to_string(),
                 level: "debug".to_string(),
@@ -188,11 +249,55 @@ impl LiteFSConfig {
             static_config: Some(StaticConfig {
                 primary: is_primary,
                 hostname: machine_id.to_string(),
-                advertise_url: format!("http://{}:20202", machine_id),
+                advertise_url: advertise_url(machine_id),
             }),
         }
     }
     
+    /// Switches the configuration to a static lease held by `primary_hostname`.
+    ///
+    /// Fails if the configuration sets another lease type, rather than
+    /// silently replacing it; production configs with a Consul lease are
+    /// converted by [`LiteFSConfig::from_production_config`] first.
+    pub fn with_static_lease(mut self, primary_hostname: &str, is_primary: bool) -> Result<Self, anyhow::Error> {
+        if self.lease.lease_type != LEASE_STATIC {
+            anyhow::bail!(
+                "LiteFS config sets a '{}' lease, but machines run locally with a static lease",
+                self.lease.lease_type
+            );
+        }
+        self.lease = LeaseConfig::static_lease(primary_hostname, is_primary);
+        self.static_config = Some(StaticConfig {
+            primary: is_primary,
+            hostname: primary_hostname.to_string(),
+            advertise_url: advertise_url(primary_hostname),
+        });
+        self.consul = None;
+        Ok(self)
+    }
+    
+    /// Checks that the lease section is usable by LiteFS
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        match self.lease.lease_type.as_str() {
+            LEASE_STATIC => {
+                if self.lease.advertise_url.is_none() {
+                    anyhow::bail!("Static lease requires an advertise-url for the primary");
+                }
+                if self.lease.candidate.is_none() {
+                    anyhow::bail!("Static lease requires candidate to mark the primary");
+                }
+                Ok(())
+            }
+            LEASE_CONSUL => {
+                if self.consul.is_none() {
+                    anyhow::bail!("Consul lease requires a consul section");
+                }
+                Ok(())
+            }
+            other => anyhow::bail!("Unknown lease type '{}' (expected static or consul)", other),
+        }
+    }
+    
     pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
         serde_yaml::to_string(self)
     }
@@ -253,7 +358,7 @@ impl LiteFSConfig {
         // Validate and adapt lease configuration
         match config.lease.lease_type.as_str() {
             "consul" => {
-                info!("Converting Consul lease to static lease for local development");
+                warn!("Converting the Consul lease of the production LiteFS config to a static lease; there is no Consul locally");
                 config.lease.lease_type = "static".to_string();
                 config.lease.candidate = Some(true);
                 config.lease.promote = Some(true);
@@ -341,4 +446,58 @@ impl LiteFSConfig {
         
         Ok(config)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_lease_document_is_valid() {
+        let config = LiteFSConfig::for_local_dev("replica", "/mnt/replica".into(), "/data/replica".into(), false)
+            .with_static_lease("primary", false)
+            .unwrap();
+        config.validate().unwrap();
+
+        let yaml = config.to_yaml().unwrap();
+        let document: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        let lease = &document["lease"];
+        assert_eq!(lease["type"].as_str(), Some("static"));
+        assert_eq!(lease["advertise-url"].as_str(), Some("http://primary:20202"));
+        assert_eq!(lease["candidate"].as_bool(), Some(false));
+        assert!(document["consul"].is_null());
+
+        let parsed = LiteFSConfig::from_yaml(&yaml).unwrap();
+        parsed.validate().unwrap();
+        assert_eq!(parsed.lease.advertise_url.as_deref(), Some("http://primary:20202"));
+    }
+
+    #[test]
+    fn test_parse_lag() {
+        assert_eq!(parse_lag("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_lag("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_lag("40").unwrap(), Duration::from_millis(40));
+        assert!(parse_lag("soon").is_err());
+        assert!(parse_lag("-1s").is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_incomplete_leases() {
+        let mut config = LiteFSConfig::default();
+        config.lease.advertise_url = None;
+        assert!(config.validate().is_err());
+
+        config.lease.lease_type = LEASE_CONSUL.to_string();
+        assert!(config.validate().is_err());
+
+        config.lease.lease_type = "etcd".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_static_lease_does_not_replace_a_consul_lease() {
+        let mut config = LiteFSConfig::default();
+        config.lease.lease_type = LEASE_CONSUL.to_string();
+        let err = config.with_static_lease("primary", true).unwrap_err();
+        assert!(err.to_string().contains("'consul' lease"), "{}", err);
+    }
+}
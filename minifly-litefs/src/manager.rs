@@ -1,19 +1,79 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use serde::Serialize;
 use tracing::{info, warn};
+use minifly_logging::fields;
 use reqwest;
 use tokio::fs;
-use crate::config::LiteFSConfig;
-use crate::process::LiteFSProcessManager;
+use crate::config::{LiteFSConfig, ReplicationConfig};
+use crate::process::{LiteFSExit, LiteFSProcessManager, RestartPolicy};
 use minifly_core::Error;
 use crate::Result;
 
 const LITEFS_VERSION: &str = "development";
 
+/// Whether a machine's LiteFS node holds the write lease
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiteFSRole {
+    Primary,
+    Replica,
+}
+
+/// LiteFS role and mount health of one machine
+#[derive(Debug, Clone, Serialize)]
+pub struct LiteFSMachineStatus {
+    pub machine_id: String,
+    pub role: LiteFSRole,
+    /// Whether the LiteFS process is running
+    pub running: bool,
+    pub mount_path: PathBuf,
+    pub mount_exists: bool,
+}
+
+/// Host directory bound at a machine's database mount
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseMount {
+    pub dir: PathBuf,
+    pub read_only: bool,
+}
+
+/// Role a machine's LiteFS node was started with
+#[derive(Debug, Clone)]
+struct MachineRole {
+    app_name: Option<String>,
+    is_primary: bool,
+    /// The machine's own database directory, usually its database volume
+    database_dir: Option<PathBuf>,
+}
+
+/// Suffixes of the files SQLite keeps next to a database
+const DATABASE_SIDECARS: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+/// Version of a file: its modification time and size, if it exists
+type FileVersion = Option<(Option<SystemTime>, u64)>;
+
+/// A replica's lagging view of one of the primary's databases
+#[derive(Debug, Default)]
+struct ReplicaView {
+    /// Versions of the primary's database, -wal and -shm files when last copied
+    seen: Option<Vec<FileVersion>>,
+    /// Snapshot directories waiting out the lag, oldest first
+    pending: VecDeque<(Instant, PathBuf)>,
+    snapshots: u64,
+}
+
 pub struct LiteFSManager {
     base_dir: PathBuf,
     binary_path: PathBuf,
     process_manager: LiteFSProcessManager,
+    roles: RwLock<HashMap<String, MachineRole>>,
+    replication: ReplicationConfig,
+    /// Lagging views keyed by replica machine ID and database name
+    replicas: std::sync::Mutex<HashMap<(String, String), ReplicaView>>,
 }
 
 impl LiteFSManager {
@@ -71,6 +131,9 @@ impl LiteFSManager {
                 base_dir,
                 binary_path: final_binary_path,
                 process_manager,
+                roles: RwLock::new(HashMap::new()),
+                replication: ReplicationConfig::default(),
+                replicas: std::sync::Mutex::new(HashMap::new()),
             })
         } else {
             let process_manager = LiteFSProcessManager::new(binary_path.clone());
@@ -79,10 +142,64 @@ impl LiteFSManager {
                 base_dir,
                 binary_path,
                 process_manager,
+                roles: RwLock::new(HashMap::new()),
+                replication: ReplicationConfig::default(),
+                replicas: std::sync::Mutex::new(HashMap::new()),
             })
         }
     }
     
+    /// Sets what happens when a LiteFS process exits on its own
+    pub fn with_restart_policy(self, restart_policy: RestartPolicy) -> Self {
+        Self {
+            process_manager: self.process_manager.with_restart_policy(restart_policy),
+            ..self
+        }
+    }
+    
+    /// Sets the simulated replication between primary and replicas
+    pub fn with_replication(self, replication: ReplicationConfig) -> Self {
+        Self { replication, ..self }
+    }
+    
+    /// Runs [`replicate_all`](Self::replicate_all) every `interval`
+    pub fn spawn_replication(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let manager = self.clone();
+                match tokio::task::spawn_blocking(move || manager.replicate_all()).await {
+                    Ok(Err(e)) => warn!("LiteFS replication failed: {}", e),
+                    Err(e) => warn!("LiteFS replication task failed: {}", e),
+                    Ok(Ok(())) => {}
+                }
+            }
+        })
+    }
+    
+    /// Checks every `interval` for LiteFS processes that exited, applying
+    /// the restart policy
+    pub fn spawn_monitor(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.process_manager.check_processes().await;
+            }
+        })
+    }
+    
+    /// Returns a receiver for LiteFS process exits detected by the monitor
+    pub fn subscribe_exits(&self) -> tokio::sync::broadcast::Receiver<LiteFSExit> {
+        self.process_manager.subscribe_exits()
+    }
+    
+    /// The last exit of every machine whose LiteFS process is down
+    pub fn crashed(&self) -> Vec<LiteFSExit> {
+        self.process_manager.crashed()
+    }
+    
     async fn download_litefs(target_path: &PathBuf) -> Result<()> {
         let arch = if cfg!(target_arch = "x86_64") {
             "amd64"
@@ -160,10 +277,52 @@ impl LiteFSManager {
     }
     
     pub async fn start_for_machine(&self, machine_id: &str, is_primary: bool) -> Result<()> {
-        self.start_for_machine_with_config(machine_id, is_primary, None).await
+        self.start_for_machine_with_config(machine_id, is_primary, None, None).await
     }
     
-    pub async fn start_for_machine_with_config(&self, machine_id: &str, is_primary: bool, app_name: Option<&str>) -> Result<()> {
+    /// Starts a machine's LiteFS node.
+    ///
+    /// `database_dir` is the machine's own database directory, typically the
+    /// host directory of its database volume; without one the machine gets a
+    /// directory under the LiteFS base directory. Which directory the machine
+    /// actually uses depends on its role, see [`database_mount`](Self::database_mount).
+    ///
+    /// An app has at most one primary: a machine asking to be primary while
+    /// another machine of its app holds the role starts as a replica. Use
+    /// [`is_primary`](Self::is_primary) for the role it got.
+    pub async fn start_for_machine_with_config(
+        &self,
+        machine_id: &str,
+        is_primary: bool,
+        app_name: Option<&str>,
+        database_dir: Option<&Path>,
+    ) -> Result<()> {
+        // Track the role even when LiteFS itself is unavailable, so role
+        // changes and write forwarding still work locally
+        let is_primary = {
+            let mut roles = self.roles.write().unwrap();
+            let current_primary = app_name.and_then(|app_name| roles.iter()
+                .find(|(id, role)| id.as_str() != machine_id && role.is_primary && role.app_name.as_deref() == Some(app_name))
+                .map(|(id, _)| id.clone()));
+            if let (true, Some(current_primary)) = (is_primary, &current_primary) {
+                warn!(
+                    machine.id = %machine_id,
+                    "Starting LiteFS as a replica: {} is already the primary", current_primary
+                );
+            }
+            
+            let is_primary = is_primary && current_primary.is_none();
+            roles.insert(machine_id.to_string(), MachineRole {
+                app_name: app_name.map(str::to_string),
+                is_primary,
+                database_dir: database_dir.map(Path::to_path_buf),
+            });
+            is_primary
+        };
+        let own_dir = self.own_database_dir(machine_id);
+        fs::create_dir_all(&own_dir).await
+            .map_err(|e| Error::LiteFSError(format!("Failed to create database dir: {}", e)))?;
+        
         // Check if we have a real LiteFS binary
         if self.binary_path == PathBuf::from("litefs") {
             // Check if litefs actually exists
@@ -174,7 +333,7 @@ impl LiteFSManager {
         }
         
         let mount_dir = self.base_dir.join("mounts").join(machine_id);
-        let data_dir = self.base_dir.join("data").join(machine_id);
+        let data_dir = self.data_dir(machine_id);
         let config_dir = self.base_dir.join("configs");
         
         // Create directories
@@ -207,6 +366,16 @@ impl LiteFSManager {
             LiteFSConfig::for_local_dev(machine_id, mount_dir, data_dir, is_primary)
         };
         
+        // There is no Consul locally, so the app's primary holds a static lease
+        let primary = if is_primary {
+            machine_id.to_string()
+        } else {
+            self.primary_of(machine_id).unwrap_or_else(|| machine_id.to_string())
+        };
+        let config = config.with_static_lease(&primary, is_primary)
+            .and_then(|config| config.validate().map(|_| config))
+            .map_err(|e| Error::LiteFSError(format!("Invalid LiteFS config: {}", e)))?;
+        
         self.process_manager.start_litefs(machine_id, &config, &config_dir).await?;
         
         Ok(())
@@ -214,6 +383,10 @@ impl LiteFSManager {
     
     pub async fn stop_for_machine(&self, machine_id: &str) -> Result<()> {
         self.process_manager.stop_litefs(machine_id).await?;
+        self.roles.write().unwrap().remove(machine_id);
+        self.replicas.lock().unwrap().retain(|(id, _), _| id != machine_id);
+        let _ = fs::remove_dir_all(self.replica_dir(machine_id)).await;
+        let _ = fs::remove_dir_all(self.base_dir.join("snapshots").join(machine_id)).await;
         
         // Clean up mount point
         let mount_dir = self.base_dir.join("mounts").join(machine_id);
@@ -263,6 +436,266 @@ impl LiteFSManager {
         Ok(())
     }
     
+    /// Returns whether the machine's LiteFS node is primary, if it is tracked
+    pub fn is_primary(&self, machine_id: &str) -> Option<bool> {
+        self.roles.read().unwrap().get(machine_id).map(|role| role.is_primary)
+    }
+    
+    /// Role and mount health of every LiteFS machine of an app, by machine ID
+    pub async fn app_status(&self, app_name: &str) -> Vec<LiteFSMachineStatus> {
+        let mut machines: Vec<(String, bool)> = self.roles.read().unwrap()
+            .iter()
+            .filter(|(_, role)| role.app_name.as_deref() == Some(app_name))
+            .map(|(machine_id, role)| (machine_id.clone(), role.is_primary))
+            .collect();
+        machines.sort();
+        
+        let mut statuses = Vec::with_capacity(machines.len());
+        for (machine_id, is_primary) in machines {
+            let mount_path = self.get_mount_path(&machine_id);
+            statuses.push(LiteFSMachineStatus {
+                role: if is_primary { LiteFSRole::Primary } else { LiteFSRole::Replica },
+                running: self.is_running(&machine_id).await,
+                mount_exists: mount_path.exists(),
+                mount_path,
+                machine_id,
+            });
+        }
+        statuses
+    }
+    
+    /// Makes a machine the app's only primary, demoting every other machine.
+    ///
+    /// Machines whose LiteFS process is running are restarted with their new role.
+    pub async fn promote(&self, app_name: &str, machine_id: &str) -> Result<()> {
+        let changed: Vec<(String, bool)> = {
+            let mut roles = self.roles.write().unwrap();
+            if roles.get(machine_id).and_then(|role| role.app_name.as_deref()) != Some(app_name) {
+                return Err(Error::MachineNotFound(machine_id.to_string()));
+            }
+            
+            roles.iter_mut()
+                .filter(|(_, role)| role.app_name.as_deref() == Some(app_name))
+                .filter_map(|(id, role)| {
+                    let is_primary = id == machine_id;
+                    if role.is_primary == is_primary {
+                        return None;
+                    }
+                    role.is_primary = is_primary;
+                    Some((id.clone(), is_primary))
+                })
+                .collect()
+        };
+        
+        let mut restart = Vec::new();
+        let mut old_primary = None;
+        let database_dirs: HashMap<String, Option<PathBuf>> = self.roles.read().unwrap().iter()
+            .map(|(id, role)| (id.clone(), role.database_dir.clone()))
+            .collect();
+        for (id, is_primary) in changed {
+            info!(
+                machine.id = %id,
+                app.name = %app_name,
+                { fields::LITEFS_IS_PRIMARY } = is_primary,
+                "LiteFS role changed"
+            );
+            
+            if !is_primary {
+                old_primary = Some(id.clone());
+            }
+            if self.is_running(&id).await {
+                self.process_manager.stop_litefs(&id).await?;
+                restart.push((id, is_primary));
+            }
+        }
+        
+        // Replicas write through to the primary's database directory, so the
+        // databases move with the primary role
+        if let Some(old_primary) = old_primary {
+            self.move_databases(&old_primary, machine_id).await?;
+        }
+        self.replicas.lock().unwrap().retain(|(id, _), _| id != machine_id);
+        
+        for (id, is_primary) in restart {
+            let database_dir = database_dirs.get(&id).cloned().flatten();
+            self.start_for_machine_with_config(&id, is_primary, Some(app_name), database_dir.as_deref()).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Directory holding the SQLite databases a machine reads and writes.
+    ///
+    /// Locally there is no replication stream, so writes on a replica are
+    /// forwarded by sharing storage: a replica of an app with a primary uses
+    /// the primary's database directory, and its writes are immediately
+    /// visible on the primary. Primaries and machines without a primary use
+    /// their own.
+    ///
+    /// With a replication lag configured, a replica instead reads a copy of
+    /// the primary's databases that trails it (see [`replicate_all`](Self::replicate_all)).
+    pub fn database_dir(&self, machine_id: &str) -> PathBuf {
+        match self.primary_of(machine_id) {
+            Some(_) if !self.replication.lag.is_zero() => self.replica_dir(machine_id),
+            Some(primary) => self.own_database_dir(&primary),
+            None => self.own_database_dir(machine_id),
+        }
+    }
+    
+    /// Where a machine's container should bind its database mount, if the
+    /// machine runs LiteFS (see [`database_dir`](Self::database_dir))
+    pub fn database_mount(&self, machine_id: &str) -> Option<DatabaseMount> {
+        if !self.roles.read().unwrap().contains_key(machine_id) {
+            return None;
+        }
+        
+        Some(DatabaseMount {
+            dir: self.database_dir(machine_id),
+            read_only: !self.replication.lag.is_zero() && self.primary_of(machine_id).is_some(),
+        })
+    }
+    
+    /// The database directory a machine was started with, whatever its role
+    fn own_database_dir(&self, machine_id: &str) -> PathBuf {
+        self.roles.read().unwrap().get(machine_id)
+            .and_then(|role| role.database_dir.clone())
+            .unwrap_or_else(|| self.data_dir(machine_id))
+    }
+    
+    /// The primary of a replica's app, if the machine is a replica and the app has one
+    fn primary_of(&self, machine_id: &str) -> Option<String> {
+        let roles = self.roles.read().unwrap();
+        let app_name = roles.get(machine_id)
+            .filter(|role| !role.is_primary)
+            .and_then(|role| role.app_name.as_deref())?;
+        
+        roles.iter()
+            .filter(|(_, role)| role.is_primary && role.app_name.as_deref() == Some(app_name))
+            .map(|(id, _)| id.clone())
+            .min()
+    }
+    
+    /// Brings every lagging replica's copy of its primary's databases up to
+    /// date, minus the lag. Does nothing without a replication lag.
+    ///
+    /// A copy covers each database with its -wal and -shm files, so changes
+    /// still in the primary's write-ahead log replicate too. A change becomes
+    /// visible on a replica once the lag has passed since it was copied, so
+    /// stale reads can be reproduced locally.
+    pub fn replicate_all(&self) -> Result<()> {
+        if self.replication.lag.is_zero() {
+            return Ok(());
+        }
+        
+        let ids: Vec<String> = self.roles.read().unwrap().keys().cloned().collect();
+        for id in ids {
+            let Some(primary) = self.primary_of(&id) else {
+                continue;
+            };
+            let source_dir = self.own_database_dir(&primary);
+            let Ok(entries) = std::fs::read_dir(&source_dir) else {
+                continue;
+            };
+            
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let is_sidecar = DATABASE_SIDECARS.iter().any(|suffix| name.ends_with(suffix));
+                if !is_sidecar && entry.path().is_file() {
+                    self.replicate(&id, &source_dir, &name)?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Copies one of the primary's databases for a replica if it changed,
+    /// and applies every copy that has waited out the lag
+    fn replicate(&self, machine_id: &str, source_dir: &Path, name: &str) -> Result<()> {
+        let io_error = |e: std::io::Error| Error::LiteFSError(format!("Failed to replicate {}: {}", name, e));
+        let files = [name.to_string(), format!("{}-wal", name), format!("{}-shm", name)];
+        let version: Vec<FileVersion> = files.iter()
+            .map(|file| std::fs::metadata(source_dir.join(file)).ok().map(|m| (m.modified().ok(), m.len())))
+            .collect();
+        let view_dir = self.replica_dir(machine_id);
+        std::fs::create_dir_all(&view_dir).map_err(io_error)?;
+        
+        let mut replicas = self.replicas.lock().unwrap();
+        let replica = replicas.entry((machine_id.to_string(), name.to_string())).or_default();
+        
+        // Snapshots live outside the view, which may be bound into a container
+        if replica.seen.as_ref() != Some(&version) {
+            let snapshot = self.base_dir.join("snapshots").join(machine_id).join(replica.snapshots.to_string());
+            replica.snapshots += 1;
+            std::fs::create_dir_all(&snapshot).map_err(io_error)?;
+            for file in &files {
+                match std::fs::copy(source_dir.join(file), snapshot.join(file)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(io_error(e)),
+                    _ => {}
+                }
+            }
+            replica.seen = Some(version);
+            replica.pending.push_back((Instant::now(), snapshot));
+        }
+        
+        while replica.pending.front().is_some_and(|(taken, _)| taken.elapsed() >= self.replication.lag) {
+            let (_, snapshot) = replica.pending.pop_front().unwrap();
+            for file in &files {
+                let copied = snapshot.join(file);
+                if copied.exists() {
+                    std::fs::rename(&copied, view_dir.join(file)).map_err(io_error)?;
+                } else {
+                    let _ = std::fs::remove_file(view_dir.join(file));
+                }
+            }
+            let _ = std::fs::remove_dir(&snapshot);
+        }
+        
+        // An empty file is an empty database until the first copy arrives
+        let view = view_dir.join(name);
+        if !view.exists() {
+            std::fs::File::create(&view).map_err(io_error)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Directory holding a lagging replica's copy of the primary's databases
+    fn replica_dir(&self, machine_id: &str) -> PathBuf {
+        self.base_dir.join("replicas").join(machine_id)
+    }
+    
+    fn data_dir(&self, machine_id: &str) -> PathBuf {
+        self.base_dir.join("data").join(machine_id)
+    }
+    
+    /// Moves the databases of a demoted primary to the new primary, unless
+    /// the new primary already has its own.
+    ///
+    /// Files are moved rather than the directory, since a database directory
+    /// may be a volume bound into a container.
+    async fn move_databases(&self, from: &str, to: &str) -> Result<()> {
+        let io_error = |e: std::io::Error| Error::LiteFSError(format!("Failed to move databases: {}", e));
+        let source = self.own_database_dir(from);
+        let target = self.own_database_dir(to);
+        if source == target || !source.exists() {
+            return Ok(());
+        }
+        if let Ok(mut entries) = fs::read_dir(&target).await {
+            if entries.next_entry().await.ok().flatten().is_some() {
+                warn!("Not moving LiteFS data from {} to {}: target is not empty", from, to);
+                return Ok(());
+            }
+        }
+        
+        fs::create_dir_all(&target).await.map_err(io_error)?;
+        let mut entries = fs::read_dir(&source).await.map_err(io_error)?;
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            fs::rename(entry.path(), target.join(entry.file_name())).await.map_err(io_error)?;
+        }
+        Ok(())
+    }
+    
     pub fn get_mount_path(&self, machine_id: &str) -> PathBuf {
         self.base_dir.join("mounts").join(machine_id)
     }
@@ -285,4 +718,126 @@ impl LiteFSManager {
         
         Err(Error::LiteFSError("No production LiteFS config found".to_string()))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_promote_flips_roles() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LiteFSManager::new(dir.path().to_path_buf()).await.unwrap();
+        manager.start_for_machine_with_config("m1", true, Some("web"), None).await.unwrap();
+        manager.start_for_machine_with_config("m2", false, Some("web"), None).await.unwrap();
+        manager.start_for_machine_with_config("m3", false, Some("web"), None).await.unwrap();
+        manager.start_for_machine_with_config("other", true, Some("api"), None).await.unwrap();
+
+        manager.promote("web", "m2").await.unwrap();
+
+        let roles: Vec<_> = manager.app_status("web").await
+            .into_iter()
+            .map(|status| (status.machine_id, status.role))
+            .collect();
+        assert_eq!(roles, vec![
+            ("m1".to_string(), LiteFSRole::Replica),
+            ("m2".to_string(), LiteFSRole::Primary),
+            ("m3".to_string(), LiteFSRole::Replica),
+        ]);
+        assert_eq!(manager.is_primary("other"), Some(true));
+
+        // Machines of another app can't be promoted through this one
+        assert!(manager.promote("web", "other").await.is_err());
+        assert!(manager.promote("web", "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_an_app_has_a_single_primary() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LiteFSManager::new(dir.path().to_path_buf()).await.unwrap();
+        manager.start_for_machine_with_config("m1", true, Some("web"), None).await.unwrap();
+        manager.start_for_machine_with_config("m2", true, Some("web"), None).await.unwrap();
+        manager.start_for_machine_with_config("other", true, Some("api"), None).await.unwrap();
+
+        assert_eq!(manager.is_primary("m1"), Some(true));
+        assert_eq!(manager.is_primary("m2"), Some(false));
+        assert_eq!(manager.is_primary("other"), Some(true));
+
+        // Restarting the primary keeps its role
+        manager.start_for_machine_with_config("m1", true, Some("web"), None).await.unwrap();
+        assert_eq!(manager.is_primary("m1"), Some(true));
+
+        // A stopped primary hands the role to the next machine that asks
+        manager.stop_for_machine("m1").await.unwrap();
+        manager.start_for_machine_with_config("m3", true, Some("web"), None).await.unwrap();
+        assert_eq!(manager.is_primary("m3"), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_replica_writes_are_visible_on_primary() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LiteFSManager::new(dir.path().join("litefs")).await.unwrap();
+        // Each machine has its own database volume
+        let volume = |id: &str| dir.path().join("volumes").join(id);
+        manager.start_for_machine_with_config("primary", true, Some("web"), Some(&volume("primary"))).await.unwrap();
+        manager.start_for_machine_with_config("replica", false, Some("web"), Some(&volume("replica"))).await.unwrap();
+        
+        // The replica's container binds the primary's volume
+        let mount = manager.database_mount("replica").unwrap();
+        assert_eq!(mount, DatabaseMount { dir: volume("primary"), read_only: false });
+        assert_eq!(manager.database_mount("primary").unwrap().dir, volume("primary"));
+        assert_eq!(manager.database_mount("untracked"), None);
+
+        let open = |id: &str| rusqlite::Connection::open(manager.database_mount(id).unwrap().dir.join("app.db")).unwrap();
+        open("replica").execute_batch("CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES ('from replica');").unwrap();
+        let body: String = open("primary").query_row("SELECT body FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(body, "from replica");
+
+        // The databases follow the primary role
+        manager.promote("web", "replica").await.unwrap();
+        assert_eq!(manager.database_mount("primary").unwrap().dir, volume("replica"));
+        let count: i64 = open("replica").query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replica_reads_lag_behind_primary() {
+        let lag = Duration::from_millis(200);
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LiteFSManager::new(dir.path().to_path_buf()).await.unwrap()
+            .with_replication(ReplicationConfig::with_lag(lag));
+        manager.start_for_machine_with_config("primary", true, Some("web"), None).await.unwrap();
+        manager.start_for_machine_with_config("replica", false, Some("web"), None).await.unwrap();
+        
+        // The replica's container gets a read-only copy
+        let mount = manager.database_mount("replica").unwrap();
+        assert!(mount.read_only);
+        assert!(!manager.database_mount("primary").unwrap().read_only);
+        
+        // Keeping the connection open leaves the writes in the -wal file
+        let primary = rusqlite::Connection::open(manager.database_dir("primary").join("app.db")).unwrap();
+        primary.execute_batch("PRAGMA journal_mode = WAL; CREATE TABLE kv (value TEXT); INSERT INTO kv VALUES ('old');").unwrap();
+        let read = || -> Option<String> {
+            manager.replicate_all().unwrap();
+            let replica = rusqlite::Connection::open_with_flags(
+                mount.dir.join("app.db"),
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+            ).unwrap();
+            replica.query_row("SELECT value FROM kv", [], |row| row.get(0)).ok()
+        };
+        
+        assert_eq!(read(), None);
+        tokio::time::sleep(lag).await;
+        assert_eq!(read().as_deref(), Some("old"));
+        
+        primary.execute("UPDATE kv SET value = 'new'", []).unwrap();
+        assert_eq!(read().as_deref(), Some("old"));
+        tokio::time::sleep(lag).await;
+        assert_eq!(read().as_deref(), Some("new"));
+        
+        // The primary always reads its own writes
+        let own: String = primary.query_row("SELECT value FROM kv", [], |row| row.get(0)).unwrap();
+        assert_eq!(own, "new");
+        assert!(manager.database_dir("primary").join("app.db-wal").exists());
+    }
+}
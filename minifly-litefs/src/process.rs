@@ -1,17 +1,66 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Mutex};
 use tracing::{info, warn, error};
+use minifly_logging::fields;
 use crate::config::LiteFSConfig;
 use minifly_core::Error;
 use crate::Result;
 
+/// Exit notifications buffered per subscriber
+const EXIT_CAPACITY: usize = 64;
+
+/// What to do when a LiteFS process exits on its own
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "policy")]
+pub enum RestartPolicy {
+    /// Leave the process stopped and report the machine unhealthy
+    #[default]
+    Never,
+    /// Restart the process up to `max_restarts` times
+    OnFailure { max_restarts: u32 },
+}
+
+impl RestartPolicy {
+    /// Parses `never`, `on-failure` (3 restarts) or `on-failure:<max_restarts>`
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.split_once(':') {
+            _ if value == "never" => Ok(Self::Never),
+            _ if value == "on-failure" => Ok(Self::OnFailure { max_restarts: 3 }),
+            Some(("on-failure", max)) => max.parse()
+                .map(|max_restarts| Self::OnFailure { max_restarts })
+                .map_err(|_| Error::InvalidConfiguration(format!("Invalid LiteFS restart limit '{}'", max))),
+            _ => Err(Error::InvalidConfiguration(format!(
+                "Unknown LiteFS restart policy '{}'; expected never or on-failure[:<max_restarts>]",
+                value
+            ))),
+        }
+    }
+}
+
+/// A LiteFS process that exited without being stopped
+#[derive(Debug, Clone, Serialize)]
+pub struct LiteFSExit {
+    pub machine_id: String,
+    pub mount_path: PathBuf,
+    /// Exit code, or `None` if the process was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Whether the restart policy started the process again
+    pub restarted: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
 pub struct LiteFSProcess {
     child: Option<Child>,
     config_path: PathBuf,
     binary_path: PathBuf,
     machine_id: String,
+    mount_path: PathBuf,
+    restarts: u32,
 }
 
 impl LiteFSProcess {
@@ -21,9 +70,18 @@ impl LiteFSProcess {
             config_path,
             binary_path,
             machine_id,
+            mount_path: PathBuf::new(),
+            restarts: 0,
         }
     }
     
+    /// Returns the exit status if the process has exited since it was started
+    fn poll_exit(&mut self) -> Option<ExitStatus> {
+        let status = self.child.as_mut()?.try_wait().ok()??;
+        self.child = None;
+        Some(status)
+    }
+    
     pub fn start(&mut self) -> Result<()> {
         if self.child.is_some() {
             return Err(Error::LiteFSError("LiteFS process already running".to_string()));
@@ -111,16 +169,89 @@ impl Drop for LiteFSProcess {
 }
 
 pub struct LiteFSProcessManager {
-    processes: Arc<Mutex<std::collections::HashMap<String, LiteFSProcess>>>,
+    processes: Arc<Mutex<HashMap<String, LiteFSProcess>>>,
     binary_path: PathBuf,
+    restart_policy: RestartPolicy,
+    /// Machines whose process exited and was not restarted
+    crashed: std::sync::RwLock<HashMap<String, LiteFSExit>>,
+    exits: broadcast::Sender<LiteFSExit>,
 }
 
 impl LiteFSProcessManager {
     pub fn new(binary_path: PathBuf) -> Self {
         Self {
-            processes: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            processes: Arc::new(Mutex::new(HashMap::new())),
             binary_path,
+            restart_policy: RestartPolicy::default(),
+            crashed: std::sync::RwLock::new(HashMap::new()),
+            exits: broadcast::channel(EXIT_CAPACITY).0,
+        }
+    }
+    
+    pub fn with_restart_policy(mut self, restart_policy: RestartPolicy) -> Self {
+        self.restart_policy = restart_policy;
+        self
+    }
+    
+    /// Returns a receiver for process exits detected from now on
+    pub fn subscribe_exits(&self) -> broadcast::Receiver<LiteFSExit> {
+        self.exits.subscribe()
+    }
+    
+    /// The last exit of every machine whose LiteFS process is down
+    pub fn crashed(&self) -> Vec<LiteFSExit> {
+        self.crashed.read().unwrap().values().cloned().collect()
+    }
+    
+    /// Detects processes that exited on their own, restarting them if the
+    /// restart policy allows it, and returns what was found
+    pub async fn check_processes(&self) -> Vec<LiteFSExit> {
+        let mut processes = self.processes.lock().await;
+        let mut exits = Vec::new();
+        
+        for (machine_id, process) in processes.iter_mut() {
+            let Some(status) = process.poll_exit() else {
+                continue;
+            };
+            error!(
+                machine.id = %machine_id,
+                { fields::LITEFS_MOUNT_PATH } = %process.mount_path.display(),
+                exit_status = %status,
+                "LiteFS process exited unexpectedly"
+            );
+            
+            let restarted = match self.restart_policy {
+                RestartPolicy::OnFailure { max_restarts } if process.restarts < max_restarts => {
+                    process.restarts += 1;
+                    match process.start() {
+                        Ok(()) => true,
+                        Err(e) => {
+                            error!(machine.id = %machine_id, error = %e, "Failed to restart LiteFS");
+                            false
+                        }
+                    }
+                }
+                _ => false,
+            };
+            
+            let exit = LiteFSExit {
+                machine_id: machine_id.clone(),
+                mount_path: process.mount_path.clone(),
+                exit_code: status.code(),
+                restarted,
+                timestamp: Utc::now(),
+            };
+            if restarted {
+                self.crashed.write().unwrap().remove(machine_id);
+            } else {
+                self.crashed.write().unwrap().insert(machine_id.clone(), exit.clone());
+            }
+            // Sending only fails when there are no subscribers
+            let _ = self.exits.send(exit.clone());
+            exits.push(exit);
         }
+        
+        exits
     }
     
     pub async fn start_litefs(&self, machine_id: &str, config: &LiteFSConfig, config_dir: &PathBuf) -> Result<()> {
@@ -140,9 +271,11 @@ impl LiteFSProcessManager {
             self.binary_path.clone(),
             config_path,
         );
+        process.mount_path = config.fuse.dir.clone();
         
         process.start()?;
         processes.insert(machine_id.to_string(), process);
+        self.crashed.write().unwrap().remove(machine_id);
         
         Ok(())
     }
@@ -153,6 +286,7 @@ impl LiteFSProcessManager {
         if let Some(mut process) = processes.remove(machine_id) {
             process.stop()?;
         }
+        self.crashed.write().unwrap().remove(machine_id);
         
         Ok(())
     }
@@ -176,7 +310,89 @@ impl LiteFSProcessManager {
                 error!("Failed to stop LiteFS for machine {}: {}", machine_id, e);
             }
         }
+        self.crashed.write().unwrap().clear();
         
         Ok(())
     }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    
+    /// Writes a stand-in LiteFS binary that just sleeps
+    fn fake_litefs(dir: &std::path::Path) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let binary = dir.join("litefs");
+        std::fs::write(&binary, "#!/bin/sh\nexec sleep 60\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        binary
+    }
+    
+    async fn kill(manager: &LiteFSProcessManager, machine_id: &str) {
+        let mut processes = manager.processes.lock().await;
+        let child = processes.get_mut(machine_id).unwrap().child.as_mut().unwrap();
+        child.kill().unwrap();
+        // The exit status is kept for the monitor to find
+        child.wait().unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_crash_marks_machine_unhealthy() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LiteFSConfig::for_local_dev("m1", dir.path().join("mount"), dir.path().join("data"), true);
+        let manager = LiteFSProcessManager::new(fake_litefs(dir.path()));
+        let mut exits = manager.subscribe_exits();
+        manager.start_litefs("m1", &config, &dir.path().to_path_buf()).await.unwrap();
+        
+        assert!(manager.check_processes().await.is_empty());
+        assert!(manager.crashed().is_empty());
+        
+        kill(&manager, "m1").await;
+        let found = manager.check_processes().await;
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].restarted);
+        assert_eq!(found[0].mount_path, dir.path().join("mount"));
+        assert!(!manager.is_running("m1").await);
+        
+        let crashed = manager.crashed();
+        assert_eq!(crashed.len(), 1);
+        assert_eq!(crashed[0].machine_id, "m1");
+        assert_eq!(exits.try_recv().unwrap().machine_id, "m1");
+        
+        // Restarting the machine clears the crash
+        manager.stop_litefs("m1").await.unwrap();
+        assert!(manager.crashed().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_restart_policy_restarts_crashed_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LiteFSConfig::for_local_dev("m1", dir.path().join("mount"), dir.path().join("data"), true);
+        let manager = LiteFSProcessManager::new(fake_litefs(dir.path()))
+            .with_restart_policy(RestartPolicy::OnFailure { max_restarts: 1 });
+        manager.start_litefs("m1", &config, &dir.path().to_path_buf()).await.unwrap();
+        
+        kill(&manager, "m1").await;
+        assert!(manager.check_processes().await[0].restarted);
+        assert!(manager.is_running("m1").await);
+        assert!(manager.crashed().is_empty());
+        
+        // The restart budget is spent
+        kill(&manager, "m1").await;
+        assert!(!manager.check_processes().await[0].restarted);
+        assert_eq!(manager.crashed().len(), 1);
+        
+        manager.stop_all().await.unwrap();
+    }
+    
+    #[test]
+    fn test_parse_restart_policy() {
+        assert_eq!(RestartPolicy::parse("never").unwrap(), RestartPolicy::Never);
+        assert_eq!(RestartPolicy::parse("on-failure").unwrap(), RestartPolicy::OnFailure { max_restarts: 3 });
+        assert_eq!(RestartPolicy::parse("on-failure:5").unwrap(), RestartPolicy::OnFailure { max_restarts: 5 });
+        assert!(RestartPolicy::parse("on-failure:x").is_err());
+        assert!(RestartPolicy::parse("always").is_err());
+    }
 }
\ No newline at end of file
@@ -87,7 +87,7 @@ async fn get_instance(
     let status = LiteFSStatus {
         machine_id: machine_id.clone(),
         is_running,
-        is_primary: true, // TODO: Get actual primary status
+        is_primary: state.manager.is_primary(&machine_id).unwrap_or(true),
         mount_path: state.manager.get_mount_path(&machine_id).to_string_lossy().to_string(),
         proxy_url: state.manager.get_proxy_url(&machine_id),
     };
@@ -1,6 +1,14 @@
+use std::path::{Path, PathBuf};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use uuid::Uuid;
 
+#[cfg(feature = "otlp")]
+mod otlp;
+mod sampling;
+
+pub use sampling::SamplingLayer;
+
 /// Logging configuration for Minifly services
 #[derive(Debug, Clone)]
 pub struct LoggingConfig {
@@ -9,6 +17,15 @@ pub struct LoggingConfig {
     pub environment: String,
     pub format: LogFormat,
     pub level: String,
+    /// OTLP collector endpoint to export traces to, e.g. `http://localhost:4317`.
+    /// Only honored when built with the `otlp` feature.
+    pub otlp_endpoint: Option<String>,
+    /// Fraction of `info`/`debug`/`trace` events to keep; `warn` and `error`
+    /// are always kept
+    pub sample_rate: f64,
+    /// File to additionally write JSON logs to, rotated per `file_rotation`
+    pub log_file: Option<PathBuf>,
+    pub file_rotation: LogRotation,
 }
 
 /// Log output format options
@@ -20,6 +37,40 @@ pub enum LogFormat {
     Json,
 }
 
+/// How often the log file is rotated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Weekly,
+    /// Keep appending to a single file
+    Never,
+}
+
+impl LogRotation {
+    /// Number of rotated files kept before the oldest is deleted
+    const MAX_FILES: usize = 7;
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hourly" => Some(LogRotation::Hourly),
+            "daily" => Some(LogRotation::Daily),
+            "weekly" => Some(LogRotation::Weekly),
+            "never" => Some(LogRotation::Never),
+            _ => None,
+        }
+    }
+
+    fn rotation(self) -> Rotation {
+        match self {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Weekly => Rotation::WEEKLY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
 impl Default for LoggingConfig {
     fn default() -> Self {
         Self {
@@ -28,6 +79,10 @@ impl Default for LoggingConfig {
             environment: "development".to_string(),
             format: LogFormat::Human,
             level: "info".to_string(),
+            otlp_endpoint: None,
+            sample_rate: 1.0,
+            log_file: None,
+            file_rotation: LogRotation::Daily,
         }
     }
 }
@@ -59,6 +114,31 @@ impl LoggingConfig {
         self
     }
 
+    /// Export traces to an OpenTelemetry collector at `endpoint`
+    pub fn with_otlp(mut self, endpoint: &str) -> Self {
+        self.otlp_endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Keep only `rate` of `info`/`debug`/`trace` events
+    pub fn with_sampling(mut self, rate: f64) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    /// Also write JSON logs to `path`. Rotated files are named
+    /// `<stem>.<date>.<extension>` next to it.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    /// Set how often the log file is rotated
+    pub fn with_file_rotation(mut self, rotation: LogRotation) -> Self {
+        self.file_rotation = rotation;
+        self
+    }
+
     /// Build config from environment variables
     pub fn from_env(service_name: &str) -> Self {
         let format = match std::env::var("MINIFLY_LOG_FORMAT").as_deref() {
@@ -68,6 +148,18 @@ impl LoggingConfig {
 
         let level = std::env::var("MINIFLY_LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
         let environment = std::env::var("MINIFLY_ENVIRONMENT").unwrap_or_else(|_| "development".to_string());
+        let otlp_endpoint = std::env::var("MINIFLY_OTLP_ENDPOINT")
+            .or_else(|_| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+            .ok();
+        let sample_rate = std::env::var("MINIFLY_LOG_SAMPLE_RATE")
+            .ok()
+            .and_then(|rate| rate.parse().ok())
+            .unwrap_or(1.0);
+        let log_file = std::env::var_os("MINIFLY_LOG_FILE").map(PathBuf::from);
+        let file_rotation = std::env::var("MINIFLY_LOG_ROTATION")
+            .ok()
+            .and_then(|rotation| LogRotation::parse(&rotation))
+            .unwrap_or(LogRotation::Daily);
 
         Self {
             service_name: service_name.to_string(),
@@ -75,17 +167,68 @@ impl LoggingConfig {
             environment,
             format,
             level,
+            otlp_endpoint,
+            sample_rate,
+            log_file,
+            file_rotation,
         }
     }
 }
 
+/// Opens a rotating appender for `path`
+fn file_appender(path: &Path, rotation: LogRotation) -> anyhow::Result<RollingFileAppender> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let prefix = path
+        .file_stem()
+        .ok_or_else(|| anyhow::anyhow!("Invalid log file path: {}", path.display()))?
+        .to_string_lossy();
+
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation.rotation())
+        .filename_prefix(prefix)
+        .max_log_files(LogRotation::MAX_FILES);
+    if let Some(extension) = path.extension() {
+        builder = builder.filename_suffix(extension.to_string_lossy());
+    }
+
+    Ok(builder.build(directory)?)
+}
+
 /// Initialize structured logging for a Minifly service
 pub fn init_logging(config: LoggingConfig) -> anyhow::Result<()> {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(&config.level));
 
+    #[cfg(feature = "otlp")]
+    let otel_layer = match &config.otlp_endpoint {
+        Some(endpoint) => Some(otlp::layer(otlp::tracer_provider(&config, endpoint)?, &config)),
+        None => None,
+    };
+    #[cfg(not(feature = "otlp"))]
+    let otel_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    // The file always gets JSON, whatever the console format
+    let file_layer = match &config.log_file {
+        Some(path) => Some(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(false)
+                .with_span_list(true)
+                .with_target(true)
+                .with_ansi(false)
+                .with_writer(file_appender(path, config.file_rotation)?)
+        ),
+        None => None,
+    };
+
     let subscriber = tracing_subscriber::registry()
-        .with(env_filter);
+        .with(env_filter)
+        .with((config.sample_rate < 1.0).then(|| SamplingLayer::new(config.sample_rate)))
+        .with(otel_layer)
+        .with(file_layer);
 
     match config.format {
         LogFormat::Json => {
@@ -121,12 +264,30 @@ pub fn init_logging(config: LoggingConfig) -> anyhow::Result<()> {
         environment = %config.environment,
         log.format = ?config.format,
         log.level = %config.level,
+        log.sample_rate = config.sample_rate,
         "Structured logging initialized"
     );
 
+    if let Some(endpoint) = &config.otlp_endpoint {
+        if cfg!(feature = "otlp") {
+            tracing::info!(otlp.endpoint = %endpoint, "Exporting traces over OTLP");
+        } else {
+            tracing::warn!(
+                otlp.endpoint = %endpoint,
+                "OTLP endpoint set but minifly-logging was built without the `otlp` feature; traces will not be exported"
+            );
+        }
+    }
+
     Ok(())
 }
 
+/// Flush any pending trace exports. Call before the process exits.
+pub fn shutdown_logging() {
+    #[cfg(feature = "otlp")]
+    otlp::shutdown();
+}
+
 /// Standard field names for consistent logging across all Minifly components
 pub mod fields {
     // Identity and correlation fields
@@ -244,6 +405,27 @@ mod tests {
         assert_eq!(config.level, "debug");
         assert_eq!(config.environment, "production");
         assert!(matches!(config.format, LogFormat::Json));
+        assert_eq!(config.otlp_endpoint, None);
+        assert_eq!(config.sample_rate, 1.0);
+
+        let config = config.with_otlp("http://localhost:4317");
+        assert_eq!(config.otlp_endpoint.as_deref(), Some("http://localhost:4317"));
+    }
+
+    #[test]
+    fn test_file_logging_writes_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LoggingConfig::new("file-test-service")
+            .with_file(dir.path().join("minifly.log"))
+            .with_file_rotation(LogRotation::Never);
+
+        init_logging(config).unwrap();
+        tracing::warn!("written to the log file");
+
+        let contents = std::fs::read_to_string(dir.path().join("minifly.log")).unwrap();
+        let init_line: serde_json::Value = serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+        assert_eq!(init_line["fields"]["service.name"], "file-test-service");
+        assert!(contents.contains("written to the log file"));
     }
 
     #[test]
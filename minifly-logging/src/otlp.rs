@@ -0,0 +1,106 @@
+//! OpenTelemetry trace export, enabled with the `otlp` feature.
+//!
+//! Spans such as those created by [`operation_span!`](crate::operation_span)
+//! are exported over OTLP/gRPC to a collector. Span fields become span
+//! attributes, so `correlation_id` can be used to find a request's trace.
+
+use crate::LoggingConfig;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Builds a tracer provider exporting batches of spans to `endpoint`
+pub(crate) fn tracer_provider(config: &LoggingConfig, endpoint: &str) -> anyhow::Result<TracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource(config))
+        .build())
+}
+
+fn resource(config: &LoggingConfig) -> Resource {
+    Resource::new([
+        KeyValue::new("service.name", config.service_name.clone()),
+        KeyValue::new("service.version", config.service_version.clone()),
+        KeyValue::new("deployment.environment", config.environment.clone()),
+    ])
+}
+
+/// Creates the tracing layer that hands spans to the provider and installs
+/// the provider globally so [`shutdown`] can flush it
+pub(crate) fn layer<S>(provider: TracerProvider, config: &LoggingConfig) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let tracer = provider.tracer(config.service_name.clone());
+    opentelemetry::global::set_tracer_provider(provider);
+    tracing_opentelemetry::layer().with_tracer(tracer)
+}
+
+/// Flushes and shuts down the global tracer provider
+pub(crate) fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Keeps exported spans in memory
+    #[derive(Debug, Clone, Default)]
+    struct CapturingExporter(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanExporter for CapturingExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    #[test]
+    fn test_operation_span_exports_correlation_id() {
+        let exporter = CapturingExporter::default();
+        let config = LoggingConfig::new("minifly-test");
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .with_resource(resource(&config))
+            .build();
+        let tracer = provider.tracer("minifly-test");
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = crate::operation_span!("deploy", app_name = "web");
+            let _entered = span.enter();
+        });
+
+        let spans = exporter.0.lock().unwrap();
+        assert_eq!(spans.len(), 1);
+        let attribute = |key: &str| {
+            spans[0]
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("operation"), Some(Value::from("deploy")));
+        assert_eq!(attribute("app_name"), Some(Value::from("web")));
+        let correlation_id = attribute("correlation_id").expect("correlation_id attribute");
+        assert!(uuid::Uuid::parse_str(correlation_id.as_str().as_ref()).is_ok());
+    }
+}
@@ -0,0 +1,91 @@
+//! Sampling of low-severity events to keep logs readable under load.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Layer that keeps only a fraction of `info`, `debug` and `trace` events.
+///
+/// `warn` and `error` events are always kept. Sampling is deterministic:
+/// with a rate of `0.25`, exactly one in every four sampled events passes.
+#[derive(Debug)]
+pub struct SamplingLayer {
+    rate: f64,
+    seen: AtomicU64,
+}
+
+impl SamplingLayer {
+    /// Creates a layer keeping `rate` (clamped to `0.0..=1.0`) of low-severity events
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    fn keep(&self) -> bool {
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        // Keep the event whenever the running total of kept events ticks over
+        ((n + 1.0) * self.rate).floor() > (n * self.rate).floor()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SamplingLayer {
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        *event.metadata().level() <= Level::WARN || self.keep()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Counts events reaching it by level
+    #[derive(Clone, Default)]
+    struct Counter {
+        info: Arc<AtomicU64>,
+        error: Arc<AtomicU64>,
+    }
+
+    impl<S: Subscriber> Layer<S> for Counter {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let count = if *event.metadata().level() == Level::ERROR { &self.error } else { &self.info };
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn run(rate: f64) -> Counter {
+        let counter = Counter::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(SamplingLayer::new(rate))
+            .with(counter.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..10_000 {
+                tracing::info!(i, "request handled");
+                if i % 100 == 0 {
+                    tracing::error!(i, "request failed");
+                }
+            }
+        });
+        counter
+    }
+
+    #[test]
+    fn test_errors_are_never_dropped() {
+        for rate in [0.0, 0.1, 1.0] {
+            assert_eq!(run(rate).error.load(Ordering::Relaxed), 100);
+        }
+    }
+
+    #[test]
+    fn test_sample_rate_is_honored() {
+        let kept = run(0.1).info.load(Ordering::Relaxed);
+        assert!((950..=1050).contains(&kept), "kept {} of 10000", kept);
+
+        assert_eq!(run(0.0).info.load(Ordering::Relaxed), 0);
+        assert_eq!(run(1.0).info.load(Ordering::Relaxed), 10_000);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -44,6 +44,8 @@ pub struct InternalDnsResolver {
     app_ips: Arc<RwLock<HashMap<String, Vec<IpAddr>>>>,
     /// Map of machine IDs to their IPs
     machine_ips: Arc<RwLock<HashMap<String, IpAddr>>>,
+    /// IDs of machines excluded from `<app>.internal` resolution
+    cordoned: Arc<RwLock<HashSet<String>>>,
 }
 
 impl InternalDnsResolver {
@@ -55,6 +57,7 @@ impl InternalDnsResolver {
         Self {
             app_ips: Arc::new(RwLock::new(HashMap::new())),
             machine_ips: Arc::new(RwLock::new(HashMap::new())),
+            cordoned: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
@@ -134,10 +137,26 @@ impl InternalDnsResolver {
         Ok(())
     }
 
+    /// Cordons a machine, excluding it from `<app>.internal` resolution.
+    /// 
+    /// The machine keeps running and still resolves through its
+    /// `<machine-id>.vm.<app>.internal` domain. Cordoning survives the machine
+    /// being unregistered and registered again, e.g. across a restart.
+    pub async fn cordon_machine(&self, machine_id: &str) {
+        info!("Cordoning machine {}", machine_id);
+        self.cordoned.write().await.insert(machine_id.to_string());
+    }
+
+    /// Uncordons a machine so `<app>.internal` resolves to it again.
+    pub async fn uncordon_machine(&self, machine_id: &str) {
+        info!("Uncordoning machine {}", machine_id);
+        self.cordoned.write().await.remove(machine_id);
+    }
+
     /// Resolves a .internal domain to IP addresses.
     /// 
     /// Supports multiple domain formats for compatibility with Fly.io:
-    /// - `<app>.internal` - Returns all machine IPs for the app
+    /// - `<app>.internal` - Returns the IPs of the app's uncordoned machines
     /// - `<machine-id>.vm.<app>.internal` - Returns IP for specific machine
     /// - `fly-local-6pn.internal` - Returns local Docker DNS server IP
     /// 
@@ -183,8 +202,13 @@ impl InternalDnsResolver {
         if let Some(app_name) = hostname.strip_suffix(".internal") {
             let app_ips = self.app_ips.read().await;
             if let Some(ips) = app_ips.get(app_name) {
+                let cordoned_ips = self.cordoned_ips().await;
+                let ips: Vec<IpAddr> = ips.iter()
+                    .filter(|ip| !cordoned_ips.contains(ip))
+                    .copied()
+                    .collect();
                 debug!("Resolved {} to {:?}", hostname, ips);
-                return Ok(ips.clone());
+                return Ok(ips);
             }
         }
         
@@ -205,6 +229,30 @@ impl InternalDnsResolver {
         Ok(vec![])
     }
 
+    /// Resolves `<app>.internal` like [`resolve`](Self::resolve), pairing
+    /// each IP with the ID of the machine it belongs to.
+    pub async fn resolve_machines(&self, app_name: &str) -> Vec<(String, IpAddr)> {
+        let ips = self.resolve(&format!("{}.internal", app_name)).await.unwrap_or_default();
+        let machine_ips = self.machine_ips.read().await;
+        ips.into_iter()
+            .filter_map(|ip| {
+                machine_ips.iter()
+                    .find(|(_, machine_ip)| **machine_ip == ip)
+                    .map(|(machine_id, _)| (machine_id.clone(), ip))
+            })
+            .collect()
+    }
+
+    /// Returns the IPs of registered machines that are cordoned.
+    async fn cordoned_ips(&self) -> HashSet<IpAddr> {
+        let cordoned = self.cordoned.read().await;
+        let machine_ips = self.machine_ips.read().await;
+        
+        cordoned.iter()
+            .filter_map(|machine_id| machine_ips.get(machine_id).copied())
+            .collect()
+    }
+
     /// Returns all registered apps and their IP addresses.
     /// 
     /// This is primarily useful for debugging and monitoring the current
@@ -317,4 +365,23 @@ mod tests {
         let ips = resolver.resolve("myapp.internal").await.unwrap();
         assert_eq!(ips.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_cordoned_machine_skipped() {
+        let resolver = InternalDnsResolver::new();
+        
+        let ip1 = IpAddr::V4(Ipv4Addr::new(172, 19, 0, 2));
+        let ip2 = IpAddr::V4(Ipv4Addr::new(172, 19, 0, 3));
+        resolver.register_machine("myapp", "machine-1", ip1).await.unwrap();
+        resolver.register_machine("myapp", "machine-2", ip2).await.unwrap();
+        
+        resolver.cordon_machine("machine-1").await;
+        
+        // Cordoned machine drops out of the app domain but stays addressable
+        assert_eq!(resolver.resolve("myapp.internal").await.unwrap(), vec![ip2]);
+        assert_eq!(resolver.resolve("machine-1.vm.myapp.internal").await.unwrap(), vec![ip1]);
+        
+        resolver.uncordon_machine("machine-1").await;
+        assert_eq!(resolver.resolve("myapp.internal").await.unwrap().len(), 2);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,242 @@
+//! Builder for machine creation requests
+//!
+//! [`MachineConfigBuilder`] assembles a [`CreateMachineRequest`] for
+//! [`ApiClient::create_machine`](crate::ApiClient::create_machine) without
+//! spelling out every optional field of the API's machine config. Anything
+//! not set falls back to the same values as [`crate::MachineConfig::default`].
+//!
+//! ```rust
+//! use minifly::MachineConfigBuilder;
+//!
+//! let request = MachineConfigBuilder::new("node:18-alpine")
+//!     .name("web-1")
+//!     .region("ord")
+//!     .env("PORT", "8080")
+//!     .service(8080, 443)
+//!     .mount("vol_data", "/data")
+//!     .guest(2, 512)
+//!     .build_request();
+//!
+//! assert_eq!(request.config.guest.memory_mb, 512);
+//! assert_eq!(request.config.env.unwrap()["PORT"], "8080");
+//! ```
+
+use minifly_core::models::{
+    CreateMachineRequest, GuestConfig, MachineConfig, MountConfig, PortConfig, RestartConfig, ServiceConfig,
+};
+use std::collections::HashMap;
+
+/// Builds the config of a machine to create
+#[derive(Debug, Clone)]
+pub struct MachineConfigBuilder {
+    name: Option<String>,
+    region: Option<String>,
+    image: String,
+    env: HashMap<String, String>,
+    services: Vec<ServiceConfig>,
+    mounts: Vec<MountConfig>,
+    guest: GuestConfig,
+    restart: RestartConfig,
+    auto_destroy: bool,
+    kill_timeout: Option<u32>,
+}
+
+impl MachineConfigBuilder {
+    /// Starts a config running `image`, with a shared 1 CPU / 256 MB guest
+    /// that is restarted up to 5 times on failure
+    pub fn new(image: impl Into<String>) -> Self {
+        Self {
+            name: None,
+            region: None,
+            image: image.into(),
+            env: HashMap::new(),
+            services: Vec::new(),
+            mounts: Vec::new(),
+            guest: GuestConfig {
+                cpu_kind: "shared".to_string(),
+                cpus: 1,
+                memory_mb: 256,
+                gpu_kind: None,
+                gpus: None,
+                kernel_args: None,
+            },
+            restart: RestartConfig {
+                policy: "on-failure".to_string(),
+                max_retries: Some(5),
+            },
+            auto_destroy: false,
+            kill_timeout: None,
+        }
+    }
+
+    /// Sets the machine's name; the API generates one otherwise
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the region the machine runs in
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Sets an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Exposes `internal_port` over HTTP on `port`
+    pub fn service(self, internal_port: u16, port: u16) -> Self {
+        self.with_service(ServiceConfig {
+            ports: vec![PortConfig {
+                port,
+                handlers: vec!["http".to_string()],
+                force_https: None,
+                tls_options: None,
+            }],
+            protocol: "tcp".to_string(),
+            internal_port,
+            autostop: None,
+            autostart: None,
+            force_instance_description: None,
+        })
+    }
+
+    /// Adds a service with full control over its ports and handlers
+    pub fn with_service(mut self, service: ServiceConfig) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Mounts `volume` at `path`
+    pub fn mount(mut self, volume: impl Into<String>, path: impl Into<String>) -> Self {
+        self.mounts.push(MountConfig {
+            volume: volume.into(),
+            path: path.into(),
+        });
+        self
+    }
+
+    /// Sets the guest's CPU count and memory
+    pub fn guest(mut self, cpus: u32, memory_mb: u32) -> Self {
+        self.guest.cpus = cpus;
+        self.guest.memory_mb = memory_mb;
+        self
+    }
+
+    /// Sets the CPU kind, `shared` or `performance`
+    pub fn cpu_kind(mut self, cpu_kind: impl Into<String>) -> Self {
+        self.guest.cpu_kind = cpu_kind.into();
+        self
+    }
+
+    /// Sets the restart policy (`no`, `always` or `on-failure`)
+    pub fn restart(mut self, policy: impl Into<String>, max_retries: Option<u32>) -> Self {
+        self.restart = RestartConfig {
+            policy: policy.into(),
+            max_retries,
+        };
+        self
+    }
+
+    /// Destroys the machine once it exits
+    pub fn auto_destroy(mut self, auto_destroy: bool) -> Self {
+        self.auto_destroy = auto_destroy;
+        self
+    }
+
+    /// Sets the seconds a stopping machine gets to shut down before it is killed
+    pub fn kill_timeout(mut self, seconds: u32) -> Self {
+        self.kill_timeout = Some(seconds);
+        self
+    }
+
+    /// Builds the machine config alone
+    pub fn build(self) -> MachineConfig {
+        MachineConfig {
+            guest: self.guest,
+            env: (!self.env.is_empty()).then_some(self.env),
+            services: (!self.services.is_empty()).then_some(self.services),
+            restart: Some(self.restart),
+            auto_destroy: Some(self.auto_destroy),
+            kill_timeout: self.kill_timeout,
+            mounts: (!self.mounts.is_empty()).then_some(self.mounts),
+            ..MachineConfig::for_image(self.image)
+        }
+    }
+
+    /// Builds the request to send to the machines endpoint
+    pub fn build_request(mut self) -> CreateMachineRequest {
+        let name = self.name.take();
+        let region = self.region.take();
+        CreateMachineRequest {
+            name,
+            region,
+            config: self.build(),
+            skip_launch: None,
+            skip_service_registration: None,
+            lease_ttl: None,
+        }
+    }
+}
+
+impl Default for MachineConfigBuilder {
+    fn default() -> Self {
+        Self::new("nginx:latest")
+    }
+}
+
+impl From<&str> for MachineConfigBuilder {
+    fn from(image: &str) -> Self {
+        Self::new(image)
+    }
+}
+
+impl From<String> for MachineConfigBuilder {
+    fn from(image: String) -> Self {
+        Self::new(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_machine_config_default() {
+        let config = MachineConfigBuilder::default().build();
+        let expected = crate::MachineConfig::default();
+
+        assert_eq!(config.image, expected.image);
+        assert_eq!(config.guest.cpu_kind, expected.guest.cpu_kind);
+        assert_eq!(config.guest.cpus as i32, expected.guest.cpus);
+        assert_eq!(config.guest.memory_mb as i32, expected.guest.memory_mb);
+        let restart = config.restart.unwrap();
+        assert_eq!(restart.policy, expected.restart.policy);
+        assert_eq!(restart.max_retries, Some(expected.restart.max_retries as u32));
+        assert_eq!(config.auto_destroy, Some(expected.auto_destroy));
+    }
+
+    #[test]
+    fn test_custom_env_is_serialized() {
+        let request = MachineConfigBuilder::new("node:18-alpine")
+            .name("web-1")
+            .env("NODE_ENV", "production")
+            .env("PORT", "8080")
+            .mount("vol_data", "/data")
+            .build_request();
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["name"], "web-1");
+        assert!(json.get("region").is_none());
+        assert_eq!(json["config"]["image"], "node:18-alpine");
+        assert_eq!(
+            json["config"]["env"],
+            serde_json::json!({ "NODE_ENV": "production", "PORT": "8080" })
+        );
+        assert_eq!(json["config"]["mounts"], serde_json::json!([{ "volume": "vol_data", "path": "/data" }]));
+        assert!(json["config"].get("services").is_none());
+    }
+}
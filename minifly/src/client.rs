@@ -1,11 +1,41 @@
 //! HTTP client for communicating with the Minifly API
+//!
+//! [`ApiClient`] is a thin typed wrapper over the Machines API. Responses are
+//! returned as [`minifly_core::models`] types (re-exported as
+//! [`crate::models`]), so the same structs the API server uses can be used in
+//! integration tests and automation against a local Minifly.
+//!
+//! ```rust,no_run
+//! use minifly::{ApiClient, Config};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let client = ApiClient::new(&Config::default())?;
+//!
+//! client.create_app("my-app").await?;
+//! let machine = client.create_machine("my-app", "nginx:latest").await?;
+//! client.stop_machine("my-app", &machine.id).await?;
+//!
+//! for machine in client.list_machines("my-app").await? {
+//!     println!("{} {}", machine.id, machine.state.as_str());
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
 use anyhow::{Context, Result};
-use reqwest::{Client, ClientBuilder};
+use minifly_core::models::{
+    AppResponse, CreateAppRequest, CreateLeaseRequest, CreateMachineRequest, CreateVolumeRequest,
+    ExtendVolumeRequest, ExtendVolumeResponse, LeaseResponse, LogEntry, Machine, StartMachineResponse,
+    StopMachineResponse, Volume,
+};
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response};
 use serde::de::DeserializeOwned;
 use std::time::Duration;
 
-use crate::{Config, App, Machine, CreateMachineRequest};
+use crate::{Config, MachineConfigBuilder};
+
+/// Header carrying the nonce of the lease being released
+const LEASE_NONCE_HEADER: &str = "fly-machine-lease-nonce";
 
 /// HTTP client for the Minifly API
 #[derive(Debug, Clone)]
@@ -21,181 +51,382 @@ impl ApiClient {
         let mut builder = ClientBuilder::new()
             .timeout(Duration::from_secs(config.timeout))
             .user_agent(format!("minifly-cli/{}", env!("CARGO_PKG_VERSION")));
-            
+
         if !config.verify_ssl {
             builder = builder.danger_accept_invalid_certs(true);
         }
-        
+
         let client = builder.build()
             .context("Failed to create HTTP client")?;
-            
+
         Ok(Self {
             client,
             base_url: config.api_url.trim_end_matches('/').to_string(),
             token: config.token.clone(),
         })
     }
-    
+
     /// Check if the API server is running
     pub async fn health_check(&self) -> Result<bool> {
-        let url = format!("{}/health", self.base_url);
-        
+        let url = format!("{}/v1/health", self.base_url);
+
         match self.client.get(&url).send().await {
             Ok(response) => Ok(response.status().is_success()),
             Err(_) => Ok(false),
         }
     }
-    
+
+    // Apps API
+
     /// List all applications
-    pub async fn list_apps(&self) -> Result<Vec<App>> {
+    pub async fn list_apps(&self) -> Result<Vec<AppResponse>> {
         self.get("/v1/apps").await
     }
-    
+
+    /// Get an application
+    pub async fn get_app(&self, name: &str) -> Result<AppResponse> {
+        self.get(&format!("/v1/apps/{}", name)).await
+    }
+
     /// Create a new application
-    pub async fn create_app(&self, name: &str) -> Result<App> {
-        let body = serde_json::json!({ "app_name": name });
+    pub async fn create_app(&self, name: &str) -> Result<AppResponse> {
+        let body = CreateAppRequest {
+            app_name: name.to_string(),
+            org_slug: "personal".to_string(),
+        };
         self.post("/v1/apps", &body).await
     }
-    
+
     /// Delete an application
     pub async fn delete_app(&self, name: &str) -> Result<()> {
-        let url = format!("/v1/apps/{}", name);
-        self.delete(&url).await
+        self.delete(&format!("/v1/apps/{}", name)).await
     }
-    
+
+    // Machines API
+
     /// List machines for an application
     pub async fn list_machines(&self, app_name: &str) -> Result<Vec<Machine>> {
-        let url = format!("/v1/apps/{}/machines", app_name);
-        self.get(&url).await
+        self.get(&format!("/v1/apps/{}/machines", app_name)).await
     }
-    
-    /// Create a new machine
-    pub async fn create_machine(
-        &self,
-        app_name: &str,
-        image: &str,
-        name: Option<String>,
-        region: Option<String>,
-    ) -> Result<Machine> {
-        use crate::types::{MachineConfig, Guest, RestartPolicy};
-        use std::collections::HashMap;
-        
-        let config = MachineConfig {
-            image: image.to_string(),
-            env: HashMap::new(),
-            services: vec![],
-            guest: Guest::default(),
-            restart: RestartPolicy::default(),
-            auto_destroy: false,
-            kill_timeout: Some(5),
-        };
-        
-        let request = CreateMachineRequest {
-            name,
-            config,
-            region,
-            skip_launch: false,
-            skip_service_registration: false,
-        };
-        
-        let url = format!("/v1/apps/{}/machines", app_name);
-        self.post(&url, &request).await
+
+    /// Get machine details
+    pub async fn get_machine(&self, app_name: &str, machine_id: &str) -> Result<Machine> {
+        self.get(&format!("/v1/apps/{}/machines/{}", app_name, machine_id)).await
     }
-    
+
+    /// Create and start a machine from an image name or a [`MachineConfigBuilder`]
+    ///
+    /// ```rust,no_run
+    /// use minifly::{ApiClient, MachineConfigBuilder};
+    ///
+    /// # async fn example(client: ApiClient) -> anyhow::Result<()> {
+    /// client.create_machine("my-app", "nginx:latest").await?;
+    ///
+    /// let machine = MachineConfigBuilder::new("node:18-alpine")
+    ///     .env("DATABASE_URL", "sqlite:///litefs/app.db")
+    ///     .service(8080, 443)
+    ///     .mount("vol_data", "/litefs");
+    /// client.create_machine("my-app", machine).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_machine(&self, app_name: &str, machine: impl Into<MachineConfigBuilder>) -> Result<Machine> {
+        self.create_machine_with(app_name, &machine.into().build_request()).await
+    }
+
+    /// Create a machine from a full request, for configs beyond an image
+    pub async fn create_machine_with(&self, app_name: &str, request: &CreateMachineRequest) -> Result<Machine> {
+        self.post(&format!("/v1/apps/{}/machines", app_name), request).await
+    }
+
     /// Start a machine
-    pub async fn start_machine(&self, machine_id: &str) -> Result<Machine> {
-        let url = format!("/v1/machines/{}/start", machine_id);
+    pub async fn start_machine(&self, app_name: &str, machine_id: &str) -> Result<StartMachineResponse> {
+        let url = format!("/v1/apps/{}/machines/{}/start", app_name, machine_id);
         self.post(&url, &serde_json::json!({})).await
     }
-    
+
     /// Stop a machine
-    pub async fn stop_machine(&self, machine_id: &str) -> Result<Machine> {
-        let url = format!("/v1/machines/{}/stop", machine_id);
+    pub async fn stop_machine(&self, app_name: &str, machine_id: &str) -> Result<StopMachineResponse> {
+        let url = format!("/v1/apps/{}/machines/{}/stop", app_name, machine_id);
         self.post(&url, &serde_json::json!({})).await
     }
-    
-    /// Delete a machine
-    pub async fn delete_machine(&self, machine_id: &str, force: bool) -> Result<()> {
+
+    /// Delete a machine, stopping it first if `force` is set
+    pub async fn delete_machine(&self, app_name: &str, machine_id: &str, force: bool) -> Result<()> {
         let url = if force {
-            format!("/v1/machines/{}?force=true", machine_id)
+            format!("/v1/apps/{}/machines/{}?force=true", app_name, machine_id)
         } else {
-            format!("/v1/machines/{}", machine_id)
+            format!("/v1/apps/{}/machines/{}", app_name, machine_id)
         };
         self.delete(&url).await
     }
-    
-    /// Get machine details
-    pub async fn get_machine(&self, machine_id: &str) -> Result<Machine> {
-        let url = format!("/v1/machines/{}", machine_id);
-        self.get(&url).await
+
+    // Volumes API
+
+    /// List volumes for an application
+    pub async fn list_volumes(&self, app_name: &str) -> Result<Vec<Volume>> {
+        self.get(&format!("/v1/apps/{}/volumes", app_name)).await
+    }
+
+    /// Get volume details
+    pub async fn get_volume(&self, app_name: &str, volume_id: &str) -> Result<Volume> {
+        self.get(&format!("/v1/apps/{}/volumes/{}", app_name, volume_id)).await
+    }
+
+    /// Create a volume
+    pub async fn create_volume(&self, app_name: &str, request: &CreateVolumeRequest) -> Result<Volume> {
+        self.post(&format!("/v1/apps/{}/volumes", app_name), request).await
+    }
+
+    /// Grow a volume to `size_gb`
+    pub async fn extend_volume(&self, app_name: &str, volume_id: &str, size_gb: u32) -> Result<ExtendVolumeResponse> {
+        let url = format!("/v1/apps/{}/volumes/{}/extend", app_name, volume_id);
+        self.post(&url, &ExtendVolumeRequest { size_gb }).await
+    }
+
+    /// Delete a volume
+    pub async fn delete_volume(&self, app_name: &str, volume_id: &str) -> Result<()> {
+        self.delete(&format!("/v1/apps/{}/volumes/{}", app_name, volume_id)).await
+    }
+
+    // Leases API
+
+    /// Acquire a lease on a machine; the returned nonce is needed to release it
+    pub async fn acquire_lease(
+        &self,
+        app_name: &str,
+        machine_id: &str,
+        request: &CreateLeaseRequest,
+    ) -> Result<LeaseResponse> {
+        self.post(&format!("/v1/apps/{}/machines/{}/lease", app_name, machine_id), request).await
     }
-    
+
+    /// Get the current lease on a machine
+    pub async fn get_lease(&self, app_name: &str, machine_id: &str) -> Result<LeaseResponse> {
+        self.get(&format!("/v1/apps/{}/machines/{}/lease", app_name, machine_id)).await
+    }
+
+    /// Release a machine's lease held with `nonce`
+    pub async fn release_lease(&self, app_name: &str, machine_id: &str, nonce: &str) -> Result<()> {
+        let url = format!("{}/v1/apps/{}/machines/{}/lease", self.base_url, app_name, machine_id);
+        let request = self.client.delete(&url).header(LEASE_NONCE_HEADER, nonce);
+        self.send(request, &url).await?;
+        Ok(())
+    }
+
+    // Logs API
+
+    /// Recent log lines of a machine, optionally only the last `tail` lines
+    pub async fn machine_logs(&self, app_name: &str, machine_id: &str, tail: Option<usize>) -> Result<Vec<LogEntry>> {
+        self.logs(&format!("/v1/apps/{}/machines/{}/logs", app_name, machine_id), tail).await
+    }
+
+    /// Recent log lines of all of an app's machines, interleaved by time
+    pub async fn app_logs(&self, app_name: &str, tail: Option<usize>) -> Result<Vec<LogEntry>> {
+        self.logs(&format!("/v1/apps/{}/logs", app_name), tail).await
+    }
+
+    /// Reads a log endpoint without following it, collecting its events
+    async fn logs(&self, path: &str, tail: Option<usize>) -> Result<Vec<LogEntry>> {
+        let mut url = format!("{}{}?include_levels=true", self.base_url, path);
+        if let Some(tail) = tail {
+            url.push_str(&format!("&tail={}", tail));
+        }
+
+        let response = self.send(self.client.get(&url), &url).await?;
+        let body = response.text().await
+            .with_context(|| format!("Failed to read log stream from {}", url))?;
+
+        Ok(parse_log_events(&body))
+    }
+
     /// Generic GET request
     async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
         let url = format!("{}{}", self.base_url, path);
-        let mut request = self.client.get(&url);
-        
-        if let Some(token) = &self.token {
-            request = request.bearer_auth(token);
-        }
-        
-        let response = request.send().await
-            .with_context(|| format!("Failed to send GET request to {}", url))?;
-            
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("API request failed with status {}: {}", status, text);
-        }
-        
-        let result = response.json().await
-            .with_context(|| format!("Failed to parse JSON response from {}", url))?;
-            
-        Ok(result)
+        let response = self.send(self.client.get(&url), &url).await?;
+
+        response.json().await
+            .with_context(|| format!("Failed to parse JSON response from {}", url))
     }
-    
+
     /// Generic POST request
     async fn post<T: DeserializeOwned>(&self, path: &str, body: &impl serde::Serialize) -> Result<T> {
         let url = format!("{}{}", self.base_url, path);
-        let mut request = self.client.post(&url).json(body);
-        
-        if let Some(token) = &self.token {
-            request = request.bearer_auth(token);
-        }
-        
-        let response = request.send().await
-            .with_context(|| format!("Failed to send POST request to {}", url))?;
-            
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("API request failed with status {}: {}", status, text);
-        }
-        
-        let result = response.json().await
-            .with_context(|| format!("Failed to parse JSON response from {}", url))?;
-            
-        Ok(result)
+        let response = self.send(self.client.post(&url).json(body), &url).await?;
+
+        response.json().await
+            .with_context(|| format!("Failed to parse JSON response from {}", url))
     }
-    
+
     /// Generic DELETE request
     async fn delete(&self, path: &str) -> Result<()> {
         let url = format!("{}{}", self.base_url, path);
-        let mut request = self.client.delete(&url);
-        
+        self.send(self.client.delete(&url), &url).await?;
+        Ok(())
+    }
+
+    /// Sends a request with the auth token, failing on non-success statuses
+    async fn send(&self, mut request: RequestBuilder, url: &str) -> Result<Response> {
         if let Some(token) = &self.token {
             request = request.bearer_auth(token);
         }
-        
+
         let response = request.send().await
-            .with_context(|| format!("Failed to send DELETE request to {}", url))?;
-            
+            .with_context(|| format!("Failed to send request to {}", url))?;
+
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             anyhow::bail!("API request failed with status {}: {}", status, text);
         }
-        
-        Ok(())
+
+        Ok(response)
+    }
+}
+
+/// Parses the `data:` lines of a Server-Sent Events body into log entries,
+/// skipping heartbeats and error events
+fn parse_log_events(body: &str) -> Vec<LogEntry> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str(data.trim()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minifly_core::models::MachineState;
+    use wiremock::matchers::{bearer_token, body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(api: &MockServer) -> ApiClient {
+        let config = Config {
+            api_url: api.uri(),
+            token: Some("test-token".to_string()),
+            ..Config::default()
+        };
+        ApiClient::new(&config).unwrap()
+    }
+
+    fn machine_json(id: &str, state: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": "web-1",
+            "state": state,
+            "region": "local",
+            "image_ref": { "registry": "docker.io", "repository": "nginx", "tag": "latest", "digest": null },
+            "instance_id": "01H9EXAMPLE",
+            "private_ip": "fdaa:0:1::3",
+            "created_at": "2024-06-22T10:30:00Z",
+            "updated_at": "2024-06-22T10:31:00Z",
+            "config": { "image": "nginx:latest", "guest": { "cpu_kind": "shared", "cpus": 1, "memory_mb": 256 } },
+            "events": [],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_machines_are_typed_and_authenticated() {
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/web/machines"))
+            .and(bearer_token("test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![machine_json("m1", "started")]))
+            .mount(&api)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/apps/web/machines/m1/stop"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+            .mount(&api)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/apps/web/machines/m1"))
+            .and(query_param("force", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+            .mount(&api)
+            .await;
+
+        let client = client(&api);
+        let machines = client.list_machines("web").await.unwrap();
+        assert_eq!(machines.len(), 1);
+        assert_eq!(machines[0].state, MachineState::Started);
+        assert!(client.stop_machine("web", "m1").await.unwrap().ok);
+        client.delete_machine("web", "m1", true).await.unwrap();
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_create_machine_sends_builder_config() {
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/apps/web/machines"))
+            .and(body_partial_json(serde_json::json!({
+                "region": "ord",
+                "config": { "image": "node:18-alpine", "env": { "PORT": "8080" }, "guest": { "cpus": 2 } },
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(machine_json("m1", "started")))
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        let machine = MachineConfigBuilder::new("node:18-alpine")
+            .region("ord")
+            .env("PORT", "8080")
+            .guest(2, 512);
+        client(&api).create_machine("web", machine).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_reported() {
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/web/machines/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("Machine not found: missing"))
+            .mount(&api)
+            .await;
+
+        let err = client(&api).get_machine("web", "missing").await.unwrap_err();
+        assert!(err.to_string().contains("404"), "{}", err);
+        assert!(err.to_string().contains("Machine not found"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_release_lease_sends_nonce() {
+        let api = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/apps/web/machines/m1/lease"))
+            .and(header(LEASE_NONCE_HEADER, "abc123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        client(&api).release_lease("web", "m1", "abc123").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_logs_are_read_from_event_stream() {
+        let entry = serde_json::json!({
+            "timestamp": "2024-06-22T10:30:00Z",
+            "level": "info",
+            "region": "local",
+            "machine_id": "m1",
+            "app_name": "web",
+            "message": "listening on :8080",
+            "stream": "stdout",
+            "correlation_id": null,
+        });
+        let body = format!("data: {}\n\n: heartbeat\n\ndata: {{\"error\": \"gone\"}}\n\n", entry);
+
+        let api = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/apps/web/machines/m1/logs"))
+            .and(query_param("tail", "10"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&api)
+            .await;
+
+        let logs = client(&api).machine_logs("web", "m1", Some(10)).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].message, "listening on :8080");
+    }
+}
@@ -25,8 +25,8 @@ pub async fn list(client: &ApiClient) -> Result<()> {
                 let table_data: Vec<AppRow> = apps.into_iter().map(|app| AppRow {
                     name: app.name,
                     status: app.status,
-                    hostname: app.hostname,
-                    deployed: if app.deployed { "Yes".to_string() } else { "No".to_string() },
+                    organization: app.organization.slug,
+                    created_at: app.created_at,
                 }).collect();
                 
                 let table = Table::new(table_data);
@@ -54,7 +54,7 @@ pub async fn create(client: &ApiClient, name: &str) -> Result<()> {
     match client.create_app(name).await {
         Ok(app) => {
             println!("{}", format!("✅ Application '{}' created successfully!", app.name).green());
-            println!("  • Hostname: {}", app.hostname.cyan());
+            println!("  • Organization: {}", app.organization.slug.cyan());
             println!("  • Status: {}", app.status.yellow());
         }
         Err(e) => {
@@ -93,8 +93,8 @@ struct AppRow {
     name: String,
     #[tabled(rename = "Status")]
     status: String,
-    #[tabled(rename = "Hostname")]
-    hostname: String,
-    #[tabled(rename = "Deployed")]
-    deployed: String,
+    #[tabled(rename = "Organization")]
+    organization: String,
+    #[tabled(rename = "Created")]
+    created_at: String,
 }
\ No newline at end of file
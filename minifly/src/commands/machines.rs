@@ -3,7 +3,7 @@
 use anyhow::Result;
 use colored::*;
 use tabled::{Table, Tabled};
-use minifly::ApiClient;
+use minifly::{ApiClient, MachineConfigBuilder};
 
 /// List machines for an application
 pub async fn list(client: &ApiClient, app_name: &str) -> Result<()> {
@@ -24,7 +24,7 @@ pub async fn list(client: &ApiClient, app_name: &str) -> Result<()> {
                 let table_data: Vec<MachineRow> = machines.into_iter().map(|machine| MachineRow {
                     id: machine.id,
                     name: machine.name,
-                    state: machine.state,
+                    state: machine.state.as_str().to_string(),
                     region: machine.region,
                     image: machine.config.image,
                     private_ip: machine.private_ip,
@@ -65,11 +65,19 @@ pub async fn create(
         println!("  • Region: {}", region.cyan());
     }
     
-    match client.create_machine(app_name, image, name, region).await {
+    let mut machine = MachineConfigBuilder::new(image);
+    if let Some(name) = name {
+        machine = machine.name(name);
+    }
+    if let Some(region) = region {
+        machine = machine.region(region);
+    }
+    
+    match client.create_machine(app_name, machine).await {
         Ok(machine) => {
             println!("{}", format!("✅ Machine '{}' created successfully!", machine.id).green());
             println!("  • ID: {}", machine.id.cyan());
-            println!("  • State: {}", machine.state.yellow());
+            println!("  • State: {}", machine.state.as_str().yellow());
             println!("  • Region: {}", machine.region.cyan());
             println!("  • Private IP: {}", machine.private_ip.cyan());
         }
@@ -82,7 +90,7 @@ pub async fn create(
 }
 
 /// Start a machine
-pub async fn start(client: &ApiClient, machine_id: &str) -> Result<()> {
+pub async fn start(client: &ApiClient, app_name: &str, machine_id: &str) -> Result<()> {
     if !client.health_check().await? {
         println!("{}", "❌ Minifly API server is not running".red());
         println!("Start it with: {}", "minifly serve".cyan());
@@ -91,10 +99,10 @@ pub async fn start(client: &ApiClient, machine_id: &str) -> Result<()> {
     
     println!("{}", format!("▶️  Starting machine '{}'...", machine_id).blue());
     
-    match client.start_machine(machine_id).await {
-        Ok(machine) => {
-            println!("{}", format!("✅ Machine '{}' started successfully!", machine.id).green());
-            println!("  • State: {}", machine.state.yellow());
+    match client.start_machine(app_name, machine_id).await {
+        Ok(response) => {
+            println!("{}", format!("✅ Machine '{}' started successfully!", machine_id).green());
+            println!("  • Previous state: {}", response.previous_state.yellow());
         }
         Err(e) => {
             println!("{}", format!("❌ Failed to start machine: {}", e).red());
@@ -105,7 +113,7 @@ pub async fn start(client: &ApiClient, machine_id: &str) -> Result<()> {
 }
 
 /// Stop a machine
-pub async fn stop(client: &ApiClient, machine_id: &str) -> Result<()> {
+pub async fn stop(client: &ApiClient, app_name: &str, machine_id: &str) -> Result<()> {
     if !client.health_check().await? {
         println!("{}", "❌ Minifly API server is not running".red());
         println!("Start it with: {}", "minifly serve".cyan());
@@ -114,10 +122,9 @@ pub async fn stop(client: &ApiClient, machine_id: &str) -> Result<()> {
     
     println!("{}", format!("⏹️  Stopping machine '{}'...", machine_id).yellow());
     
-    match client.stop_machine(machine_id).await {
-        Ok(machine) => {
-            println!("{}", format!("✅ Machine '{}' stopped successfully!", machine.id).green());
-            println!("  • State: {}", machine.state.yellow());
+    match client.stop_machine(app_name, machine_id).await {
+        Ok(_) => {
+            println!("{}", format!("✅ Machine '{}' stopped successfully!", machine_id).green());
         }
         Err(e) => {
             println!("{}", format!("❌ Failed to stop machine: {}", e).red());
@@ -128,7 +135,7 @@ pub async fn stop(client: &ApiClient, machine_id: &str) -> Result<()> {
 }
 
 /// Delete a machine
-pub async fn delete(client: &ApiClient, machine_id: &str, force: bool) -> Result<()> {
+pub async fn delete(client: &ApiClient, app_name: &str, machine_id: &str, force: bool) -> Result<()> {
     if !client.health_check().await? {
         println!("{}", "❌ Minifly API server is not running".red());
         println!("Start it with: {}", "minifly serve".cyan());
@@ -138,7 +145,7 @@ pub async fn delete(client: &ApiClient, machine_id: &str, force: bool) -> Result
     let action = if force { "Force deleting" } else { "Deleting" };
     println!("{}", format!("🗑️  {} machine '{}'...", action, machine_id).yellow());
     
-    match client.delete_machine(machine_id, force).await {
+    match client.delete_machine(app_name, machine_id, force).await {
         Ok(_) => {
             println!("{}", format!("✅ Machine '{}' deleted successfully!", machine_id).green());
         }
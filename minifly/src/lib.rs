@@ -48,18 +48,26 @@
 //!     client.create_app("my-app").await?;
 //!     
 //!     // Deploy a machine
-//!     let machine = client.create_machine("my-app", "nginx:latest", None, None).await?;
+//!     let machine = client.create_machine("my-app", "nginx:latest").await?;
 //!     println!("Created machine: {}", machine.id);
 //!     
+//!     // Stop it again
+//!     client.stop_machine("my-app", &machine.id).await?;
+//!     
 //!     Ok(())
 //! }
 //! ```
 
+pub mod builder;
 pub mod config;
 pub mod client;
 pub mod logging;
 pub mod types;
 
+/// Request and response types of the Minifly API, as returned by [`ApiClient`]
+pub use minifly_core::models;
+
+pub use builder::MachineConfigBuilder;
 pub use config::Config;
 pub use client::ApiClient;
 pub use types::*;
\ No newline at end of file
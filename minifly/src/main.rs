@@ -147,18 +147,27 @@ enum MachinesCommands {
     
     /// Start a machine
     Start {
+        #[arg(short, long, help = "Application name")]
+        app: String,
+        
         #[arg(help = "Machine ID")]
         machine_id: String,
     },
     
     /// Stop a machine
     Stop {
+        #[arg(short, long, help = "Application name")]
+        app: String,
+        
         #[arg(help = "Machine ID")]
         machine_id: String,
     },
     
     /// Delete a machine
     Delete {
+        #[arg(short, long, help = "Application name")]
+        app: String,
+        
         #[arg(help = "Machine ID")]
         machine_id: String,
         
@@ -221,14 +230,14 @@ async fn main() -> Result<()> {
             MachinesCommands::Create { app, image, name, region } => {
                 machines::create(&client, &app, &image, name, region).await?;
             }
-            MachinesCommands::Start { machine_id } => {
-                machines::start(&client, &machine_id).await?;
+            MachinesCommands::Start { app, machine_id } => {
+                machines::start(&client, &app, &machine_id).await?;
             }
-            MachinesCommands::Stop { machine_id } => {
-                machines::stop(&client, &machine_id).await?;
+            MachinesCommands::Stop { app, machine_id } => {
+                machines::stop(&client, &app, &machine_id).await?;
             }
-            MachinesCommands::Delete { machine_id, force } => {
-                machines::delete(&client, &machine_id, force).await?;
+            MachinesCommands::Delete { app, machine_id, force } => {
+                machines::delete(&client, &app, &machine_id, force).await?;
             }
         },
         Commands::Deploy { path, watch } => {